@@ -0,0 +1,183 @@
+use crate::db::script::{is_keyword, is_word_char, tokenize, TokenKind, KEYWORDS};
+
+/// What a completion candidate names
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CandidateKind {
+    Column,
+    Table,
+    Keyword,
+}
+
+impl CandidateKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            CandidateKind::Column => "column",
+            CandidateKind::Table => "table",
+            CandidateKind::Keyword => "keyword",
+        }
+    }
+}
+
+/// A completion offered for the word at the cursor, ready to insert
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub text: String,
+    pub kind: CandidateKind,
+}
+
+/// The partial word before the cursor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefix {
+    /// Character index where the word starts, including any opening quote
+    pub start: usize,
+    /// The opening quote of a quoted identifier: `"`, `` ` `` or `[`
+    pub quote: Option<char>,
+    /// The word typed so far, without its quote
+    pub text: String,
+    /// The identifier before a `.`, such as the table in `users.na`
+    pub qualifier: Option<String>,
+}
+
+/// The Tab completion popup in the SQL editor
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub prefix: Prefix,
+    pub candidates: Vec<Candidate>,
+    pub selected: usize,
+}
+
+/// The word being typed at character index `cursor`. There is none inside a string
+/// literal or comment, or when the cursor doesn't follow a word or an opening quote.
+pub fn prefix_at(sql: &str, cursor: usize) -> Option<Prefix> {
+    let chars: Vec<char> = sql.chars().take(cursor).collect();
+
+    // Whether the text before the cursor ends inside quotes or a comment
+    let before: String = chars.iter().collect();
+    let last = tokenize(&before).pop();
+    let (start, quote, text) = match last {
+        Some(token) if token.kind == TokenKind::Comment => return None,
+        Some(token) if token.open && token.kind == TokenKind::String => return None,
+        Some(token) if token.open && token.kind == TokenKind::QuotedIdentifier => {
+            let start = before[..token.range.start].chars().count();
+            (
+                start,
+                Some(chars[start]),
+                chars[start + 1..].iter().collect(),
+            )
+        }
+        _ => {
+            let start = chars
+                .iter()
+                .rposition(|&c| !is_word_char(c))
+                .map_or(0, |i| i + 1);
+            let text: String = chars[start..].iter().collect();
+            if text.is_empty() || text.starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            (start, None, text)
+        }
+    };
+    let qualifier = start
+        .checked_sub(1)
+        .filter(|&dot| chars[dot] == '.')
+        .and_then(|dot| identifier_before(&chars, dot));
+    Some(Prefix {
+        start,
+        quote,
+        text,
+        qualifier,
+    })
+}
+
+/// The identifier, quoted or not, that ends just before character index `end`
+fn identifier_before(chars: &[char], end: usize) -> Option<String> {
+    let last = *chars.get(end.checked_sub(1)?)?;
+    let open = match last {
+        '"' | '`' => last,
+        ']' => '[',
+        _ => {
+            let start = chars[..end]
+                .iter()
+                .rposition(|&c| !is_word_char(c))
+                .map_or(0, |i| i + 1);
+            return (start < end).then(|| chars[start..end].iter().collect());
+        }
+    };
+    let start = chars[..end - 1].iter().rposition(|&c| c == open)?;
+    Some(chars[start + 1..end - 1].iter().collect())
+}
+
+/// Tables named anywhere in `sql`, as spelled in `tables`
+pub fn mentioned_tables(sql: &str, tables: &[String]) -> Vec<String> {
+    let words: Vec<String> = sql
+        .split(|c: char| !is_word_char(c))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let sql = sql.to_lowercase();
+    tables
+        .iter()
+        .filter(|table| {
+            let name = table.to_lowercase();
+            // Names that aren't plain words are matched inside their quotes
+            words.contains(&name) || sql.contains(&format!("\"{}\"", name))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Columns, then tables, then keywords starting with the prefix (ignoring case).
+/// After a qualifier only columns are offered; keywords never go inside quotes.
+pub fn candidates(prefix: &Prefix, tables: &[String], columns: &[String]) -> Vec<Candidate> {
+    let typed = prefix.text.to_lowercase();
+    let matches = |name: &str| name.to_lowercase().starts_with(&typed);
+    let mut found: Vec<Candidate> = Vec::new();
+    let push = |name: &str, kind: CandidateKind, found: &mut Vec<Candidate>| {
+        let text = match kind {
+            CandidateKind::Keyword if prefix.text.chars().any(char::is_lowercase) => {
+                name.to_lowercase()
+            }
+            CandidateKind::Keyword => name.to_string(),
+            _ => quote_identifier(name, prefix.quote),
+        };
+        // An exact match has nothing left to complete
+        let typed_as_is = prefix.quote.is_none() && text == prefix.text;
+        if !typed_as_is && !found.iter().any(|c| c.text == text) {
+            found.push(Candidate { text, kind });
+        }
+    };
+
+    let mut sorted_columns: Vec<&String> = columns.iter().filter(|c| matches(c)).collect();
+    sorted_columns.sort_by_key(|c| c.to_lowercase());
+    for column in sorted_columns {
+        push(column, CandidateKind::Column, &mut found);
+    }
+    if prefix.qualifier.is_some() {
+        return found;
+    }
+    let mut sorted_tables: Vec<&String> = tables.iter().filter(|t| matches(t)).collect();
+    sorted_tables.sort_by_key(|t| t.to_lowercase());
+    for table in sorted_tables {
+        push(table, CandidateKind::Table, &mut found);
+    }
+    if prefix.quote.is_none() {
+        for keyword in KEYWORDS.iter().filter(|k| matches(k)) {
+            push(keyword, CandidateKind::Keyword, &mut found);
+        }
+    }
+    found
+}
+
+/// An identifier as it should be typed: inside `quote` when the user opened one,
+/// otherwise bare unless it needs double quotes
+fn quote_identifier(name: &str, quote: Option<char>) -> String {
+    let plain = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(is_word_char)
+        && !is_keyword(name);
+    match quote {
+        Some('[') => format!("[{}]", name),
+        Some(q) => format!("{q}{}{q}", name.replace(q, &format!("{q}{q}"))),
+        None if plain => name.to_string(),
+        None => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
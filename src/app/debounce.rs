@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+/// How long typing must pause before the database work it triggers is sent
+pub const DEBOUNCE_DELAY: Duration = Duration::from_millis(200);
+
+/// The latest value typed, held until input has been quiet for a moment. Each new value
+/// replaces the one waiting and starts the wait over, so a burst of keystrokes sends
+/// one request, for the last of them; the request ids of what it sends drop answers
+/// to anything sent before.
+#[derive(Debug)]
+pub struct Debounce<T> {
+    delay: Duration,
+    waiting: Option<(T, Instant)>,
+}
+
+impl<T> Default for Debounce<T> {
+    fn default() -> Self {
+        Self {
+            delay: DEBOUNCE_DELAY,
+            waiting: None,
+        }
+    }
+}
+
+impl<T> Debounce<T> {
+    /// Hold `value`, typed at `now`, in place of whatever was waiting
+    pub fn input(&mut self, value: T, now: Instant) {
+        self.waiting = Some((value, now));
+    }
+
+    /// The value waiting, once input has been quiet for the delay as of `now`
+    pub fn ready(&mut self, now: Instant) -> Option<T> {
+        match &self.waiting {
+            Some((_, typed)) if now.saturating_duration_since(*typed) >= self.delay => {
+                self.waiting.take().map(|(value, _)| value)
+            }
+            _ => None,
+        }
+    }
+
+    /// The value waiting, straight away, as when Tab or Enter asks for it
+    pub fn flush(&mut self) -> Option<T> {
+        self.waiting.take().map(|(value, _)| value)
+    }
+
+    /// Drop the value waiting, as when Enter or Esc makes it moot
+    pub fn cancel(&mut self) {
+        self.waiting = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOON: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn a_value_is_ready_once_the_delay_passes() {
+        let start = Instant::now();
+        let mut debounce = Debounce::default();
+        debounce.input("a", start);
+        assert_eq!(debounce.ready(start), None);
+        assert_eq!(debounce.ready(start + DEBOUNCE_DELAY - SOON), None);
+        assert_eq!(debounce.ready(start + DEBOUNCE_DELAY), Some("a"));
+        // It's handed out once
+        assert_eq!(debounce.ready(start + DEBOUNCE_DELAY * 2), None);
+    }
+
+    #[test]
+    fn each_input_starts_the_delay_over() {
+        let start = Instant::now();
+        let mut debounce = Debounce::default();
+        debounce.input("a", start);
+        let later = start + DEBOUNCE_DELAY - SOON;
+        debounce.input("ab", later);
+        // The first value's delay is up, but the second replaced it and waits on
+        assert_eq!(debounce.ready(start + DEBOUNCE_DELAY), None);
+        assert_eq!(debounce.ready(later + DEBOUNCE_DELAY), Some("ab"));
+    }
+
+    #[test]
+    fn flush_hands_over_the_value_at_once() {
+        let start = Instant::now();
+        let mut debounce = Debounce::default();
+        assert_eq!(debounce.flush(), None::<&str>);
+        debounce.input("a", start);
+        assert_eq!(debounce.flush(), Some("a"));
+        assert_eq!(debounce.ready(start + DEBOUNCE_DELAY), None);
+    }
+
+    #[test]
+    fn cancel_drops_the_value() {
+        let start = Instant::now();
+        let mut debounce = Debounce::default();
+        debounce.input("a", start);
+        debounce.cancel();
+        assert_eq!(debounce.ready(start + DEBOUNCE_DELAY), None);
+        assert_eq!(debounce.flush(), None);
+    }
+}
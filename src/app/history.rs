@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Most entries kept; older ones are dropped from the file
+const MAX_ENTRIES: usize = 2000;
+
+/// A query run from the SQL editor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub database: String,
+    /// RFC 3339 local time the query ran
+    pub timestamp: String,
+    pub duration_ms: u64,
+    /// Rows returned, or rows affected for a write
+    pub row_count: u64,
+}
+
+/// Query history, kept in memory and appended to `history.jsonl` in the data directory
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest first
+    pub entries: Vec<HistoryEntry>,
+    path: Option<PathBuf>,
+    database: String,
+}
+
+impl History {
+    /// Load the history file, skipping lines that don't parse. Without a home directory
+    /// the history only lasts for the session.
+    pub fn load(database: &Path) -> Self {
+        let database = fs::canonicalize(database)
+            .unwrap_or_else(|_| database.to_path_buf())
+            .display()
+            .to_string();
+        let path = data_file("history.jsonl");
+        let mut entries: Vec<HistoryEntry> = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+        let history = Self {
+            entries,
+            path,
+            database,
+        };
+        if excess > 0 {
+            let _ = history.rewrite();
+        }
+        history
+    }
+
+    /// Remember a query that ran, unless it repeats the previous entry
+    pub fn record(&mut self, query: &str, duration_ms: u64, row_count: u64) {
+        if self.entries.last().is_some_and(|last| last.query == query) {
+            return;
+        }
+        let entry = HistoryEntry {
+            query: query.to_string(),
+            database: self.database.clone(),
+            timestamp: chrono::Local::now().to_rfc3339(),
+            duration_ms,
+            row_count,
+        };
+        self.entries.push(entry);
+        // History is a convenience, so failing to save it isn't worth interrupting for
+        if self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(..excess);
+            let _ = self.rewrite();
+        } else {
+            let _ = self.append_last();
+        }
+    }
+
+    /// Entries containing `filter` (case-insensitive), newest first
+    pub fn matching(&self, filter: &str) -> Vec<&HistoryEntry> {
+        let filter = filter.to_lowercase();
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.query.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn append_last(&self) -> std::io::Result<()> {
+        let (Some(path), Some(entry)) = (&self.path, self.entries.last()) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+    }
+
+    fn rewrite(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for entry in &self.entries {
+            text.push_str(&serde_json::to_string(entry)?);
+            text.push('\n');
+        }
+        fs::write(path, text)
+    }
+}
+
+/// `$XDG_DATA_HOME/sqr/<name>`, defaulting to `~/.local/share/sqr/<name>`
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+        })?;
+    Some(data_dir.join("sqr").join(name))
+}
@@ -1,27 +1,184 @@
+pub mod completion;
+mod debounce;
+pub mod history;
+pub mod paste;
+pub mod path_input;
+pub mod saved_queries;
+pub mod sql_template;
 mod state;
 mod text_editor;
+mod validate;
 
-use crate::worker::{Worker, WorkerMessage, WorkerResponse};
+use crate::db::pragma::Pragma;
+use crate::db::query::{affected_rows_query, changes_schema, destructive_statement, rows_select};
+use crate::db::script::{has_parameters, split_statements, statement_at};
+use crate::db::{self, ColumnAction, ColumnChange, IndexAction, TableRef};
+use crate::export::{
+    export_diagram, rows_text, DiagramFormat, ExportFormat, ExportOptions, ExportProgress,
+    ExportSource,
+};
+use crate::types::{
+    ForeignKeyInfo, PageStart, PendingEdit, QueryResult, RowsQuery, TableKind, Value,
+};
+use crate::ui;
+use crate::worker::{ErrorContext, Worker, WorkerMessage, WorkerResponse};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use debounce::Debounce;
+use sql_template::SqlTemplate;
+use std::fs;
 use std::io;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-pub use state::{AppState, Focus, ViewMode};
-use text_editor::handle_text_editor_input;
+use path_input::{complete_path, expand_tilde};
+pub use state::{
+    AppState, BlobViewer, ColumnField, ColumnForm, ColumnReplace, ConfirmAction, CursorRestore,
+    ExtensionsView, Focus, HistoryPopup, IndexForm, IntegrityCheckView, LoggedError, NavEntry,
+    ParamField, ParamForm, PendingConfirm, PragmaEdit, PragmaView, Prompt, PromptKind,
+    QueryPlanView, ResultLayout, RowGutter, SavedQueryPicker, TableEntry, TableSort, ViewMode,
+};
+use state::{CachedPage, DIAGRAM_PAN, MAX_SQL_BUFFERS, ROW_JUMP};
+use text_editor::{byte_offset, handle_text_editor_input, insert_char};
+
+/// How often watch mode checks the database for outside changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long input must pause before the next page of rows is fetched ahead
+const PREFETCH_IDLE: Duration = Duration::from_millis(300);
+/// How long a snapshot is held without input; in WAL mode a held read keeps the log
+/// from being checkpointed past it
+const SNAPSHOT_IDLE: Duration = Duration::from_secs(60);
+/// SQL files bigger than this ask before loading into the editor
+const LARGE_SQL_FILE: u64 = 1024 * 1024;
+/// Rows copied as more text than this ask before going to the clipboard
+const LARGE_COPY: usize = 1024 * 1024;
+/// Lines PgUp/PgDn move the help by
+const HELP_PAGE_LINES: usize = 10;
+
+/// What to open once the table list has loaded, as asked for on the command line
+#[derive(Debug, Default)]
+pub struct StartupIntent {
+    /// Table to open, matched case-insensitively
+    pub table: Option<String>,
+    /// Page of the table to land on, counting from 0
+    pub page: usize,
+    /// SQL to put in the editor and run
+    pub query: Option<String>,
+    /// View to start in rather than the table's rows
+    pub view: Option<ViewMode>,
+}
+
+/// A page of rows asked for ahead of time, cached when the worker answers
+struct Prefetch {
+    request: u64,
+    table: String,
+    view: RowsQuery,
+    page: usize,
+}
 
 /// Main application controller
 pub struct App {
     pub state: AppState,
     worker: Worker,
     should_quit: bool,
+    /// When watch mode last asked the worker for the data version
+    last_watch_check: Instant,
+    data_version: Option<i64>,
+    /// An outside change arrived mid-edit; reload once the edit ends
+    refresh_pending: bool,
+    /// The statements running came from the SQL editor, so they go into history
+    record_history: bool,
+    /// Opened when the first table list arrives
+    startup: Option<StartupIntent>,
+    /// Ids of the latest requests for rows, table info, a schema and row counts; answers
+    /// to earlier ones are dropped
+    rows_request: u64,
+    table_info_request: u64,
+    schema_request: u64,
+    counts_request: u64,
+    /// Id of the read waiting on a lock, as shown by [`AppState::busy`]
+    busy_request: u64,
+    /// The last write sent, and one the database was locked for, which Ctrl+R sends
+    /// again
+    last_write: Option<WorkerMessage>,
+    locked_write: Option<WorkerMessage>,
+    /// The page being fetched ahead, and when a key was last pressed, as it's only
+    /// fetched once input pauses
+    prefetch: Option<Prefetch>,
+    last_input: Instant,
+    /// Work triggered by typing, sent once typing pauses: the row filter tried while
+    /// its prompt is open, and the tables whose columns completion needs
+    filter_preview: Debounce<String>,
+    column_lookup: Debounce<Vec<String>>,
+    /// The row filter before one was tried from the prompt, put back on Esc, and the
+    /// load that tried it, whose error isn't logged as it's likely half typed
+    filter_before_preview: Option<Option<String>>,
+    preview_request: u64,
 }
 
 impl App {
-    pub fn new(worker: Worker, page_size: usize) -> Self {
+    pub fn new(worker: Worker, page_size: usize, read_only: bool) -> Self {
+        let mut state = AppState::new(page_size);
+        state.read_only = read_only;
         Self {
-            state: AppState::new(page_size),
+            state,
             worker,
             should_quit: false,
+            last_watch_check: Instant::now(),
+            data_version: None,
+            refresh_pending: false,
+            record_history: false,
+            startup: None,
+            rows_request: 0,
+            table_info_request: 0,
+            schema_request: 0,
+            counts_request: 0,
+            busy_request: 0,
+            last_write: None,
+            locked_write: None,
+            prefetch: None,
+            last_input: Instant::now(),
+            filter_preview: Debounce::default(),
+            column_lookup: Debounce::default(),
+            filter_before_preview: None,
+            preview_request: 0,
+        }
+    }
+
+    /// Open a table, query or view as soon as the table list has loaded
+    pub fn start_with(&mut self, startup: StartupIntent) {
+        self.startup = Some(startup);
+    }
+
+    /// Whether writes are allowed; shows why not when the database is read-only
+    fn check_writable(&mut self) -> bool {
+        if self.state.stdin_copy {
+            self.state.status_message =
+                Some("Read-only - a database read from stdin can't be edited".to_string());
+        } else if self.state.read_only {
+            self.state.status_message =
+                Some("Read-only - press Ctrl+W or restart with --read-write to edit".to_string());
+        }
+        !self.state.read_only
+    }
+
+    /// Whether the rows on screen can be written: the database must be writable and
+    /// the open table not a view
+    fn check_rows_writable(&mut self) -> bool {
+        if !self.check_writable() {
+            return false;
+        }
+        let table = self.state.current_table.as_deref();
+        if self.state.view_mode == ViewMode::Rows
+            && table.and_then(|t| self.state.table_kind(t)) == Some(TableKind::View)
+        {
+            self.state.status_message = Some(format!(
+                "Read-only - {} is a view",
+                table.unwrap_or_default()
+            ));
+            return false;
         }
+        true
     }
 
     /// Check if application should quit
@@ -29,89 +186,771 @@ impl App {
         self.should_quit
     }
 
+    /// Periodic work between input events: send what typing triggered once it pauses,
+    /// fetch the next page of rows ahead, and in watch mode, poll for outside changes
+    pub fn tick(&mut self) {
+        self.send_debounced();
+        self.prefetch_next_page();
+        if self.state.snapshot.is_some() && self.last_input.elapsed() >= SNAPSHOT_IDLE {
+            self.end_snapshot();
+            self.state.status_message = Some(
+                "Snapshot released after a minute idle - the next page load takes a new one"
+                    .to_string(),
+            );
+        }
+        if !self.state.watch {
+            return;
+        }
+        // Never reload rows out from under an in-progress edit
+        if self.refresh_pending && !self.state.edit_mode {
+            self.refresh_from_disk();
+        }
+        if self.last_watch_check.elapsed() >= WATCH_INTERVAL {
+            self.last_watch_check = Instant::now();
+            let _ = self.worker.send(WorkerMessage::CheckDataVersion);
+        }
+    }
+
+    /// Whether `response` answers a request for rows, table info, a schema or row counts
+    /// that a later one has replaced, like the pages passed over while paging quickly or
+    /// the table left for another
+    fn is_stale(&self, request_id: u64, response: &WorkerResponse) -> bool {
+        let latest = match response {
+            WorkerResponse::TableRowsLoaded { .. }
+            | WorkerResponse::Error {
+                context: ErrorContext::Rows,
+                ..
+            } => self.rows_request,
+            WorkerResponse::TableInfoLoaded { .. } => self.table_info_request,
+            WorkerResponse::SchemaLoaded { .. } => self.schema_request,
+            WorkerResponse::TableRowCount { .. } => self.counts_request,
+            _ => return false,
+        };
+        request_id < latest
+    }
+
     /// Process worker responses
     pub fn process_worker_responses(&mut self) -> Result<(), io::Error> {
-        while let Ok(Some(response)) = self.worker.try_recv() {
+        if !self.state.worker_crashed && self.worker.crashed() {
+            self.worker_crashed();
+        }
+        while let Ok(Some((request_id, response))) = self.worker.try_recv() {
+            if self.is_stale(request_id, &response) {
+                continue;
+            }
+            // Any answer but another wait means the read waiting on a lock is done
+            if request_id == self.busy_request && !matches!(response, WorkerResponse::Busy { .. }) {
+                self.state.busy = None;
+            }
             match response {
-                WorkerResponse::TablesLoaded { tables } => {
+                WorkerResponse::TablesLoaded {
+                    tables,
+                    journal_mode,
+                } => {
+                    self.state.journal_mode = journal_mode;
+                    // Counted next, each reported as it's done
+                    self.state.uncounted_tables = tables
+                        .iter()
+                        .filter(|t| matches!(t.kind, TableKind::Table | TableKind::Virtual))
+                        .map(|t| t.name.clone())
+                        .collect();
+                    self.state.counting_since = Instant::now();
+                    if let Ok(request_id) = self.worker.send(WorkerMessage::CountRows {
+                        tables: self.state.uncounted_tables.iter().cloned().collect(),
+                        count_limit: self.state.count_limit,
+                    }) {
+                        self.counts_request = request_id;
+                    }
                     self.state.tables = tables;
                     self.state.tables_loading = false;
+                    // The schema may have changed under a diagram already drawn
+                    if self.state.diagram_data.is_some() {
+                        self.load_diagram();
+                    }
+                    self.state.tables_error = None;
+                    // Measured after the list shows, so it doesn't hold up startup
+                    let _ = self.worker.send(WorkerMessage::LoadTableSizes);
+                    // The schema may have changed, so columns are fetched again when completing
+                    self.state.completion_columns.clear();
+                    if let Some(startup) = self.startup.take() {
+                        self.apply_startup(startup);
+                    }
+                }
+                WorkerResponse::TableRowCount {
+                    table,
+                    count,
+                    estimated,
+                } => {
+                    self.state.uncounted_tables.remove(&table);
+                    // Counts arriving can reorder a list sorted by rows; the selection
+                    // stays on its table
+                    let selected = self.state.selected_table().map(str::to_string);
+                    if let Some(info) = self.state.tables.iter_mut().find(|t| t.name == table) {
+                        info.row_count = count;
+                        info.row_count_estimated = estimated;
+                    }
+                    if let Some(selected) = selected {
+                        self.state.select_table_named(&selected);
+                    }
+                }
+                WorkerResponse::TableSizesLoaded { sizes } => {
+                    for table in &mut self.state.tables {
+                        table.size = sizes.get(&table.name).copied();
+                    }
+                }
+                WorkerResponse::ColumnNamesLoaded { columns } => {
+                    for (table_name, names) in columns {
+                        self.state
+                            .completion_columns
+                            .insert(table_name.to_lowercase(), names);
+                    }
+                    if self.state.completion.is_some() {
+                        self.open_completion();
+                    }
                 }
                 WorkerResponse::TableRowsLoaded { result } => {
+                    // Going back came up short of a page, so rows before it were removed
+                    // and it's really the first
+                    let short = self.state.page_anchor.as_ref().is_some_and(|anchor| {
+                        matches!(anchor.start, PageStart::Before(_))
+                            && result.rows.len() < self.state.page_size
+                            && result.memory_cap.is_none()
+                    });
+                    if short {
+                        if let Some(table_name) = self.state.current_table.clone() {
+                            self.state.current_page = 0;
+                            self.state.page_anchor = None;
+                            self.load_table(table_name);
+                            continue;
+                        }
+                    }
                     self.state.table_rows = Some(result);
+                    self.state.note_page_end();
                     self.state.rows_loading = false;
+                    self.state.rows_error = None;
+                    self.state.clamp_selection();
+                    self.restore_saved_cursor();
+                }
+                WorkerResponse::TableRowsPrefetched { result } => match self.prefetch.take() {
+                    Some(prefetch) if prefetch.request == request_id => {
+                        self.state.cache_page(CachedPage {
+                            table: prefetch.table,
+                            view: prefetch.view,
+                            page: prefetch.page,
+                            result,
+                        });
+                    }
+                    // Fetched before an edit or table switch emptied the cache
+                    other => self.prefetch = other,
+                },
+                WorkerResponse::RowLocated { position } => match position {
+                    Some(position) if self.state.restore_cursor.is_some() => {
+                        // The saved row sorted onto another page; load that page
+                        let (page, row) = self.state.page_of_row(position);
+                        self.state.current_page = page;
+                        self.state.selected_row = row;
+                        if let Some(table_name) = self.state.current_table.clone() {
+                            self.load_table(table_name);
+                        }
+                    }
+                    _ => self.state.restore_cursor = None,
+                },
+                WorkerResponse::ScriptExecuted { steps } => {
+                    self.state.query_loading = false;
+                    let error = steps.iter().enumerate().find_map(|(i, step)| {
+                        let error = step.outcome.as_ref()?.as_ref().err()?;
+                        Some(if steps.len() > 1 {
+                            format!("Statement {} of {} failed: {}", i + 1, steps.len(), error)
+                        } else {
+                            error.clone()
+                        })
+                    });
+                    if let Some(error) = error {
+                        self.state.record_error(ErrorContext::Query, error);
+                    }
+                    // The grid shows the last statement that returned columns, or else the
+                    // last one that ran
+                    let ran: Vec<(&String, &QueryResult)> = steps
+                        .iter()
+                        .filter_map(|step| {
+                            Some((&step.statement, step.outcome.as_ref()?.as_ref().ok()?))
+                        })
+                        .collect();
+                    let shown = ran
+                        .iter()
+                        .rev()
+                        .find(|(_, result)| !result.columns.is_empty())
+                        .or(ran.last());
+                    if let Some((statement, result)) = shown {
+                        // A re-run keeps the cursor only while the columns stay the same
+                        let same_columns = self
+                            .state
+                            .query_result
+                            .as_ref()
+                            .is_some_and(|old| old.columns == result.columns);
+                        if !same_columns {
+                            self.state.query_selected_row = 0;
+                            self.state.query_selected_col = 0;
+                        }
+                        self.state.last_query = Some(statement.to_string());
+                        self.state.query_result = Some((*result).clone());
+                        self.state.note_query_page();
+                        self.state.clamp_query_selection();
+                        self.state.view_mode = ViewMode::Query;
+                    }
+                    if std::mem::take(&mut self.record_history) {
+                        for step in &steps {
+                            if let Some(Ok(result)) = &step.outcome {
+                                let rows = result.rows_affected.unwrap_or(result.rows.len() as u64);
+                                self.state
+                                    .history
+                                    .record(&step.statement, result.exec_ms, rows);
+                            }
+                        }
+                    }
+                    // A table made or dropped in the SQL editor shows up in the list
+                    if steps.iter().any(|step| {
+                        matches!(step.outcome, Some(Ok(_))) && changes_schema(&step.statement)
+                    }) {
+                        self.load_tables();
+                    }
+                    self.state.script_steps = steps;
+                    self.restore_saved_cursor();
+                }
+                // Progress that arrives once the query has ended has nothing to update
+                WorkerResponse::QueryProgress { elapsed_ms } => {
+                    if self.state.query_loading {
+                        self.state.query_elapsed_ms = Some(elapsed_ms);
+                    }
+                    if let Some(IntegrityCheckView::Running { elapsed_ms: shown }) =
+                        &mut self.state.integrity_check
+                    {
+                        *shown = elapsed_ms;
+                    }
+                    if let Some(name) = &self.state.creating_index {
+                        self.state.status_message = Some(format!(
+                            "Creating index {}... {:.1}s (Esc: cancel)",
+                            name,
+                            elapsed_ms as f64 / 1000.0
+                        ));
+                    }
+                }
+                WorkerResponse::QueryCancelled {
+                    elapsed_ms,
+                    completed,
+                    total,
+                } => {
+                    self.state.query_loading = false;
+                    self.record_history = false;
+                    self.state.script_steps.clear();
+                    let mut message =
+                        format!("Query cancelled after {:.1}s", elapsed_ms as f64 / 1000.0);
+                    // Statements that finished before the interrupt stay done
+                    if completed > 0 {
+                        message.push_str(&format!(
+                            " ({} of {} statements had finished)",
+                            completed, total
+                        ));
+                    }
+                    self.state.status_message = Some(message.clone());
+                    self.state.query_cancelled = Some(message);
+                    self.state.query_result = None;
                 }
-                WorkerResponse::QueryExecuted { result } => {
+                WorkerResponse::QueryPageLoaded { result } => {
+                    self.state.query_loading = false;
                     self.state.query_result = Some(result);
-                    self.state.query_error = None;
+                    self.state.note_query_page();
+                    self.state.clamp_query_selection();
+                    self.restore_saved_cursor();
+                }
+                WorkerResponse::ParametersInspected { statement, names } => {
+                    if names.is_empty() {
+                        self.confirm_and_run(vec![statement], Vec::new());
+                    } else {
+                        // Start from the values last entered under the same names
+                        let fields = names
+                            .into_iter()
+                            .map(|name| match self.state.param_memory.get(&name) {
+                                Some(field) => field.clone(),
+                                None => ParamField {
+                                    name,
+                                    ..Default::default()
+                                },
+                            })
+                            .collect();
+                        self.state.param_form = Some(ParamForm {
+                            statement,
+                            fields,
+                            selected: 0,
+                            error: None,
+                        });
+                    }
+                }
+                WorkerResponse::QueryPlanLoaded { statement, steps } => {
                     self.state.query_loading = false;
-                    self.state.view_mode = ViewMode::Query;
+                    self.state.query_plan = Some(QueryPlanView {
+                        statement,
+                        steps,
+                        scroll: 0,
+                    });
                 }
                 WorkerResponse::TableInfoLoaded { info } => {
                     self.state.table_info = Some(info);
                 }
+                WorkerResponse::TableForeignKeysLoaded {
+                    table_name,
+                    foreign_keys,
+                } => {
+                    if self.state.current_table.as_deref() == Some(table_name.as_str()) {
+                        self.state.table_foreign_keys = foreign_keys;
+                    }
+                }
                 WorkerResponse::SchemaLoaded {
                     columns,
                     indexes,
                     foreign_keys,
+                    referenced_by,
                 } => {
                     self.state.schema_columns = columns;
                     self.state.schema_indexes = indexes;
                     self.state.schema_foreign_keys = foreign_keys;
+                    self.state.schema_referenced_by = referenced_by;
+                    self.state.schema_selected = self
+                        .state
+                        .schema_selected
+                        .min(self.state.schema_entries().saturating_sub(1));
                     self.state.schema_loading = false;
+                    self.state.schema_error = None;
                 }
                 WorkerResponse::DiagramLoaded { data } => {
-                    self.state.diagram_data = Some(data);
+                    // Answered from the worker's cache, it's the same diagram again
+                    if self.state.diagram_data.as_ref() != Some(&data) {
+                        self.state.set_diagram_data(data);
+                    }
                     self.state.diagram_loading = false;
+                    self.state.schema_error = None;
                 }
                 WorkerResponse::CellUpdated => {
-                    // Cell was successfully updated, reload table and exit edit mode
-                    if let Some(table_name) = &self.state.current_table {
-                        self.load_table(table_name.clone());
+                    // Cell was successfully updated; reload the grid and come back to the cell
+                    self.state.restore_cursor = self.state.edited_cell();
+                    self.reload_grid();
+                    self.state.exit_edit_mode();
+                }
+                WorkerResponse::EditsApplied { count } => {
+                    // Pending edits and multi-row edits both land here
+                    self.state.pending_edits.clear();
+                    self.state.exit_edit_mode();
+                    self.state.status_message = Some(format!("Wrote {} edit(s)", count));
+                    self.reload_grid();
+                }
+                WorkerResponse::AffectedRowsCounted { count } => {
+                    if let Some(pending) = self.state.pending_confirm.as_mut() {
+                        pending.affected_rows = count;
+                        pending.counting = false;
                     }
-                    self.state.edit_mode = false;
-                    self.state.editing_row = None;
-                    self.state.editing_col = None;
-                    self.state.edit_buffer.clear();
-                    self.state.edit_cursor_pos = 0;
-                    self.state.full_edit_mode = false;
                 }
-                WorkerResponse::Error { message } => {
-                    // Set error based on what was loading
-                    if self.state.query_loading {
-                        self.state.query_error = Some(message);
-                        self.state.query_loading = false;
-                    } else if self.state.rows_loading {
-                        self.state.query_error = Some(message);
-                        self.state.rows_loading = false;
-                    } else if self.state.tables_loading {
-                        self.state.query_error = Some(message);
-                        self.state.tables_loading = false;
-                    } else if self.state.schema_loading {
-                        self.state.query_error = Some(message);
-                        self.state.schema_loading = false;
-                    } else if self.state.diagram_loading {
-                        self.state.query_error = Some(message);
-                        self.state.diagram_loading = false;
-                    } else if self.state.edit_mode {
-                        // Show error in edit mode
-                        self.state.query_error = Some(message);
-                        // Don't exit edit mode on error, let user try again
-                        // Clear the error after a delay or when user starts editing again
+                WorkerResponse::ColumnReplaced { count } => {
+                    self.state.replace_running = false;
+                    self.state.status_message = Some(format!("Replaced in {} row(s)", count));
+                    if let Some(table_name) = self.state.current_table.clone() {
+                        self.load_table(table_name);
+                    }
+                }
+                WorkerResponse::Imported {
+                    table_name,
+                    inserted,
+                } => {
+                    self.state.status_message =
+                        Some(format!("Imported {} rows into {}", inserted, table_name));
+                    if self.state.current_table.as_deref() == Some(table_name.as_str()) {
+                        self.load_table(table_name);
+                    }
+                }
+                WorkerResponse::TableAltered {
+                    table_name,
+                    message,
+                } => {
+                    self.state.creating_index = None;
+                    self.state.status_message = Some(message);
+                    // The Tables pane, completion and diagram all know the old columns
+                    self.load_tables();
+                    self.load_schema(table_name.clone());
+                    if self.state.current_table.as_deref() == Some(table_name.as_str()) {
+                        // A sort on a renamed or dropped column would fail
+                        self.state.sort_column = None;
+                        self.load_table(table_name);
+                    }
+                }
+                WorkerResponse::IndexCancelled { elapsed_ms } => {
+                    self.state.creating_index = None;
+                    self.state.status_message = Some(format!(
+                        "Index creation cancelled after {:.1}s",
+                        elapsed_ms as f64 / 1000.0
+                    ));
+                }
+                WorkerResponse::ReopenedReadWrite => {
+                    self.state.read_only = false;
+                    // data_version is per connection, so start watching afresh
+                    self.data_version = None;
+                    self.state.status_message = Some("Reopened read-write".to_string());
+                }
+                WorkerResponse::Exported {
+                    path,
+                    bytes,
+                    rows,
+                    unit,
+                    warnings,
+                } => {
+                    self.state.exporting = None;
+                    let mut message = format!(
+                        "Exported {} {} ({} bytes) to {}",
+                        rows,
+                        unit,
+                        bytes,
+                        path.display()
+                    );
+                    for warning in warnings {
+                        message.push_str("; ");
+                        message.push_str(&warning);
+                    }
+                    self.state.status_message = Some(message);
+                }
+                WorkerResponse::ExportProgress { rows, bytes } => {
+                    if let Some(path) = &self.state.exporting {
+                        self.state.status_message = Some(format!(
+                            "Exporting to {}... {} (Esc: cancel)",
+                            path.display(),
+                            ui::export_progress_text(ExportProgress { rows, bytes })
+                        ));
+                    }
+                }
+                WorkerResponse::ExportFailed { message } => {
+                    self.state.exporting = None;
+                    self.state.status_message = Some(message);
+                }
+                WorkerResponse::BackupProgress { remaining, total } => {
+                    if let Some(path) = &self.state.backing_up {
+                        self.state.status_message = Some(format!(
+                            "Backing up to {} {} (Esc: cancel)",
+                            path.display(),
+                            ui::progress_bar((total - remaining).into(), total.into())
+                        ));
+                    }
+                }
+                WorkerResponse::BackedUp { path, pages } => {
+                    self.state.backing_up = None;
+                    self.state.status_message = Some(format!(
+                        "Backed up {} pages to {}",
+                        ui::group_digits(pages.into()),
+                        path.display()
+                    ));
+                }
+                WorkerResponse::BackupFailed { message } => {
+                    self.state.backing_up = None;
+                    self.state.status_message = Some(message);
+                }
+                WorkerResponse::DatabaseAttached { path, alias } => {
+                    self.state.status_message = Some(format!("Attached {} as {}", path, alias));
+                    self.state.attached.push((path, alias));
+                    self.load_tables();
+                }
+                WorkerResponse::DatabaseDetached { alias } => {
+                    // The open table may have gone with it
+                    let prefix = format!("{}.", alias);
+                    if self
+                        .state
+                        .current_table
+                        .as_ref()
+                        .is_some_and(|table| table.starts_with(&prefix))
+                    {
+                        self.state.current_table = None;
+                        self.state.table_rows = None;
+                        self.state.rows_error = None;
+                    }
+                    self.state
+                        .attached
+                        .retain(|(_, attached)| *attached != alias);
+                    self.state.status_message = Some(format!("Detached {}", alias));
+                    self.load_tables();
+                }
+                WorkerResponse::AttachFailed { message } => {
+                    self.state.status_message = Some(message.clone());
+                    self.state.record_error(ErrorContext::Tables, message);
+                }
+                WorkerResponse::ExtensionsListed { list } => {
+                    self.state.extensions_view = Some(ExtensionsView {
+                        lines: list.lines(&self.state.extensions),
+                        scroll: 0,
+                    });
+                }
+                WorkerResponse::PragmasRead { settings, message } => {
+                    // Snapshots only stay safe while the journal mode is WAL
+                    if !settings.journal_mode.eq_ignore_ascii_case("wal") {
+                        self.state.snapshot_mode = false;
+                        self.end_snapshot();
+                    }
+                    self.state.journal_mode = Some(settings.journal_mode.clone());
+                    if let Some(view) = self.state.pragma_view.as_mut() {
+                        view.settings = Some(settings);
+                        view.message = message;
+                    }
+                }
+                WorkerResponse::PragmaFailed { message } => {
+                    match self.state.pragma_view.as_mut() {
+                        Some(view) => view.message = Some(message.clone()),
+                        None => self.state.status_message = Some(message.clone()),
+                    }
+                    self.state.record_error(ErrorContext::Schema, message);
+                }
+                WorkerResponse::IntegrityChecked { report } => {
+                    if self.state.integrity_check.is_some() {
+                        self.state.integrity_check =
+                            Some(IntegrityCheckView::Done { report, scroll: 0 });
+                    }
+                }
+                WorkerResponse::IntegrityCheckCancelled { elapsed_ms } => {
+                    self.state.integrity_check = None;
+                    self.state.status_message = Some(format!(
+                        "Integrity check cancelled after {:.1}s",
+                        elapsed_ms as f64 / 1000.0
+                    ));
+                }
+                WorkerResponse::IntegrityCheckFailed { message } => {
+                    self.state.integrity_check = None;
+                    self.state.status_message = Some(message);
+                }
+                WorkerResponse::ReopenFailed { message } => {
+                    self.state.status_message = Some(message);
+                }
+                WorkerResponse::SnapshotTaken => {}
+                WorkerResponse::SnapshotReleased => self.state.snapshot = None,
+                WorkerResponse::SnapshotFailed { message } => {
+                    self.state.snapshot = None;
+                    self.state.snapshot_mode = false;
+                    self.state.status_message =
+                        Some(format!("Snapshot browsing off - {}", message));
+                }
+                WorkerResponse::DataVersion { version } => {
+                    if self.data_version.is_some_and(|v| v != version) {
+                        self.refresh_pending = true;
+                    }
+                    self.data_version = Some(version);
+                }
+                WorkerResponse::Error {
+                    context: ErrorContext::Rows,
+                    message,
+                } if request_id == self.preview_request => {
+                    // A filter tried while typing; Enter reports its error as usual
+                    self.state.rows_loading = false;
+                    self.state.rows_error = Some(message);
+                }
+                WorkerResponse::Error { context, message } => self.show_error(context, message),
+                WorkerResponse::Busy { attempt, waited_ms } => {
+                    self.state.busy = Some((attempt, waited_ms));
+                    self.busy_request = request_id;
+                }
+                WorkerResponse::Locked { context, message } => {
+                    // Edit mode stays open, where Enter saves again
+                    let retry = if self.state.edit_mode {
+                        None
                     } else {
-                        // Generic error - show it
-                        self.state.query_error = Some(message);
+                        self.last_write.take()
+                    };
+                    self.show_error(context, message);
+                    if retry.is_some() {
+                        self.state.status_message = Some(
+                            "Database is locked by another connection - Ctrl+R: retry".to_string(),
+                        );
                     }
+                    self.locked_write = retry;
                 }
             }
         }
         Ok(())
     }
 
+    /// A worker thread died, so nothing in flight will be answered: stop waiting on it
+    /// and offer a restart
+    fn worker_crashed(&mut self) {
+        self.state.worker_crashed = true;
+        self.state.tables_loading = false;
+        self.state.rows_loading = false;
+        self.state.query_loading = false;
+        self.state.schema_loading = false;
+        self.state.diagram_loading = false;
+        self.state.replace_running = false;
+        self.state.creating_index = None;
+        self.state.exporting = None;
+        self.state.backing_up = None;
+        self.state.busy = None;
+        self.state.snapshot = None;
+        self.forget_prefetched();
+        self.state.record_error(
+            ErrorContext::Tables,
+            "The database worker crashed".to_string(),
+        );
+    }
+
+    /// Start the worker again on a new connection to the database file, then load the
+    /// table list and open table again
+    fn restart_worker(&mut self) {
+        let restarted = self.worker.restart(
+            self.state.read_only,
+            self.state.key.as_deref(),
+            &self.state.extensions,
+            self.state.allow_extensions,
+            &self.state.attached,
+        );
+        match restarted {
+            Ok(()) => {
+                self.state.worker_crashed = false;
+                self.state.status_message = Some("Database worker restarted".to_string());
+                self.load_tables();
+                if let Some(table_name) = self.state.current_table.clone() {
+                    self.load_table(table_name);
+                }
+            }
+            Err(e) => {
+                self.state.status_message = Some(format!("Couldn't restart the worker: {:#}", e));
+            }
+        }
+    }
+
+    /// Put a failed request's error where its part of the UI shows it, and in the log
+    fn show_error(&mut self, context: ErrorContext, message: String) {
+        match context {
+            ErrorContext::Tables => self.state.tables_loading = false,
+            ErrorContext::Rows => {
+                // Drop a sort that may reference a column that no longer exists,
+                // so re-selecting the table recovers
+                self.state.sort_column = None;
+                self.state.rows_loading = false;
+            }
+            ErrorContext::Query => self.state.query_loading = false,
+            ErrorContext::Schema => {
+                self.state.schema_loading = false;
+                self.state.creating_index = None;
+            }
+            ErrorContext::Diagram => self.state.diagram_loading = false,
+            // Edit mode stays open so the save can be retried; a failed replace or
+            // write of pending edits is reported without hiding the grid
+            ErrorContext::Edit if !self.state.edit_mode => {
+                self.state.replace_running = false;
+                self.state.status_message = Some(message.clone());
+            }
+            ErrorContext::Edit => {}
+        }
+        self.state.record_error(context, message);
+    }
+
+    /// Send a write, keeping a copy to send again if another connection's lock stops it
+    fn send_write(&mut self, message: WorkerMessage) -> anyhow::Result<u64> {
+        self.forget_prefetched();
+        self.end_snapshot();
+        self.last_write = Some(message.clone());
+        self.worker.send(message)
+    }
+
+    /// Send again a write the database was locked for, showing it running as before
+    fn retry_write(&mut self, message: WorkerMessage) {
+        match &message {
+            WorkerMessage::ReplaceInColumn { .. } => self.state.replace_running = true,
+            WorkerMessage::CreateIndex { index, .. } => {
+                self.state.creating_index = Some(index.name.clone());
+            }
+            _ => {}
+        }
+        self.state.status_message = Some("Retrying...".to_string());
+        if let Err(e) = self.send_write(message) {
+            self.state.status_message = Some(format!("Failed to send retry: {}", e));
+        }
+    }
+
     /// Handle a key event
     pub fn handle_key_event(&mut self, event: KeyEvent) -> Result<(), io::Error> {
+        self.last_input = Instant::now();
+        // An open prompt captures all input
+        if self.state.prompt.is_some() {
+            self.handle_prompt_key(event);
+            return Ok(());
+        }
+        // And a destructive query waiting for confirmation
+        if self.state.pending_confirm.is_some() {
+            self.handle_confirm_key(event);
+            return Ok(());
+        }
+        // So do the help, BLOB viewer and query plan
+        if self.state.show_help {
+            self.handle_help_key(event);
+            return Ok(());
+        }
+        if self.state.blob_viewer.is_some() {
+            self.handle_blob_viewer_key(event);
+            return Ok(());
+        }
+        if self.state.query_plan.is_some() {
+            self.handle_query_plan_key(event);
+            return Ok(());
+        }
+        if self.state.error_log_popup.is_some() {
+            self.handle_error_log_key(event);
+            return Ok(());
+        }
+        if self.state.integrity_check.is_some() {
+            self.handle_integrity_check_key(event);
+            return Ok(());
+        }
+        if self.state.extensions_view.is_some() {
+            self.handle_extensions_key(event);
+            return Ok(());
+        }
+        if self.state.pragma_view.is_some() {
+            self.handle_pragma_key(event);
+            return Ok(());
+        }
+        // And the history search and saved query picker
+        if self.state.history_popup.is_some() {
+            self.handle_history_popup_key(event);
+            return Ok(());
+        }
+        if self.state.saved_query_picker.is_some() {
+            self.handle_saved_query_picker_key(event);
+            return Ok(());
+        }
+        // And the form for a query's parameter values
+        if self.state.param_form.is_some() {
+            self.handle_param_form_key(event);
+            return Ok(());
+        }
+        if self.state.column_form.is_some() {
+            self.handle_column_form_key(event);
+            return Ok(());
+        }
+        if self.state.index_form.is_some() {
+            self.handle_index_form_key(event);
+            return Ok(());
+        }
+        if self.state.sql_template_menu.is_some() {
+            self.handle_sql_template_key(event);
+            return Ok(());
+        }
+        // The completion popup takes its keys, while typing carries on and narrows it
+        if self.state.completion.is_some() && self.handle_completion_key(event) {
+            return Ok(());
+        }
+
+        // Status messages only last until the next key press
+        self.state.status_message = None;
+        // So does the offer to retry a write the database was locked for
+        if let Some(write) = self.locked_write.take() {
+            if event.code == KeyCode::Char('r') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                self.retry_write(write);
+                return Ok(());
+            }
+        }
+
         // Check if SQL editor is active and should capture input
-        let sql_editor_active = self.state.show_sql_editor && self.state.focus == Focus::Content;
+        let sql_editor_active = self.state.sql_editor_focused();
         // Check if full editor is active - it should capture all input
         let full_editor_active = self.state.full_edit_mode;
 
@@ -121,9 +960,48 @@ impl App {
             {
                 self.should_quit = true;
             }
+            KeyCode::Char('R')
+                if self.state.worker_crashed && !sql_editor_active && !full_editor_active =>
+            {
+                self.restart_worker();
+            }
+            KeyCode::Esc if self.state.query_loading => {
+                self.cancel_query();
+            }
+            KeyCode::Char('c')
+                if event.modifiers.contains(KeyModifiers::CONTROL) && self.state.query_loading =>
+            {
+                self.cancel_query();
+            }
+            KeyCode::Esc if self.state.exporting.is_some() => {
+                self.worker.cancel_copy();
+                self.state.status_message = Some("Cancelling export...".to_string());
+            }
+            KeyCode::Esc if self.state.backing_up.is_some() => {
+                self.worker.cancel_copy();
+                self.state.status_message = Some("Cancelling backup...".to_string());
+            }
+            KeyCode::Esc if self.state.creating_index.is_some() => {
+                self.worker.interrupt();
+                self.state.status_message = Some("Cancelling index creation...".to_string());
+            }
             KeyCode::Tab => {
+                // In the SQL editor, Tab after a word completes it; otherwise it moves panes
+                let completing = sql_editor_active
+                    && !event.modifiers.contains(KeyModifiers::SHIFT)
+                    && self.open_completion();
+                // Asked for, so columns it needs are loaded without waiting
+                if let Some(table_names) = self.column_lookup.flush() {
+                    self.load_column_names(table_names);
+                }
+                // In the diagram, Tab goes through the tables before moving on
+                let selecting = !sql_editor_active
+                    && self.state.diagram_focused()
+                    && self
+                        .state
+                        .cycle_diagram_selection(!event.modifiers.contains(KeyModifiers::SHIFT));
                 // Don't allow tab navigation when full editor is active
-                if !full_editor_active {
+                if !full_editor_active && !completing && !selecting {
                     if event.modifiers.contains(KeyModifiers::SHIFT) {
                         self.state.prev_pane();
                     } else {
@@ -131,30 +1009,193 @@ impl App {
                     }
                 }
             }
+            KeyCode::Char('p' | 'n')
+                if event.modifiers.contains(KeyModifiers::CONTROL) && sql_editor_active =>
+            {
+                self.recall_history(event.code == KeyCode::Char('p'));
+            }
+            KeyCode::Up | KeyCode::Down
+                if event.modifiers.is_empty()
+                    && sql_editor_active
+                    && self.state.browsing_history() =>
+            {
+                self.recall_history(event.code == KeyCode::Up);
+            }
+            KeyCode::Char('r')
+                if event.modifiers.contains(KeyModifiers::CONTROL) && sql_editor_active =>
+            {
+                self.state.history_popup = Some(HistoryPopup::default());
+            }
+            KeyCode::Char('r')
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
+            {
+                self.rerun_last_query();
+            }
+            KeyCode::Char('r')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.view_mode == ViewMode::Query =>
+            {
+                self.rerun_last_query();
+            }
+            KeyCode::Char('q')
+                if event.modifiers.contains(KeyModifiers::ALT) && sql_editor_active =>
+            {
+                if self.state.sql_buffer().query.trim().is_empty() {
+                    self.state.status_message = Some("Nothing to save".to_string());
+                } else {
+                    self.state.prompt = Some(Prompt::new(PromptKind::SaveQuery, String::new()));
+                }
+            }
+            KeyCode::Char('q')
+                if event.modifiers.contains(KeyModifiers::CONTROL) && sql_editor_active =>
+            {
+                self.open_saved_query_picker();
+            }
+            KeyCode::Char('x')
+                if event.modifiers.contains(KeyModifiers::CONTROL) && sql_editor_active =>
+            {
+                self.explain_query();
+            }
+            KeyCode::Char('t')
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && self.state.show_sql_editor
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
+            {
+                if !self.sql_buffers_busy() && !self.state.new_sql_buffer() {
+                    self.state.status_message =
+                        Some(format!("At most {} SQL buffers", MAX_SQL_BUFFERS));
+                }
+            }
+            KeyCode::Char(digit @ '1'..='9')
+                if event.modifiers.contains(KeyModifiers::ALT)
+                    && self.state.show_sql_editor
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
+            {
+                let index = digit as usize - '1' as usize;
+                if index < self.state.sql_buffers.len() && !self.sql_buffers_busy() {
+                    self.state.switch_sql_buffer(index);
+                }
+            }
+            KeyCode::Char('w')
+                if event.modifiers.contains(KeyModifiers::ALT)
+                    && self.state.show_sql_editor
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
+            {
+                if !self.sql_buffers_busy() {
+                    self.state.close_sql_buffer();
+                }
+            }
+            KeyCode::Char('o')
+                if event.modifiers.contains(KeyModifiers::CONTROL) && sql_editor_active =>
+            {
+                // Start from the directory of the last file used
+                let initial = self
+                    .state
+                    .sql_file
+                    .as_ref()
+                    .and_then(|path| path.parent())
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .map(|dir| format!("{}/", dir.display()))
+                    .unwrap_or_default();
+                self.state.prompt = Some(Prompt::new(PromptKind::OpenFile, initial));
+            }
+            KeyCode::Char('s')
+                if event.modifiers.contains(KeyModifiers::CONTROL) && sql_editor_active =>
+            {
+                let initial = self
+                    .state
+                    .sql_file
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default();
+                self.state.prompt = Some(Prompt::new(PromptKind::SaveFile, initial));
+            }
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Char('h' | 'j' | 'k' | 'l')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && self.state.diagram_focused() =>
+            {
+                let (dx, dy) = match event.code {
+                    KeyCode::Left | KeyCode::Char('h') => (-1, 0),
+                    KeyCode::Right | KeyCode::Char('l') => (1, 0),
+                    KeyCode::Up | KeyCode::Char('k') => (0, -1),
+                    _ => (0, 1),
+                };
+                // With a table selected the keys move between tables, the view following
+                if self.state.diagram_selected.is_some() {
+                    self.state.move_diagram_selection(dx, dy);
+                } else {
+                    let (x, y) = DIAGRAM_PAN;
+                    self.state.pan_diagram(dx * x, dy * y);
+                }
+            }
+            KeyCode::Esc
+                if self.state.diagram_focused()
+                    && self.state.diagram_selected.is_some()
+                    && !sql_editor_active =>
+            {
+                self.state.diagram_selected = None;
+            }
+            KeyCode::Home if self.state.diagram_focused() && !sql_editor_active => {
+                self.state.diagram_offset = (0, 0);
+            }
+            KeyCode::Up | KeyCode::Down
+                if event.modifiers.contains(KeyModifiers::SHIFT)
+                    && !sql_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                // Shift+Up/Down grows the row selection from where it started
+                self.state.row_anchor.get_or_insert(self.state.selected_row);
+                if event.code == KeyCode::Up {
+                    self.state.select_prev_row(1);
+                } else {
+                    self.state.select_next_row(1);
+                }
+            }
+            KeyCode::Char('p')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                self.paste_from_clipboard();
+            }
+            KeyCode::Char('V')
+                if !sql_editor_active && !full_editor_active && self.state.rows_view_focused() =>
+            {
+                // Visual mode: start or drop a selection at the cursor row
+                self.state.row_anchor = match self.state.row_anchor {
+                    Some(_) => None,
+                    None => Some(self.state.selected_row),
+                };
+            }
             KeyCode::Up => {
                 // In full editor mode, Up is handled in the _ => branch for line navigation
                 if !full_editor_active {
                     if self.state.edit_mode && !self.state.full_edit_mode {
-                        if let Some(row) = self.state.editing_row {
-                            if row > 0 {
-                                self.state.editing_row = Some(row - 1);
-                                if let Some(result) = &self.state.table_rows {
-                                    if let Some(col) = self.state.editing_col {
-                                        if let Some(row_data) = result.rows.get(row - 1) {
-                                            if let Some(val) = row_data.get(col) {
-                                                let full_value = val.display(10000);
-                                                self.state.edit_buffer = full_value.clone();
-                                                self.state.edit_cursor_pos = full_value.len();
-                                                self.state.full_edit_mode = full_value.len() > 50
-                                                    || full_value.contains('\n');
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                        self.move_edit_cell(-1, 0);
                     } else if self.state.focus == Focus::Tables {
                         self.state.move_up();
+                    } else if self.state.focus == Focus::Content
+                        && self.state.view_mode == ViewMode::Schema
+                    {
+                        self.state.schema_selected = self.state.schema_selected.saturating_sub(1);
+                    } else if self.state.rows_view_focused() {
+                        self.state.select_prev_row(1);
+                    } else if self.state.query_grid_active() {
+                        self.state.move_query_row(1, false);
                     }
                 }
             }
@@ -162,59 +1203,110 @@ impl App {
                 // In full editor mode, Down is handled in the _ => branch for line navigation
                 if !full_editor_active {
                     if self.state.edit_mode && !self.state.full_edit_mode {
-                        if let Some(row) = self.state.editing_row {
-                            if let Some(result) = &self.state.table_rows {
-                                if row < result.rows.len().saturating_sub(1) {
-                                    self.state.editing_row = Some(row + 1);
-                                    if let Some(col) = self.state.editing_col {
-                                        if let Some(row_data) = result.rows.get(row + 1) {
-                                            if let Some(val) = row_data.get(col) {
-                                                let full_value = val.display(10000);
-                                                self.state.edit_buffer = full_value.clone();
-                                                self.state.edit_cursor_pos = full_value.len();
-                                                self.state.full_edit_mode = full_value.len() > 50
-                                                    || full_value.contains('\n');
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                        self.move_edit_cell(1, 0);
                     } else if self.state.focus == Focus::Tables {
                         self.state.move_down();
+                    } else if self.state.focus == Focus::Content
+                        && self.state.view_mode == ViewMode::Schema
+                    {
+                        self.state.schema_selected = (self.state.schema_selected + 1)
+                            .min(self.state.schema_entries().saturating_sub(1));
+                    } else if self.state.rows_view_focused() {
+                        self.state.select_next_row(1);
+                    } else if self.state.query_grid_active() {
+                        self.state.move_query_row(1, true);
                     }
                 }
             }
+            KeyCode::PageUp | KeyCode::PageDown if self.state.query_grid_active() => {
+                self.state
+                    .move_query_row(ROW_JUMP, event.code == KeyCode::PageDown);
+            }
+            KeyCode::Char('g')
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content
+                    && self.state.view_mode == ViewMode::Query
+                    && self.state.query_result.is_some() =>
+            {
+                // Switch keys between the SQL editor and the results grid
+                self.state.query_grid_focused = !self.state.query_grid_focused;
+            }
+            KeyCode::PageUp if self.state.rows_view_focused() => {
+                self.state.select_prev_row(ROW_JUMP);
+            }
+            KeyCode::PageDown if self.state.rows_view_focused() => {
+                self.state.select_next_row(ROW_JUMP);
+            }
+            KeyCode::Home if self.state.rows_view_focused() && !sql_editor_active => {
+                self.state.selected_row = 0;
+            }
+            KeyCode::End if self.state.rows_view_focused() && !sql_editor_active => {
+                self.state.select_last_row();
+            }
             KeyCode::Enter => {
                 if self.state.full_edit_mode {
                     // In full editor panel, Enter saves (matching SQL editor behavior)
                     // Shift+Enter inserts newline for multi-line text
                     if event.modifiers.contains(KeyModifiers::SHIFT) {
                         // Shift+Enter inserts newline at cursor
-                        let pos = self.state.edit_cursor_pos.min(self.state.edit_buffer.len());
-                        self.state.edit_buffer.insert(pos, '\n');
-                        self.state.edit_cursor_pos = pos + 1;
+                        self.state.edit_null = false;
+                        insert_char(
+                            &mut self.state.edit_buffer,
+                            &mut self.state.edit_cursor_pos,
+                            '\n',
+                        );
                     } else {
-                        // Regular Enter saves
-                        self.save_edited_cell();
+                        // Regular Enter saves; Ctrl+Enter (or Alt+Enter, which more
+                        // terminals pass through) skips validation
+                        self.save_edited_cell(
+                            event
+                                .modifiers
+                                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT),
+                        );
                     }
                 } else if self.state.edit_mode {
-                    // Inline edit mode - Enter saves
-                    self.save_edited_cell();
-                } else if self.state.show_sql_editor && self.state.focus == Focus::Content {
+                    // Inline edit mode - Enter saves; Ctrl/Alt+Enter skips validation
+                    self.save_edited_cell(
+                        event
+                            .modifiers
+                            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT),
+                    );
+                } else if self.state.sql_editor_focused() {
                     // In SQL editor, Enter executes query
                     // Shift+Enter inserts newline for multi-line queries
                     if event.modifiers.contains(KeyModifiers::SHIFT) {
                         // Shift+Enter inserts newline at cursor
-                        let pos = self.state.sql_cursor_pos.min(self.state.sql_query.len());
-                        self.state.sql_query.insert(pos, '\n');
-                        self.state.sql_cursor_pos = pos + 1;
+                        let buffer = self.state.sql_buffer_mut();
+                        insert_char(&mut buffer.query, &mut buffer.cursor_pos, '\n');
+                    } else {
+                        // Enter runs the statement under the cursor, Ctrl/Alt+Enter all of them
+                        self.execute_query(
+                            event
+                                .modifiers
+                                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT),
+                        );
+                    }
+                } else if self.state.query_grid_active() {
+                    if self.state.edit_table().is_some() {
+                        self.enter_edit_mode();
                     } else {
-                        // Regular Enter executes query
-                        self.execute_query();
+                        self.state.status_message = Some(
+                            "Read-only result: only single-table SELECTs can be edited".to_string(),
+                        );
                     }
                 } else if self.state.focus == Focus::Tables {
-                    if let Some(table_name) = self.state.selected_table() {
+                    if self.state.toggle_selected_section() {
+                        // Enter on a section header folds or unfolds it
+                    } else if let Some(trigger) = self
+                        .state
+                        .selected_table_info()
+                        .filter(|t| t.kind == TableKind::Trigger)
+                    {
+                        // A trigger has no rows or columns, just the SQL the Info pane shows
+                        self.state.table_info = Some(trigger.clone());
+                    } else if let Some(table_name) = self.state.selected_table() {
                         let table_name = table_name.to_string();
                         if self.state.view_mode == ViewMode::Schema {
                             self.load_schema(table_name);
@@ -222,24 +1314,28 @@ impl App {
                             self.load_table(table_name);
                         }
                     }
+                } else if self.state.focus == Focus::Content
+                    && self.state.view_mode == ViewMode::Schema
+                {
+                    if let Some(fk) = self.state.selected_schema_reference().cloned() {
+                        self.show_referencing_rows(fk);
+                    }
+                } else if self.state.diagram_focused() {
+                    self.open_diagram_table();
                 } else if self.state.focus == Focus::Content
                     && self.state.view_mode == ViewMode::Rows
                 {
-                    // Enter edit mode for selected cell
-                    self.enter_edit_mode();
+                    // BLOBs open in the hex viewer; anything else is edited
+                    if !self.state.open_blob_viewer() {
+                        self.enter_edit_mode();
+                    }
                 }
             }
             KeyCode::Char('d')
                 if event.modifiers.is_empty() && !sql_editor_active && !full_editor_active =>
             {
                 // Open diagram from anywhere
-                self.state.focus = Focus::Content;
-                self.state.view_mode = ViewMode::Diagram;
-                // Load diagram data if not already loaded
-                if self.state.diagram_data.is_none() && !self.state.diagram_loading {
-                    self.state.diagram_loading = true;
-                    let _ = self.worker.send(WorkerMessage::LoadDiagram);
-                }
+                self.open_diagram();
             }
             KeyCode::Char('s')
                 if event.modifiers.is_empty() && !sql_editor_active && !full_editor_active =>
@@ -253,13 +1349,7 @@ impl App {
                                 self.load_schema(table_name.clone());
                             }
                         }
-                        ViewMode::Diagram => {
-                            // Load diagram data
-                            if self.state.diagram_data.is_none() && !self.state.diagram_loading {
-                                self.state.diagram_loading = true;
-                                let _ = self.worker.send(WorkerMessage::LoadDiagram);
-                            }
-                        }
+                        ViewMode::Diagram => self.load_diagram(),
                         ViewMode::Rows => {
                             if let Some(table_name) = self.state.current_table.as_ref() {
                                 self.load_table(table_name.clone());
@@ -270,18 +1360,37 @@ impl App {
                 }
             }
             KeyCode::Char('/')
-                if event.modifiers.is_empty() && !sql_editor_active && !full_editor_active =>
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
             {
                 if self.state.focus == Focus::Tables {
                     self.state.table_filter.clear();
+                } else if self.state.rows_view_focused() && self.state.table_rows.is_some() {
+                    let initial = self.state.search_term.clone().unwrap_or_default();
+                    self.state.prompt = Some(Prompt::new(PromptKind::Search, initial));
+                } else if self.state.diagram_focused() && self.state.diagram_data.is_some() {
+                    let initial = self.state.diagram_filter.clone().unwrap_or_default();
+                    self.state.prompt = Some(Prompt::new(PromptKind::DiagramFilter, initial));
                 }
             }
+            KeyCode::Char('n') | KeyCode::Char('N')
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused()
+                    && self.state.search_term.is_some() =>
+            {
+                self.state.jump_to_match(event.code == KeyCode::Char('n'));
+            }
             KeyCode::Char('c')
                 if event.modifiers.contains(KeyModifiers::CONTROL) && sql_editor_active =>
             {
                 // Ctrl+C in SQL editor: Clear query results and reset to table view
                 self.state.query_result = None;
                 self.state.query_error = None;
+                self.state.query_cancelled = None;
                 if self.state.view_mode == ViewMode::Query {
                     self.state.view_mode = ViewMode::Rows;
                     // Reload current table if we have one
@@ -295,15 +1404,11 @@ impl App {
             {
                 if self.state.edit_mode {
                     // Exit edit mode
-                    self.state.edit_mode = false;
-                    self.state.editing_row = None;
-                    self.state.editing_col = None;
-                    self.state.edit_buffer.clear();
+                    self.state.exit_edit_mode();
                 } else {
                     self.state.show_sql_editor = !self.state.show_sql_editor;
                     if !self.state.show_sql_editor {
-                        self.state.sql_query.clear();
-                        self.state.sql_cursor_pos = 0;
+                        self.state.sql_buffer_mut().replace(String::new());
                         // Clear query results and reset view mode when closing SQL editor
                         self.state.query_result = None;
                         self.state.query_error = None;
@@ -315,14 +1420,458 @@ impl App {
                         }
                     } else {
                         self.state.focus = Focus::Content;
-                        self.state.sql_cursor_pos = self.state.sql_query.len();
+                        let buffer = self.state.sql_buffer_mut();
+                        buffer.cursor_pos = buffer.query.chars().count();
                     }
                 }
             }
             KeyCode::Char('?') if event.modifiers.is_empty() => {
-                self.state.show_help = !self.state.show_help;
+                self.state.show_help = true;
+                self.state.help_scroll = 0;
             }
-            KeyCode::Left => {
+            KeyCode::Left | KeyCode::Right
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && self.state.focus == Focus::Content
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
+            {
+                // Ctrl+Left/Right pages through the table, even with the SQL editor open
+                self.change_page(event.code == KeyCode::Right);
+            }
+            KeyCode::Char('w')
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
+            {
+                if self.state.stdin_copy {
+                    self.check_writable();
+                } else if self.state.read_only {
+                    self.state.pending_confirm = Some(PendingConfirm {
+                        action: ConfirmAction::ReopenReadWrite,
+                        affected_rows: None,
+                        counting: false,
+                    });
+                } else {
+                    self.state.status_message = Some("Already read-write".to_string());
+                }
+            }
+            KeyCode::Char('h')
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                // Find and replace within the selected column
+                if !self.check_rows_writable() {
+                    return Ok(());
+                }
+                let table = self.state.current_table.clone();
+                let column = self
+                    .state
+                    .table_rows
+                    .as_ref()
+                    .and_then(|r| r.columns.get(self.state.selected_col))
+                    .cloned();
+                if let (Some(table), Some(column)) = (table, column) {
+                    self.state.replace_draft = Some(ColumnReplace {
+                        table,
+                        column,
+                        ..Default::default()
+                    });
+                    self.state.prompt = Some(Prompt::new(PromptKind::ReplaceFind, String::new()));
+                }
+            }
+            KeyCode::Char('g')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.focus == Focus::Content
+                    && self.state.view_mode == ViewMode::Schema
+                    && !self.state.schema_columns.is_empty() =>
+            {
+                self.state.sql_template_menu = Some(0);
+            }
+            KeyCode::Char('n' | 'x')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.focus == Focus::Content
+                    && self.state.view_mode == ViewMode::Schema
+                    && (event.code == KeyCode::Char('n')
+                        || self.state.selected_schema_index().is_some()) =>
+            {
+                let action = if event.code == KeyCode::Char('n') {
+                    IndexAction::Create
+                } else {
+                    IndexAction::Drop
+                };
+                self.start_index_change(action);
+            }
+            KeyCode::Char('a' | 'r' | 'x')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.focus == Focus::Content
+                    && self.state.view_mode == ViewMode::Schema =>
+            {
+                let action = match event.code {
+                    KeyCode::Char('a') => ColumnAction::Add,
+                    KeyCode::Char('r') => ColumnAction::Rename,
+                    _ => ColumnAction::Drop,
+                };
+                self.start_column_change(action);
+            }
+            KeyCode::Char('x')
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.focus == Focus::Content
+                    && self.state.view_mode == ViewMode::Schema =>
+            {
+                // `x` drops a column in the Schema view, so its CREATE statements export here
+                self.start_export();
+            }
+            KeyCode::Char('x')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.start_export();
+            }
+            KeyCode::Char('y' | 'Y')
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content
+                    && matches!(self.state.view_mode, ViewMode::Rows | ViewMode::Query) =>
+            {
+                let format = if event.code == KeyCode::Char('Y') {
+                    ExportFormat::Markdown
+                } else {
+                    ExportFormat::Csv
+                };
+                self.copy_rows(format);
+            }
+            KeyCode::Char('i')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused()
+                    && self.state.current_table.is_some() =>
+            {
+                if !self.check_rows_writable() {
+                    return Ok(());
+                }
+                let name = format!(
+                    "{}.csv",
+                    self.state.current_table.as_deref().unwrap_or_default()
+                );
+                self.state.prompt = Some(Prompt::new(PromptKind::Import, name));
+            }
+            KeyCode::Char('\\')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.view_mode == ViewMode::Query =>
+            {
+                self.state.result_layout = self.state.result_layout.next();
+                self.state.status_message = Some(format!(
+                    "Query results: {} layout",
+                    self.state.result_layout.label()
+                ));
+            }
+            KeyCode::Char('S')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.view_mode == ViewMode::Query =>
+            {
+                self.state.show_column_summary = !self.state.show_column_summary;
+            }
+            KeyCode::Char('E')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                if self.state.error_log.is_empty() {
+                    self.state.status_message = Some("No errors this session".to_string());
+                } else {
+                    self.state.error_log_popup = Some(0);
+                }
+            }
+            KeyCode::Char('B')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                if self.state.backing_up.is_some() {
+                    self.state.status_message = Some("A backup is already running".to_string());
+                } else {
+                    self.state.prompt =
+                        Some(Prompt::new(PromptKind::Backup, "backup.db".to_string()));
+                }
+            }
+            KeyCode::Char('A')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.state.prompt = Some(Prompt::new(PromptKind::Attach, String::new()));
+            }
+            KeyCode::Char('D')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                match self.state.selected_schema() {
+                    Some(alias) => {
+                        let alias = alias.to_string();
+                        let _ = self.worker.send(WorkerMessage::DetachDatabase { alias });
+                    }
+                    None => {
+                        self.state.status_message =
+                            Some("Select a table of an attached database to detach it".to_string());
+                    }
+                }
+            }
+            KeyCode::Char('X')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                let _ = self.worker.send(WorkerMessage::ListExtensions);
+            }
+            KeyCode::Char('P')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.state.pragma_view = Some(PragmaView {
+                    settings: None,
+                    selected: 0,
+                    editing: None,
+                    message: None,
+                });
+                let _ = self.worker.send(WorkerMessage::ReadPragmas);
+            }
+            KeyCode::Char('Z')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.toggle_snapshot_mode();
+            }
+            KeyCode::Char('I')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.state.integrity_check = Some(IntegrityCheckView::Running { elapsed_ms: 0 });
+                if let Err(e) = self.worker.send(WorkerMessage::IntegrityCheck) {
+                    self.state.integrity_check = None;
+                    self.state.status_message =
+                        Some(format!("Failed to start the integrity check: {}", e));
+                }
+            }
+            KeyCode::Char('L')
+                if !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                let limit = self.state.query_limit.to_string();
+                self.state.prompt = Some(Prompt::new(PromptKind::QueryLimit, limit));
+            }
+            KeyCode::Char('h') | KeyCode::Char('l')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.change_page(event.code == KeyCode::Char('l'));
+            }
+            KeyCode::Char('f')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused()
+                    && self.state.current_table.is_some() =>
+            {
+                let initial = self.state.row_filter.clone().unwrap_or_default();
+                self.state.prompt = Some(Prompt::new(PromptKind::RowFilter, initial));
+            }
+            KeyCode::Char('=')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.state.auto_column_width = !self.state.auto_column_width;
+            }
+            KeyCode::Char('#')
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                self.state.row_gutter = self.state.row_gutter.next();
+            }
+            KeyCode::Char('w' | 'W')
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !self.state.pending_edits.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode =>
+            {
+                if event.code == KeyCode::Char('w') {
+                    self.write_pending_edits();
+                } else {
+                    let count = self.state.pending_edits.len();
+                    self.state.pending_edits.clear();
+                    self.state.status_message = Some(format!("Discarded {} edit(s)", count));
+                }
+            }
+            KeyCode::Char('w')
+                if event.modifiers.is_empty()
+                    && self.state.staged_edits
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                // With --staged, w writes edits, so watch mode comes from --watch instead
+                self.state.status_message = Some("No pending edits to write".to_string());
+            }
+            KeyCode::Char('w')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.toggle_watch();
+            }
+            KeyCode::Char('v')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                if !self.state.open_blob_viewer() {
+                    self.state.status_message = Some("Selected cell is not a BLOB".to_string());
+                }
+            }
+            KeyCode::Char('m')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                self.state.show_value_markers = !self.state.show_value_markers;
+            }
+            KeyCode::Char('W')
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                self.state.wrap_cells = !self.state.wrap_cells;
+            }
+            KeyCode::Char('[') | KeyCode::Char(']')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && !self.state.edit_mode
+                    && self.state.focus == Focus::Content =>
+            {
+                if event.code == KeyCode::Char(']') {
+                    self.state.pin_more_columns();
+                } else {
+                    self.state.pinned_cols = self.state.pinned_cols.saturating_sub(1);
+                }
+            }
+            KeyCode::Char('F')
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                self.follow_foreign_key();
+            }
+            KeyCode::Backspace
+                if !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused()
+                    && !self.state.nav_stack.is_empty() =>
+            {
+                self.navigate_back();
+            }
+            KeyCode::Char('o')
+                if event.modifiers.contains(KeyModifiers::CONTROL)
+                    && !full_editor_active
+                    && self.state.focus == Focus::Tables =>
+            {
+                // Order the table list by name, rows or size, keeping the selection. A
+                // chord, as plain letters type into the table filter.
+                let selected = self.state.selected_table().map(str::to_string);
+                self.state.table_sort = self.state.table_sort.next();
+                if let Some(selected) = selected {
+                    self.state.select_table_named(&selected);
+                }
+            }
+            KeyCode::Char('o')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.rows_view_focused() =>
+            {
+                if self.state.cycle_sort() {
+                    if let Some(table_name) = self.state.current_table.as_ref() {
+                        self.load_table(table_name.clone());
+                    }
+                }
+            }
+            KeyCode::Char('o')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.diagram_focused() =>
+            {
+                // Switch between keeping related tables together and a plain grid
+                self.state.diagram_layout_kind = self.state.diagram_layout_kind.next();
+                self.state.relayout_diagram();
+            }
+            KeyCode::Char('c' | 'n')
+                if event.modifiers.is_empty()
+                    && !sql_editor_active
+                    && !full_editor_active
+                    && self.state.diagram_focused() =>
+            {
+                let toggled = if event.code == KeyCode::Char('c') {
+                    self.state.toggle_diagram_collapsed()
+                } else {
+                    self.state.toggle_diagram_neighbours()
+                };
+                if !toggled {
+                    self.state.status_message = Some("Select a table first (Tab)".to_string());
+                }
+            }
+            KeyCode::Left => {
                 // In full editor or SQL editor mode, use text editor handler for character navigation
                 if full_editor_active {
                     if handle_text_editor_input(
@@ -333,36 +1882,24 @@ impl App {
                     ) {
                         return Ok(());
                     }
-                } else if self.state.show_sql_editor && self.state.focus == Focus::Content {
+                } else if self.state.sql_editor_focused() {
+                    let buffer = self.state.sql_buffer_mut();
                     if handle_text_editor_input(
                         event,
-                        &mut self.state.sql_query,
-                        &mut self.state.sql_cursor_pos,
+                        &mut buffer.query,
+                        &mut buffer.cursor_pos,
                         true,
                     ) {
                         return Ok(());
                     }
                 } else if self.state.edit_mode && !self.state.full_edit_mode {
-                    if let Some(col) = self.state.editing_col {
-                        if col > 0 {
-                            self.state.editing_col = Some(col - 1);
-                            if let Some(result) = &self.state.table_rows {
-                                if let Some(row) = self.state.editing_row {
-                                    if let Some(row_data) = result.rows.get(row) {
-                                        if let Some(val) = row_data.get(col - 1) {
-                                            self.state.edit_buffer = val.display(1000);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+                    self.move_edit_cell(0, -1);
                     return Ok(());
-                } else if self.state.focus == Focus::Content {
-                    self.state.prev_page();
-                    if let Some(table_name) = self.state.current_table.as_ref() {
-                        self.load_table(table_name.clone());
-                    }
+                } else if self.state.rows_view_focused() {
+                    self.state.select_prev_col();
+                    return Ok(());
+                } else if self.state.query_grid_active() {
+                    self.state.select_prev_query_col();
                     return Ok(());
                 }
             }
@@ -377,36 +1914,24 @@ impl App {
                     ) {
                         return Ok(());
                     }
-                } else if self.state.show_sql_editor && self.state.focus == Focus::Content {
+                } else if self.state.sql_editor_focused() {
+                    let buffer = self.state.sql_buffer_mut();
                     if handle_text_editor_input(
                         event,
-                        &mut self.state.sql_query,
-                        &mut self.state.sql_cursor_pos,
+                        &mut buffer.query,
+                        &mut buffer.cursor_pos,
                         true,
                     ) {
                         return Ok(());
                     }
                 } else if self.state.edit_mode && !self.state.full_edit_mode {
-                    if let Some(col) = self.state.editing_col {
-                        if let Some(result) = &self.state.table_rows {
-                            if col < result.columns.len().saturating_sub(1) {
-                                self.state.editing_col = Some(col + 1);
-                                if let Some(row) = self.state.editing_row {
-                                    if let Some(row_data) = result.rows.get(row) {
-                                        if let Some(val) = row_data.get(col + 1) {
-                                            self.state.edit_buffer = val.display(1000);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+                    self.move_edit_cell(0, 1);
                     return Ok(());
-                } else if self.state.focus == Focus::Content {
-                    self.state.next_page();
-                    if let Some(table_name) = self.state.current_table.as_ref() {
-                        self.load_table(table_name.clone());
-                    }
+                } else if self.state.rows_view_focused() {
+                    self.state.select_next_col();
+                    return Ok(());
+                } else if self.state.query_grid_active() {
+                    self.state.select_next_query_col();
                     return Ok(());
                 }
             }
@@ -416,18 +1941,16 @@ impl App {
                     self.state.full_edit_mode = false;
                 } else if self.state.edit_mode {
                     // Cancel edit mode completely
-                    self.state.edit_mode = false;
-                    self.state.editing_row = None;
-                    self.state.editing_col = None;
-                    self.state.edit_buffer.clear();
-                    self.state.edit_cursor_pos = 0;
-                    self.state.query_error = None;
-                } else if self.state.show_help {
-                    self.state.show_help = false;
+                    self.state.exit_edit_mode();
+                } else if self.state.row_anchor.is_some() && self.state.rows_view_focused() {
+                    self.state.row_anchor = None;
+                } else if self.state.search_term.is_some() {
+                    self.state.clear_search();
+                } else if self.state.query_grid_active() {
+                    self.state.query_grid_focused = false;
                 } else if self.state.show_sql_editor {
                     self.state.show_sql_editor = false;
-                    self.state.sql_query.clear();
-                    self.state.sql_cursor_pos = 0;
+                    self.state.sql_buffer_mut().replace(String::new());
                     self.state.query_result = None;
                     self.state.query_error = None;
                     if self.state.view_mode == ViewMode::Query {
@@ -444,8 +1967,18 @@ impl App {
                 // Handle text input
                 // Full editor panel captures input when active
                 if self.state.full_edit_mode {
+                    if let KeyCode::Char(c) = event.code {
+                        self.state.edit_error = None;
+                        if event.modifiers.contains(KeyModifiers::CONTROL) && c == 'n' {
+                            self.state.toggle_edit_null();
+                            return Ok(());
+                        }
+                    }
+                    // Typing text means the cell is no longer NULL
                     if let KeyCode::Char(_) = event.code {
-                        self.state.query_error = None;
+                        if !event.modifiers.contains(KeyModifiers::CONTROL) {
+                            self.state.edit_null = false;
+                        }
                     }
                     if handle_text_editor_input(
                         event,
@@ -456,59 +1989,45 @@ impl App {
                         return Ok(());
                     }
                 } else if self.state.edit_mode {
-                    let pos = self.state.edit_cursor_pos.min(self.state.edit_buffer.len());
-
                     match event.code {
-                        KeyCode::Char(c) => {
-                            self.state.query_error = None;
-
-                            if event.modifiers.contains(KeyModifiers::CONTROL) {
-                                if c == 'e' {
-                                    self.state.full_edit_mode = true;
-                                    self.state.focus = Focus::Content;
-                                    self.state.edit_cursor_pos = self.state.edit_buffer.len();
-                                }
-                            } else {
-                                self.state.edit_buffer.insert(pos, c);
-                                self.state.edit_cursor_pos = pos + 1;
-                            }
-                        }
-                        KeyCode::Backspace => {
-                            if pos > 0 {
-                                self.state.edit_buffer.remove(pos - 1);
-                                self.state.edit_cursor_pos = pos - 1;
+                        KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.state.edit_error = None;
+                            if c == 'e' {
+                                self.state.full_edit_mode = true;
+                                self.state.focus = Focus::Content;
+                                self.state.edit_cursor_pos = self.state.edit_buffer.chars().count();
+                            } else if c == 'n' {
+                                self.state.toggle_edit_null();
                             }
                         }
-                        KeyCode::Delete => {
-                            if pos < self.state.edit_buffer.len() {
-                                self.state.edit_buffer.remove(pos);
+                        KeyCode::Char(_)
+                        | KeyCode::Backspace
+                        | KeyCode::Delete
+                        | KeyCode::Left
+                        | KeyCode::Right
+                        | KeyCode::Home
+                        | KeyCode::End => {
+                            if let KeyCode::Char(_) = event.code {
+                                self.state.edit_error = None;
+                                self.state.edit_null = false;
                             }
-                        }
-                        KeyCode::Left => {
-                            if pos > 0 {
-                                self.state.edit_cursor_pos = pos - 1;
-                            }
-                        }
-                        KeyCode::Right => {
-                            if pos < self.state.edit_buffer.len() {
-                                self.state.edit_cursor_pos = pos + 1;
-                            }
-                        }
-                        KeyCode::Home => {
-                            self.state.edit_cursor_pos = 0;
-                        }
-                        KeyCode::End => {
-                            self.state.edit_cursor_pos = self.state.edit_buffer.len();
+                            handle_text_editor_input(
+                                event,
+                                &mut self.state.edit_buffer,
+                                &mut self.state.edit_cursor_pos,
+                                false,
+                            );
                         }
                         _ => {}
                     }
-                } else if self.state.show_sql_editor && self.state.focus == Focus::Content {
+                } else if self.state.sql_editor_focused() {
                     // SQL editor input (when content pane is focused)
                     // Use shared text editor handler with line navigation support
+                    let buffer = self.state.sql_buffer_mut();
                     if handle_text_editor_input(
                         event,
-                        &mut self.state.sql_query,
-                        &mut self.state.sql_cursor_pos,
+                        &mut buffer.query,
+                        &mut buffer.cursor_pos,
                         true, // supports_line_navigation
                     ) {
                         return Ok(());
@@ -530,120 +2049,2529 @@ impl App {
         Ok(())
     }
 
-    /// Load tables from database
-    pub fn load_tables(&mut self) {
-        self.state.tables_loading = true;
-        let _ = self.worker.send(WorkerMessage::LoadTables {
-            include_internal: self.state.show_internal_tables,
-        });
+    /// Handle a key while a prompt popup is open
+    fn handle_prompt_key(&mut self, event: KeyEvent) {
+        match event.code {
+            KeyCode::Esc => {
+                if let Some(prompt) = self.state.prompt.take() {
+                    if prompt.kind == PromptKind::Search {
+                        self.state.clear_search();
+                    }
+                    if prompt.kind == PromptKind::RowFilter {
+                        self.filter_preview.cancel();
+                        self.undo_filter_preview();
+                    }
+                    self.state.replace_draft = None;
+                }
+            }
+            KeyCode::Enter
+                if event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                    && self
+                        .state
+                        .prompt
+                        .as_ref()
+                        .is_some_and(|p| p.kind == PromptKind::OpenFile) =>
+            {
+                if let Some(prompt) = self.state.prompt.take() {
+                    self.open_sql_file(&prompt.buffer, true);
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(prompt) = self.state.prompt.take() {
+                    self.submit_prompt(prompt);
+                }
+            }
+            KeyCode::Tab if self.state.prompt.as_ref().is_some_and(|p| p.kind.is_path()) => {
+                if let Some(prompt) = self.state.prompt.as_mut() {
+                    if let Some(completed) = complete_path(&prompt.buffer) {
+                        *prompt = Prompt::new(prompt.kind, completed);
+                    }
+                }
+            }
+            _ => {
+                if let Some(prompt) = self.state.prompt.as_mut() {
+                    handle_text_editor_input(
+                        event,
+                        &mut prompt.buffer,
+                        &mut prompt.cursor_pos,
+                        false,
+                    );
+                    // Search highlights incrementally as the term is typed, and the
+                    // row filter is tried once typing pauses
+                    if prompt.kind == PromptKind::Search {
+                        let term = prompt.buffer.clone();
+                        self.state.set_search_term(&term);
+                    } else if prompt.kind == PromptKind::RowFilter {
+                        self.filter_preview
+                            .input(prompt.buffer.clone(), Instant::now());
+                    }
+                }
+            }
+        }
     }
 
-    /// Load a specific table
-    fn load_table(&mut self, table_name: String) {
-        self.state.current_table = Some(table_name.clone());
-        self.state.rows_loading = true;
-        self.state.table_rows = None;
-
-        let offset = self.state.current_page * self.state.page_size;
-        let _ = self.worker.send(WorkerMessage::LoadTableRows {
-            table_name: table_name.clone(),
-            limit: self.state.page_size,
-            offset,
-        });
+    /// Handle input while a destructive query waits for confirmation
+    fn handle_confirm_key(&mut self, event: KeyEvent) {
+        let Some(pending) = self.state.pending_confirm.take() else {
+            return;
+        };
+        match (event.code, pending.action) {
+            (
+                KeyCode::Enter | KeyCode::Char('y'),
+                ConfirmAction::Query {
+                    statements, params, ..
+                },
+            ) => {
+                self.run_statements(statements, params);
+            }
+            (
+                KeyCode::Char('a'),
+                ConfirmAction::Query {
+                    statements, params, ..
+                },
+            ) => {
+                // Power users can skip the confirmation for the rest of the session
+                self.state.confirm_destructive = false;
+                self.run_statements(statements, params);
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::ReplaceInColumn(replace)) => {
+                self.state.replace_running = true;
+                let _ = self.send_write(WorkerMessage::ReplaceInColumn {
+                    table_name: replace.table,
+                    column_name: replace.column,
+                    find: replace.find,
+                    replacement: replace.replacement,
+                });
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::DropColumn { table, column }) => {
+                self.send_alter_column(table, ColumnChange::Drop { column });
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::DropIndex { table, index }) => {
+                let _ = self.send_write(WorkerMessage::DropIndex {
+                    table_name: table,
+                    index_name: index,
+                });
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::ReopenReadWrite) => {
+                let _ = self.worker.send(WorkerMessage::ReopenReadWrite {
+                    key: self.state.key.clone(),
+                    extensions: self.state.extensions.clone(),
+                    allow_extensions: self.state.allow_extensions,
+                });
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::Paste { edits, .. }) => {
+                if self.state.staged_edits {
+                    for edit in edits {
+                        self.state.stage_edit(edit);
+                    }
+                } else {
+                    let _ = self.send_write(WorkerMessage::ApplyEdits { edits });
+                }
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::LoadFile { path, run, .. }) => {
+                self.load_sql_file(path, run);
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::OverwriteFile { path }) => {
+                self.write_sql_file(path);
+            }
+            (KeyCode::Enter | KeyCode::Char('y'), ConfirmAction::OverwriteBackup { path }) => {
+                self.send_backup(path);
+            }
+            (
+                KeyCode::Enter | KeyCode::Char('y'),
+                ConfirmAction::OverwriteDiagram { path, format },
+            ) => self.write_diagram_file(path, format),
+            (
+                KeyCode::Enter | KeyCode::Char('y'),
+                ConfirmAction::CopyRows { text, rows, format },
+            ) => self.set_clipboard_rows(text, rows, format),
+            (
+                KeyCode::Enter | KeyCode::Char('y'),
+                ConfirmAction::OverwriteExport {
+                    path,
+                    format,
+                    source,
+                },
+            ) => self.send_export(path, format, source),
+            (
+                KeyCode::Enter | KeyCode::Char('y'),
+                ConfirmAction::ExportView {
+                    path,
+                    format,
+                    source,
+                    ..
+                },
+            ) => self.send_export(path, format, source),
+            (
+                KeyCode::Char('p'),
+                ConfirmAction::ExportView {
+                    path,
+                    format,
+                    source: ExportSource::View { table, .. },
+                    ..
+                },
+            ) => {
+                let source = self.view_export_source(&table, true);
+                self.send_export(path, format, source);
+            }
+            (KeyCode::Esc | KeyCode::Char('n'), _) => {
+                self.state.status_message = Some("Cancelled".to_string());
+            }
+            (_, action) => {
+                // Any other key leaves the confirmation open
+                self.state.pending_confirm = Some(PendingConfirm { action, ..pending });
+            }
+        }
+    }
 
-        // Also load table info
-        let _ = self.worker.send(WorkerMessage::GetTableInfo {
-            table_name: table_name.clone(),
-        });
+    /// Handle input while the BLOB hex viewer is open
+    fn handle_blob_viewer_key(&mut self, event: KeyEvent) {
+        let Some(viewer) = self.state.blob_viewer.as_mut() else {
+            return;
+        };
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('v') => {
+                self.state.blob_viewer = None;
+            }
+            KeyCode::Char('e') => {
+                // Edit the BLOB as hex in the full editor
+                self.state.blob_viewer = None;
+                self.enter_edit_mode();
+            }
+            KeyCode::Up => viewer.scroll_up(1),
+            KeyCode::Down => viewer.scroll_down(1),
+            KeyCode::PageUp => viewer.scroll_up(BlobViewer::PAGE_LINES),
+            KeyCode::PageDown => viewer.scroll_down(BlobViewer::PAGE_LINES),
+            KeyCode::Home => viewer.scroll = 0,
+            KeyCode::End => viewer.scroll = viewer.line_count().saturating_sub(1),
+            _ => {}
+        }
     }
 
-    /// Load schema for a table
-    fn load_schema(&mut self, table_name: String) {
-        self.state.schema_loading = true;
-        self.state.schema_columns.clear();
-        self.state.schema_indexes.clear();
-        self.state.schema_foreign_keys.clear();
-        let _ = self.worker.send(WorkerMessage::LoadSchema {
-            table_name: table_name.clone(),
-        });
+    /// Handle input while the help is shown
+    fn handle_help_key(&mut self, event: KeyEvent) {
+        let last = ui::help_lines().len().saturating_sub(1);
+        let scroll = &mut self.state.help_scroll;
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('?') => self.state.show_help = false,
+            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => *scroll = (*scroll + 1).min(last),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(HELP_PAGE_LINES),
+            KeyCode::PageDown => *scroll = (*scroll + HELP_PAGE_LINES).min(last),
+            KeyCode::Home => *scroll = 0,
+            KeyCode::End => *scroll = last,
+            _ => {}
+        }
     }
 
-    /// Execute SQL query
-    fn execute_query(&mut self) {
-        if self.state.sql_query.trim().is_empty() {
+    /// Handle input while the query plan is shown
+    fn handle_query_plan_key(&mut self, event: KeyEvent) {
+        let Some(plan) = self.state.query_plan.as_mut() else {
             return;
+        };
+        let last = plan.steps.len().saturating_sub(1);
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.state.query_plan = None,
+            KeyCode::Char('x') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.state.query_plan = None;
+            }
+            KeyCode::Up => plan.scroll = plan.scroll.saturating_sub(1),
+            KeyCode::Down => plan.scroll = (plan.scroll + 1).min(last),
+            KeyCode::Home => plan.scroll = 0,
+            KeyCode::End => plan.scroll = last,
+            KeyCode::Char('y') => {
+                let text = plan.text();
+                self.state.status_message = Some(
+                    match arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+                        Ok(()) => "Copied the query plan".to_string(),
+                        Err(e) => format!("Clipboard unavailable: {}", e),
+                    },
+                );
+            }
+            _ => {}
         }
+    }
 
-        self.state.query_loading = true;
-        self.state.query_error = None;
-        let query = self.state.sql_query.clone();
-        let _ = self.worker.send(WorkerMessage::ExecuteQuery {
-            query,
-            max_rows: Some(1000),
-        });
+    /// Handle input while the error log is open; it scrolls from the newest error back
+    fn handle_error_log_key(&mut self, event: KeyEvent) {
+        let Some(scroll) = self.state.error_log_popup.as_mut() else {
+            return;
+        };
+        let last = self.state.error_log.len().saturating_sub(1);
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'E') => self.state.error_log_popup = None,
+            KeyCode::Up => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down => *scroll = (*scroll + 1).min(last),
+            KeyCode::Home => *scroll = 0,
+            KeyCode::End => *scroll = last,
+            KeyCode::Char('c') => {
+                self.state.error_log.clear();
+                self.state.error_log_popup = None;
+            }
+            _ => {}
+        }
     }
 
-    /// Enter edit mode for the first cell
-    fn enter_edit_mode(&mut self) {
-        if let Some(result) = &self.state.table_rows {
-            if !result.rows.is_empty() && !result.columns.is_empty() {
-                self.state.edit_mode = true;
-                self.state.editing_row = Some(0);
-                self.state.editing_col = Some(0);
-                if let Some(row) = result.rows.first() {
-                    if let Some(val) = row.first() {
-                        let full_value = val.display(10000);
-                        self.state.edit_buffer = full_value.clone();
-                        self.state.edit_cursor_pos = full_value.len();
-                        self.state.full_edit_mode =
-                            full_value.len() > 50 || full_value.contains('\n');
-                    }
+    /// Handle input while the integrity check popup is open: Esc cancels a running
+    /// check and closes a finished one
+    fn handle_integrity_check_key(&mut self, event: KeyEvent) {
+        let ctrl_c =
+            event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL);
+        match self.state.integrity_check.as_mut() {
+            Some(IntegrityCheckView::Running { .. }) if event.code == KeyCode::Esc || ctrl_c => {
+                self.worker.interrupt_background();
+                self.state.status_message = Some("Cancelling integrity check...".to_string());
+            }
+            Some(IntegrityCheckView::Done { report, scroll }) => {
+                let last = report.lines().len().saturating_sub(1);
+                match event.code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'I') => self.state.integrity_check = None,
+                    KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                    KeyCode::Down => *scroll = (*scroll + 1).min(last),
+                    KeyCode::Home => *scroll = 0,
+                    KeyCode::End => *scroll = last,
+                    _ => {}
                 }
             }
+            Some(IntegrityCheckView::Running { .. }) | None => {}
         }
     }
 
-    /// Save edited cell value
-    fn save_edited_cell(&mut self) {
-        // Clear any previous errors
-        self.state.query_error = None;
-
-        if let (Some(row_idx), Some(col_idx), Some(table_name)) = (
-            self.state.editing_row,
-            self.state.editing_col,
-            &self.state.current_table,
-        ) {
-            if let Some(result) = &self.state.table_rows {
-                if col_idx < result.columns.len() {
-                    let column_name = result.columns[col_idx].clone();
-                    let new_value = self.state.edit_buffer.clone();
-                    let actual_row_index = self.state.current_page * self.state.page_size + row_idx;
+    /// Handle input while the extensions popup is open
+    fn handle_extensions_key(&mut self, event: KeyEvent) {
+        let Some(view) = self.state.extensions_view.as_mut() else {
+            return;
+        };
+        let last = view.lines.len().saturating_sub(1);
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'X') => self.state.extensions_view = None,
+            KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Down => view.scroll = (view.scroll + 1).min(last),
+            KeyCode::Home => view.scroll = 0,
+            KeyCode::End => view.scroll = last,
+            _ => {}
+        }
+    }
 
-                    if let Err(e) = self.worker.send(WorkerMessage::UpdateCell {
-                        table_name: table_name.clone(),
-                        row_index: actual_row_index,
-                        column_name,
-                        new_value,
-                    }) {
-                        self.state.query_error =
-                            Some(format!("Failed to send update request: {}", e));
+    /// Handle input while the Database popup is open: pick a PRAGMA, then Enter picks
+    /// or types its new value and Enter again applies it
+    fn handle_pragma_key(&mut self, event: KeyEvent) {
+        let read_only = self.state.read_only;
+        let Some(view) = self.state.pragma_view.as_mut() else {
+            return;
+        };
+        let pragma = view.selected_pragma();
+        let choices = pragma.choices().unwrap_or_default();
+        match view.editing.as_mut() {
+            None => match event.code {
+                KeyCode::Esc | KeyCode::Char('q' | 'P') => self.state.pragma_view = None,
+                KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+                KeyCode::Down => view.selected = (view.selected + 1).min(Pragma::ALL.len() - 1),
+                KeyCode::Char('r') => {
+                    let _ = self.worker.send(WorkerMessage::ReadPragmas);
+                }
+                KeyCode::Enter => {
+                    let Some(settings) = &view.settings else {
+                        return;
+                    };
+                    if read_only {
+                        view.message = Some(
+                            "Read-only mode: reopen read-write (Ctrl+W) to change settings"
+                                .to_string(),
+                        );
+                    } else if !pragma.is_writable() {
+                        view.message = Some(format!("{} can't be changed", pragma.name()));
+                    } else {
+                        let current = settings.value(pragma);
+                        view.message = None;
+                        view.editing = Some(match pragma.choices() {
+                            Some(choices) => PragmaEdit::Choice(
+                                choices
+                                    .iter()
+                                    .position(|c| c.eq_ignore_ascii_case(&current))
+                                    .unwrap_or(0),
+                            ),
+                            None => PragmaEdit::Number(current),
+                        });
                     }
-                } else {
-                    self.state.query_error = Some("Invalid column index".to_string());
                 }
-            } else {
-                self.state.query_error = Some("No table data available".to_string());
-            }
-        } else {
-            self.state.query_error =
-                Some("Invalid edit state: missing row, column, or table name".to_string());
+                _ => {}
+            },
+            Some(edit) => match (event.code, edit) {
+                (KeyCode::Esc, _) => view.editing = None,
+                (KeyCode::Enter, edit) => {
+                    let value = match edit {
+                        PragmaEdit::Choice(at) => choices[*at].to_string(),
+                        PragmaEdit::Number(text) => text.clone(),
+                    };
+                    view.editing = None;
+                    view.message = Some(format!("Setting {}...", pragma.name()));
+                    let _ = self.worker.send(WorkerMessage::SetPragma { pragma, value });
+                }
+                (KeyCode::Up, PragmaEdit::Choice(at)) => *at = at.saturating_sub(1),
+                (KeyCode::Down, PragmaEdit::Choice(at)) => {
+                    *at = (*at + 1).min(choices.len().saturating_sub(1))
+                }
+                (KeyCode::Backspace, PragmaEdit::Number(text)) => {
+                    text.pop();
+                }
+                (KeyCode::Char(c), PragmaEdit::Number(text))
+                    if c.is_ascii_digit() || (c == '-' && text.is_empty()) =>
+                {
+                    text.push(c)
+                }
+                _ => {}
+            },
         }
     }
 
-    /// Shutdown the application
-    pub fn shutdown(self) -> Result<(), io::Error> {
-        self.worker
+    /// Handle input while the parameter form is open
+    fn handle_param_form_key(&mut self, event: KeyEvent) {
+        let Some(form) = self.state.param_form.as_mut() else {
+            return;
+        };
+        let last = form.fields.len().saturating_sub(1);
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        match event.code {
+            KeyCode::Esc => self.state.param_form = None,
+            KeyCode::Up | KeyCode::BackTab => {
+                form.selected = form.selected.checked_sub(1).unwrap_or(last);
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                form.selected = if form.selected >= last {
+                    0
+                } else {
+                    form.selected + 1
+                };
+            }
+            KeyCode::Char('n') if ctrl => {
+                let field = &mut form.fields[form.selected];
+                field.null = !field.null;
+                form.error = None;
+            }
+            KeyCode::Char('t') if ctrl => {
+                let field = &mut form.fields[form.selected];
+                field.kind = field.kind.next();
+                form.error = None;
+            }
+            KeyCode::Enter => {
+                let values: Result<Vec<Value>, String> =
+                    form.fields.iter().map(ParamField::bound_value).collect();
+                match values {
+                    Ok(values) => {
+                        let Some(form) = self.state.param_form.take() else {
+                            return;
+                        };
+                        for field in form.fields {
+                            self.state.param_memory.insert(field.name.clone(), field);
+                        }
+                        self.confirm_and_run(vec![form.statement], values);
+                    }
+                    Err(error) => form.error = Some(error),
+                }
+            }
+            _ => {
+                let field = &mut form.fields[form.selected];
+                if handle_text_editor_input(event, &mut field.value, &mut field.cursor_pos, false) {
+                    // Typing a value replaces NULL
+                    field.null = false;
+                    form.error = None;
+                }
+            }
+        }
+    }
+
+    /// Handle input while the Schema view's add-column form is open
+    fn handle_column_form_key(&mut self, event: KeyEvent) {
+        let Some(form) = self.state.column_form.as_mut() else {
+            return;
+        };
+        match event.code {
+            KeyCode::Esc => self.state.column_form = None,
+            KeyCode::Up | KeyCode::BackTab => form.move_field(-1),
+            KeyCode::Down | KeyCode::Tab => form.move_field(1),
+            KeyCode::Char(' ') if form.selected == ColumnField::NotNull => {
+                form.not_null = !form.not_null;
+                form.error = None;
+            }
+            KeyCode::Enter => match form.column() {
+                Ok(column) => {
+                    let table = form.table.clone();
+                    self.state.column_form = None;
+                    self.send_alter_column(table, ColumnChange::Add(column));
+                }
+                Err(error) => form.error = Some(error),
+            },
+            _ => {
+                let mut cursor_pos = form.cursor_pos;
+                if let Some(text) = form.text_mut() {
+                    if handle_text_editor_input(event, text, &mut cursor_pos, false) {
+                        form.error = None;
+                    }
+                }
+                form.cursor_pos = cursor_pos;
+            }
+        }
+    }
+
+    /// Start adding, renaming or dropping a column in the Schema view, unless the table
+    /// or selected column can't take it. Dropping asks first, counting what it loses.
+    fn start_column_change(&mut self, action: ColumnAction) {
+        if let Some(reason) = self.state.column_action_blocked(action) {
+            self.state.status_message = Some(format!("Can't {}: {}", action.label(), reason));
+            return;
+        }
+        let Some(table) = self.state.schema_table.clone() else {
+            return;
+        };
+        let column = self.state.selected_schema_column().map(|c| c.name.clone());
+        match (action, column) {
+            (ColumnAction::Add, _) => self.state.column_form = Some(ColumnForm::new(table)),
+            (ColumnAction::Rename, Some(column)) => {
+                self.state.prompt = Some(Prompt::new(PromptKind::RenameColumn, column));
+            }
+            (ColumnAction::Drop, Some(column)) => {
+                let counting = self
+                    .worker
+                    .send(WorkerMessage::CountColumnValues {
+                        table_name: table.clone(),
+                        column_name: column.clone(),
+                    })
+                    .is_ok();
+                self.state.pending_confirm = Some(PendingConfirm {
+                    action: ConfirmAction::DropColumn { table, column },
+                    affected_rows: None,
+                    counting,
+                });
+            }
+            (_, None) => {}
+        }
+    }
+
+    /// Handle input while the Schema view's create-index form is open
+    fn handle_index_form_key(&mut self, event: KeyEvent) {
+        let Some(form) = self.state.index_form.as_mut() else {
+            return;
+        };
+        match event.code {
+            KeyCode::Esc => self.state.index_form = None,
+            KeyCode::Up => form.selected = form.selected.saturating_sub(1),
+            KeyCode::Down => {
+                form.selected = (form.selected + 1).min(form.columns.len().saturating_sub(1));
+            }
+            KeyCode::Char(' ') => form.toggle_selected(),
+            KeyCode::Char('u') => form.unique = !form.unique,
+            KeyCode::Enter => match form.index() {
+                Ok(index) => {
+                    let table_name = form.table.clone();
+                    self.state.index_form = None;
+                    self.state.status_message = Some(format!("Creating index {}...", index.name));
+                    self.state.creating_index = Some(index.name.clone());
+                    let _ = self.send_write(WorkerMessage::CreateIndex { table_name, index });
+                }
+                Err(error) => form.error = Some(error),
+            },
+            _ => {}
+        }
+    }
+
+    /// Handle input while the Schema view's menu of SQL to generate is open: Enter
+    /// opens the SQL in the editor, `y` copies it
+    fn handle_sql_template_key(&mut self, event: KeyEvent) {
+        let Some(selected) = self.state.sql_template_menu.as_mut() else {
+            return;
+        };
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'g') => self.state.sql_template_menu = None,
+            KeyCode::Up => *selected = selected.saturating_sub(1),
+            KeyCode::Down => *selected = (*selected + 1).min(SqlTemplate::ALL.len() - 1),
+            KeyCode::Enter | KeyCode::Char('y') => {
+                let template = SqlTemplate::ALL[*selected];
+                self.state.sql_template_menu = None;
+                let Some((sql, placeholder)) = self.generate_sql(template) else {
+                    self.state.status_message =
+                        Some("The table has no CREATE statement to copy".to_string());
+                    return;
+                };
+                if event.code == KeyCode::Enter {
+                    self.open_in_sql_editor(sql, placeholder);
+                } else {
+                    self.state.status_message = Some(
+                        match arboard::Clipboard::new().and_then(|mut c| c.set_text(sql)) {
+                            Ok(()) => format!("Copied the {}", template.label()),
+                            Err(e) => format!("Clipboard unavailable: {}", e),
+                        },
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `template` written for the Schema view's table, with where its first placeholder is
+    fn generate_sql(&self, template: SqlTemplate) -> Option<(String, Option<usize>)> {
+        let name = self.state.schema_table.as_deref()?;
+        let table = TableRef::among(name, &self.state.attached_schemas());
+        let create_sql = self
+            .state
+            .tables
+            .iter()
+            .find(|t| t.name == name)
+            .and_then(|t| t.sql.as_deref());
+        template.generate(&table, &self.state.schema_columns, create_sql)
+    }
+
+    /// Put `sql` in the SQL editor with the cursor on its first placeholder, or at its
+    /// end. Text already in the editor is kept by opening another buffer.
+    fn open_in_sql_editor(&mut self, sql: String, placeholder: Option<usize>) {
+        if !self.state.sql_buffer().query.trim().is_empty() {
+            if self.sql_buffers_busy() {
+                return;
+            }
+            if !self.state.new_sql_buffer() {
+                self.state.status_message = Some(format!(
+                    "At most {} SQL buffers; clear one for the generated SQL",
+                    MAX_SQL_BUFFERS
+                ));
+                return;
+            }
+        }
+        let buffer = self.state.sql_buffer_mut();
+        buffer.replace(sql);
+        if let Some(at) = placeholder {
+            buffer.cursor_pos = at;
+        }
+        self.state.show_sql_editor = true;
+        self.state.focus = Focus::Content;
+    }
+
+    /// Start creating an index in the Schema view, or dropping the selected one after
+    /// asking, unless the table or index can't take it
+    fn start_index_change(&mut self, action: IndexAction) {
+        if let Some(reason) = self.state.index_action_blocked(action) {
+            self.state.status_message = Some(format!("Can't {}: {}", action.label(), reason));
+            return;
+        }
+        if self.state.creating_index.is_some() {
+            self.state.status_message = Some("An index is already being created".to_string());
+            return;
+        }
+        let Some(table) = self.state.schema_table.clone() else {
+            return;
+        };
+        match action {
+            IndexAction::Create => {
+                self.state.index_form = Some(IndexForm {
+                    table,
+                    columns: self
+                        .state
+                        .schema_columns
+                        .iter()
+                        .map(|c| c.name.clone())
+                        .collect(),
+                    taken: self
+                        .state
+                        .schema_indexes
+                        .iter()
+                        .map(|i| i.name.clone())
+                        .collect(),
+                    ..IndexForm::default()
+                });
+            }
+            IndexAction::Drop => {
+                if let Some(index) = self.state.selected_schema_index() {
+                    self.state.pending_confirm = Some(PendingConfirm {
+                        action: ConfirmAction::DropIndex {
+                            table,
+                            index: index.name.clone(),
+                        },
+                        affected_rows: None,
+                        counting: false,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Have the worker make `change` to the columns of `table`
+    fn send_alter_column(&mut self, table: String, change: ColumnChange) {
+        let _ = self.send_write(WorkerMessage::AlterColumn {
+            table_name: table,
+            change,
+        });
+    }
+
+    /// Act on the text entered into a prompt
+    fn submit_prompt(&mut self, prompt: Prompt) {
+        match prompt.kind {
+            PromptKind::RowFilter => {
+                self.filter_preview.cancel();
+                self.filter_before_preview = None;
+                self.state.set_row_filter(&prompt.buffer);
+                self.state.rows_error = None;
+                if let Some(table_name) = self.state.current_table.as_ref() {
+                    self.load_table(table_name.clone());
+                }
+            }
+            PromptKind::Search => {
+                self.state.set_search_term(&prompt.buffer);
+            }
+            PromptKind::DiagramFilter => {
+                self.state.set_diagram_filter(&prompt.buffer);
+            }
+            PromptKind::SaveQuery => {
+                let name = prompt.buffer.trim();
+                if name.is_empty() {
+                    self.state.status_message = Some("A saved query needs a name".to_string());
+                    return;
+                }
+                let query = self.state.sql_buffer().query.trim().to_string();
+                self.state.status_message =
+                    Some(match self.state.saved_queries.save(name, &query) {
+                        Ok(false) => format!("Saved query '{}'", name),
+                        Ok(true) => format!("Replaced saved query '{}'", name),
+                        Err(e) => format!("Failed to save query: {:#}", e),
+                    });
+            }
+            PromptKind::RenameColumn => {
+                let (Some(table), Some(column)) = (
+                    self.state.schema_table.clone(),
+                    self.state.selected_schema_column().map(|c| c.name.clone()),
+                ) else {
+                    return;
+                };
+                let new_name = prompt.buffer.trim().to_string();
+                if new_name.is_empty() || new_name == column {
+                    self.state.status_message = Some(format!("{} keeps its name", column));
+                    return;
+                }
+                self.send_alter_column(table, ColumnChange::Rename { column, new_name });
+            }
+            PromptKind::OpenFile => self.open_sql_file(&prompt.buffer, false),
+            PromptKind::QueryLimit => match prompt.buffer.trim().parse::<usize>() {
+                Ok(limit) if limit > 0 => {
+                    self.state.query_limit = limit;
+                    self.state.status_message =
+                        Some(format!("Query results now show {} rows per page", limit));
+                    // Refetch the shown page at the new size
+                    if let Some(result) = &self.state.query_result {
+                        if result.pageable && (result.truncated || result.offset > 0) {
+                            self.load_query_page(result.offset);
+                        }
+                    }
+                }
+                _ => {
+                    self.state.status_message =
+                        Some(format!("Not a row count: {}", prompt.buffer.trim()));
+                }
+            },
+            PromptKind::SaveFile => {
+                let input = prompt.buffer.trim();
+                if input.is_empty() {
+                    return;
+                }
+                let path = expand_tilde(input);
+                // Saving back to the file the editor came from needs no confirmation
+                if path.exists() && self.state.sql_file.as_ref() != Some(&path) {
+                    self.state.pending_confirm = Some(PendingConfirm {
+                        action: ConfirmAction::OverwriteFile { path },
+                        affected_rows: None,
+                        counting: false,
+                    });
+                } else {
+                    self.write_sql_file(path);
+                }
+            }
+            PromptKind::Export => {
+                let input = prompt.buffer.trim();
+                if input.is_empty() {
+                    return;
+                }
+                let path = expand_tilde(input);
+                let Some(format) = ExportFormat::from_path(&path) else {
+                    self.state.status_message = Some(
+                        "Export needs a path ending in .csv, .tsv, .json, .jsonl, .sql, .md, .html, .xlsx or .parquet (plus .gz)"
+                            .to_string(),
+                    );
+                    return;
+                };
+                let source = match self.export_source() {
+                    Ok(source) => source,
+                    Err(reason) => {
+                        self.state.status_message = Some(reason);
+                        return;
+                    }
+                };
+                if matches!(source, ExportSource::Schema { .. }) && format != ExportFormat::Sql {
+                    self.state.status_message =
+                        Some("A schema export needs a path ending in .sql".to_string());
+                    return;
+                }
+                if matches!(source, ExportSource::View { .. }) {
+                    // Show the SQL that picks the rows, which also asks before overwriting
+                    self.state.pending_confirm = Some(PendingConfirm {
+                        action: ConfirmAction::ExportView {
+                            overwrite: path.exists(),
+                            path,
+                            format,
+                            source,
+                        },
+                        affected_rows: None,
+                        counting: false,
+                    });
+                } else if path.exists() {
+                    self.state.pending_confirm = Some(PendingConfirm {
+                        action: ConfirmAction::OverwriteExport {
+                            path,
+                            format,
+                            source,
+                        },
+                        affected_rows: None,
+                        counting: false,
+                    });
+                } else {
+                    self.send_export(path, format, source);
+                }
+            }
+            PromptKind::ExportDiagram => {
+                let input = prompt.buffer.trim();
+                if input.is_empty() {
+                    return;
+                }
+                let path = expand_tilde(input);
+                let Some(format) = DiagramFormat::from_path(&path) else {
+                    self.state.status_message = Some(
+                        "A diagram export needs a path ending in .mmd, .dot or .puml".to_string(),
+                    );
+                    return;
+                };
+                if path.exists() {
+                    self.state.pending_confirm = Some(PendingConfirm {
+                        action: ConfirmAction::OverwriteDiagram { path, format },
+                        affected_rows: None,
+                        counting: false,
+                    });
+                } else {
+                    self.write_diagram_file(path, format);
+                }
+            }
+            PromptKind::Backup => {
+                let input = prompt.buffer.trim();
+                if input.is_empty() {
+                    return;
+                }
+                let path = expand_tilde(input);
+                if path.exists() {
+                    self.state.pending_confirm = Some(PendingConfirm {
+                        action: ConfirmAction::OverwriteBackup { path },
+                        affected_rows: None,
+                        counting: false,
+                    });
+                } else {
+                    self.send_backup(path);
+                }
+            }
+            PromptKind::Attach => {
+                if prompt.buffer.trim().is_empty() {
+                    return;
+                }
+                match db::parse_attach(&prompt.buffer) {
+                    Ok((path, alias)) => {
+                        let path = expand_tilde(&path).display().to_string();
+                        self.state.status_message = Some(format!("Attaching {}...", path));
+                        let _ = self
+                            .worker
+                            .send(WorkerMessage::AttachDatabase { path, alias });
+                    }
+                    Err(e) => self.state.status_message = Some(e.to_string()),
+                }
+            }
+            PromptKind::Import => {
+                let input = prompt.buffer.trim();
+                if input.is_empty() {
+                    return;
+                }
+                let path = expand_tilde(input);
+                let Some(table_name) = self.state.current_table.clone() else {
+                    return;
+                };
+                if !path.is_file() {
+                    self.state.status_message = Some(format!("No file at {}", path.display()));
+                    return;
+                }
+                self.state.status_message = Some(format!(
+                    "Importing {} into {}...",
+                    path.display(),
+                    table_name
+                ));
+                let _ = self
+                    .worker
+                    .send(WorkerMessage::ImportCsv { table_name, path });
+            }
+            PromptKind::ReplaceFind => {
+                if prompt.buffer.is_empty() {
+                    self.state.replace_draft = None;
+                    self.state.status_message = Some("Nothing to find".to_string());
+                } else if let Some(draft) = self.state.replace_draft.as_mut() {
+                    draft.find = prompt.buffer;
+                    self.state.prompt = Some(Prompt::new(PromptKind::ReplaceWith, String::new()));
+                }
+            }
+            PromptKind::ReplaceWith => {
+                let Some(mut replace) = self.state.replace_draft.take() else {
+                    return;
+                };
+                replace.replacement = prompt.buffer;
+                // Preview how many rows will change before anything is written
+                let counting = self
+                    .worker
+                    .send(WorkerMessage::CountColumnMatches {
+                        table_name: replace.table.clone(),
+                        column_name: replace.column.clone(),
+                        find: replace.find.clone(),
+                    })
+                    .is_ok();
+                self.state.pending_confirm = Some(PendingConfirm {
+                    action: ConfirmAction::ReplaceInColumn(replace),
+                    affected_rows: None,
+                    counting,
+                });
+            }
+        }
+    }
+
+    /// Reload the table list and current table after another process changed the database.
+    /// In the Query view a lone read-only query is run again instead.
+    fn refresh_from_disk(&mut self) {
+        self.refresh_pending = false;
+        // The table is read again from a new snapshot
+        self.end_snapshot();
+        self.load_tables();
+        let rerun_query = self.state.view_mode == ViewMode::Query
+            && !self.state.query_loading
+            && self.state.query_result.as_ref().is_some_and(|r| r.pageable)
+            && self
+                .state
+                .last_executed_query
+                .as_deref()
+                .is_some_and(|sql| split_statements(sql).len() == 1);
+        if rerun_query {
+            self.reload_grid();
+        } else if let Some(table_name) = self.state.current_table.clone() {
+            self.load_table(table_name);
+        }
+        self.state.last_refresh = Some(chrono::Local::now().format("%H:%M:%S").to_string());
+    }
+
+    /// Turn snapshot browsing on or off; only in WAL mode, where a held read doesn't
+    /// keep other connections from writing
+    fn toggle_snapshot_mode(&mut self) {
+        if self.state.snapshot_mode {
+            self.state.snapshot_mode = false;
+            self.end_snapshot();
+            self.state.status_message = Some("Snapshot browsing off".to_string());
+            return;
+        }
+        let mode = self.state.journal_mode.as_deref().unwrap_or("unknown");
+        if !mode.eq_ignore_ascii_case("wal") {
+            self.state.status_message = Some(format!(
+                "Snapshots need WAL mode - in journal_mode {} a held read blocks other writers",
+                mode
+            ));
+            return;
+        }
+        self.state.snapshot_mode = true;
+        self.state.status_message =
+            Some("Snapshot browsing on - pages of a table come from one point in time".to_string());
+        // The page shown is read again from the snapshot
+        if let Some(table_name) = self.state.current_table.clone() {
+            self.load_table(table_name);
+        }
+    }
+
+    /// Have the worker hold a read transaction, so the pages loaded next agree
+    fn take_snapshot(&mut self) {
+        if self.worker.send(WorkerMessage::BeginSnapshot).is_ok() {
+            self.state.snapshot = Some(chrono::Local::now().format("%H:%M:%S").to_string());
+        }
+    }
+
+    /// Let go of the snapshot held, if any
+    fn end_snapshot(&mut self) {
+        if self.state.snapshot.take().is_some() {
+            let _ = self.worker.send(WorkerMessage::EndSnapshot);
+        }
+    }
+
+    /// Turn watch mode on or off
+    fn toggle_watch(&mut self) {
+        self.state.watch = !self.state.watch;
+        self.data_version = None;
+        self.refresh_pending = false;
+        self.state.status_message = Some(
+            if self.state.watch {
+                "Watching for changes"
+            } else {
+                "Stopped watching for changes"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Load tables from database
+    pub fn load_tables(&mut self) {
+        // The list is loaded again when rows may have come or gone, which moves a page
+        // found by rowid away from the number it was given and leaves pages fetched
+        // ahead out of date
+        if let Some(anchor) = self.state.page_anchor.as_mut() {
+            anchor.estimated = true;
+        }
+        self.forget_prefetched();
+        self.state.tables_loading = true;
+        self.state.tables_error = None;
+        let _ = self.worker.send(WorkerMessage::LoadTables {
+            include_internal: self.state.show_internal_tables,
+        });
+    }
+
+    /// Switch to the diagram, loading it if it isn't already
+    fn open_diagram(&mut self) {
+        self.state.focus = Focus::Content;
+        self.state.view_mode = ViewMode::Diagram;
+        self.load_diagram();
+    }
+
+    /// Show the rows of the table selected in the diagram
+    fn open_diagram_table(&mut self) {
+        let Some(table_name) = self.state.diagram_selected.clone() else {
+            return;
+        };
+        self.state.view_mode = ViewMode::Rows;
+        self.state.select_table_named(&table_name);
+        self.load_table(table_name);
+    }
+
+    /// Ask for the diagram, showing it loading unless one is already drawn. The worker
+    /// answers at once while the schema is unchanged, and reads it again when it isn't.
+    fn load_diagram(&mut self) {
+        if self.state.diagram_loading {
+            return;
+        }
+        self.state.diagram_loading = self.state.diagram_data.is_none();
+        let _ = self.worker.send(WorkerMessage::LoadDiagram);
+    }
+
+    /// Open what the command line asked for: a table at a page, a view, and a query run
+    /// from the SQL editor
+    fn apply_startup(&mut self, startup: StartupIntent) {
+        if let Some(name) = startup.table {
+            let found = self.state.table_entries().iter().position(|entry| {
+                matches!(entry, TableEntry::Table(table)
+                    if table.kind != TableKind::Trigger && table.name.eq_ignore_ascii_case(&name))
+            });
+            match found {
+                Some(index) => {
+                    self.state.selected_table_index = index;
+                    self.state.focus = Focus::Content;
+                    self.state.current_page = startup.page;
+                    let table_name = self.state.selected_table().unwrap_or_default().to_string();
+                    self.load_table(table_name);
+                }
+                None => self.state.status_message = Some(format!("No table named {}", name)),
+            }
+        }
+        match startup.view {
+            Some(ViewMode::Diagram) => self.open_diagram(),
+            Some(view) => self.state.view_mode = view,
+            None => {}
+        }
+        if let Some(query) = startup.query {
+            self.state.show_sql_editor = true;
+            self.state.focus = Focus::Content;
+            self.state.sql_buffer_mut().replace(query);
+            self.execute_query(true);
+        }
+    }
+
+    /// Load a specific table
+    fn load_table(&mut self, table_name: String) {
+        if self.state.current_table.as_deref() != Some(table_name.as_str()) {
+            self.state.selected_row = 0;
+            self.state.selected_col = 0;
+            self.state.sort_column = None;
+            self.state.row_filter = None;
+            self.state.key_match = None;
+            self.state.table_foreign_keys.clear();
+            self.state.clear_search();
+            self.forget_prefetched();
+            self.end_snapshot();
+            // Declared column types decide which cells are edited as BLOBs
+            self.load_schema(table_name.clone());
+        }
+        self.state.current_table = Some(table_name.clone());
+        self.state.rows_loading = true;
+        self.state.rows_error = None;
+        // Selections are by position, so they don't survive a reload
+        self.state.row_anchor = None;
+        self.state.table_rows = None;
+
+        let offset = self.state.page_offset(self.state.current_page);
+        let view = self.state.rows_query();
+        // A page found by rowid is found the same way when it's loaded again
+        let page_start = match &self.state.page_anchor {
+            Some(anchor)
+                if anchor.table == table_name
+                    && anchor.view == view
+                    && anchor.page == self.state.current_page =>
+            {
+                Some(anchor.start)
+            }
+            _ => {
+                self.state.page_anchor = None;
+                None
+            }
+        };
+        if self.state.snapshot_mode && self.state.snapshot.is_none() {
+            self.take_snapshot();
+        }
+        let cached = self
+            .state
+            .take_cached_page(&table_name, &view, self.state.current_page);
+        if let Some(result) = cached {
+            // Loads sent before this were for pages since left
+            self.rows_request = self.worker.next_request_id();
+            self.state.table_rows = Some(result);
+            self.state.note_page_end();
+            self.state.rows_loading = false;
+            self.state.clamp_selection();
+            self.restore_saved_cursor();
+        } else if let Ok(request_id) = self.worker.send(WorkerMessage::LoadTableRows {
+            table_name: table_name.clone(),
+            limit: self.state.page_size,
+            offset,
+            view,
+            page_start,
+            max_bytes: self.state.result_memory,
+        }) {
+            self.rows_request = request_id;
+        }
+
+        // Also load table info and the foreign keys used for navigation
+        if let Ok(request_id) = self.worker.send(WorkerMessage::GetTableInfo {
+            table_name: table_name.clone(),
+        }) {
+            self.table_info_request = request_id;
+        }
+        let _ = self.worker.send(WorkerMessage::LoadTableForeignKeys {
+            table_name: table_name.clone(),
+        });
+    }
+
+    /// Once input has paused on a full page of rows, ask the worker for the page after it
+    /// so moving on shows it at once
+    fn prefetch_next_page(&mut self) {
+        if self.last_input.elapsed() < PREFETCH_IDLE
+            || self.state.view_mode != ViewMode::Rows
+            || self.state.rows_loading
+            || self.state.edit_mode
+        {
+            return;
+        }
+        let (Some(table), Some(result)) = (&self.state.current_table, &self.state.table_rows)
+        else {
+            return;
+        };
+        // A short page is the last one
+        if result.rows.len() < self.state.page_size && result.memory_cap.is_none() {
+            return;
+        }
+        let page = self.state.current_page + 1;
+        let view = self.state.rows_query();
+        let asked = self
+            .prefetch
+            .as_ref()
+            .is_some_and(|p| &p.table == table && p.view == view && p.page == page);
+        let cached = self
+            .state
+            .page_cache
+            .iter()
+            .any(|c| &c.table == table && c.view == view && c.page == page);
+        if asked || cached {
+            return;
+        }
+        let table = table.clone();
+        let page_start = self
+            .state
+            .adjacent_page_anchor(true)
+            .map(|anchor| anchor.start);
+        if let Ok(request) = self.worker.send(WorkerMessage::PrefetchTableRows {
+            table_name: table.clone(),
+            limit: self.state.page_size,
+            offset: self.state.page_offset(page),
+            view: view.clone(),
+            page_start,
+            max_bytes: self.state.result_memory,
+        }) {
+            self.prefetch = Some(Prefetch {
+                request,
+                table,
+                view,
+                page,
+            });
+        }
+    }
+
+    /// Drop the pages fetched ahead, and the answer to any still being fetched
+    fn forget_prefetched(&mut self) {
+        self.prefetch = None;
+        self.state.page_cache.clear();
+    }
+
+    /// Move to the next or previous page and reload the current table
+    fn change_page(&mut self, forward: bool) {
+        if self.state.view_mode == ViewMode::Query {
+            self.change_query_page(forward);
+            return;
+        }
+        self.state.clear_search();
+        // Deep pages are found from the rowids of the one shown rather than by skipping
+        // every row before them
+        let anchor = self.state.adjacent_page_anchor(forward);
+        if forward {
+            self.state.next_page();
+        } else {
+            self.state.prev_page();
+        }
+        self.state.page_anchor = anchor;
+        if let Some(table_name) = self.state.current_table.as_ref() {
+            self.load_table(table_name.clone());
+        }
+    }
+
+    /// Show the next or previous page of the query result by running its statement again
+    fn change_query_page(&mut self, forward: bool) {
+        let Some(result) = &self.state.query_result else {
+            return;
+        };
+        if !result.pageable {
+            if result.truncated {
+                self.state.status_message =
+                    Some("Only results of a read-only statement can be paged".to_string());
+            }
+            return;
+        }
+        let offset = match forward {
+            true if result.truncated => result.offset + result.rows.len(),
+            false => match self.state.prev_query_page_offset() {
+                Some(offset) => offset,
+                None => return,
+            },
+            _ => return,
+        };
+        self.state.query_selected_row = 0;
+        self.load_query_page(offset);
+    }
+
+    /// Ask the worker for the last query's rows starting `offset` rows in
+    fn load_query_page(&mut self, offset: usize) {
+        let Some(query) = self.state.last_query.clone() else {
+            return;
+        };
+        self.state.query_loading = true;
+        self.state.query_error = None;
+        self.state.query_cancelled = None;
+        self.state.query_elapsed_ms = None;
+        let _ = self.worker.send(WorkerMessage::LoadQueryPage {
+            query,
+            params: self
+                .state
+                .query_params
+                .iter()
+                .cloned()
+                .map(Into::into)
+                .collect(),
+            offset,
+            max_rows: Some(self.state.query_limit),
+            max_bytes: self.state.result_memory,
+        });
+    }
+
+    /// Jump from the selected cell to the row its foreign key references
+    fn follow_foreign_key(&mut self) {
+        let (Some(result), Some(current_table)) =
+            (&self.state.table_rows, &self.state.current_table)
+        else {
+            return;
+        };
+        let Some(column) = result.columns.get(self.state.selected_col) else {
+            return;
+        };
+        let Some(fk) = self
+            .state
+            .table_foreign_keys
+            .iter()
+            .find(|fk| &fk.from_column == column)
+        else {
+            self.state.status_message = Some(format!("Column {} is not a foreign key", column));
+            return;
+        };
+        let value = result
+            .rows
+            .get(self.state.selected_row)
+            .and_then(|row| row.get(self.state.selected_col))
+            .cloned()
+            .unwrap_or(crate::types::Value::Null);
+        if value == crate::types::Value::Null {
+            self.state.status_message = Some("NULL does not reference any row".to_string());
+            return;
+        }
+
+        let entry = NavEntry {
+            table: current_table.clone(),
+            page: self.state.current_page,
+            view: self.state.rows_query(),
+            selected_row: self.state.selected_row,
+            selected_col: self.state.selected_col,
+        };
+        let (to_table, to_column) = (fk.to_table.clone(), fk.to_column.clone());
+        self.state.nav_stack.push(entry);
+
+        // Set up the target view before loading so load_table doesn't reset it
+        self.state.current_table = Some(to_table.clone());
+        self.state.current_page = 0;
+        self.state.row_filter = None;
+        self.state.sort_column = None;
+        self.state.key_match = Some((to_column, value));
+        self.state.selected_row = 0;
+        self.state.selected_col = 0;
+        self.state.table_foreign_keys.clear();
+        self.state.clear_search();
+        self.load_table(to_table);
+    }
+
+    /// Jump from the Schema view to the rows of the table holding `fk` that reference
+    /// the row selected in the table's rows, or all of them when none is selected
+    fn show_referencing_rows(&mut self, fk: ForeignKeyInfo) {
+        let Some(current_table) = self.state.current_table.clone() else {
+            return;
+        };
+        // The rows on hand are the Schema view's table's unless another was opened since
+        let value = self
+            .state
+            .table_rows
+            .as_ref()
+            .filter(|_| self.state.schema_table.as_deref() == Some(current_table.as_str()))
+            .and_then(|result| {
+                let col = result
+                    .columns
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case(&fk.to_column))?;
+                result.rows.get(self.state.selected_row)?.get(col).cloned()
+            });
+        if value == Some(Value::Null) {
+            self.state.status_message = Some(format!(
+                "The selected row's {} is NULL, so no rows reference it",
+                fk.to_column
+            ));
+            return;
+        }
+
+        self.state.nav_stack.push(NavEntry {
+            table: current_table,
+            page: self.state.current_page,
+            view: self.state.rows_query(),
+            selected_row: self.state.selected_row,
+            selected_col: self.state.selected_col,
+        });
+        if value.is_none() {
+            self.state.status_message = Some(format!(
+                "No row selected, so showing all of {}",
+                fk.from_table
+            ));
+        }
+
+        // Set up the target view before loading so load_table doesn't reset it
+        self.state.view_mode = ViewMode::Rows;
+        self.state.current_table = Some(fk.from_table.clone());
+        self.state.current_page = 0;
+        self.state.row_filter = None;
+        self.state.sort_column = None;
+        self.state.key_match = value.map(|value| (fk.from_column, value));
+        self.state.selected_row = 0;
+        self.state.selected_col = 0;
+        self.state.table_foreign_keys.clear();
+        self.state.clear_search();
+        self.load_table(fk.from_table);
+    }
+
+    /// Return to the view saved before the last foreign key jump
+    fn navigate_back(&mut self) {
+        let Some(entry) = self.state.nav_stack.pop() else {
+            return;
+        };
+        if self.state.current_table.as_deref() != Some(entry.table.as_str()) {
+            self.state.table_foreign_keys.clear();
+        }
+        self.state.current_table = Some(entry.table.clone());
+        self.state.current_page = entry.page;
+        self.state.row_filter = entry.view.filter;
+        self.state.key_match = entry.view.key_match;
+        self.state.sort_column = entry.view.order_by;
+        self.state.selected_row = entry.selected_row;
+        self.state.selected_col = entry.selected_col;
+        self.state.clear_search();
+        self.load_table(entry.table);
+    }
+
+    /// Load schema for a table
+    fn load_schema(&mut self, table_name: String) {
+        self.state.schema_loading = true;
+        self.state.schema_error = None;
+        if self.state.schema_table.as_deref() != Some(table_name.as_str()) {
+            self.state.schema_selected = 0;
+        }
+        self.state.schema_table = Some(table_name.clone());
+        self.state.schema_columns.clear();
+        self.state.schema_indexes.clear();
+        self.state.schema_foreign_keys.clear();
+        self.state.schema_referenced_by.clear();
+        if let Ok(request_id) = self.worker.send(WorkerMessage::LoadSchema {
+            table_name: table_name.clone(),
+        }) {
+            self.schema_request = request_id;
+        }
+    }
+
+    /// Execute SQL query
+    fn execute_query(&mut self, all: bool) {
+        let buffer = self.state.sql_buffer();
+        let sql = &buffer.query;
+        let ranges = if all {
+            split_statements(sql)
+        } else {
+            let cursor = byte_offset(sql, buffer.cursor_pos);
+            statement_at(sql, cursor).into_iter().collect()
+        };
+        // A statement ending in \G asks for the vertical layout, as in the mysql client
+        let mut vertical = false;
+        let statements: Vec<String> = ranges
+            .into_iter()
+            .map(|r| match sql[r.clone()].trim_end().strip_suffix("\\G") {
+                Some(statement) => {
+                    vertical = true;
+                    statement.to_string()
+                }
+                None => sql[r].to_string(),
+            })
+            .collect();
+        if statements.is_empty() {
+            return;
+        }
+        if vertical {
+            self.state.result_layout = ResultLayout::Vertical;
+        }
+        // A lone statement with placeholders asks for their values first
+        if let [statement] = statements.as_slice() {
+            if has_parameters(statement) {
+                let statement = statement.clone();
+                if let Err(e) = self
+                    .worker
+                    .send(WorkerMessage::InspectParameters { statement })
+                {
+                    self.state.query_error = Some(format!("Failed to send query: {}", e));
+                }
+                return;
+            }
+        }
+        self.confirm_and_run(statements, Vec::new());
+    }
+
+    /// Run statements, first asking to confirm any destructive one when that's enabled
+    fn confirm_and_run(&mut self, statements: Vec<String>, params: Vec<Value>) {
+        if self.state.confirm_destructive {
+            if let Some(keyword) = statements.iter().find_map(|s| destructive_statement(s)) {
+                // Hold the statements until confirmed, estimating a lone one's reach meanwhile
+                // The count can't bind the parameters, so there's no estimate with them
+                let count_query = match statements.as_slice() {
+                    [statement] if params.is_empty() => affected_rows_query(statement),
+                    _ => None,
+                };
+                let counting = match count_query {
+                    Some(count_query) => self
+                        .worker
+                        .send(WorkerMessage::CountAffectedRows { count_query })
+                        .is_ok(),
+                    None => false,
+                };
+                self.state.pending_confirm = Some(PendingConfirm {
+                    action: ConfirmAction::Query {
+                        statements,
+                        keyword,
+                        params,
+                    },
+                    affected_rows: None,
+                    counting,
+                });
+                return;
+            }
+        }
+        self.run_statements(statements, params);
+    }
+
+    /// Ask the worker for the query plan of the statement under the cursor
+    fn explain_query(&mut self) {
+        let buffer = self.state.sql_buffer();
+        let sql = &buffer.query;
+        let cursor = byte_offset(sql, buffer.cursor_pos);
+        let Some(range) = statement_at(sql, cursor) else {
+            return;
+        };
+        let statement = sql[range].to_string();
+        self.state.query_error = None;
+        self.state.query_loading = true;
+        if let Err(e) = self.worker.send(WorkerMessage::ExplainQuery { statement }) {
+            self.state.query_error = Some(format!("Failed to send query: {}", e));
+            self.state.query_loading = false;
+        }
+    }
+
+    /// Interrupt the running query; the worker answers with QueryCancelled
+    fn cancel_query(&mut self) {
+        self.worker.interrupt();
+        self.state.status_message = Some("Cancelling query...".to_string());
+    }
+
+    /// Run statements from the SQL editor, resetting the results grid
+    fn run_statements(&mut self, statements: Vec<String>, params: Vec<Value>) {
+        self.state.query_selected_row = 0;
+        self.state.query_selected_col = 0;
+        self.state.query_grid_focused = false;
+        self.record_history = true;
+        self.state.last_executed_query = Some(statements.join(";\n"));
+        self.send_statements(statements, params);
+    }
+
+    /// Whether a query is running, so the SQL buffers can't change under its results.
+    /// Says so in the status line.
+    fn sql_buffers_busy(&mut self) -> bool {
+        if self.state.query_loading {
+            self.state.status_message = Some("Wait for the query to finish".to_string());
+        }
+        self.state.query_loading
+    }
+
+    /// Run the last statements from the SQL editor again without touching the editor,
+    /// asking first if one is destructive
+    fn rerun_last_query(&mut self) {
+        let Some(sql) = self.state.last_executed_query.clone() else {
+            self.state.status_message = Some("No query to re-run".to_string());
+            return;
+        };
+        let statements: Vec<String> = split_statements(&sql)
+            .into_iter()
+            .map(|range| sql[range].to_string())
+            .collect();
+        let params = self.state.query_params.clone();
+        let destructive = statements
+            .iter()
+            .any(|s| destructive_statement(s).is_some());
+        if self.state.confirm_destructive && destructive {
+            self.confirm_and_run(statements, params);
+        } else {
+            self.send_statements(statements, params);
+        }
+    }
+
+    /// Reload whichever grid edits were made in
+    fn reload_grid(&mut self) {
+        if self.state.view_mode == ViewMode::Query {
+            // A later page is fetched again on its own
+            match &self.state.query_result {
+                Some(result) if result.offset > 0 => self.load_query_page(result.offset),
+                _ => {
+                    if let Some(query) = self.state.last_query.clone() {
+                        let params = self.state.query_params.clone();
+                        self.send_statements(vec![query], params);
+                    }
+                }
+            }
+        } else if let Some(table_name) = &self.state.current_table {
+            self.load_table(table_name.clone());
+        }
+    }
+
+    /// Put the next older or newer history entry in the SQL editor; stepping newer past
+    /// the newest entry empties it again
+    fn recall_history(&mut self, older: bool) {
+        let count = self.state.history.entries.len();
+        if count == 0 {
+            return;
+        }
+        let index = match (self.state.recalled_history_index(), older) {
+            (None, true) => 0,
+            (None, false) => return,
+            (Some(index), true) => (index + 1).min(count - 1),
+            (Some(0), false) => {
+                self.state.history_index = None;
+                self.state.sql_buffer_mut().replace(String::new());
+                return;
+            }
+            (Some(index), false) => index - 1,
+        };
+        self.state.history_index = Some(index);
+        let query = self.state.history.entries[count - 1 - index].query.clone();
+        self.state.sql_buffer_mut().replace(query);
+    }
+
+    /// Handle input while the history search popup is open
+    fn handle_history_popup_key(&mut self, event: KeyEvent) {
+        let Some(popup) = self.state.history_popup.as_mut() else {
+            return;
+        };
+        let matches = self.state.history.matching(&popup.filter).len();
+        match event.code {
+            KeyCode::Esc => self.state.history_popup = None,
+            KeyCode::Up => popup.selected = popup.selected.saturating_sub(1),
+            KeyCode::Down => popup.selected = (popup.selected + 1).min(matches.saturating_sub(1)),
+            KeyCode::Backspace => {
+                popup.filter.pop();
+                popup.selected = 0;
+            }
+            KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => {
+                popup.filter.push(c);
+                popup.selected = 0;
+            }
+            KeyCode::Enter => {
+                let query = self
+                    .state
+                    .history
+                    .matching(&popup.filter)
+                    .get(popup.selected)
+                    .map(|entry| entry.query.clone());
+                self.state.history_popup = None;
+                if let Some(query) = query {
+                    // Insert at the cursor, so an empty editor just takes the query
+                    let buffer = self.state.sql_buffer_mut();
+                    let pos = byte_offset(&buffer.query, buffer.cursor_pos);
+                    buffer.query.insert_str(pos, &query);
+                    buffer.cursor_pos += query.chars().count();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the completion popup for the word at the SQL editor's cursor, asking the
+    /// worker for columns of mentioned tables. Returns false when nothing completes it.
+    fn open_completion(&mut self) -> bool {
+        let Some((completion, missing)) = self.state.completion_at_cursor() else {
+            self.state.completion = None;
+            return false;
+        };
+        // With no match yet the popup waits for the columns being loaded
+        if completion.candidates.is_empty() && missing.is_empty() {
+            self.state.completion = None;
+            return false;
+        }
+        // Typing narrows the popup with every key, so tables mentioned are looked up
+        // once it pauses
+        if !missing.is_empty() {
+            self.column_lookup.input(missing, Instant::now());
+        }
+        self.state.completion = Some(completion);
+        true
+    }
+
+    fn load_column_names(&mut self, table_names: Vec<String>) {
+        let _ = self
+            .worker
+            .send(WorkerMessage::LoadColumnNames { table_names });
+    }
+
+    /// Send what typing triggered once it has paused
+    fn send_debounced(&mut self) {
+        let now = Instant::now();
+        if let Some(table_names) = self.column_lookup.ready(now) {
+            self.load_column_names(table_names);
+        }
+        if let Some(filter) = self.filter_preview.ready(now) {
+            self.preview_filter(&filter);
+        }
+    }
+
+    /// Show the rows the filter being typed matches, remembering the filter it replaces
+    fn preview_filter(&mut self, filter: &str) {
+        let still_typing = self
+            .state
+            .prompt
+            .as_ref()
+            .is_some_and(|p| p.kind == PromptKind::RowFilter);
+        let Some(table_name) = self.state.current_table.clone() else {
+            return;
+        };
+        let unchanged = self.state.row_filter.as_deref().unwrap_or_default() == filter.trim();
+        if !still_typing || unchanged {
+            return;
+        }
+        if self.filter_before_preview.is_none() {
+            self.filter_before_preview = Some(self.state.row_filter.clone());
+        }
+        self.state.set_row_filter(filter);
+        self.state.rows_error = None;
+        self.load_table(table_name);
+        self.preview_request = self.rows_request;
+    }
+
+    /// Put back the row filter from before the prompt tried others
+    fn undo_filter_preview(&mut self) {
+        let Some(filter) = self.filter_before_preview.take() else {
+            return;
+        };
+        self.state
+            .set_row_filter(filter.as_deref().unwrap_or_default());
+        self.state.rows_error = None;
+        if let Some(table_name) = self.state.current_table.clone() {
+            self.load_table(table_name);
+        }
+    }
+
+    /// Handle a key while the completion popup is open. Returns false for keys that close
+    /// it and should then act as usual.
+    fn handle_completion_key(&mut self, event: KeyEvent) -> bool {
+        let Some(completion) = self.state.completion.as_mut() else {
+            return false;
+        };
+        let count = completion.candidates.len();
+        match event.code {
+            KeyCode::Esc => self.state.completion = None,
+            KeyCode::Up => completion.selected = completion.selected.saturating_sub(1),
+            KeyCode::Down => {
+                completion.selected = (completion.selected + 1).min(count.saturating_sub(1))
+            }
+            KeyCode::Enter | KeyCode::Tab if event.modifiers.is_empty() => {
+                self.accept_completion();
+            }
+            KeyCode::Char(_) | KeyCode::Backspace
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                let buffer = self.state.sql_buffer_mut();
+                handle_text_editor_input(event, &mut buffer.query, &mut buffer.cursor_pos, true);
+                self.open_completion();
+            }
+            _ => {
+                self.state.completion = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Replace the word at the cursor with the selected completion
+    fn accept_completion(&mut self) {
+        let Some(completion) = self.state.completion.take() else {
+            return;
+        };
+        let Some(candidate) = completion.candidates.get(completion.selected) else {
+            return;
+        };
+        let buffer = self.state.sql_buffer_mut();
+        let sql = &mut buffer.query;
+        let start = byte_offset(sql, completion.prefix.start);
+        let mut end = byte_offset(sql, buffer.cursor_pos);
+        // A closing quote already after the cursor is replaced along with the word
+        if let Some(quote) = completion.prefix.quote {
+            let close = if quote == '[' { ']' } else { quote };
+            if sql[end..].starts_with(close) {
+                end += close.len_utf8();
+            }
+        }
+        sql.replace_range(start..end, &candidate.text);
+        buffer.cursor_pos = completion.prefix.start + candidate.text.chars().count();
+    }
+
+    /// Load a SQL file typed into the prompt, asking first when it's large
+    fn open_sql_file(&mut self, input: &str, run: bool) {
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+        let path = expand_tilde(input);
+        match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => {
+                self.state.status_message = Some(format!("{} is a directory", path.display()));
+            }
+            Ok(metadata) if metadata.len() > LARGE_SQL_FILE => {
+                self.state.pending_confirm = Some(PendingConfirm {
+                    action: ConfirmAction::LoadFile {
+                        path,
+                        size: metadata.len(),
+                        run,
+                    },
+                    affected_rows: None,
+                    counting: false,
+                });
+            }
+            Ok(_) => self.load_sql_file(path, run),
+            Err(e) => {
+                self.state.status_message = Some(format!("Can't open {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    /// Replace the SQL editor's buffer with a file, then run every statement if asked
+    fn load_sql_file(&mut self, path: PathBuf, run: bool) {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                self.state.status_message = Some(format!("Can't read {}: {}", path.display(), e));
+                return;
+            }
+        };
+        self.state.status_message = Some(format!(
+            "Loaded {} ({} lines)",
+            path.display(),
+            text.lines().count()
+        ));
+        let buffer = self.state.sql_buffer_mut();
+        buffer.query = text;
+        buffer.cursor_pos = 0;
+        self.state.sql_file = Some(path);
+        if run {
+            self.execute_query(true);
+        }
+    }
+
+    /// Write the SQL editor's buffer to a file
+    fn write_sql_file(&mut self, path: PathBuf) {
+        self.state.status_message = Some(match fs::write(&path, &self.state.sql_buffer().query) {
+            Ok(()) => {
+                let message = format!(
+                    "Saved {} bytes to {}",
+                    self.state.sql_buffer().query.len(),
+                    path.display()
+                );
+                self.state.sql_file = Some(path);
+                message
+            }
+            Err(e) => format!("Can't write {}: {}", path.display(), e),
+        });
+    }
+
+    /// Write the diagram as shown, filters and all, to `path` in `format`. It's already
+    /// loaded and makes little text, so it's written straight away rather than by the
+    /// worker.
+    fn write_diagram_file(&mut self, path: PathBuf, format: DiagramFormat) {
+        let Some(diagram) = &self.state.diagram_shown else {
+            return;
+        };
+        let mut text = Vec::new();
+        let written = export_diagram(diagram, format, &mut text)
+            .and_then(|()| fs::write(&path, &text).map_err(Into::into));
+        self.state.status_message = Some(match written {
+            Ok(()) => format!(
+                "Exported the diagram of {} tables to {}",
+                diagram.tables.len(),
+                path.display()
+            ),
+            Err(e) => format!("Can't write {}: {:#}", path.display(), e),
+        });
+    }
+
+    /// Copy the rows on screen, the page of a table or the loaded query results, as CSV
+    /// or Markdown text, asking first when that's a lot of text
+    fn copy_rows(&mut self, format: ExportFormat) {
+        let Some(result) = self.state.edit_target() else {
+            self.state.status_message = Some("No rows to copy".to_string());
+            return;
+        };
+        let rows = result.rows.len();
+        let values = result
+            .rows
+            .iter()
+            .map(|row| row.iter().cloned().map(Into::into).collect());
+        let text = match rows_text(format, &result.columns, values, &ExportOptions::default()) {
+            Ok(text) => text,
+            Err(e) => {
+                self.state.status_message = Some(format!("Copy failed: {:#}", e));
+                return;
+            }
+        };
+        if text.len() > LARGE_COPY {
+            self.state.pending_confirm = Some(PendingConfirm {
+                action: ConfirmAction::CopyRows { text, rows, format },
+                affected_rows: None,
+                counting: false,
+            });
+        } else {
+            self.set_clipboard_rows(text, rows, format);
+        }
+    }
+
+    /// Put copied rows on the clipboard, e.g. "Copied 57 rows as markdown (4.1 KB)"
+    fn set_clipboard_rows(&mut self, text: String, rows: usize, format: ExportFormat) {
+        let size = text.len() as f64 / 1024.0;
+        let name = match format {
+            ExportFormat::Markdown => "markdown",
+            _ => format.extension(),
+        };
+        self.state.status_message = Some(
+            match arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+                Ok(()) => format!(
+                    "Copied {} rows as {} ({:.1} KB)",
+                    ui::group_digits(rows as i64),
+                    name,
+                    size
+                ),
+                Err(e) => format!("Clipboard unavailable: {}", e),
+            },
+        );
+    }
+
+    /// Ask where to export the rows on screen, named after their table
+    fn start_export(&mut self) {
+        // The diagram is exported as text for documentation tools to draw
+        if self.state.view_mode == ViewMode::Diagram {
+            if self.state.diagram_data.is_some() {
+                let name = format!("schema.{}", DiagramFormat::Mermaid.extension());
+                self.state.prompt = Some(Prompt::new(PromptKind::ExportDiagram, name));
+            }
+            return;
+        }
+        match self.export_source() {
+            Ok(source) => {
+                let name = match &source {
+                    ExportSource::Table(table) | ExportSource::View { table, .. } => {
+                        format!("{}.csv", table)
+                    }
+                    ExportSource::Query { .. } => "query.csv".to_string(),
+                    ExportSource::Schema { table: Some(table) } => format!("{}.schema.sql", table),
+                    ExportSource::Schema { table: None } => "schema.sql".to_string(),
+                    ExportSource::Database => "dump.sql".to_string(),
+                };
+                self.state.prompt = Some(Prompt::new(PromptKind::Export, name));
+            }
+            Err(reason) => self.state.status_message = Some(reason),
+        }
+    }
+
+    /// What `x` exports: every row of the query in the Query view, the current table's
+    /// CREATE statements in the Schema view, otherwise every row of the current table.
+    /// The query runs again, so only one that changes nothing qualifies.
+    fn export_source(&self) -> Result<ExportSource, String> {
+        if self.state.view_mode == ViewMode::Schema {
+            return Ok(ExportSource::Schema {
+                table: self.state.current_table.clone(),
+            });
+        }
+        if self.state.view_mode == ViewMode::Query {
+            return match (&self.state.last_query, &self.state.query_result) {
+                (Some(query), Some(result)) if result.pageable && !result.columns.is_empty() => {
+                    Ok(ExportSource::Query {
+                        query: query.clone(),
+                        params: self
+                            .state
+                            .query_params
+                            .iter()
+                            .cloned()
+                            .map(Into::into)
+                            .collect(),
+                    })
+                }
+                (Some(_), Some(_)) => {
+                    Err("Only results of a read-only query can be exported".to_string())
+                }
+                _ => Err("Run a query first to export its results".to_string()),
+            };
+        }
+        match &self.state.current_table {
+            Some(table) => Ok(self.view_export_source(table, false)),
+            None => Err("Select a table or run a query to export".to_string()),
+        }
+    }
+
+    /// The rows of `table` filtered and sorted as the rows view shows them, or only the
+    /// page on screen
+    fn view_export_source(&self, table: &str, page_only: bool) -> ExportSource {
+        let page = page_only.then(|| {
+            (
+                self.state.page_size,
+                self.state.page_offset(self.state.current_page),
+            )
+        });
+        let table = TableRef::among(table, &self.state.attached_schemas());
+        let (query, params) = rows_select(&table, "*", &self.state.rows_query(), page);
+        ExportSource::View {
+            table: table.name,
+            query,
+            params: params.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Have the worker write the rows to `path`
+    fn send_export(&mut self, path: PathBuf, format: ExportFormat, source: ExportSource) {
+        self.state.status_message = Some(format!("Exporting to {}...", path.display()));
+        self.state.exporting = Some(path.clone());
+        let _ = self.worker.send(WorkerMessage::ExportResult {
+            format,
+            path,
+            source,
+        });
+    }
+
+    /// Have the worker copy the database to `path`
+    fn send_backup(&mut self, path: PathBuf) {
+        self.state.status_message = Some(format!("Backing up to {}...", path.display()));
+        self.state.backing_up = Some(path.clone());
+        let _ = self.worker.send(WorkerMessage::Backup { path });
+    }
+
+    /// Open the picker over saved queries, or say why there is nothing to pick
+    fn open_saved_query_picker(&mut self) {
+        match self.state.saved_queries.list() {
+            Ok(queries) if queries.is_empty() => {
+                self.state.status_message =
+                    Some("No saved queries yet (Alt+Q saves the editor's query)".to_string());
+            }
+            Ok(queries) => {
+                self.state.saved_query_picker = Some(SavedQueryPicker {
+                    queries,
+                    selected: 0,
+                });
+            }
+            Err(e) => {
+                self.state.status_message = Some(format!("Can't read saved queries: {:#}", e));
+            }
+        }
+    }
+
+    /// Handle input while the saved query picker is open
+    fn handle_saved_query_picker_key(&mut self, event: KeyEvent) {
+        let Some(picker) = self.state.saved_query_picker.as_mut() else {
+            return;
+        };
+        let count = picker.queries.len();
+        match event.code {
+            KeyCode::Esc => self.state.saved_query_picker = None,
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Down => picker.selected = (picker.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Enter => {
+                if let Some(saved) = picker.queries.get(picker.selected) {
+                    let query = saved.query.clone();
+                    self.state.sql_buffer_mut().replace(query);
+                }
+                self.state.saved_query_picker = None;
+            }
+            KeyCode::Char('x') => {
+                let Some(saved) = picker.queries.get(picker.selected).cloned() else {
+                    return;
+                };
+                match self.state.saved_queries.delete(&saved) {
+                    Ok(()) => {
+                        picker.queries.remove(picker.selected);
+                        picker.selected =
+                            picker.selected.min(picker.queries.len().saturating_sub(1));
+                        if picker.queries.is_empty() {
+                            self.state.saved_query_picker = None;
+                        }
+                        self.state.status_message =
+                            Some(format!("Deleted saved query '{}'", saved.name));
+                    }
+                    Err(e) => {
+                        self.state.status_message = Some(format!("Failed to delete: {:#}", e));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Run statements on the worker, one after another. Parameters go with a lone
+    /// statement.
+    fn send_statements(&mut self, statements: Vec<String>, params: Vec<Value>) {
+        // Any statement may write
+        self.forget_prefetched();
+        self.end_snapshot();
+        self.state.query_loading = true;
+        self.state.query_error = None;
+        self.state.query_cancelled = None;
+        self.state.query_elapsed_ms = None;
+        self.state.query_params = params.clone();
+        let message = match statements.as_slice() {
+            [query] if !params.is_empty() => WorkerMessage::ExecuteQuery {
+                query: query.clone(),
+                params: params.into_iter().map(Into::into).collect(),
+                max_rows: Some(self.state.query_limit),
+                max_bytes: self.state.result_memory,
+            },
+            _ => WorkerMessage::ExecuteScript {
+                statements,
+                max_rows: Some(self.state.query_limit),
+                max_bytes: self.state.result_memory,
+            },
+        };
+        let _ = self.worker.send(message);
+    }
+
+    /// Enter edit mode for the selected cell
+    fn enter_edit_mode(&mut self) {
+        if !self.check_rows_writable() {
+            return;
+        }
+        self.state.clamp_selection();
+        self.state.clamp_query_selection();
+        let (row_idx, col_idx) = self.state.cursor();
+        if !self.state.load_edit_cell(row_idx, col_idx) {
+            return;
+        }
+        self.state.edit_mode = true;
+        self.state.edit_error = None;
+        self.state.editing_row = Some(row_idx);
+        self.state.editing_col = Some(col_idx);
+        self.state.full_edit_mode = self.state.edit_blob
+            || self.state.edit_buffer.len() > 50
+            || self.state.edit_buffer.contains('\n');
+    }
+
+    /// Put the cursor back on the saved cell once its grid has reloaded, asking the
+    /// worker which page the row is on when sorting moved it off this one
+    fn restore_saved_cursor(&mut self) {
+        let Some(restore) = self.state.restore_cursor.take() else {
+            return;
+        };
+        let row = self
+            .state
+            .edit_target()
+            .and_then(|result| result.row_keys.iter().position(|key| *key == restore.key));
+        match row {
+            Some(row) => {
+                self.state.set_cursor(row, restore.col);
+                if self.state.edit_next_row {
+                    self.edit_next_row(row, restore.col);
+                }
+            }
+            None if !restore.located && self.state.view_mode == ViewMode::Rows => {
+                if let Some(table_name) = self.state.current_table.clone() {
+                    let _ = self.worker.send(WorkerMessage::LocateRow {
+                        table_name,
+                        view: self.state.rows_query(),
+                        key: restore.key.clone(),
+                    });
+                    self.state.restore_cursor = Some(CursorRestore {
+                        located: true,
+                        ..restore
+                    });
+                }
+            }
+            // The row no longer matches the view, so keep the clamped selection
+            None => {}
+        }
+    }
+
+    /// Spreadsheet-style: after a save, start editing the same column one row down
+    fn edit_next_row(&mut self, row: usize, col: usize) {
+        let rows = self.state.edit_target().map(|r| r.rows.len()).unwrap_or(0);
+        if row + 1 < rows {
+            self.state.set_cursor(row + 1, col);
+            self.enter_edit_mode();
+        } else {
+            self.state.status_message = Some("Last row on this page".to_string());
+        }
+    }
+
+    /// Move the inline editor to a neighbouring cell, loading its value
+    fn move_edit_cell(&mut self, row_delta: isize, col_delta: isize) {
+        let (Some(row), Some(col)) = (self.state.editing_row, self.state.editing_col) else {
+            return;
+        };
+        let Some((row, col)) = row
+            .checked_add_signed(row_delta)
+            .zip(col.checked_add_signed(col_delta))
+        else {
+            return;
+        };
+        if !self.state.load_edit_cell(row, col) {
+            return;
+        }
+        self.state.editing_row = Some(row);
+        self.state.editing_col = Some(col);
+        self.state.set_cursor(row, col);
+        // Moving between rows reopens the full editor for BLOBs and long or multi-line values
+        if row_delta != 0 {
+            self.state.full_edit_mode = self.state.edit_blob
+                || self.state.edit_buffer.len() > 50
+                || self.state.edit_buffer.contains('\n');
+        }
+    }
+
+    /// Save edited cell value. `force` skips validation against the schema.
+    fn save_edited_cell(&mut self, force: bool) {
+        // Clear any previous errors
+        self.state.edit_error = None;
+
+        if let (Some(rows), Some(col_idx)) = (self.state.multi_edit_rows(), self.state.editing_col)
+        {
+            self.save_multi_edit(rows, col_idx, force);
+            return;
+        }
+
+        if let (Some(row_idx), Some(col_idx), Some(table_name)) = (
+            self.state.editing_row,
+            self.state.editing_col,
+            self.state.edit_table(),
+        ) {
+            if let Some(result) = self.state.edit_target() {
+                if col_idx < result.columns.len() {
+                    let column_name = result.columns[col_idx].clone();
+                    let new_value = match self.state.typed_edit_value(row_idx, col_idx) {
+                        Ok(value) => value,
+                        Err(e) => {
+                            self.state.edit_error = Some(e.to_string());
+                            return;
+                        }
+                    };
+                    if let Err(message) = self.state.validate_edit(row_idx, col_idx, &new_value) {
+                        if !force {
+                            self.state.edit_error =
+                                Some(format!("{} (Ctrl/Alt+Enter: save anyway)", message));
+                            return;
+                        }
+                    }
+                    let Some(key) = result.row_keys.get(row_idx).cloned() else {
+                        self.state.edit_error = Some(
+                            "Can't edit this row: the table has no rowid or primary key"
+                                .to_string(),
+                        );
+                        return;
+                    };
+
+                    if self.state.staged_edits {
+                        // Hold the edit until all pending edits are written together
+                        self.state.stage_edit(PendingEdit {
+                            table_name: table_name.to_string(),
+                            key,
+                            column_name,
+                            value: new_value,
+                        });
+                        self.state.exit_edit_mode();
+                        if self.state.edit_next_row {
+                            self.edit_next_row(row_idx, col_idx);
+                        }
+                        return;
+                    }
+
+                    if let Err(e) = self.send_write(WorkerMessage::UpdateCell {
+                        table_name: table_name.to_string(),
+                        key,
+                        column_name,
+                        new_value,
+                    }) {
+                        self.state.edit_error =
+                            Some(format!("Failed to send update request: {}", e));
+                    }
+                } else {
+                    self.state.edit_error = Some("Invalid column index".to_string());
+                }
+            } else {
+                self.state.edit_error = Some("No table data available".to_string());
+            }
+        } else {
+            self.state.edit_error =
+                Some("Invalid edit state: missing row, column, or table name".to_string());
+        }
+    }
+
+    /// Write the edit to the same column of every selected row, in one transaction
+    fn save_multi_edit(&mut self, rows: RangeInclusive<usize>, col_idx: usize, force: bool) {
+        let (Some(result), Some(table_name)) = (self.state.edit_target(), self.state.edit_table())
+        else {
+            return;
+        };
+        let Some(column_name) = result.columns.get(col_idx) else {
+            return;
+        };
+        let mut edits = Vec::new();
+        for row in rows.filter(|&row| row < result.rows.len()) {
+            let Some(key) = result.row_keys.get(row).cloned() else {
+                self.state.edit_error = Some(format!(
+                    "Can't edit row {}: the table has no rowid or primary key",
+                    row + 1
+                ));
+                return;
+            };
+            let value = match self.state.typed_edit_value(row, col_idx) {
+                Ok(value) => value,
+                Err(e) => {
+                    self.state.edit_error = Some(e.to_string());
+                    return;
+                }
+            };
+            if let Err(message) = self.state.validate_edit(row, col_idx, &value) {
+                if !force {
+                    self.state.edit_error =
+                        Some(format!("{} (Ctrl/Alt+Enter: save anyway)", message));
+                    return;
+                }
+            }
+            edits.push(PendingEdit {
+                table_name: table_name.to_string(),
+                key,
+                column_name: column_name.clone(),
+                value,
+            });
+        }
+
+        if self.state.staged_edits {
+            for edit in edits {
+                self.state.stage_edit(edit);
+            }
+            self.state.exit_edit_mode();
+            self.state.row_anchor = None;
+        } else if let Err(e) = self.send_write(WorkerMessage::ApplyEdits { edits }) {
+            self.state.edit_error = Some(format!("Failed to send edits: {}", e));
+        }
+    }
+
+    /// Paste the clipboard's cells into the grid from the cursor, after a preview
+    fn paste_from_clipboard(&mut self) {
+        if !self.check_rows_writable() {
+            return;
+        }
+        let text = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => text,
+            Err(e) => {
+                self.state.status_message = Some(format!("Clipboard unavailable: {}", e));
+                return;
+            }
+        };
+        match self.paste_edits(&text) {
+            Ok((edits, _, _)) if edits.is_empty() => {
+                self.state.status_message = Some("Nothing to change".to_string());
+            }
+            Ok((edits, unchanged, out_of_bounds)) => {
+                self.state.pending_confirm = Some(PendingConfirm {
+                    action: ConfirmAction::Paste {
+                        edits,
+                        unchanged,
+                        out_of_bounds,
+                    },
+                    affected_rows: None,
+                    counting: false,
+                });
+            }
+            Err(message) => self.state.status_message = Some(message),
+        }
+    }
+
+    /// Edits for pasting `text` at the cursor, with counts of the unchanged cells and
+    /// the cells that fall outside the loaded page
+    fn paste_edits(&self, text: &str) -> Result<(Vec<PendingEdit>, usize, usize), String> {
+        let (Some(result), Some(table_name)) = (self.state.edit_target(), self.state.edit_table())
+        else {
+            return Err("No table to paste into".to_string());
+        };
+        let block = paste::parse_block(text);
+        let plan = paste::plan_paste(
+            &block,
+            self.state.cursor(),
+            result.rows.len(),
+            result.columns.len(),
+        );
+
+        let mut edits = Vec::new();
+        let mut unchanged = 0;
+        for cell in plan.cells {
+            let key =
+                result.row_keys.get(cell.row).cloned().ok_or_else(|| {
+                    "Can't paste: the table has no rowid or primary key".to_string()
+                })?;
+            let value = self
+                .state
+                .typed_input(&cell.text, cell.row, cell.col)
+                .map_err(|e| {
+                    format!("Row {}, {}: {}", cell.row + 1, result.columns[cell.col], e)
+                })?;
+            if self.state.edit_value(cell.row, cell.col).as_ref() == Some(&value) {
+                unchanged += 1;
+                continue;
+            }
+            edits.push(PendingEdit {
+                table_name: table_name.to_string(),
+                key,
+                column_name: result.columns[cell.col].clone(),
+                value,
+            });
+        }
+        Ok((edits, unchanged, plan.out_of_bounds))
+    }
+
+    /// Write all pending edits in one transaction
+    fn write_pending_edits(&mut self) {
+        let edits = self.state.pending_edits.clone();
+        if let Err(e) = self.send_write(WorkerMessage::ApplyEdits { edits }) {
+            self.state.status_message = Some(format!("Failed to send edits: {}", e));
+        }
+    }
+
+    /// Shutdown the application
+    pub fn shutdown(self) -> Result<(), io::Error> {
+        self.worker
             .shutdown()
             .map_err(|e| io::Error::other(format!("Failed to shutdown worker: {}", e)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::BusyPolicy;
+    use rusqlite::Connection;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// An app over an in-memory database, with table `t` open in the rows view
+    fn app() -> App {
+        let conn = Connection::open_in_memory().unwrap();
+        let busy = BusyPolicy {
+            timeout: Duration::from_secs(1),
+            retries: 0,
+        };
+        let worker = Worker::new(conn, None, PathBuf::from(":memory:"), busy);
+        let mut app = App::new(worker, 50, false);
+        app.state.current_table = Some("t".to_string());
+        app.state.view_mode = ViewMode::Rows;
+        app.state.focus = Focus::Content;
+        app.state.show_sql_editor = false;
+        app.state.table_rows = Some(QueryResult::new(
+            vec!["id".to_string()],
+            vec![vec![Value::Integer(1)]],
+            0,
+        ));
+        app
+    }
+
+    fn press(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+        app.handle_key_event(KeyEvent::new(code, modifiers))
+            .unwrap();
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            let modifiers = if c.is_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            press(app, KeyCode::Char(c), modifiers);
+        }
+    }
+
+    #[test]
+    fn i_is_typed_into_the_sql_editor_over_a_table() {
+        let mut app = app();
+        app.state.show_sql_editor = true;
+        type_text(&mut app, "SELECT id FROM t");
+        assert_eq!(app.state.sql_buffer().query, "SELECT id FROM t");
+        assert!(app.state.prompt.is_none());
+    }
+
+    #[test]
+    fn ctrl_h_in_the_sql_editor_leaves_find_and_replace_closed() {
+        let mut app = app();
+        app.state.show_sql_editor = true;
+        press(&mut app, KeyCode::Char('h'), KeyModifiers::CONTROL);
+        assert!(app.state.replace_draft.is_none());
+        assert!(app.state.prompt.is_none());
+    }
+
+    #[test]
+    fn o_filters_the_table_list_and_ctrl_o_orders_it() {
+        let mut app = app();
+        app.state.focus = Focus::Tables;
+        type_text(&mut app, "photo");
+        assert_eq!(app.state.table_filter, "photo");
+        assert_eq!(app.state.table_sort, TableSort::Name);
+
+        press(&mut app, KeyCode::Char('o'), KeyModifiers::CONTROL);
+        assert_eq!(app.state.table_filter, "photo");
+        assert_eq!(app.state.table_sort, TableSort::Name.next());
+    }
+
+    #[test]
+    fn w_toggles_watch_only_over_the_rows_and_not_when_it_writes_edits() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('w'), KeyModifiers::NONE);
+        assert!(app.state.watch);
+        press(&mut app, KeyCode::Char('w'), KeyModifiers::NONE);
+        assert!(!app.state.watch);
+
+        app.state.focus = Focus::Tables;
+        type_text(&mut app, "wow");
+        assert_eq!(app.state.table_filter, "wow");
+        assert!(!app.state.watch);
+
+        app.state.focus = Focus::Content;
+        app.state.staged_edits = true;
+        press(&mut app, KeyCode::Char('w'), KeyModifiers::NONE);
+        assert!(!app.state.watch);
+    }
+
+    #[test]
+    fn uppercase_letters_type_into_the_table_filter() {
+        let mut app = app();
+        app.state.focus = Focus::Tables;
+        type_text(&mut app, "BIZ_EXPLAIN_DATA");
+        assert_eq!(app.state.table_filter, "BIZ_EXPLAIN_DATA");
+        assert!(app.state.prompt.is_none());
+        assert!(app.state.pragma_view.is_none());
+        assert!(app.state.integrity_check.is_none());
+        assert!(app.state.snapshot.is_none());
+    }
+
+    #[test]
+    fn ctrl_o_and_ctrl_s_open_and_save_sql_files() {
+        let mut app = app();
+        app.state.show_sql_editor = true;
+        type_text(&mut app, "SELECT 1");
+        let prompt = |app: &App| app.state.prompt.as_ref().map(|prompt| prompt.kind);
+
+        press(&mut app, KeyCode::Char('o'), KeyModifiers::CONTROL);
+        assert_eq!(prompt(&app), Some(PromptKind::OpenFile));
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        press(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(prompt(&app), Some(PromptKind::SaveFile));
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        press(&mut app, KeyCode::Char('q'), KeyModifiers::ALT);
+        assert_eq!(prompt(&app), Some(PromptKind::SaveQuery));
+    }
+
+    #[test]
+    fn the_help_scrolls_and_keeps_its_keys_from_the_view_below() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('?'), KeyModifiers::NONE);
+        assert!(app.state.show_help);
+
+        press(&mut app, KeyCode::Char('j'), KeyModifiers::NONE);
+        press(&mut app, KeyCode::Char('j'), KeyModifiers::NONE);
+        press(&mut app, KeyCode::Char('k'), KeyModifiers::NONE);
+        assert_eq!(app.state.help_scroll, 1);
+        press(&mut app, KeyCode::PageDown, KeyModifiers::NONE);
+        assert_eq!(app.state.help_scroll, 1 + HELP_PAGE_LINES);
+        press(&mut app, KeyCode::End, KeyModifiers::NONE);
+        press(&mut app, KeyCode::PageDown, KeyModifiers::NONE);
+        assert_eq!(app.state.help_scroll, ui::help_lines().len() - 1);
+        press(&mut app, KeyCode::PageUp, KeyModifiers::NONE);
+        press(&mut app, KeyCode::Home, KeyModifiers::NONE);
+        assert_eq!(app.state.help_scroll, 0);
+
+        // Keys under the help don't reach the table
+        press(&mut app, KeyCode::Char('s'), KeyModifiers::NONE);
+        assert_eq!(app.state.view_mode, ViewMode::Rows);
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(!app.state.show_help);
+    }
+}
@@ -0,0 +1,55 @@
+/// A pasted cell's target position in the grid and its text
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasteCell {
+    pub row: usize,
+    pub col: usize,
+    pub text: String,
+}
+
+/// Where a pasted block lands: the cells inside the grid, and how many fall outside it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PastePlan {
+    pub cells: Vec<PasteCell>,
+    pub out_of_bounds: usize,
+}
+
+/// Split clipboard text into rows on newlines and cells on tabs, as spreadsheets copy it
+pub fn parse_block(text: &str) -> Vec<Vec<String>> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    let text = text.strip_suffix('\r').unwrap_or(text);
+    text.split('\n')
+        .map(|line| {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            line.split('\t').map(str::to_string).collect()
+        })
+        .collect()
+}
+
+/// Lay `block` out from (start_row, start_col) over a grid of `rows` by `cols` cells
+pub fn plan_paste(
+    block: &[Vec<String>],
+    (start_row, start_col): (usize, usize),
+    rows: usize,
+    cols: usize,
+) -> PastePlan {
+    let mut cells = Vec::new();
+    let mut out_of_bounds = 0;
+    for (i, line) in block.iter().enumerate() {
+        for (j, text) in line.iter().enumerate() {
+            let (row, col) = (start_row + i, start_col + j);
+            if row < rows && col < cols {
+                cells.push(PasteCell {
+                    row,
+                    col,
+                    text: text.clone(),
+                });
+            } else {
+                out_of_bounds += 1;
+            }
+        }
+    }
+    PastePlan {
+        cells,
+        out_of_bounds,
+    }
+}
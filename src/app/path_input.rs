@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::PathBuf;
+
+/// A typed path with a leading `~` expanded to the home directory
+pub fn expand_tilde(input: &str) -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    match input.strip_prefix('~') {
+        Some("") => home().unwrap_or_else(|| PathBuf::from(input)),
+        Some(rest) if rest.starts_with('/') => match home() {
+            Some(home) => home.join(&rest[1..]),
+            None => PathBuf::from(input),
+        },
+        _ => PathBuf::from(input),
+    }
+}
+
+/// Complete the last component of a typed path from the filesystem, as far as the
+/// matching entries agree. A lone directory match gets a trailing `/`. Returns None
+/// when there is nothing to add.
+pub fn complete_path(input: &str) -> Option<String> {
+    let (dir, partial) = match input.rfind('/') {
+        Some(slash) => input.split_at(slash + 1),
+        None => ("", input),
+    };
+    let search_dir = if dir.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_tilde(dir)
+    };
+    let mut matches: Vec<(String, bool)> = fs::read_dir(search_dir)
+        .ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden entries only when asked for
+            let wanted =
+                name.starts_with(partial) && (partial.starts_with('.') || !name.starts_with('.'));
+            let is_dir = entry.path().is_dir();
+            wanted.then_some((name, is_dir))
+        })
+        .collect();
+    matches.sort();
+
+    let completed = match matches.as_slice() {
+        [] => return None,
+        [(name, true)] => format!("{}/", name),
+        [(name, false)] => name.clone(),
+        [(first, _), rest @ ..] => rest.iter().fold(first.clone(), |common, (name, _)| {
+            common
+                .chars()
+                .zip(name.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        }),
+    };
+    (completed != partial).then(|| format!("{}{}", dir, completed))
+}
@@ -0,0 +1,151 @@
+use super::history::data_file;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Layout of `queries.toml`: name to SQL, shared or per database
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueriesFile {
+    /// Queries offered with every database
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    queries: BTreeMap<String, String>,
+    /// Queries saved for one database, keyed by its canonical path
+    #[serde(default)]
+    databases: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// A named query from `queries.toml`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedQuery {
+    pub name: String,
+    pub query: String,
+    /// Saved in the shared section rather than for this database
+    pub shared: bool,
+}
+
+/// Named queries stored in `queries.toml` next to the history file
+#[derive(Debug, Default)]
+pub struct SavedQueries {
+    file: QueriesFile,
+    path: Option<PathBuf>,
+    database: String,
+    /// Why the file couldn't be read; saving is refused so it isn't overwritten
+    load_error: Option<String>,
+}
+
+impl SavedQueries {
+    /// Load the saved queries for `database`. A missing file is an empty list; a file
+    /// that can't be parsed is kept as is and reported by `list` and `save`.
+    pub fn load(database: &Path) -> Self {
+        let database = fs::canonicalize(database)
+            .unwrap_or_else(|_| database.to_path_buf())
+            .display()
+            .to_string();
+        let path = data_file("queries.toml");
+        let mut saved = Self {
+            path,
+            database,
+            ..Default::default()
+        };
+        if let Some(path) = &saved.path {
+            match read_file(path) {
+                Ok(file) => saved.file = file,
+                Err(e) => saved.load_error = Some(format!("{:#}", e)),
+            }
+        }
+        saved
+    }
+
+    /// This database's queries, then the shared ones, each sorted by name
+    pub fn list(&self) -> Result<Vec<SavedQuery>> {
+        self.check_loaded()?;
+        let own = self
+            .file
+            .databases
+            .get(&self.database)
+            .into_iter()
+            .flatten()
+            .map(|(name, query)| (name, query, false));
+        let shared = self
+            .file
+            .queries
+            .iter()
+            .map(|(name, query)| (name, query, true));
+        Ok(own
+            .chain(shared)
+            .map(|(name, query, shared)| SavedQuery {
+                name: name.clone(),
+                query: query.clone(),
+                shared,
+            })
+            .collect())
+    }
+
+    /// Save `query` under `name` for this database, replacing any query of that name.
+    /// Returns whether one was replaced.
+    pub fn save(&mut self, name: &str, query: &str) -> Result<bool> {
+        self.check_loaded()?;
+        let replaced = self
+            .file
+            .databases
+            .entry(self.database.clone())
+            .or_default()
+            .insert(name.to_string(), query.to_string())
+            .is_some();
+        self.write()?;
+        Ok(replaced)
+    }
+
+    /// Delete a saved query from this database's section, or the shared one
+    pub fn delete(&mut self, query: &SavedQuery) -> Result<()> {
+        self.check_loaded()?;
+        if query.shared {
+            self.file.queries.remove(&query.name);
+        } else if let Some(queries) = self.file.databases.get_mut(&self.database) {
+            queries.remove(&query.name);
+            if queries.is_empty() {
+                self.file.databases.remove(&self.database);
+            }
+        }
+        self.write()
+    }
+
+    fn check_loaded(&self) -> Result<()> {
+        match &self.load_error {
+            Some(error) => bail!("{}", error),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            bail!("No home directory to save queries in");
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let text = toml::to_string_pretty(&self.file).context("Failed to encode saved queries")?;
+        fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Parse `queries.toml`; a file that doesn't exist yet holds no queries
+fn read_file(path: &Path) -> Result<QueriesFile> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(QueriesFile::default()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    toml::from_str(&text).map_err(|e| {
+        // The parse error spans several lines with a snippet; its first line has the position
+        let detail = e.to_string();
+        anyhow::anyhow!(
+            "{} is not valid ({}); fix or remove it to save queries",
+            path.display(),
+            detail.lines().next().unwrap_or_default()
+        )
+    })
+}
@@ -0,0 +1,152 @@
+use crate::db::TableRef;
+use crate::export::quote_identifier;
+use crate::types::ColumnInfo;
+
+/// SQL the Schema view writes from its table's columns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SqlTemplate {
+    CreateTable,
+    Select,
+    Insert,
+    Update,
+}
+
+impl SqlTemplate {
+    pub const ALL: [SqlTemplate; 4] = [
+        SqlTemplate::CreateTable,
+        SqlTemplate::Select,
+        SqlTemplate::Insert,
+        SqlTemplate::Update,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SqlTemplate::CreateTable => "CREATE TABLE statement",
+            SqlTemplate::Select => "SELECT with all columns",
+            SqlTemplate::Insert => "INSERT with named placeholders",
+            SqlTemplate::Update => "UPDATE by primary key",
+        }
+    }
+
+    /// The SQL for `table`, and the character offset of its first placeholder. CREATE
+    /// TABLE is the statement the table was made with, so None when there isn't one.
+    pub fn generate(
+        self,
+        table: &TableRef,
+        columns: &[ColumnInfo],
+        create_sql: Option<&str>,
+    ) -> Option<(String, Option<usize>)> {
+        let mut sql = SqlText::default();
+        let quoted = table.quoted();
+        match self {
+            SqlTemplate::CreateTable => sql.push(&format!("{};", create_sql?.trim_end())),
+            SqlTemplate::Select => {
+                let names: Vec<String> =
+                    columns.iter().map(|c| quote_identifier(&c.name)).collect();
+                sql.push(&format!("SELECT {}\nFROM {};", names.join(", "), quoted));
+            }
+            SqlTemplate::Insert => {
+                // SQLite numbers a rowid alias itself
+                let mut inserted: Vec<&ColumnInfo> = columns
+                    .iter()
+                    .filter(|c| !is_rowid_alias(c, columns))
+                    .collect();
+                if inserted.is_empty() {
+                    inserted = columns.iter().collect();
+                }
+                let names: Vec<String> =
+                    inserted.iter().map(|c| quote_identifier(&c.name)).collect();
+                sql.push(&format!(
+                    "INSERT INTO {} ({})\nVALUES (",
+                    quoted,
+                    names.join(", ")
+                ));
+                for (i, column) in inserted.iter().enumerate() {
+                    if i > 0 {
+                        sql.push(", ");
+                    }
+                    sql.placeholder(&column.name);
+                }
+                sql.push(");");
+            }
+            SqlTemplate::Update => {
+                let keys: Vec<&str> = columns
+                    .iter()
+                    .filter(|c| c.primary_key)
+                    .map(|c| c.name.as_str())
+                    .collect();
+                // Without a primary key, rows are found by rowid
+                let keys = if keys.is_empty() { vec!["rowid"] } else { keys };
+                let mut set: Vec<&ColumnInfo> = columns
+                    .iter()
+                    .filter(|c| !keys.contains(&c.name.as_str()))
+                    .collect();
+                if set.is_empty() {
+                    set = columns.iter().collect();
+                }
+                sql.push(&format!("UPDATE {}\nSET ", quoted));
+                for (i, column) in set.iter().enumerate() {
+                    if i > 0 {
+                        sql.push(",\n    ");
+                    }
+                    sql.push(&format!("{} = ", quote_identifier(&column.name)));
+                    sql.placeholder(&column.name);
+                }
+                sql.push("\nWHERE ");
+                for (i, key) in keys.iter().enumerate() {
+                    if i > 0 {
+                        sql.push(" AND ");
+                    }
+                    let name = if *key == "rowid" {
+                        key.to_string()
+                    } else {
+                        quote_identifier(key)
+                    };
+                    sql.push(&format!("{} = ", name));
+                    sql.placeholder(key);
+                }
+                sql.push(";");
+            }
+        }
+        Some((sql.text, sql.first_placeholder))
+    }
+}
+
+/// Generated SQL, noting where its first placeholder went
+#[derive(Default)]
+struct SqlText {
+    text: String,
+    first_placeholder: Option<usize>,
+}
+
+impl SqlText {
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// A named placeholder for `column`, `:name` with what a parameter name can't hold
+    /// replaced by underscores
+    fn placeholder(&mut self, column: &str) {
+        self.first_placeholder
+            .get_or_insert(self.text.chars().count());
+        let name: String = column
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.text.push(':');
+        self.text.push_str(&name);
+    }
+}
+
+/// Whether `column` is the table's lone INTEGER PRIMARY KEY, an alias of the rowid
+fn is_rowid_alias(column: &ColumnInfo, columns: &[ColumnInfo]) -> bool {
+    column.primary_key
+        && column.data_type.eq_ignore_ascii_case("INTEGER")
+        && columns.iter().filter(|c| c.primary_key).count() == 1
+}
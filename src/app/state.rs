@@ -1,4 +1,21 @@
-use crate::types::{ColumnInfo, DiagramData, ForeignKeyInfo, IndexInfo, QueryResult, TableInfo};
+use crate::db::pragma::{Pragma, PragmaSettings};
+use crate::db::{index_name, CheckReport, ColumnAction, IndexAction, NewColumn, NewIndex};
+use crate::export::{DiagramFormat, ExportFormat, ExportSource};
+use crate::types::{
+    ColumnInfo, DiagramData, DiagramLayout, DiagramLayoutKind, DiagramTable, ForeignKeyInfo,
+    IndexInfo, IndexOrigin, PageStart, PendingEdit, PlanStep, QueryResult, RowKey, RowsQuery,
+    ScriptStep, SortOrder, TableInfo, TableKind, Value,
+};
+use crate::worker::ErrorContext;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use super::completion::{candidates, mentioned_tables, prefix_at, Completion};
+use super::history::History;
+use super::saved_queries::{SavedQueries, SavedQuery};
+use super::validate::validate_value;
 
 /// Current view mode in the content pane
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -17,56 +34,1017 @@ pub enum Focus {
     Info,
 }
 
+/// Labels shown to the left of each row in the rows view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowGutter {
+    Hidden,
+    Numbers,
+    NumbersAndRowid,
+}
+
+impl RowGutter {
+    /// Next gutter mode in the toggle cycle
+    pub fn next(self) -> Self {
+        match self {
+            RowGutter::Hidden => RowGutter::Numbers,
+            RowGutter::Numbers => RowGutter::NumbersAndRowid,
+            RowGutter::NumbersAndRowid => RowGutter::Hidden,
+        }
+    }
+}
+
+/// What a text prompt is collecting input for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    RowFilter,
+    Search,
+    ReplaceFind,
+    ReplaceWith,
+    SaveQuery,
+    RenameColumn,
+    OpenFile,
+    SaveFile,
+    QueryLimit,
+    Export,
+    ExportDiagram,
+    DiagramFilter,
+    Import,
+    Backup,
+    Attach,
+}
+
+impl PromptKind {
+    /// Title shown on the prompt popup
+    pub fn title(&self) -> &'static str {
+        match self {
+            PromptKind::RowFilter => "Row filter: WHERE ... (Enter: apply, Esc: cancel)",
+            PromptKind::Search => "Search rows (Enter: keep, Esc: cancel, n/N: next/prev)",
+            PromptKind::ReplaceFind => "Replace in column: find (Enter: next, Esc: cancel)",
+            PromptKind::ReplaceWith => "Replace with (Enter: preview, Esc: cancel)",
+            PromptKind::SaveQuery => "Save query as (Enter: save, Esc: cancel)",
+            PromptKind::RenameColumn => "Rename column to (Enter: rename, Esc: cancel)",
+            PromptKind::OpenFile => {
+                "Open SQL file (Enter: load, Ctrl+Enter: load and run, Tab: complete, Esc: cancel)"
+            }
+            PromptKind::SaveFile => {
+                "Save SQL editor to file (Enter: save, Tab: complete, Esc: cancel)"
+            }
+            PromptKind::QueryLimit => "Rows per page of query results (Enter: apply, Esc: cancel)",
+            PromptKind::Export => {
+                "Export to a .csv, .tsv, .json, .jsonl, .sql, .md, .html, .xlsx or .parquet file, .gz to compress (Enter: export, Tab: complete, Esc: cancel)"
+            }
+            PromptKind::ExportDiagram => {
+                "Export the diagram to a .mmd (Mermaid), .dot (Graphviz) or .puml (PlantUML) file (Enter: export, Tab: complete, Esc: cancel)"
+            }
+            PromptKind::DiagramFilter => "Filter the diagram's tables (Enter: apply, Esc: cancel)",
+            PromptKind::Import => {
+                "Import rows from a .csv or .tsv file with a header row (Enter: import, Tab: complete, Esc: cancel)"
+            }
+            PromptKind::Backup => {
+                "Back up the database to a file (Enter: back up, Tab: complete, Esc: cancel)"
+            }
+            PromptKind::Attach => {
+                "Attach a database as PATH AS ALIAS (Enter: attach, Tab: complete, Esc: cancel)"
+            }
+        }
+    }
+
+    /// Whether the prompt takes a file path, which Tab completes
+    pub fn is_path(&self) -> bool {
+        matches!(
+            self,
+            PromptKind::OpenFile
+                | PromptKind::SaveFile
+                | PromptKind::Export
+                | PromptKind::ExportDiagram
+                | PromptKind::Import
+                | PromptKind::Backup
+                | PromptKind::Attach
+        )
+    }
+
+    /// Placeholder shown while the prompt is empty
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            PromptKind::RowFilter => "e.g. age > 30 AND name LIKE 'A%' (empty clears)",
+            PromptKind::Search => "Text to find in the loaded page",
+            PromptKind::ReplaceFind => "Text to find, matched exactly (case-sensitive)",
+            PromptKind::ReplaceWith => "Replacement text (empty removes the matches)",
+            PromptKind::SaveQuery => {
+                "Name for the SQL editor's query (an existing name is replaced)"
+            }
+            PromptKind::RenameColumn => "New name for the column",
+            PromptKind::OpenFile | PromptKind::SaveFile => "Path to a .sql file (~ for home)",
+            PromptKind::QueryLimit => "A number of rows, e.g. 1000",
+            PromptKind::Export => {
+                "Path ending in .csv, .tsv, .json, .jsonl, .sql, .md, .html, .xlsx or .parquet, plus .gz to compress (~ for home)"
+            }
+            PromptKind::ExportDiagram => "Path ending in .mmd, .dot or .puml (~ for home)",
+            PromptKind::DiagramFilter => {
+                "Part of a table's name; the tables joined to it show too (empty clears)"
+            }
+            PromptKind::Import => "Path to a .csv or .tsv file whose header names the columns (~ for home)",
+            PromptKind::Backup => "Path for the copy of the database (~ for home)",
+            PromptKind::Attach => {
+                "e.g. other.db AS other; without AS, the file's name is the alias (~ for home)"
+            }
+        }
+    }
+}
+
+/// Hex dump popup for a BLOB cell
+#[derive(Debug, Clone)]
+pub struct BlobViewer {
+    pub column: String,
+    pub bytes: Vec<u8>,
+    /// First hex dump line shown
+    pub scroll: usize,
+}
+
+impl BlobViewer {
+    /// Bytes shown on each hex dump line
+    pub const BYTES_PER_LINE: usize = 16;
+    /// Lines moved by PageUp/PageDown
+    pub const PAGE_LINES: usize = 16;
+
+    pub fn new(column: String, bytes: Vec<u8>) -> Self {
+        Self {
+            column,
+            bytes,
+            scroll: 0,
+        }
+    }
+
+    /// Total number of hex dump lines
+    pub fn line_count(&self) -> usize {
+        self.bytes.len().div_ceil(Self::BYTES_PER_LINE)
+    }
+
+    /// Scroll up by `n` lines
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll = self.scroll.saturating_sub(n);
+    }
+
+    /// Scroll down by `n` lines, stopping at the last line
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll = (self.scroll + n).min(self.line_count().saturating_sub(1));
+    }
+}
+
+/// `EXPLAIN QUERY PLAN` output for a statement from the SQL editor, shown as a tree
+#[derive(Debug, Clone)]
+pub struct QueryPlanView {
+    pub statement: String,
+    pub steps: Vec<PlanStep>,
+    /// First step shown
+    pub scroll: usize,
+}
+
+impl QueryPlanView {
+    /// Tree branches to draw before each step, like the sqlite3 shell's `.eqp` output
+    pub fn branches(&self) -> Vec<String> {
+        let steps = &self.steps;
+        // Whether an ancestor at each depth has siblings still to come
+        let mut open: Vec<bool> = Vec::new();
+        steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let last = steps[i + 1..]
+                    .iter()
+                    .take_while(|next| next.depth >= step.depth)
+                    .all(|next| next.depth > step.depth);
+                open.truncate(step.depth);
+                let mut branch: String = open
+                    .iter()
+                    .map(|&more| if more { "│  " } else { "   " })
+                    .collect();
+                branch.push_str(if last { "└─ " } else { "├─ " });
+                open.push(!last);
+                branch
+            })
+            .collect()
+    }
+
+    /// The plan as plain text, for the clipboard
+    pub fn text(&self) -> String {
+        let mut text = String::from("QUERY PLAN\n");
+        for (branch, step) in self.branches().iter().zip(&self.steps) {
+            text.push_str(branch);
+            text.push_str(&step.detail);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Single-line text prompt shown as a popup
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub buffer: String,
+    pub cursor_pos: usize,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, initial: String) -> Self {
+        Self {
+            kind,
+            cursor_pos: initial.chars().count(),
+            buffer: initial,
+        }
+    }
+}
+
+/// How a query parameter's text is bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamType {
+    /// INTEGER or REAL when the text is a number, TEXT otherwise
+    #[default]
+    Auto,
+    Text,
+    Integer,
+    Real,
+}
+
+impl ParamType {
+    /// The next type in the Ctrl+T cycle
+    pub fn next(self) -> Self {
+        match self {
+            ParamType::Auto => ParamType::Text,
+            ParamType::Text => ParamType::Integer,
+            ParamType::Integer => ParamType::Real,
+            ParamType::Real => ParamType::Auto,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ParamType::Auto => "auto",
+            ParamType::Text => "text",
+            ParamType::Integer => "integer",
+            ParamType::Real => "real",
+        }
+    }
+}
+
+/// One placeholder's entry in the parameter form
+#[derive(Debug, Clone, Default)]
+pub struct ParamField {
+    /// As written in the statement, e.g. `:id` or `?1`
+    pub name: String,
+    pub value: String,
+    pub cursor_pos: usize,
+    pub null: bool,
+    pub kind: ParamType,
+}
+
+impl ParamField {
+    /// The value to bind, or why the text doesn't fit the chosen type
+    pub fn bound_value(&self) -> Result<Value, String> {
+        if self.null {
+            return Ok(Value::Null);
+        }
+        let trimmed = self.value.trim();
+        match self.kind {
+            ParamType::Text => Ok(Value::Text(self.value.clone())),
+            ParamType::Integer => trimmed
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("{} isn't an integer", self.name)),
+            ParamType::Real => trimmed
+                .parse()
+                .map(Value::Real)
+                .map_err(|_| format!("{} isn't a number", self.name)),
+            ParamType::Auto => {
+                // Only plain numerals count; f64 parsing also takes "inf" and "NaN"
+                let numeral = trimmed
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+                Ok(match (trimmed.parse(), trimmed.parse()) {
+                    (Ok(i), _) => Value::Integer(i),
+                    (_, Ok(r)) if numeral => Value::Real(r),
+                    _ => Value::Text(self.value.clone()),
+                })
+            }
+        }
+    }
+}
+
+/// How query results are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResultLayout {
+    /// A grid, except a single row too wide to fit is listed one column per line
+    #[default]
+    Auto,
+    Grid,
+    /// The selected row listed one column per line
+    Vertical,
+}
+
+impl ResultLayout {
+    /// The next layout in the `\` cycle
+    pub fn next(self) -> Self {
+        match self {
+            ResultLayout::Auto => ResultLayout::Vertical,
+            ResultLayout::Vertical => ResultLayout::Grid,
+            ResultLayout::Grid => ResultLayout::Auto,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ResultLayout::Auto => "automatic",
+            ResultLayout::Grid => "grid",
+            ResultLayout::Vertical => "vertical",
+        }
+    }
+}
+
+/// Values to bind to a statement's placeholders before it runs
+#[derive(Debug, Clone)]
+pub struct ParamForm {
+    pub statement: String,
+    pub fields: Vec<ParamField>,
+    pub selected: usize,
+    pub error: Option<String>,
+}
+
+/// Replacement of a substring in every value of a column
+#[derive(Debug, Clone, Default)]
+pub struct ColumnReplace {
+    pub table: String,
+    pub column: String,
+    pub find: String,
+    pub replacement: String,
+}
+
+/// The Schema view's form for a column to add to its table
+#[derive(Debug, Clone, Default)]
+pub struct ColumnForm {
+    pub table: String,
+    pub name: String,
+    pub data_type: String,
+    pub not_null: bool,
+    pub default: String,
+    pub selected: ColumnField,
+    /// Cursor in the selected text field
+    pub cursor_pos: usize,
+    pub error: Option<String>,
+}
+
+/// A field of the add-column form, in the order the form shows them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnField {
+    #[default]
+    Name,
+    Type,
+    NotNull,
+    Default,
+}
+
+impl ColumnField {
+    pub const ALL: [ColumnField; 4] = [
+        ColumnField::Name,
+        ColumnField::Type,
+        ColumnField::NotNull,
+        ColumnField::Default,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColumnField::Name => "Name",
+            ColumnField::Type => "Type",
+            ColumnField::NotNull => "NOT NULL",
+            ColumnField::Default => "Default",
+        }
+    }
+}
+
+impl ColumnForm {
+    pub fn new(table: String) -> Self {
+        Self {
+            table,
+            ..Self::default()
+        }
+    }
+
+    /// The selected field's text; None for the NOT NULL checkbox
+    pub fn text_mut(&mut self) -> Option<&mut String> {
+        match self.selected {
+            ColumnField::Name => Some(&mut self.name),
+            ColumnField::Type => Some(&mut self.data_type),
+            ColumnField::NotNull => None,
+            ColumnField::Default => Some(&mut self.default),
+        }
+    }
+
+    /// Select the field `step` places down the form, wrapping around
+    pub fn move_field(&mut self, step: isize) {
+        let fields = ColumnField::ALL.len() as isize;
+        let at = ColumnField::ALL
+            .iter()
+            .position(|field| *field == self.selected)
+            .unwrap_or(0) as isize;
+        self.selected = ColumnField::ALL[(at + step).rem_euclid(fields) as usize];
+        self.cursor_pos = self.text_mut().map_or(0, |text| text.chars().count());
+    }
+
+    /// The column the form describes, or why it can't be added
+    pub fn column(&self) -> Result<NewColumn, String> {
+        if self.name.trim().is_empty() {
+            return Err("The column needs a name".to_string());
+        }
+        let default = Some(self.default.trim())
+            .filter(|default| !default.is_empty())
+            .map(str::to_string);
+        if self.not_null && default.is_none() {
+            return Err("A NOT NULL column needs a default for the rows already there".to_string());
+        }
+        Ok(NewColumn {
+            name: self.name.trim().to_string(),
+            data_type: self.data_type.trim().to_string(),
+            not_null: self.not_null,
+            default,
+        })
+    }
+}
+
+/// The Schema view's form for an index to create on its table
+#[derive(Debug, Clone, Default)]
+pub struct IndexForm {
+    pub table: String,
+    /// The table's columns, in table order
+    pub columns: Vec<String>,
+    /// Indexes into `columns` of those picked, in the order the index takes them
+    pub picked: Vec<usize>,
+    pub selected: usize,
+    pub unique: bool,
+    /// Names of the table's indexes, which the generated name steers clear of
+    pub taken: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl IndexForm {
+    /// Pick the selected column, or unpick it
+    pub fn toggle_selected(&mut self) {
+        match self.picked.iter().position(|&i| i == self.selected) {
+            Some(at) => {
+                self.picked.remove(at);
+            }
+            None => self.picked.push(self.selected),
+        }
+        self.error = None;
+    }
+
+    pub fn picked_columns(&self) -> Vec<String> {
+        self.picked
+            .iter()
+            .map(|&i| self.columns[i].clone())
+            .collect()
+    }
+
+    /// The name the index gets, from its table and columns
+    pub fn name(&self) -> String {
+        index_name(&self.table, &self.picked_columns(), &self.taken)
+    }
+
+    /// The index the form describes, or why it can't be created
+    pub fn index(&self) -> Result<NewIndex, String> {
+        if self.picked.is_empty() {
+            return Err("Pick at least one column with Space".to_string());
+        }
+        Ok(NewIndex {
+            name: self.name(),
+            columns: self.picked_columns(),
+            unique: self.unique,
+        })
+    }
+}
+
+/// Order of the tables within each section of the Tables pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableSort {
+    #[default]
+    Name,
+    /// Most rows first
+    Rows,
+    /// Largest on disk first
+    Size,
+}
+
+impl TableSort {
+    pub fn next(self) -> Self {
+        match self {
+            TableSort::Name => TableSort::Rows,
+            TableSort::Rows => TableSort::Size,
+            TableSort::Size => TableSort::Name,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TableSort::Name => "name",
+            TableSort::Rows => "rows",
+            TableSort::Size => "size",
+        }
+    }
+}
+
+/// A line of the Tables pane: a section header or what it lists
+#[derive(Debug, Clone, Copy)]
+pub enum TableEntry<'a> {
+    Section {
+        /// Alias of the attached database the section belongs to
+        schema: Option<&'a str>,
+        kind: TableKind,
+        /// Entries in the section that pass the filter
+        count: usize,
+        collapsed: bool,
+    },
+    Table(&'a TableInfo),
+}
+
+/// The integrity check popup, open from starting the check until it's closed
+#[derive(Debug, Clone)]
+pub enum IntegrityCheckView {
+    Running { elapsed_ms: u64 },
+    Done { report: CheckReport, scroll: usize },
+}
+
+/// The Database popup listing the PRAGMA settings, open from asking for them until
+/// it's closed
+#[derive(Debug, Clone)]
+pub struct PragmaView {
+    /// None until the worker has read them
+    pub settings: Option<PragmaSettings>,
+    /// Index into [`Pragma::ALL`]
+    pub selected: usize,
+    pub editing: Option<PragmaEdit>,
+    /// What the last change did, or why it failed
+    pub message: Option<String>,
+}
+
+impl PragmaView {
+    pub fn selected_pragma(&self) -> Pragma {
+        Pragma::ALL[self.selected]
+    }
+}
+
+/// A new value being picked for the selected pragma
+#[derive(Debug, Clone)]
+pub enum PragmaEdit {
+    /// Index into the pragma's choices
+    Choice(usize),
+    /// Integer being typed
+    Number(String),
+}
+
+/// The popup listing what loaded extensions added
+#[derive(Debug, Clone)]
+pub struct ExtensionsView {
+    pub lines: Vec<String>,
+    /// First line shown
+    pub scroll: usize,
+}
+
+/// A write that waits for the user to confirm it
+#[derive(Debug, Clone)]
+pub enum ConfirmAction {
+    /// SQL statements to run, one of them destructive, and the keyword that flagged it,
+    /// e.g. "DELETE"
+    Query {
+        statements: Vec<String>,
+        keyword: &'static str,
+        /// Values bound to a lone statement's placeholders
+        params: Vec<Value>,
+    },
+    ReplaceInColumn(ColumnReplace),
+    /// Reopen the database with write access
+    ReopenReadWrite,
+    /// Write a pasted block; cells already holding the pasted value are left out
+    Paste {
+        edits: Vec<PendingEdit>,
+        unchanged: usize,
+        out_of_bounds: usize,
+    },
+    /// Load a large SQL file into the editor, then run it if asked
+    LoadFile {
+        path: PathBuf,
+        size: u64,
+        run: bool,
+    },
+    /// Save the SQL editor over an existing file
+    OverwriteFile {
+        path: PathBuf,
+    },
+    /// Back the database up over an existing file
+    OverwriteBackup {
+        path: PathBuf,
+    },
+    /// Export the diagram over an existing file
+    OverwriteDiagram {
+        path: PathBuf,
+        format: DiagramFormat,
+    },
+    /// Export the rows view as filtered and sorted, or only its page on screen
+    ExportView {
+        path: PathBuf,
+        format: ExportFormat,
+        source: ExportSource,
+        /// The file exists and would be replaced
+        overwrite: bool,
+    },
+    /// Copy rows that make a lot of text to the clipboard
+    CopyRows {
+        text: String,
+        rows: usize,
+        format: ExportFormat,
+    },
+    /// Drop a column of a table, losing its values
+    DropColumn {
+        table: String,
+        column: String,
+    },
+    DropIndex {
+        table: String,
+        index: String,
+    },
+    /// Export rows over an existing file
+    OverwriteExport {
+        path: PathBuf,
+        format: ExportFormat,
+        source: ExportSource,
+    },
+}
+
+/// A write held until the user confirms it, with an estimate of its reach
+#[derive(Debug, Clone)]
+pub struct PendingConfirm {
+    pub action: ConfirmAction,
+    /// Rows the write would affect, once counted
+    pub affected_rows: Option<i64>,
+    /// A row count was requested and hasn't arrived yet
+    pub counting: bool,
+}
+
+/// A rows-view position saved before following a foreign key, restored on Backspace
+#[derive(Debug, Clone)]
+pub struct NavEntry {
+    pub table: String,
+    pub page: usize,
+    pub view: RowsQuery,
+    pub selected_row: usize,
+    pub selected_col: usize,
+}
+
+/// How the page of rows shown was found when it was found by rowid, from the page next
+/// to it, rather than by position; loading it again starts from the same rowid
+#[derive(Debug, Clone)]
+pub struct PageAnchor {
+    pub table: String,
+    pub view: RowsQuery,
+    pub page: usize,
+    pub start: PageStart,
+    /// Rows before the page may have come or gone since its number was worked out
+    pub estimated: bool,
+}
+
+/// Where the pages of a table's rows start, as far as paging through them has found:
+/// a page the memory cap cut short moves the start of every page after it
+#[derive(Debug, Clone)]
+pub struct PageOffsets {
+    pub table: String,
+    pub view: RowsQuery,
+    pub page_size: usize,
+    /// The offset of each page from the first, up to the page after the last loaded
+    pub starts: Vec<usize>,
+}
+
+/// A page of rows fetched ahead of being asked for, shown at once when it is
+#[derive(Debug, Clone)]
+pub struct CachedPage {
+    pub table: String,
+    pub view: RowsQuery,
+    pub page: usize,
+    pub result: QueryResult,
+}
+
+/// The Ctrl+R history search popup
+#[derive(Debug, Clone, Default)]
+pub struct HistoryPopup {
+    pub filter: String,
+    /// Index into the entries matching the filter, newest first
+    pub selected: usize,
+}
+
+/// The Ctrl+Q picker over saved queries
+#[derive(Debug, Clone)]
+pub struct SavedQueryPicker {
+    pub queries: Vec<SavedQuery>,
+    pub selected: usize,
+}
+
+/// A saved cell to select again after the reload, found by its row key since sorting
+/// can move the row
+#[derive(Debug, Clone)]
+pub struct CursorRestore {
+    pub key: RowKey,
+    pub col: usize,
+    /// Whether the worker has already been asked which page the row is on
+    pub located: bool,
+}
+
+/// The query results of an SQL editor buffer that isn't the active one
+#[derive(Debug, Clone, Default)]
+pub struct BufferResults {
+    pub query_result: Option<QueryResult>,
+    pub query_page_starts: Vec<usize>,
+    pub query_error: Option<String>,
+    pub last_query: Option<String>,
+    pub last_executed_query: Option<String>,
+    pub query_params: Vec<Value>,
+    pub script_steps: Vec<ScriptStep>,
+    pub selected_row: usize,
+    pub selected_col: usize,
+}
+
+/// One of the SQL editor's scratch buffers
+#[derive(Debug, Clone, Default)]
+pub struct SqlBuffer {
+    pub query: String,
+    pub cursor_pos: usize,
+    /// Results parked while another buffer is active; the active buffer's are in AppState
+    pub results: BufferResults,
+}
+
+impl SqlBuffer {
+    /// Replace the text, putting the cursor at its end
+    pub fn replace(&mut self, query: String) {
+        self.cursor_pos = query.chars().count();
+        self.query = query;
+    }
+}
+
+/// A failed request kept in the error log
+#[derive(Debug, Clone)]
+pub struct LoggedError {
+    /// Local time it arrived, as HH:MM:SS
+    pub at: String,
+    pub context: ErrorContext,
+    pub message: String,
+}
+
+/// Number of rows PageUp/PageDown move the row selection by
+pub const ROW_JUMP: usize = 10;
+/// Rows fetched per page of query results unless `--query-limit` says otherwise
+pub const DEFAULT_QUERY_LIMIT: usize = 1000;
+/// Rows past which a table shows its largest rowid rather than being counted, unless
+/// `--count-limit` says otherwise
+pub const DEFAULT_COUNT_LIMIT: u64 = 5_000_000;
+/// Bytes of rows a page of table rows or query results may hold unless
+/// `--result-memory` says otherwise
+pub const DEFAULT_RESULT_MEMORY: usize = 16 * 1024 * 1024;
+/// Retries of a read on a locked database unless `--busy-retries` says otherwise
+pub const DEFAULT_BUSY_RETRIES: u32 = 3;
+/// Most pages of rows fetched ahead and kept, to bound their memory
+pub const MAX_CACHED_PAGES: usize = 2;
+/// Most SQL editor buffers open at once, one per Alt+digit
+pub const MAX_SQL_BUFFERS: usize = 9;
+/// Errors kept for the `E` popup; older ones are dropped
+pub const ERROR_LOG_LEN: usize = 50;
+/// Cells an arrow key pans the diagram by, across and down
+pub const DIAGRAM_PAN: (i32, i32) = (8, 3);
+
 /// Application state
 #[derive(Debug)]
 pub struct AppState {
     // Tables pane
     pub tables: Vec<TableInfo>,
+    /// Index into [`AppState::table_entries`]
     pub selected_table_index: usize,
+    /// Sections of the Tables pane folded away, by database alias and kind
+    pub collapsed_sections: Vec<(Option<String>, TableKind)>,
+    pub table_sort: TableSort,
     pub table_filter: String,
     pub show_internal_tables: bool,
     pub tables_loading: bool,
+    pub tables_error: Option<String>,
+    /// Tables whose rows the worker hasn't counted yet
+    pub uncounted_tables: HashSet<String>,
+    /// When the tables started being counted, which turns the Tables pane's spinner
+    pub counting_since: Instant,
+    /// Rows past which a table's largest rowid stands in for its count
+    pub count_limit: u64,
+    /// A read waiting on another connection's lock: the retry it's on and the
+    /// milliseconds waited so far
+    pub busy: Option<(u32, u64)>,
+    /// Retries a read on a locked database gets
+    pub busy_retries: u32,
+    /// The database was opened read-only, so edits are refused up front
+    pub read_only: bool,
+    /// The database is a temporary copy of one piped on stdin, which stays read-only
+    /// as edits to it would be thrown away
+    pub stdin_copy: bool,
+    /// Key the database was unlocked with, used again if it's reopened
+    pub key: Option<String>,
+    /// Extensions loaded with --extension, loaded again if the database is reopened
+    pub extensions: Vec<String>,
+    /// SQL can call load_extension()
+    pub allow_extensions: bool,
+    /// Databases attached, by file and alias, attached again if the worker is restarted
+    pub attached: Vec<(String, String)>,
+    /// A worker thread died; nothing reaches the database until it's restarted
+    pub worker_crashed: bool,
+    /// The database's journal mode, read with the table list
+    pub journal_mode: Option<String>,
+    /// Pages of a table are read from one snapshot, taken when it's opened and let go
+    /// on refresh, table switch, any write, or after a while without input
+    pub snapshot_mode: bool,
+    /// When the snapshot held now was taken
+    pub snapshot: Option<String>,
+    /// What loaded extensions added, once listed
+    pub extensions_view: Option<ExtensionsView>,
 
     // Content pane
     pub view_mode: ViewMode,
     pub current_table: Option<String>,
     pub table_rows: Option<QueryResult>,
     pub current_page: usize,
+    /// Where the pages of the rows shown start, once one was cut short
+    pub page_offsets: Option<PageOffsets>,
+    /// Set while the page shown was found by rowid
+    pub page_anchor: Option<PageAnchor>,
+    /// Pages after the one shown, fetched while idle; emptied on any edit, table switch
+    /// or refresh
+    pub page_cache: VecDeque<CachedPage>,
     pub page_size: usize,
     pub rows_loading: bool,
+    pub rows_error: Option<String>,
+    pub selected_row: usize,
+    pub selected_col: usize,
+    /// Where a multi-row selection started; the selection runs from here to the cursor row
+    pub row_anchor: Option<usize>,
+    pub sort_column: Option<(String, SortOrder)>,
+    pub row_filter: Option<String>,
+    pub key_match: Option<(String, Value)>,
+    pub table_foreign_keys: Vec<ForeignKeyInfo>,
+    pub nav_stack: Vec<NavEntry>,
+    pub search_term: Option<String>,
+    pub search_match_index: usize,
+    pub auto_column_width: bool,
+    pub pinned_cols: usize,
+    pub row_gutter: RowGutter,
+    pub show_value_markers: bool,
+    pub wrap_cells: bool,
 
     // Query editor
-    pub sql_query: String,
+    pub sql_buffers: Vec<SqlBuffer>,
+    /// Index of the buffer shown in the SQL editor
+    pub active_buffer: usize,
     pub query_result: Option<QueryResult>,
+    /// Where the pages of the query result start, up to the one shown: a page the
+    /// memory cap cut short moves the start of every page after it
+    pub query_page_starts: Vec<usize>,
     pub query_error: Option<String>,
     pub query_loading: bool,
+    /// Rows fetched per page of query results
+    pub query_limit: usize,
+    /// Bytes of rows a page of table rows or query results may hold; the rest are left
+    /// for the next page
+    pub result_memory: usize,
+    pub result_layout: ResultLayout,
+    /// Show value counts and ranges under the query results grid
+    pub show_column_summary: bool,
+    pub query_selected_row: usize,
+    pub query_selected_col: usize,
+    /// Keys go to the results grid instead of the SQL editor in the Query view
+    pub query_grid_focused: bool,
+    /// Statement whose result is in the grid, re-run after editing its results
+    pub last_query: Option<String>,
+    /// Statements of the last run from the SQL editor, joined by `;`, for `r` to run again
+    pub last_executed_query: Option<String>,
+    /// Values bound to the last query's placeholders, bound again when it re-runs
+    pub query_params: Vec<Value>,
+    /// Every statement of the last run from the SQL editor, with its outcome
+    pub script_steps: Vec<ScriptStep>,
+    /// The file the SQL editor was last loaded from or saved to
+    pub sql_file: Option<PathBuf>,
+    /// How long the running query has taken, as last reported by the worker
+    pub query_elapsed_ms: Option<u64>,
+    /// Why the last run from the SQL editor has no results: it was cancelled
+    pub query_cancelled: Option<String>,
+    pub history: History,
+    /// How far back Up/Ctrl+P has stepped through history, 0 being the newest entry
+    pub history_index: Option<usize>,
+    pub history_popup: Option<HistoryPopup>,
+    pub completion: Option<Completion>,
+    pub query_plan: Option<QueryPlanView>,
+    /// Column names for completion, keyed by lowercased table name
+    pub completion_columns: HashMap<String, Vec<String>>,
+    pub saved_queries: SavedQueries,
+    pub saved_query_picker: Option<SavedQueryPicker>,
+    pub param_form: Option<ParamForm>,
+    /// The last values entered for each parameter name this session
+    pub param_memory: HashMap<String, ParamField>,
 
     // Info pane
     pub table_info: Option<TableInfo>,
 
     // Schema data
+    /// Table the schema data below describes
+    pub schema_table: Option<String>,
     pub schema_columns: Vec<ColumnInfo>,
     pub schema_indexes: Vec<IndexInfo>,
     pub schema_foreign_keys: Vec<ForeignKeyInfo>,
+    /// Other tables' foreign keys referencing the table
+    pub schema_referenced_by: Vec<ForeignKeyInfo>,
+    /// What the Schema view has selected: an index into its columns, then its indexes,
+    /// then the keys referencing the table
+    pub schema_selected: usize,
+    pub column_form: Option<ColumnForm>,
+    pub index_form: Option<IndexForm>,
+    /// Selected entry of the Schema view's menu of SQL to generate, while it's open
+    pub sql_template_menu: Option<usize>,
+    /// Name of the index being built
+    pub creating_index: Option<String>,
     pub schema_loading: bool,
+    /// Why the schema or diagram couldn't be loaded
+    pub schema_error: Option<String>,
 
     // Diagram data
     pub diagram_data: Option<DiagramData>,
     pub diagram_loading: bool,
+    /// How far the diagram is panned, in cells from its top left corner
+    pub diagram_offset: (i32, i32),
+    /// Table selected in the diagram, by name so it stays selected when the diagram
+    /// is read again
+    pub diagram_selected: Option<String>,
+    pub diagram_layout_kind: DiagramLayoutKind,
+    /// Text a table's name must contain for the diagram to show it, with the tables it's
+    /// joined to
+    pub diagram_filter: Option<String>,
+    /// Table the diagram shows alone with the tables joined to it
+    pub diagram_neighbours_of: Option<String>,
+    /// Tables whose box in the diagram is shrunk to its title bar, by name
+    pub diagram_collapsed: HashSet<String>,
+    /// The tables the diagram shows, those of `diagram_data` the filters leave
+    pub diagram_shown: Option<DiagramData>,
+    /// For each table shown, how many of its keys join it to a table left out
+    pub diagram_hidden_keys: Vec<usize>,
+    /// Where the shown tables go, worked out when the diagram, its filters or the kind
+    /// of layout change rather than on every frame
+    pub diagram_layout: Option<DiagramLayout>,
 
     // UI state
     pub focus: Focus,
     pub show_help: bool,
+    /// First line of the help popup shown
+    pub help_scroll: usize,
     pub show_sql_editor: bool,
+    pub prompt: Option<Prompt>,
+    pub pending_confirm: Option<PendingConfirm>,
+    /// Column replace being filled in through the find and replace prompts
+    pub replace_draft: Option<ColumnReplace>,
+    /// A column replace was sent to the worker and hasn't finished
+    pub replace_running: bool,
+    /// Ask before running DELETE/UPDATE/DROP/ALTER from the SQL editor
+    pub confirm_destructive: bool,
+    pub blob_viewer: Option<BlobViewer>,
+    pub watch: bool,
+    pub last_refresh: Option<String>,
+    pub status_message: Option<String>,
+    /// The file an export is writing, while it runs
+    pub exporting: Option<PathBuf>,
+    /// Where the running backup is going
+    pub backing_up: Option<PathBuf>,
+    /// The most recent failed requests, oldest first
+    pub error_log: VecDeque<LoggedError>,
+    /// Scroll offset of the error log popup while it's open
+    pub error_log_popup: Option<usize>,
+    pub integrity_check: Option<IntegrityCheckView>,
+    pub pragma_view: Option<PragmaView>,
 
     // Edit mode
     pub edit_mode: bool,
     pub editing_row: Option<usize>,
     pub editing_col: Option<usize>,
     pub edit_buffer: String,
+    /// Why the last save of an edit failed or was refused
+    pub edit_error: Option<String>,
+    /// The edit stores SQL NULL rather than the buffer's text
+    pub edit_null: bool,
+    /// The cell being edited is a BLOB, so the buffer holds hex or base64
+    pub edit_blob: bool,
+    /// Hold edits as pending until written with `w`, instead of saving each one
+    pub staged_edits: bool,
+    pub pending_edits: Vec<PendingEdit>,
+    /// After saving a cell, go on to edit the same column of the next row
+    pub edit_next_row: bool,
+    /// Cell to put the cursor back on once the grid reloads after a save
+    pub restore_cursor: Option<CursorRestore>,
     pub edit_cursor_pos: usize,
     pub full_edit_mode: bool,
-    pub sql_cursor_pos: usize,
 }
 
 impl AppState {
@@ -74,42 +1052,166 @@ impl AppState {
         Self {
             tables: Vec::new(),
             selected_table_index: 0,
+            collapsed_sections: Vec::new(),
+            table_sort: TableSort::default(),
             table_filter: String::new(),
             show_internal_tables: false,
             tables_loading: false,
+            tables_error: None,
+            uncounted_tables: HashSet::new(),
+            counting_since: Instant::now(),
+            count_limit: DEFAULT_COUNT_LIMIT,
+            busy: None,
+            busy_retries: DEFAULT_BUSY_RETRIES,
+            read_only: true,
+            stdin_copy: false,
+            key: None,
+            extensions: Vec::new(),
+            allow_extensions: false,
+            attached: Vec::new(),
+            worker_crashed: false,
+            journal_mode: None,
+            snapshot_mode: false,
+            snapshot: None,
+            extensions_view: None,
             view_mode: ViewMode::Rows,
             current_table: None,
             table_rows: None,
             current_page: 0,
+            page_offsets: None,
+            page_anchor: None,
+            page_cache: VecDeque::new(),
             page_size,
             rows_loading: false,
-            sql_query: String::new(),
+            rows_error: None,
+            selected_row: 0,
+            selected_col: 0,
+            row_anchor: None,
+            sort_column: None,
+            row_filter: None,
+            key_match: None,
+            table_foreign_keys: Vec::new(),
+            nav_stack: Vec::new(),
+            search_term: None,
+            search_match_index: 0,
+            auto_column_width: true,
+            pinned_cols: 0,
+            row_gutter: RowGutter::Hidden,
+            show_value_markers: true,
+            wrap_cells: false,
+            sql_buffers: vec![SqlBuffer::default()],
+            active_buffer: 0,
             query_result: None,
             query_error: None,
             query_loading: false,
+            query_page_starts: Vec::new(),
+            query_limit: DEFAULT_QUERY_LIMIT,
+            result_memory: DEFAULT_RESULT_MEMORY,
+            result_layout: ResultLayout::Auto,
+            show_column_summary: false,
+            query_selected_row: 0,
+            query_selected_col: 0,
+            query_grid_focused: false,
+            last_query: None,
+            last_executed_query: None,
+            query_params: Vec::new(),
+            script_steps: Vec::new(),
+            query_elapsed_ms: None,
+            sql_file: None,
+            query_cancelled: None,
+            history: History::default(),
+            history_index: None,
+            history_popup: None,
+            completion: None,
+            query_plan: None,
+            completion_columns: HashMap::new(),
+            saved_queries: SavedQueries::default(),
+            saved_query_picker: None,
+            param_form: None,
+            param_memory: HashMap::new(),
             table_info: None,
+            schema_table: None,
             schema_columns: Vec::new(),
             schema_indexes: Vec::new(),
             schema_foreign_keys: Vec::new(),
+            schema_referenced_by: Vec::new(),
+            schema_selected: 0,
+            column_form: None,
+            index_form: None,
+            sql_template_menu: None,
+            creating_index: None,
             schema_loading: false,
+            schema_error: None,
             diagram_data: None,
             diagram_loading: false,
+            diagram_offset: (0, 0),
+            diagram_selected: None,
+            diagram_layout_kind: DiagramLayoutKind::default(),
+            diagram_filter: None,
+            diagram_neighbours_of: None,
+            diagram_collapsed: HashSet::new(),
+            diagram_shown: None,
+            diagram_hidden_keys: Vec::new(),
+            diagram_layout: None,
             focus: Focus::Content,
             show_help: false,
+            help_scroll: 0,
             show_sql_editor: true,
+            prompt: None,
+            pending_confirm: None,
+            replace_draft: None,
+            replace_running: false,
+            confirm_destructive: true,
+            blob_viewer: None,
+            watch: false,
+            last_refresh: None,
+            status_message: None,
+            exporting: None,
+            backing_up: None,
+            error_log: VecDeque::new(),
+            error_log_popup: None,
+            integrity_check: None,
+            pragma_view: None,
             edit_mode: false,
             editing_row: None,
             editing_col: None,
             edit_buffer: String::new(),
+            edit_error: None,
+            edit_null: false,
+            edit_blob: false,
+            staged_edits: false,
+            pending_edits: Vec::new(),
+            edit_next_row: false,
+            restore_cursor: None,
             edit_cursor_pos: 0,
             full_edit_mode: false,
-            sql_cursor_pos: 0,
         }
     }
 
-    /// Get filtered tables
+    /// Log a failed request and show it where its context belongs
+    pub fn record_error(&mut self, context: ErrorContext, message: String) {
+        if self.error_log.len() == ERROR_LOG_LEN {
+            self.error_log.pop_front();
+        }
+        self.error_log.push_back(LoggedError {
+            at: chrono::Local::now().format("%H:%M:%S").to_string(),
+            context,
+            message: message.clone(),
+        });
+        let slot = match context {
+            ErrorContext::Tables => &mut self.tables_error,
+            ErrorContext::Rows => &mut self.rows_error,
+            ErrorContext::Query => &mut self.query_error,
+            ErrorContext::Schema | ErrorContext::Diagram => &mut self.schema_error,
+            ErrorContext::Edit => &mut self.edit_error,
+        };
+        *slot = Some(message);
+    }
+
+    /// Get filtered tables, ordered by [`AppState::table_sort`] within each database
+    /// and kind
     pub fn filtered_tables(&self) -> Vec<&TableInfo> {
-        if self.table_filter.is_empty() {
+        let mut tables: Vec<&TableInfo> = if self.table_filter.is_empty() {
             self.tables.iter().collect()
         } else {
             self.tables
@@ -120,20 +1222,270 @@ impl AppState {
                         .contains(&self.table_filter.to_lowercase())
                 })
                 .collect()
+        };
+        let metric = match self.table_sort {
+            TableSort::Name => return tables,
+            TableSort::Rows => |t: &TableInfo| t.row_count,
+            TableSort::Size => |t: &TableInfo| t.size,
+        };
+        // The list comes grouped by database and kind; biggest first within each group,
+        // unmeasured last, and by name among equals as the sort is stable
+        let mut groups: Vec<(Option<&str>, TableKind)> = Vec::new();
+        for table in &self.tables {
+            let group = (table.schema.as_deref(), table.kind);
+            if groups.last() != Some(&group) {
+                groups.push(group);
+            }
+        }
+        tables.sort_by_key(|t| {
+            let group = groups
+                .iter()
+                .position(|g| *g == (t.schema.as_deref(), t.kind));
+            (group, std::cmp::Reverse(metric(t)))
+        });
+        tables
+    }
+
+    /// The lines of the Tables pane the selection moves over. With only tables to list,
+    /// that's the filtered tables; otherwise each kind of object gets a section header,
+    /// and a folded section lists nothing under it.
+    pub fn table_entries(&self) -> Vec<TableEntry<'_>> {
+        let tables = self.filtered_tables();
+        if self.tables.iter().all(|t| t.kind == TableKind::Table) {
+            return tables.into_iter().map(TableEntry::Table).collect();
+        }
+        let mut entries = Vec::new();
+        let mut rest = tables.as_slice();
+        while let Some(first) = rest.first() {
+            let (schema, kind) = (first.schema.as_deref(), first.kind);
+            let count = rest
+                .iter()
+                .take_while(|t| t.schema.as_deref() == schema && t.kind == kind)
+                .count();
+            let collapsed = self
+                .collapsed_sections
+                .iter()
+                .any(|(s, k)| s.as_deref() == schema && *k == kind);
+            entries.push(TableEntry::Section {
+                schema,
+                kind,
+                count,
+                collapsed,
+            });
+            if !collapsed {
+                entries.extend(rest[..count].iter().copied().map(TableEntry::Table));
+            }
+            rest = &rest[count..];
+        }
+        entries
+    }
+
+    /// The selected table, view or trigger; None on a section header
+    pub fn selected_table_info(&self) -> Option<&TableInfo> {
+        match self.table_entries().get(self.selected_table_index) {
+            Some(TableEntry::Table(table)) => Some(table),
+            _ => None,
         }
     }
 
     /// Get selected table name
     pub fn selected_table(&self) -> Option<&str> {
-        let filtered = self.filtered_tables();
-        filtered
-            .get(self.selected_table_index)
-            .map(|t| t.name.as_str())
+        self.selected_table_info().map(|t| t.name.as_str())
+    }
+
+    /// What a read waiting on a lock shows in place of its loading message
+    pub fn busy_message(&self) -> Option<String> {
+        self.busy.map(|(attempt, waited_ms)| {
+            format!(
+                "Database locked, retrying ({}/{}) after {:.1}s...",
+                attempt,
+                self.busy_retries,
+                waited_ms as f64 / 1000.0
+            )
+        })
+    }
+
+    /// Roughly how many bytes of rows are held: the table rows shown, and the query
+    /// results of every SQL buffer, counting each statement of a script run
+    pub fn result_memory_in_use(&self) -> (usize, usize) {
+        let rows = self.table_rows.as_ref().map_or(0, |result| result.bytes);
+        let step_results = |steps: &[ScriptStep]| -> usize {
+            steps
+                .iter()
+                .filter_map(|step| step.outcome.as_ref()?.as_ref().ok())
+                .map(|result| result.bytes)
+                .sum()
+        };
+        let parked: usize = self
+            .sql_buffers
+            .iter()
+            .map(|buffer| {
+                let results = &buffer.results;
+                results.query_result.as_ref().map_or(0, |r| r.bytes)
+                    + step_results(&results.script_steps)
+            })
+            .sum();
+        let active =
+            self.query_result.as_ref().map_or(0, |r| r.bytes) + step_results(&self.script_steps);
+        (rows, parked + active)
+    }
+
+    /// Select the table called `name` in the Tables pane, if it's listed
+    pub fn select_table_named(&mut self, name: &str) {
+        let found = self
+            .table_entries()
+            .iter()
+            .position(|entry| matches!(entry, TableEntry::Table(t) if t.name == name));
+        if let Some(index) = found {
+            self.selected_table_index = index;
+        }
+    }
+
+    /// Alias of the attached database the selected table or section is in
+    pub fn selected_schema(&self) -> Option<&str> {
+        match self.table_entries().get(self.selected_table_index) {
+            Some(TableEntry::Table(table)) => table.schema.as_deref(),
+            Some(TableEntry::Section { schema, .. }) => *schema,
+            None => None,
+        }
+    }
+
+    /// Fold or unfold the section whose header is selected. Returns false when a
+    /// header isn't selected.
+    pub fn toggle_selected_section(&mut self) -> bool {
+        let Some(TableEntry::Section { schema, kind, .. }) =
+            self.table_entries().get(self.selected_table_index).copied()
+        else {
+            return false;
+        };
+        let section = (schema.map(str::to_string), kind);
+        match self.collapsed_sections.iter().position(|s| *s == section) {
+            Some(at) => {
+                self.collapsed_sections.remove(at);
+            }
+            None => self.collapsed_sections.push(section),
+        }
+        true
+    }
+
+    /// What kind of object the table, view or trigger named `name` is
+    pub fn table_kind(&self, name: &str) -> Option<TableKind> {
+        self.tables.iter().find(|t| t.name == name).map(|t| t.kind)
+    }
+
+    /// The column selected in the Schema view
+    pub fn selected_schema_column(&self) -> Option<&ColumnInfo> {
+        self.schema_columns.get(self.schema_selected)
+    }
+
+    /// The index selected in the Schema view, listed after the columns
+    pub fn selected_schema_index(&self) -> Option<&IndexInfo> {
+        self.schema_selected
+            .checked_sub(self.schema_columns.len())
+            .and_then(|i| self.schema_indexes.get(i))
+    }
+
+    /// The referencing key selected in the Schema view, listed after the indexes
+    pub fn selected_schema_reference(&self) -> Option<&ForeignKeyInfo> {
+        self.schema_selected
+            .checked_sub(self.schema_columns.len() + self.schema_indexes.len())
+            .and_then(|i| self.schema_referenced_by.get(i))
+    }
+
+    /// Columns, indexes and referencing keys the Schema view can select
+    pub fn schema_entries(&self) -> usize {
+        self.schema_columns.len() + self.schema_indexes.len() + self.schema_referenced_by.len()
+    }
+
+    /// Why the Schema view's table can't be changed at all, if it can't: a read-only
+    /// database, or not a plain table
+    fn table_change_blocked(&self) -> Option<String> {
+        if self.read_only {
+            return Some("Read-only - press Ctrl+W to change the table".to_string());
+        }
+        let table = self.schema_table.as_deref()?;
+        match self.table_kind(table) {
+            Some(TableKind::View) => Some(format!("{} is a view", table)),
+            Some(TableKind::Virtual) => Some(format!("{} is a virtual table", table)),
+            _ => None,
+        }
+    }
+
+    /// Why the Schema view can't `action` its table, or the selected column, if it
+    /// can't: SQLite too old for the ALTER TABLE form, a table that can't change, or a
+    /// column a constraint or index depends on
+    pub fn column_action_blocked(&self, action: ColumnAction) -> Option<String> {
+        if let Some(reason) = self.table_change_blocked() {
+            return Some(reason);
+        }
+        if let Some(reason) = action.unsupported() {
+            return Some(reason);
+        }
+        if action == ColumnAction::Add {
+            return None;
+        }
+        let column = self.selected_schema_column()?;
+        if action == ColumnAction::Drop {
+            let name = &column.name;
+            if self.schema_columns.len() == 1 {
+                return Some(format!("{} is the table's only column", name));
+            }
+            if column.primary_key {
+                return Some(format!("{} is part of the primary key", name));
+            }
+            if let Some(index) = self
+                .schema_indexes
+                .iter()
+                .find(|index| index.columns.contains(name))
+            {
+                return Some(if index.name.starts_with("sqlite_autoindex_") {
+                    format!("{} has a UNIQUE constraint", name)
+                } else {
+                    format!("{} is indexed by {}", name, index.name)
+                });
+            }
+            if let Some(fk) = self
+                .schema_foreign_keys
+                .iter()
+                .find(|fk| fk.from_column == *name)
+            {
+                return Some(format!("{} is a foreign key to {}", name, fk.to_table));
+            }
+        }
+        None
+    }
+
+    /// Why the Schema view can't `action` its table's indexes, or the selected one:
+    /// a table that can't change, or an index a constraint made
+    pub fn index_action_blocked(&self, action: IndexAction) -> Option<String> {
+        if let Some(reason) = self.table_change_blocked() {
+            return Some(reason);
+        }
+        if action == IndexAction::Create {
+            return None;
+        }
+        let index = self.selected_schema_index()?;
+        match index.origin {
+            IndexOrigin::Created => None,
+            IndexOrigin::Unique => Some(format!("{} belongs to a UNIQUE constraint", index.name)),
+            IndexOrigin::PrimaryKey => Some(format!("{} is the table's primary key", index.name)),
+        }
+    }
+
+    /// Aliases of the attached databases with tables listed, in the order they're listed
+    pub fn attached_schemas(&self) -> Vec<String> {
+        let mut schemas: Vec<String> = Vec::new();
+        for schema in self.tables.iter().filter_map(|t| t.schema.as_ref()) {
+            if !schemas.contains(schema) {
+                schemas.push(schema.clone());
+            }
+        }
+        schemas
     }
 
     /// Move selection up
     pub fn move_up(&mut self) {
-        let filtered_len = self.filtered_tables().len();
+        let filtered_len = self.table_entries().len();
         if filtered_len > 0 {
             self.selected_table_index =
                 (self.selected_table_index + filtered_len - 1) % filtered_len;
@@ -142,7 +1494,7 @@ impl AppState {
 
     /// Move selection down
     pub fn move_down(&mut self) {
-        let filtered_len = self.filtered_tables().len();
+        let filtered_len = self.table_entries().len();
         if filtered_len > 0 {
             self.selected_table_index = (self.selected_table_index + 1) % filtered_len;
         }
@@ -176,6 +1528,148 @@ impl AppState {
         };
     }
 
+    /// How to find the next or previous page by rowid from the one shown: the rows after
+    /// its last rowid or before its first. None when the rows are sorted or have no
+    /// rowids, or for the first page, which is cheap to find by position.
+    pub fn adjacent_page_anchor(&self, forward: bool) -> Option<PageAnchor> {
+        if self.sort_column.is_some() {
+            return None;
+        }
+        let table = self.current_table.clone()?;
+        let rowids = self.table_rows.as_ref()?.rowids()?;
+        let (page, start) = if forward {
+            (self.current_page + 1, PageStart::After(*rowids.last()?))
+        } else {
+            let page = self.current_page.checked_sub(1).filter(|&page| page > 0)?;
+            (page, PageStart::Before(*rowids.first()?))
+        };
+        // A page counted from a guess is a guess too
+        let estimated = self
+            .page_anchor
+            .as_ref()
+            .is_some_and(|anchor| anchor.estimated && anchor.page == self.current_page);
+        Some(PageAnchor {
+            table,
+            view: self.rows_query(),
+            page,
+            start,
+            estimated,
+        })
+    }
+
+    /// Whether the number of the page shown is a guess, as rows before it may have come
+    /// or gone since it was found by rowid
+    pub fn page_estimated(&self) -> bool {
+        self.page_anchor
+            .as_ref()
+            .is_some_and(|anchor| anchor.estimated && anchor.page == self.current_page)
+    }
+
+    /// Keep a page fetched ahead, dropping the oldest past [`MAX_CACHED_PAGES`]
+    pub fn cache_page(&mut self, page: CachedPage) {
+        self.page_cache.retain(|cached| {
+            !(cached.table == page.table && cached.view == page.view && cached.page == page.page)
+        });
+        self.page_cache.push_back(page);
+        while self.page_cache.len() > MAX_CACHED_PAGES {
+            self.page_cache.pop_front();
+        }
+    }
+
+    /// Take the page `page` of `table` seen through `view` out of the cache, if it's there
+    pub fn take_cached_page(
+        &mut self,
+        table: &str,
+        view: &RowsQuery,
+        page: usize,
+    ) -> Option<QueryResult> {
+        let index = self.page_cache.iter().position(|cached| {
+            cached.table == table && &cached.view == view && cached.page == page
+        })?;
+        self.page_cache.remove(index).map(|cached| cached.result)
+    }
+
+    /// How many rows in the current table's page `page` starts. Pages are `page_size`
+    /// rows apart, except after one the memory cap cut short, where the next begins at
+    /// the first row it left out.
+    pub fn page_offset(&self, page: usize) -> usize {
+        match self.known_page_starts() {
+            Some(starts) => match starts.get(page) {
+                Some(&start) => start,
+                None => starts[starts.len() - 1] + (page + 1 - starts.len()) * self.page_size,
+            },
+            None => page * self.page_size,
+        }
+    }
+
+    /// The page of the current table holding the row `position` rows in, and the row's
+    /// index on it, going by where the pages start like `page_offset`
+    pub fn page_of_row(&self, position: usize) -> (usize, usize) {
+        let page = match self.known_page_starts() {
+            Some(starts) if position >= starts[starts.len() - 1] => {
+                starts.len() - 1 + (position - starts[starts.len() - 1]) / self.page_size
+            }
+            Some(starts) => starts.partition_point(|&start| start <= position) - 1,
+            None => position / self.page_size,
+        };
+        (page, position - self.page_offset(page))
+    }
+
+    /// Where the current table's pages start, if paging through them has noted it
+    fn known_page_starts(&self) -> Option<&[usize]> {
+        let known = self.page_offsets.as_ref().filter(|offsets| {
+            Some(&offsets.table) == self.current_table.as_ref()
+                && offsets.view == self.rows_query()
+                && offsets.page_size == self.page_size
+        });
+        known
+            .map(|offsets| offsets.starts.as_slice())
+            .filter(|starts| !starts.is_empty())
+    }
+
+    /// Note where the page after the one shown starts, from how many rows it holds
+    pub fn note_page_end(&mut self) {
+        let (Some(table), Some(result)) = (&self.current_table, &self.table_rows) else {
+            return;
+        };
+        let page = self.current_page;
+        let end = result.offset + result.rows.len();
+        let mut starts: Vec<usize> = (0..=page).map(|p| self.page_offset(p)).collect();
+        starts[page] = result.offset;
+        starts.push(end);
+        self.page_offsets = Some(PageOffsets {
+            table: table.clone(),
+            view: self.rows_query(),
+            page_size: self.page_size,
+            starts,
+        });
+    }
+
+    /// Note where the page of query results shown starts, forgetting the pages after it
+    pub fn note_query_page(&mut self) {
+        let Some(result) = &self.query_result else {
+            return;
+        };
+        let offset = result.offset;
+        self.query_page_starts.retain(|&start| start < offset);
+        self.query_page_starts.push(offset);
+    }
+
+    /// Where the page of query results before the one shown starts, if there is one
+    pub fn prev_query_page_offset(&self) -> Option<usize> {
+        let offset = self.query_result.as_ref()?.offset;
+        if offset == 0 {
+            return None;
+        }
+        let noted = self
+            .query_page_starts
+            .iter()
+            .rev()
+            .find(|&&start| start < offset)
+            .copied();
+        Some(noted.unwrap_or(offset.saturating_sub(self.query_limit)))
+    }
+
     /// Go to next page
     pub fn next_page(&mut self) {
         self.current_page += 1;
@@ -187,4 +1681,919 @@ impl AppState {
             self.current_page -= 1;
         }
     }
+
+    /// Whether the content pane is showing rows and should receive row navigation
+    pub fn rows_view_focused(&self) -> bool {
+        self.focus == Focus::Content && self.view_mode == ViewMode::Rows && !self.edit_mode
+    }
+
+    /// Whether the diagram receives arrow keys, to pan it
+    pub fn diagram_focused(&self) -> bool {
+        self.focus == Focus::Content && self.view_mode == ViewMode::Diagram
+    }
+
+    /// Show `data` as the diagram, laying it out afresh
+    pub fn set_diagram_data(&mut self, data: DiagramData) {
+        // Showing the neighbours of a table that's gone would show nothing
+        if let Some(name) = &self.diagram_neighbours_of {
+            if data.table_named(name).is_none() {
+                self.diagram_neighbours_of = None;
+            }
+        }
+        self.diagram_data = Some(data);
+        self.relayout_diagram();
+    }
+
+    /// Filter the diagram's tables and lay them out again, after its data, its filters
+    /// or the kind of layout changed
+    pub fn relayout_diagram(&mut self) {
+        let Some(diagram) = &self.diagram_data else {
+            self.diagram_shown = None;
+            self.diagram_layout = None;
+            return;
+        };
+        let matching: Option<HashSet<&str>> = self.diagram_filter.as_deref().map(|filter| {
+            let filter = filter.to_lowercase();
+            let mut names = HashSet::new();
+            for table in &diagram.tables {
+                if table.name.to_lowercase().contains(&filter) {
+                    names.insert(table.name.as_str());
+                    names.extend(diagram.neighbours(&table.name));
+                }
+            }
+            names
+        });
+        let around: Option<Vec<&str>> = self.diagram_neighbours_of.as_deref().map(|name| {
+            let mut names = diagram.neighbours(name);
+            names.extend(diagram.table_named(name).map(|t| t.name.as_str()));
+            names
+        });
+        let (shown, hidden_keys) = diagram.subset(|table| {
+            matching
+                .as_ref()
+                .is_none_or(|names| names.contains(table.name.as_str()))
+                && around
+                    .as_ref()
+                    .is_none_or(|names| names.contains(&table.name.as_str()))
+        });
+        let mut layout = shown.layout(self.diagram_layout_kind);
+        for (i, table) in shown.tables.iter().enumerate() {
+            if self.diagram_collapsed.contains(&table.name) {
+                layout.collapse(i);
+            }
+        }
+        self.diagram_shown = Some(shown);
+        self.diagram_hidden_keys = hidden_keys;
+        self.diagram_layout = Some(layout);
+        // A table filtered out can't stay selected
+        if self.diagram_selected_index().is_none() {
+            self.diagram_selected = None;
+        }
+        // Tables in new places may leave the view panned past the last
+        self.pan_diagram(0, 0);
+    }
+
+    /// Show only the tables whose name contains `filter`, with the tables they're
+    /// joined to; all of them when it's empty
+    pub fn set_diagram_filter(&mut self, filter: &str) {
+        let filter = filter.trim();
+        self.diagram_filter = (!filter.is_empty()).then(|| filter.to_string());
+        self.relayout_diagram();
+    }
+
+    /// Show only the selected table and the tables joined to it, or every table again.
+    /// Returns false when there's no table selected to show the neighbours of.
+    pub fn toggle_diagram_neighbours(&mut self) -> bool {
+        if self.diagram_neighbours_of.is_some() {
+            self.diagram_neighbours_of = None;
+        } else if let Some(name) = self.diagram_selected.clone() {
+            self.diagram_neighbours_of = Some(name);
+        } else {
+            return false;
+        }
+        self.relayout_diagram();
+        true
+    }
+
+    /// Shrink the selected table's box to its title bar, or bring its columns back.
+    /// Returns false when there's no table selected.
+    pub fn toggle_diagram_collapsed(&mut self) -> bool {
+        let Some(name) = self.diagram_selected.clone() else {
+            return false;
+        };
+        if !self.diagram_collapsed.remove(&name) {
+            self.diagram_collapsed.insert(name);
+        }
+        self.relayout_diagram();
+        true
+    }
+
+    /// Pan the diagram by `dx`, `dy` cells, no further than its last table
+    pub fn pan_diagram(&mut self, dx: i32, dy: i32) {
+        if let Some(layout) = &self.diagram_layout {
+            let (x, y) = self.diagram_offset;
+            self.diagram_offset = layout.clamp_offset((x + dx, y + dy));
+        }
+    }
+
+    /// Position in the diagram's tables of the one selected
+    pub fn diagram_selected_index(&self) -> Option<usize> {
+        let name = self.diagram_selected.as_deref()?;
+        self.diagram_shown
+            .as_ref()?
+            .tables
+            .iter()
+            .position(|t| t.name == name)
+    }
+
+    /// The table selected in the diagram, while the diagram is showing
+    pub fn diagram_selected_table(&self) -> Option<&DiagramTable> {
+        if self.view_mode != ViewMode::Diagram {
+            return None;
+        }
+        let index = self.diagram_selected_index()?;
+        self.diagram_shown.as_ref()?.tables.get(index)
+    }
+
+    /// Select the next or previous table in the diagram. Returns false, leaving none
+    /// selected, when that runs past the last or first, so Tab can move on to the
+    /// next pane.
+    pub fn cycle_diagram_selection(&mut self, forward: bool) -> bool {
+        let Some(tables) = self.diagram_shown.as_ref().map(|d| &d.tables) else {
+            return false;
+        };
+        let next = match (self.diagram_selected_index(), forward) {
+            (None, true) => Some(0),
+            (None, false) => tables.len().checked_sub(1),
+            (Some(index), true) => Some(index + 1).filter(|&i| i < tables.len()),
+            (Some(index), false) => index.checked_sub(1),
+        };
+        self.diagram_selected = next.and_then(|i| tables.get(i)).map(|t| t.name.clone());
+        self.diagram_selected.is_some()
+    }
+
+    /// Select the nearest table in the diagram in the direction of `dx`, `dy`,
+    /// keeping the selection when there's none that way
+    pub fn move_diagram_selection(&mut self, dx: i32, dy: i32) {
+        let (Some(diagram), Some(layout), Some(index)) = (
+            &self.diagram_shown,
+            &self.diagram_layout,
+            self.diagram_selected_index(),
+        ) else {
+            return;
+        };
+        let (x, y) = layout.boxes[index].center();
+        // Distance along the way moved, with straying off that line counting double
+        let nearest = layout
+            .boxes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| {
+                let (bx, by) = b.center();
+                let along = (bx - x) * dx.signum() + (by - y) * dy.signum();
+                let across = if dx != 0 {
+                    (by - y).abs()
+                } else {
+                    (bx - x).abs()
+                };
+                (along > 0).then_some((along + 2 * across, i))
+            })
+            .min();
+        if let Some((_, i)) = nearest {
+            self.diagram_selected = Some(diagram.tables[i].name.clone());
+        }
+    }
+
+    /// Whether the query results grid, rather than the SQL editor, receives keys
+    pub fn query_grid_active(&self) -> bool {
+        self.query_grid_focused && self.focus == Focus::Content && self.view_mode == ViewMode::Query
+    }
+
+    /// Whether typing goes to the SQL editor
+    pub fn sql_editor_focused(&self) -> bool {
+        self.show_sql_editor && self.focus == Focus::Content && !self.query_grid_active()
+    }
+
+    /// Grid that cell edits apply to: query results in the Query view, otherwise table rows
+    pub fn edit_target(&self) -> Option<&QueryResult> {
+        if self.view_mode == ViewMode::Query {
+            self.query_result.as_ref()
+        } else {
+            self.table_rows.as_ref()
+        }
+    }
+
+    /// Table that cell edits are written to
+    pub fn edit_table(&self) -> Option<&str> {
+        if self.view_mode == ViewMode::Query {
+            self.query_result.as_ref()?.source_table.as_deref()
+        } else {
+            self.current_table.as_deref()
+        }
+    }
+
+    /// Cell cursor (row, column) of the grid being edited
+    pub fn cursor(&self) -> (usize, usize) {
+        if self.view_mode == ViewMode::Query {
+            (self.query_selected_row, self.query_selected_col)
+        } else {
+            (self.selected_row, self.selected_col)
+        }
+    }
+
+    /// Rows in the rows-view selection, from the anchor to the cursor row
+    pub fn selected_row_range(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.row_anchor?;
+        Some(anchor.min(self.selected_row)..=anchor.max(self.selected_row))
+    }
+
+    /// Rows an edit applies to when more than one row is selected
+    pub fn multi_edit_rows(&self) -> Option<RangeInclusive<usize>> {
+        if self.view_mode != ViewMode::Rows {
+            return None;
+        }
+        self.selected_row_range()
+            .filter(|rows| rows.end() > rows.start())
+    }
+
+    /// The edit buffer as the value to store at (row, col), typed like single-cell edits
+    pub fn typed_edit_value(&self, row: usize, col: usize) -> anyhow::Result<Value> {
+        if self.edit_null {
+            Ok(Value::Null)
+        } else if self.edit_blob {
+            Value::blob_from_edit(&self.edit_buffer).map(Value::Blob)
+        } else {
+            let original = self.edit_value(row, col).unwrap_or(Value::Null);
+            Ok(Value::from_edit(&self.edit_buffer, &original))
+        }
+    }
+
+    /// `input` as the value to store at (row, col), typed the same way as a cell edit
+    pub fn typed_input(&self, input: &str, row: usize, col: usize) -> anyhow::Result<Value> {
+        let original = self.edit_value(row, col).unwrap_or(Value::Null);
+        if matches!(original, Value::Blob(_)) || self.column_is_blob(col) {
+            Value::blob_from_edit(input).map(Value::Blob)
+        } else {
+            Ok(Value::from_edit(input, &original))
+        }
+    }
+
+    /// Move the cell cursor of the grid being edited
+    pub fn set_cursor(&mut self, row: usize, col: usize) {
+        if self.view_mode == ViewMode::Query {
+            self.query_selected_row = row;
+            self.query_selected_col = col;
+        } else {
+            self.selected_row = row;
+            self.selected_col = col;
+        }
+    }
+
+    /// Number of rows on the currently loaded page
+    pub fn loaded_row_count(&self) -> usize {
+        self.table_rows.as_ref().map(|r| r.rows.len()).unwrap_or(0)
+    }
+
+    /// Number of columns in the currently loaded page
+    pub fn loaded_column_count(&self) -> usize {
+        self.table_rows
+            .as_ref()
+            .map(|r| r.columns.len())
+            .unwrap_or(0)
+    }
+
+    /// Move the cell cursor one column left
+    pub fn select_prev_col(&mut self) {
+        self.selected_col = self.selected_col.saturating_sub(1);
+    }
+
+    /// Move the cell cursor one column right, stopping at the last column
+    pub fn select_next_col(&mut self) {
+        let last = self.loaded_column_count().saturating_sub(1);
+        self.selected_col = (self.selected_col + 1).min(last);
+    }
+
+    /// Move the query results row focus by `n` rows, up or down
+    pub fn move_query_row(&mut self, n: usize, down: bool) {
+        let last = self
+            .query_result
+            .as_ref()
+            .map(|r| r.rows.len())
+            .unwrap_or(0)
+            .saturating_sub(1);
+        self.query_selected_row = if down {
+            (self.query_selected_row + n).min(last)
+        } else {
+            self.query_selected_row.saturating_sub(n)
+        };
+    }
+
+    /// Move the query results column focus one column left
+    pub fn select_prev_query_col(&mut self) {
+        self.query_selected_col = self.query_selected_col.saturating_sub(1);
+    }
+
+    /// Move the query results column focus one column right
+    pub fn select_next_query_col(&mut self) {
+        let count = self
+            .query_result
+            .as_ref()
+            .map(|r| r.columns.len())
+            .unwrap_or(0);
+        self.query_selected_col = (self.query_selected_col + 1).min(count.saturating_sub(1));
+    }
+
+    /// Pin one more leading column, keeping at least one column scrollable
+    pub fn pin_more_columns(&mut self) {
+        let count = match self.view_mode {
+            ViewMode::Query => self.query_result.as_ref().map(|r| r.columns.len()),
+            _ => self.table_rows.as_ref().map(|r| r.columns.len()),
+        }
+        .unwrap_or(0);
+        if self.pinned_cols + 1 < count {
+            self.pinned_cols += 1;
+        }
+    }
+
+    /// Load a cell's value into the edit buffer, with the cursor at the end
+    pub fn load_edit_value(&mut self, val: &Value) {
+        self.edit_null = matches!(val, Value::Null);
+        self.edit_buffer = val.edit_text();
+        self.edit_cursor_pos = self.edit_buffer.chars().count();
+    }
+
+    /// Load the value at (row, col) of the edit target into the edit buffer.
+    /// Returns false when there is no such cell.
+    pub fn load_edit_cell(&mut self, row: usize, col: usize) -> bool {
+        let Some(val) = self.edit_value(row, col) else {
+            return false;
+        };
+        self.load_edit_value(&val);
+        self.edit_blob = matches!(val, Value::Blob(_)) || self.column_is_blob(col);
+        true
+    }
+
+    /// Schema entry for the edit target's column, when the loaded schema is for that table
+    fn edit_column_info(&self, col: usize) -> Option<&ColumnInfo> {
+        let name = self.edit_target()?.columns.get(col)?;
+        let table = self.edit_table()?;
+        if self.schema_table.as_deref() != Some(table) {
+            return None;
+        }
+        self.schema_columns.iter().find(|c| c.name == *name)
+    }
+
+    /// Whether the edit target's column is declared as a BLOB in the loaded schema
+    fn column_is_blob(&self, col: usize) -> bool {
+        self.edit_column_info(col)
+            .is_some_and(|c| c.data_type.to_ascii_uppercase().contains("BLOB"))
+    }
+
+    /// Check a value for (row, col) of the edit target against what the cell held before
+    /// and the column's declared type and constraints
+    pub fn validate_edit(&self, row: usize, col: usize, value: &Value) -> Result<(), String> {
+        if let (Some(Value::Blob(bytes)), Value::Integer(_) | Value::Real(_) | Value::Text(_)) =
+            (self.edit_value(row, col), value)
+        {
+            return Err(format!(
+                "This replaces a {}-byte BLOB in row {} with text",
+                bytes.len(),
+                row + 1
+            ));
+        }
+        let Some(column) = self.edit_column_info(col) else {
+            return Ok(());
+        };
+        let table_sql = self
+            .table_info
+            .as_ref()
+            .filter(|info| Some(info.name.as_str()) == self.edit_table())
+            .and_then(|info| info.sql.as_deref());
+        validate_value(value, column, table_sql)
+    }
+
+    /// The cell being edited, keyed so it can be found again after a reload
+    pub fn edited_cell(&self) -> Option<CursorRestore> {
+        let (row, col) = (self.editing_row?, self.editing_col?);
+        let key = self.edit_target()?.row_keys.get(row)?.clone();
+        Some(CursorRestore {
+            key,
+            col,
+            located: false,
+        })
+    }
+
+    /// The buffer shown in the SQL editor
+    pub fn sql_buffer(&self) -> &SqlBuffer {
+        &self.sql_buffers[self.active_buffer]
+    }
+
+    pub fn sql_buffer_mut(&mut self) -> &mut SqlBuffer {
+        &mut self.sql_buffers[self.active_buffer]
+    }
+
+    /// Open an empty SQL buffer after the others and switch to it. Returns false when
+    /// there are already `MAX_SQL_BUFFERS`.
+    pub fn new_sql_buffer(&mut self) -> bool {
+        if self.sql_buffers.len() >= MAX_SQL_BUFFERS {
+            return false;
+        }
+        self.sql_buffers.push(SqlBuffer::default());
+        self.switch_sql_buffer(self.sql_buffers.len() - 1);
+        true
+    }
+
+    /// Show buffer `index` in the SQL editor, parking the current buffer's results with it
+    /// and bringing back the ones parked with `index`
+    pub fn switch_sql_buffer(&mut self, index: usize) {
+        if index == self.active_buffer || index >= self.sql_buffers.len() {
+            return;
+        }
+        self.sql_buffers[self.active_buffer].results = self.take_buffer_results();
+        self.active_buffer = index;
+        let results = std::mem::take(&mut self.sql_buffers[index].results);
+        self.query_result = results.query_result;
+        self.query_page_starts = results.query_page_starts;
+        self.query_error = results.query_error;
+        self.last_query = results.last_query;
+        self.last_executed_query = results.last_executed_query;
+        self.query_params = results.query_params;
+        self.script_steps = results.script_steps;
+        self.query_selected_row = results.selected_row;
+        self.query_selected_col = results.selected_col;
+        self.query_cancelled = None;
+        self.query_grid_focused = false;
+        self.history_index = None;
+        self.completion = None;
+    }
+
+    /// Close the active buffer, unsaved text and all, and show the one before it. The
+    /// last buffer is emptied instead.
+    pub fn close_sql_buffer(&mut self) {
+        if self.sql_buffers.len() == 1 {
+            self.take_buffer_results();
+            self.sql_buffers[0] = SqlBuffer::default();
+            return;
+        }
+        let closed = self.active_buffer;
+        self.switch_sql_buffer(closed.checked_sub(1).unwrap_or(1));
+        self.sql_buffers.remove(closed);
+        if self.active_buffer > closed {
+            self.active_buffer -= 1;
+        }
+    }
+
+    /// Move the query results out of the state, leaving it with none
+    fn take_buffer_results(&mut self) -> BufferResults {
+        BufferResults {
+            query_result: self.query_result.take(),
+            query_page_starts: std::mem::take(&mut self.query_page_starts),
+            query_error: self.query_error.take(),
+            last_query: self.last_query.take(),
+            last_executed_query: self.last_executed_query.take(),
+            query_params: std::mem::take(&mut self.query_params),
+            script_steps: std::mem::take(&mut self.script_steps),
+            selected_row: std::mem::take(&mut self.query_selected_row),
+            selected_col: std::mem::take(&mut self.query_selected_col),
+        }
+    }
+
+    /// Whether the SQL editor holds nothing, or just the history entry last recalled,
+    /// so Up/Down step through history instead of moving the cursor
+    pub fn browsing_history(&self) -> bool {
+        self.sql_buffer().query.is_empty() || self.recalled_history_index().is_some()
+    }
+
+    /// Completions for the word at the SQL editor's cursor, along with the tables whose
+    /// columns belong in them but aren't cached yet
+    pub fn completion_at_cursor(&self) -> Option<(Completion, Vec<String>)> {
+        let buffer = self.sql_buffer();
+        let prefix = prefix_at(&buffer.query, buffer.cursor_pos)?;
+        // Triggers can't be selected from
+        let tables: Vec<String> = self
+            .tables
+            .iter()
+            .filter(|t| t.kind != TableKind::Trigger)
+            .map(|t| t.name.clone())
+            .collect();
+        // A qualifier naming a table narrows the columns to it; an alias keeps them all
+        let qualified = prefix
+            .qualifier
+            .as_ref()
+            .and_then(|q| tables.iter().find(|t| t.eq_ignore_ascii_case(q)));
+        let sources = match qualified {
+            Some(table) => vec![table.clone()],
+            None => mentioned_tables(&buffer.query, &tables),
+        };
+        let missing = sources
+            .iter()
+            .filter(|t| !self.completion_columns.contains_key(&t.to_lowercase()))
+            .cloned()
+            .collect();
+        let columns: Vec<String> = sources
+            .iter()
+            .filter_map(|t| self.completion_columns.get(&t.to_lowercase()))
+            .flatten()
+            .cloned()
+            .collect();
+        let candidates = candidates(&prefix, &tables, &columns);
+        Some((
+            Completion {
+                prefix,
+                candidates,
+                selected: 0,
+            },
+            missing,
+        ))
+    }
+
+    /// `history_index`, if the SQL editor still holds that entry unchanged
+    pub fn recalled_history_index(&self) -> Option<usize> {
+        let index = self.history_index?;
+        let entries = &self.history.entries;
+        let entry = entries.get(entries.len().checked_sub(index + 1)?)?;
+        (entry.query == self.sql_buffer().query).then_some(index)
+    }
+
+    /// Leave edit mode, dropping the edit buffer
+    pub fn exit_edit_mode(&mut self) {
+        self.edit_mode = false;
+        self.editing_row = None;
+        self.editing_col = None;
+        self.edit_buffer.clear();
+        self.edit_cursor_pos = 0;
+        self.full_edit_mode = false;
+        self.edit_blob = false;
+        self.edit_error = None;
+    }
+
+    /// Pending value for a cell, if it has been edited but not written yet
+    pub fn pending_value(&self, table: &str, key: &RowKey, column: &str) -> Option<&Value> {
+        self.pending_edits
+            .iter()
+            .find(|e| e.table_name == table && e.key == *key && e.column_name == column)
+            .map(|e| &e.value)
+    }
+
+    /// Value to edit at (row, col) of the edit target, showing pending edits over stored values
+    pub fn edit_value(&self, row: usize, col: usize) -> Option<Value> {
+        let result = self.edit_target()?;
+        let stored = result.rows.get(row)?.get(col)?;
+        let pending = self
+            .edit_table()
+            .zip(result.row_keys.get(row))
+            .and_then(|(table, key)| self.pending_value(table, key, result.columns.get(col)?));
+        Some(pending.unwrap_or(stored).clone())
+    }
+
+    /// Add an edit to the pending list, replacing an earlier edit of the same cell
+    pub fn stage_edit(&mut self, edit: PendingEdit) {
+        match self.pending_edits.iter_mut().find(|e| {
+            e.table_name == edit.table_name
+                && e.key == edit.key
+                && e.column_name == edit.column_name
+        }) {
+            Some(existing) => existing.value = edit.value,
+            None => self.pending_edits.push(edit),
+        }
+    }
+
+    /// Switch the edit between SQL NULL and the buffer's text
+    pub fn toggle_edit_null(&mut self) {
+        self.edit_null = !self.edit_null;
+        if self.edit_null {
+            self.edit_buffer.clear();
+            self.edit_cursor_pos = 0;
+        }
+    }
+
+    /// Value under the cell cursor in the rows view
+    pub fn selected_value(&self) -> Option<&Value> {
+        self.table_rows
+            .as_ref()?
+            .rows
+            .get(self.selected_row)?
+            .get(self.selected_col)
+    }
+
+    /// Open the hex viewer if the selected cell holds a BLOB; returns whether it opened
+    pub fn open_blob_viewer(&mut self) -> bool {
+        let Some(Value::Blob(bytes)) = self.selected_value() else {
+            return false;
+        };
+        let bytes = bytes.clone();
+        let column = self
+            .table_rows
+            .as_ref()
+            .and_then(|r| r.columns.get(self.selected_col))
+            .cloned()
+            .unwrap_or_default();
+        self.blob_viewer = Some(BlobViewer::new(column, bytes));
+        true
+    }
+
+    /// Move row selection up by `n` rows
+    pub fn select_prev_row(&mut self, n: usize) {
+        self.selected_row = self.selected_row.saturating_sub(n);
+    }
+
+    /// Move row selection down by `n` rows, stopping at the last loaded row
+    pub fn select_next_row(&mut self, n: usize) {
+        let last = self.loaded_row_count().saturating_sub(1);
+        self.selected_row = (self.selected_row + n).min(last);
+    }
+
+    /// Select the last loaded row
+    pub fn select_last_row(&mut self) {
+        self.selected_row = self.loaded_row_count().saturating_sub(1);
+    }
+
+    /// Keep the cell cursor inside the loaded page (e.g. after a shorter page loads)
+    pub fn clamp_selection(&mut self) {
+        let last_row = self.loaded_row_count().saturating_sub(1);
+        self.selected_row = self.selected_row.min(last_row);
+        let last_col = self.loaded_column_count().saturating_sub(1);
+        self.selected_col = self.selected_col.min(last_col);
+    }
+
+    /// Keep the query results cursor inside the current result
+    pub fn clamp_query_selection(&mut self) {
+        let (rows, cols) = self
+            .query_result
+            .as_ref()
+            .map(|r| (r.rows.len(), r.columns.len()))
+            .unwrap_or((0, 0));
+        self.query_selected_row = self.query_selected_row.min(rows.saturating_sub(1));
+        self.query_selected_col = self.query_selected_col.min(cols.saturating_sub(1));
+    }
+
+    /// Cycle sorting of the selected column: none -> ASC -> DESC -> none.
+    /// Returns false when there is no column to sort by.
+    pub fn cycle_sort(&mut self) -> bool {
+        let Some(column) = self
+            .table_rows
+            .as_ref()
+            .and_then(|r| r.columns.get(self.selected_col))
+            .cloned()
+        else {
+            return false;
+        };
+
+        self.sort_column = match self.sort_column.take() {
+            Some((name, SortOrder::Asc)) if name == column => Some((name, SortOrder::Desc)),
+            Some((name, SortOrder::Desc)) if name == column => None,
+            _ => Some((column, SortOrder::Asc)),
+        };
+        self.current_page = 0;
+        self.clear_search();
+        true
+    }
+
+    /// Filter and ordering the rows view is currently showing
+    pub fn rows_query(&self) -> RowsQuery {
+        RowsQuery {
+            filter: self.row_filter.clone(),
+            key_match: self.key_match.clone(),
+            order_by: self.sort_column.clone(),
+        }
+    }
+
+    /// Apply a row filter; an empty expression clears it
+    pub fn set_row_filter(&mut self, expr: &str) {
+        let expr = expr.trim();
+        self.row_filter = if expr.is_empty() {
+            None
+        } else {
+            Some(expr.to_string())
+        };
+        self.current_page = 0;
+        self.selected_row = 0;
+        self.clear_search();
+    }
+
+    /// Forget the current search and its matches
+    pub fn clear_search(&mut self) {
+        self.search_term = None;
+        self.search_match_index = 0;
+    }
+
+    /// Update the search term while typing, selecting the first match
+    pub fn set_search_term(&mut self, term: &str) {
+        self.search_term = if term.is_empty() {
+            None
+        } else {
+            Some(term.to_string())
+        };
+        self.search_match_index = 0;
+        if let Some(&(row, col)) = self.search_matches().first() {
+            self.selected_row = row;
+            self.selected_col = col;
+        }
+    }
+
+    /// Whether a cell value matches the active search term (case-insensitive)
+    pub fn cell_matches_search(&self, value: &Value) -> bool {
+        match &self.search_term {
+            Some(term) => value
+                .display(usize::MAX)
+                .to_lowercase()
+                .contains(&term.to_lowercase()),
+            None => false,
+        }
+    }
+
+    /// All (row, column) cells on the loaded page matching the search term
+    pub fn search_matches(&self) -> Vec<(usize, usize)> {
+        let (Some(result), Some(_)) = (&self.table_rows, &self.search_term) else {
+            return Vec::new();
+        };
+        result
+            .rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row_idx, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, val)| self.cell_matches_search(val))
+                    .map(move |(col_idx, _)| (row_idx, col_idx))
+            })
+            .collect()
+    }
+
+    /// Move the cell cursor to the next (or previous) search match, wrapping around
+    pub fn jump_to_match(&mut self, forward: bool) {
+        let matches = self.search_matches();
+        if matches.is_empty() {
+            return;
+        }
+        let cursor = (self.selected_row, self.selected_col);
+        let index = if forward {
+            matches.iter().position(|&m| m > cursor).unwrap_or(0)
+        } else {
+            matches
+                .iter()
+                .rposition(|&m| m < cursor)
+                .unwrap_or(matches.len() - 1)
+        };
+        let (row, col) = matches[index];
+        self.selected_row = row;
+        self.selected_col = col;
+        self.search_match_index = index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::text_editor::handle_text_editor_input;
+    use crate::db::query::{execute_query, get_table_rows};
+    use crate::db::update_cell;
+    use crate::types::{RowKey, SortOrder};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use rusqlite::Connection;
+
+    #[test]
+    fn pages_cut_by_the_memory_cap_lose_no_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, body TEXT);
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 50)
+             INSERT INTO t SELECT i, printf('%0100d', i) FROM n;",
+        )
+        .unwrap();
+
+        let mut state = AppState::new(10);
+        state.current_table = Some("t".to_string());
+        state.sort_column = Some(("id".to_string(), SortOrder::Desc));
+        // Room for about three of the rows, so every page is cut short
+        let max_bytes = 400;
+
+        let mut seen = Vec::new();
+        let mut cut = false;
+        loop {
+            let offset = state.page_offset(state.current_page);
+            let view = state.rows_query();
+            let result = get_table_rows(&conn, "t", 10, offset, &view, None, max_bytes).unwrap();
+            if result.rows.is_empty() {
+                break;
+            }
+            cut |= result.memory_cap.is_some() && result.rows.len() < 10;
+            seen.extend(result.rows.iter().map(|row| row[0].clone()));
+            state.table_rows = Some(result);
+            state.note_page_end();
+            state.next_page();
+        }
+
+        assert!(cut, "the cap should cut pages short");
+        let expected: Vec<Value> = (1..=50).rev().map(Value::Integer).collect();
+        assert_eq!(seen, expected);
+
+        // Going back lands on the same rows as before
+        state.current_page = 1;
+        let offset = state.page_offset(1);
+        let view = state.rows_query();
+        let page = get_table_rows(&conn, "t", 10, offset, &view, None, max_bytes).unwrap();
+        assert_eq!(page.rows[0][0], seen[offset]);
+    }
+
+    #[test]
+    fn a_located_row_lands_on_the_page_that_holds_it() {
+        let mut state = AppState::new(10);
+        state.current_table = Some("t".to_string());
+        assert_eq!(state.page_of_row(25), (2, 5));
+
+        // Pages cut to three rows by the memory cap, as far as paging has seen
+        state.page_offsets = Some(PageOffsets {
+            table: "t".to_string(),
+            view: state.rows_query(),
+            page_size: 10,
+            starts: vec![0, 3, 6, 9],
+        });
+        assert_eq!(state.page_of_row(0), (0, 0));
+        assert_eq!(state.page_of_row(4), (1, 1));
+        assert_eq!(state.page_of_row(9), (3, 0));
+        assert_eq!(state.page_of_row(25), (4, 6));
+    }
+
+    #[test]
+    fn paging_back_through_query_results_retraces_pages_cut_by_the_memory_cap() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, body TEXT);
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 50)
+             INSERT INTO t SELECT i, printf('%0100d', i) FROM n;",
+        )
+        .unwrap();
+        let query = "SELECT * FROM t ORDER BY id";
+        let mut state = AppState::new(10);
+        state.query_limit = 10;
+        // Room for about three of the rows, so every page is cut short
+        let max_bytes = 400;
+        let load = |state: &mut AppState, offset| {
+            let result = execute_query(&conn, query, Some(10), max_bytes, offset, &[]).unwrap();
+            let first = result.rows[0][0].clone();
+            state.query_result = Some(result);
+            state.note_query_page();
+            first
+        };
+
+        let mut firsts = vec![load(&mut state, 0)];
+        while let Some(result) = state.query_result.as_ref().filter(|r| r.truncated) {
+            let offset = result.offset + result.rows.len();
+            firsts.push(load(&mut state, offset));
+        }
+        assert!(firsts.len() > 5, "the cap should cut pages short");
+
+        let mut back = Vec::new();
+        while let Some(offset) = state.prev_query_page_offset() {
+            back.push(load(&mut state, offset));
+        }
+        firsts.pop();
+        firsts.reverse();
+        assert_eq!(back, firsts);
+    }
+
+    #[test]
+    fn a_long_cell_is_edited_and_saved_whole() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)")
+            .unwrap();
+        // 20,000 characters, some of them several bytes long
+        let body: String = "naïve 日本 ✓ ".chars().cycle().take(20_000).collect();
+        conn.execute("INSERT INTO notes VALUES (1, ?)", [&body])
+            .unwrap();
+        let read = |conn: &Connection| {
+            let view = RowsQuery::default();
+            let result = get_table_rows(conn, "notes", 10, 0, &view, None, usize::MAX).unwrap();
+            result.rows[0][1].clone()
+        };
+
+        // Open it for editing
+        let original = read(&conn);
+        let mut state = AppState::new(10);
+        state.load_edit_value(&original);
+        assert_eq!(state.edit_buffer, body);
+
+        // Saved as it was opened, it's stored as it was
+        let value = Value::from_edit(&state.edit_buffer, &original);
+        update_cell(&conn, "notes", &RowKey::Rowid(1), "body", &value).unwrap();
+        assert_eq!(read(&conn), Value::Text(body.clone()));
+
+        // Then type a character at the end
+        let key = KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE);
+        handle_text_editor_input(
+            key,
+            &mut state.edit_buffer,
+            &mut state.edit_cursor_pos,
+            true,
+        );
+
+        let value = Value::from_edit(&state.edit_buffer, &original);
+        update_cell(&conn, "notes", &RowKey::Rowid(1), "body", &value).unwrap();
+        let expected = format!("{}!", body);
+        match read(&conn) {
+            Value::Text(saved) => assert_eq!(saved.as_bytes(), expected.as_bytes()),
+            other => panic!("saved as {:?}", other),
+        }
+    }
 }
@@ -1,6 +1,61 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
 
-/// Handle text editor input for a buffer with cursor position
+/// Byte offset of the character at `char_pos`, or the buffer length past the end
+pub fn byte_offset(buffer: &str, char_pos: usize) -> usize {
+    buffer
+        .char_indices()
+        .nth(char_pos)
+        .map(|(i, _)| i)
+        .unwrap_or(buffer.len())
+}
+
+/// Character index of a byte offset that lies on a character boundary
+fn char_pos(buffer: &str, byte: usize) -> usize {
+    buffer[..byte].chars().count()
+}
+
+/// Byte offset where the grapheme before `byte` starts
+fn prev_grapheme(buffer: &str, byte: usize) -> usize {
+    buffer[..byte]
+        .grapheme_indices(true)
+        .next_back()
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Byte offset where the grapheme at `byte` ends
+fn next_grapheme(buffer: &str, byte: usize) -> usize {
+    buffer[byte..]
+        .graphemes(true)
+        .next()
+        .map(|g| byte + g.len())
+        .unwrap_or(buffer.len())
+}
+
+/// Character index of the start of the grapheme holding the character at `char_pos`,
+/// so a cursor put there by column doesn't split a letter from its accents
+fn grapheme_start(buffer: &str, char_pos: usize) -> usize {
+    let byte = byte_offset(buffer, char_pos);
+    let start = buffer
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .chain(std::iter::once(buffer.len()))
+        .take_while(|&i| i <= byte)
+        .last()
+        .unwrap_or(0);
+    self::char_pos(buffer, start)
+}
+
+/// Insert a character at a character-index cursor and move the cursor past it
+pub fn insert_char(buffer: &mut String, cursor_pos: &mut usize, c: char) {
+    let pos = (*cursor_pos).min(buffer.chars().count());
+    buffer.insert(byte_offset(buffer, pos), c);
+    *cursor_pos = pos + 1;
+}
+
+/// Handle text editor input for a buffer with cursor position.
+/// The cursor is a character index; it moves and deletes over whole graphemes.
 /// Returns true if the event was handled, false otherwise
 pub fn handle_text_editor_input(
     event: KeyEvent,
@@ -8,7 +63,12 @@ pub fn handle_text_editor_input(
     cursor_pos: &mut usize,
     supports_line_navigation: bool,
 ) -> bool {
-    let pos = (*cursor_pos).min(buffer.len());
+    let pos = byte_offset(buffer, *cursor_pos);
+    let line_start = buffer[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = buffer[pos..]
+        .find('\n')
+        .map(|i| pos + i)
+        .unwrap_or(buffer.len());
 
     match event.code {
         KeyCode::Char(c) => {
@@ -16,28 +76,24 @@ pub fn handle_text_editor_input(
                 match c {
                     'u' => {
                         // Ctrl+U: Clear from start of current line to cursor
-                        if supports_line_navigation {
-                            let line_start = buffer[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
-                            buffer.drain(line_start..pos);
-                            *cursor_pos = line_start;
+                        // If no line navigation, clear from buffer start
+                        let start = if supports_line_navigation {
+                            line_start
                         } else {
-                            // If no line navigation, clear from buffer start
-                            buffer.drain(0..pos);
-                            *cursor_pos = 0;
-                        }
+                            0
+                        };
+                        buffer.drain(start..pos);
+                        *cursor_pos = char_pos(buffer, start);
                     }
                     'k' => {
                         // Ctrl+K: Clear from cursor to end of current line
-                        if supports_line_navigation {
-                            let line_end = buffer[pos..]
-                                .find('\n')
-                                .map(|i| pos + i)
-                                .unwrap_or(buffer.len());
-                            buffer.drain(pos..line_end);
+                        // If no line navigation, clear to end of buffer
+                        let end = if supports_line_navigation {
+                            line_end
                         } else {
-                            // If no line navigation, clear to end of buffer
-                            buffer.drain(pos..);
-                        }
+                            buffer.len()
+                        };
+                        buffer.drain(pos..end);
                     }
                     'a' => {
                         // Ctrl+A: Move to beginning
@@ -45,140 +101,227 @@ pub fn handle_text_editor_input(
                     }
                     'e' => {
                         // Ctrl+E: Move to end
-                        *cursor_pos = buffer.len();
+                        *cursor_pos = buffer.chars().count();
                     }
                     'w' => {
-                        // Ctrl+W: Delete word before cursor
-                        if pos > 0 {
-                            let mut new_pos = pos;
-                            // Skip whitespace
-                            while new_pos > 0
-                                && buffer
-                                    .chars()
-                                    .nth(new_pos - 1)
-                                    .is_some_and(|c| c.is_whitespace())
-                            {
-                                new_pos -= 1;
-                            }
-                            // Skip word characters
-                            while new_pos > 0
-                                && buffer
-                                    .chars()
-                                    .nth(new_pos - 1)
-                                    .is_some_and(|c| !c.is_whitespace())
-                            {
-                                new_pos -= 1;
-                            }
-                            buffer.drain(new_pos..pos);
-                            *cursor_pos = new_pos;
-                        }
+                        // Ctrl+W: Delete word before cursor, skipping whitespace first
+                        let before = &buffer[..pos];
+                        let word_end = before.trim_end().len();
+                        let start = before[..word_end]
+                            .char_indices()
+                            .rev()
+                            .find(|(_, c)| c.is_whitespace())
+                            .map(|(i, c)| i + c.len_utf8())
+                            .unwrap_or(0);
+                        buffer.drain(start..pos);
+                        *cursor_pos = char_pos(buffer, start);
                     }
                     'd' => {
                         // Ctrl+D: Delete character at cursor
-                        if pos < buffer.len() {
-                            buffer.remove(pos);
-                        }
+                        let end = next_grapheme(buffer, pos);
+                        buffer.drain(pos..end);
                     }
                     _ => return false,
                 }
             } else {
                 // Regular character insertion
                 buffer.insert(pos, c);
-                *cursor_pos = pos + 1;
+                *cursor_pos = char_pos(buffer, pos) + 1;
             }
             true
         }
         KeyCode::Backspace => {
-            if pos > 0 {
-                buffer.remove(pos - 1);
-                *cursor_pos = pos - 1;
-            }
+            let start = prev_grapheme(buffer, pos);
+            buffer.drain(start..pos);
+            *cursor_pos = char_pos(buffer, start);
             true
         }
         KeyCode::Delete => {
-            if pos < buffer.len() {
-                buffer.remove(pos);
-            }
+            let end = next_grapheme(buffer, pos);
+            buffer.drain(pos..end);
             true
         }
         KeyCode::Left => {
-            if pos > 0 {
-                *cursor_pos = pos - 1;
-            }
+            *cursor_pos = char_pos(buffer, prev_grapheme(buffer, pos));
             true
         }
         KeyCode::Right => {
-            if pos < buffer.len() {
-                *cursor_pos = pos + 1;
-            }
+            *cursor_pos = char_pos(buffer, next_grapheme(buffer, pos));
             true
         }
         KeyCode::Home => {
-            if supports_line_navigation {
-                // Move to start of current line
-                let line_start = buffer[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
-                *cursor_pos = line_start;
+            // Move to start of current line, or of the buffer without line navigation
+            *cursor_pos = if supports_line_navigation {
+                char_pos(buffer, line_start)
             } else {
-                // Move to beginning of buffer
-                *cursor_pos = 0;
-            }
+                0
+            };
             true
         }
         KeyCode::End => {
-            if supports_line_navigation {
-                // Move to end of current line
-                let line_end = buffer[pos..]
-                    .find('\n')
-                    .map(|i| pos + i)
-                    .unwrap_or(buffer.len());
-                *cursor_pos = line_end;
+            // Move to end of current line, or of the buffer without line navigation
+            *cursor_pos = if supports_line_navigation {
+                char_pos(buffer, line_end)
             } else {
-                // Move to end of buffer
-                *cursor_pos = buffer.len();
-            }
+                buffer.chars().count()
+            };
             true
         }
         KeyCode::Up => {
-            if supports_line_navigation {
-                // Move to previous line
-                if pos > 0 {
-                    let line_start = buffer[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
-                    if line_start > 0 {
-                        let prev_line_start = buffer[..line_start - 1]
-                            .rfind('\n')
-                            .map(|i| i + 1)
-                            .unwrap_or(0);
-                        let prev_line_end = buffer[prev_line_start..]
-                            .find('\n')
-                            .map(|i| prev_line_start + i)
-                            .unwrap_or(buffer.len());
-                        let col = pos - line_start;
-                        *cursor_pos = (prev_line_start + col).min(prev_line_end);
-                    }
-                }
+            // Move to the same column of the previous line
+            if supports_line_navigation && line_start > 0 {
+                let col = buffer[line_start..pos].chars().count();
+                let prev_line_start = buffer[..line_start - 1]
+                    .rfind('\n')
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                let prev_line_len = buffer[prev_line_start..line_start - 1].chars().count();
+                *cursor_pos = grapheme_start(
+                    buffer,
+                    char_pos(buffer, prev_line_start) + col.min(prev_line_len),
+                );
             }
             true
         }
         KeyCode::Down => {
-            if supports_line_navigation {
-                // Move to next line
-                let line_start = buffer[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
-                let line_end = buffer[pos..]
+            // Move to the same column of the next line
+            if supports_line_navigation && line_end < buffer.len() {
+                let col = buffer[line_start..pos].chars().count();
+                let next_line_start = line_end + 1;
+                let next_line_end = buffer[next_line_start..]
                     .find('\n')
-                    .map(|i| pos + i)
+                    .map(|i| next_line_start + i)
                     .unwrap_or(buffer.len());
-                if line_end < buffer.len() {
-                    let next_line_start = line_end + 1;
-                    let next_line_end = buffer[next_line_start..]
-                        .find('\n')
-                        .map(|i| next_line_start + i)
-                        .unwrap_or(buffer.len());
-                    let col = pos - line_start;
-                    *cursor_pos = (next_line_start + col).min(next_line_end);
-                }
+                let next_line_len = buffer[next_line_start..next_line_end].chars().count();
+                *cursor_pos = grapheme_start(
+                    buffer,
+                    char_pos(buffer, next_line_start) + col.min(next_line_len),
+                );
             }
             true
         }
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    /// Apply `keys` to `text` with the cursor at `cursor`, returning the text and cursor
+    fn edit(text: &str, cursor: usize, keys: &[KeyEvent]) -> (String, usize) {
+        let mut buffer = text.to_string();
+        let mut cursor_pos = cursor;
+        for &event in keys {
+            handle_text_editor_input(event, &mut buffer, &mut cursor_pos, true);
+        }
+        (buffer, cursor_pos)
+    }
+
+    #[test]
+    fn backspace_removes_a_whole_emoji() {
+        // A family: four people joined by zero-width joiners, seven chars in all
+        let family = "👨\u{200d}👩\u{200d}👧\u{200d}👦";
+        let text = format!("a{}b", family);
+        let after_family = 1 + family.chars().count();
+        assert_eq!(
+            edit(&text, after_family, &[key(KeyCode::Backspace)]),
+            ("ab".to_string(), 1)
+        );
+        // A flag is two regional indicators
+        assert_eq!(
+            edit("x🇫🇷", 3, &[key(KeyCode::Backspace)]),
+            ("x".to_string(), 1)
+        );
+        // A skin tone modifier goes with the hand it colours
+        assert_eq!(
+            edit("👋🏽", 2, &[key(KeyCode::Backspace)]),
+            (String::new(), 0)
+        );
+    }
+
+    #[test]
+    fn delete_removes_a_whole_emoji() {
+        assert_eq!(
+            edit("🇯🇵z", 0, &[key(KeyCode::Delete)]),
+            ("z".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn ctrl_w_deletes_multibyte_words() {
+        assert_eq!(
+            edit("SELECT 名前 FROM 表", 16, &[ctrl('w')]),
+            ("SELECT 名前 FROM ".to_string(), 15)
+        );
+        // Whitespace before the cursor goes with the word before it
+        assert_eq!(
+            edit("café crème  ", 12, &[ctrl('w')]),
+            ("café ".to_string(), 5)
+        );
+        assert_eq!(
+            edit("café crème", 10, &[ctrl('w'), ctrl('w')]),
+            (String::new(), 0)
+        );
+    }
+
+    #[test]
+    fn home_and_end_on_cjk_lines() {
+        let text = "一行目\n二行目です\n三";
+        // From inside the second line
+        assert_eq!(edit(text, 6, &[key(KeyCode::Home)]).1, 4);
+        assert_eq!(edit(text, 6, &[key(KeyCode::End)]).1, 9);
+        assert_eq!(edit(text, 11, &[key(KeyCode::Home)]).1, 10);
+    }
+
+    #[test]
+    fn arrows_step_over_combining_characters() {
+        // "e" with an acute accent, and "n" with a tilde, as combining marks
+        let text = "e\u{301}n\u{303}o";
+        assert_eq!(edit(text, 0, &[key(KeyCode::Right)]).1, 2);
+        assert_eq!(
+            edit(text, 0, &[key(KeyCode::Right), key(KeyCode::Right)]).1,
+            4
+        );
+        assert_eq!(edit(text, 4, &[key(KeyCode::Left)]).1, 2);
+        assert_eq!(
+            edit(text, 4, &[key(KeyCode::Backspace)]),
+            ("e\u{301}o".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn up_and_down_keep_the_column_on_cjk_lines() {
+        let text = "日本語\nabcdef\n漢字";
+        // From the third character of the first line to the third of the next
+        assert_eq!(edit(text, 2, &[key(KeyCode::Down)]).1, 6);
+        // The last line is shorter, so the cursor stops at its end
+        assert_eq!(edit(text, 9, &[key(KeyCode::Down)]).1, 13);
+        assert_eq!(edit(text, 13, &[key(KeyCode::Up)]).1, 6);
+        assert_eq!(edit(text, 6, &[key(KeyCode::Up)]).1, 2);
+    }
+
+    #[test]
+    fn up_and_down_never_split_an_accented_letter() {
+        let text = "e\u{301}e\u{301}\nabcd";
+        // Column 1 of the first line is between an "e" and its accent
+        assert_eq!(edit(text, 6, &[key(KeyCode::Up)]).1, 0);
+        assert_eq!(edit(text, 8, &[key(KeyCode::Up)]).1, 2);
+    }
+
+    #[test]
+    fn typing_after_multibyte_text() {
+        assert_eq!(
+            edit("名前", 1, &[key(KeyCode::Char('x'))]),
+            ("名x前".to_string(), 2)
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::types::{ColumnInfo, Value};
+
+/// Check `value` against what the schema declares for `column`: INTEGER/REAL affinity,
+/// NOT NULL without a default, and simple CHECK constraints found in the table's SQL.
+/// Affinity is only advisory in SQLite, so callers let the user save anyway.
+pub fn validate_value(
+    value: &Value,
+    column: &ColumnInfo,
+    table_sql: Option<&str>,
+) -> Result<(), String> {
+    let required = column.not_null && column.default_value.is_none();
+    match value {
+        Value::Null if required => {
+            return Err(format!("{} is NOT NULL", column.name));
+        }
+        Value::Text(text) if text.is_empty() && required => {
+            return Err(format!(
+                "{} is NOT NULL and can't be left empty",
+                column.name
+            ));
+        }
+        Value::Text(text)
+            if is_numeric_affinity(&column.data_type) && text.trim().parse::<f64>().is_err() =>
+        {
+            return Err(format!(
+                "{} is {}, but {} is not a number",
+                column.name,
+                column.data_type,
+                value.display(30)
+            ));
+        }
+        _ => {}
+    }
+
+    for check in table_sql.map(check_constraints).unwrap_or_default() {
+        if check_holds(&check, &column.name, value) == Some(false) {
+            return Err(format!("{} breaks CHECK ({})", value.display(30), check));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a declared type gets INTEGER or REAL affinity under SQLite's rules
+fn is_numeric_affinity(declared: &str) -> bool {
+    let declared = declared.to_ascii_uppercase();
+    if declared.contains("INT") {
+        return true;
+    }
+    let texty = ["CHAR", "CLOB", "TEXT", "BLOB"]
+        .iter()
+        .any(|t| declared.contains(t));
+    !texty
+        && ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|t| declared.contains(t))
+}
+
+/// Bodies of the CHECK (...) constraints in a CREATE TABLE statement
+fn check_constraints(sql: &str) -> Vec<String> {
+    let upper = sql.to_ascii_uppercase();
+    let mut checks = Vec::new();
+    let mut from = 0;
+    while let Some(found) = upper[from..].find("CHECK") {
+        let start = from + found + "CHECK".len();
+        from = start;
+        let Some(open) = sql[start..].find(|c: char| !c.is_whitespace()) else {
+            break;
+        };
+        if !sql[start + open..].starts_with('(') {
+            continue;
+        }
+        // Take everything up to the matching closing parenthesis
+        let body_start = start + open + 1;
+        let mut depth = 1;
+        for (i, c) in sql[body_start..].char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                checks.push(sql[body_start..body_start + i].trim().to_string());
+                from = body_start + i;
+                break;
+            }
+        }
+    }
+    checks
+}
+
+/// Evaluate a CHECK body for `value` in `column`. Returns None when the check is about
+/// another column or is too complex to evaluate here.
+fn check_holds(check: &str, column: &str, value: &Value) -> Option<bool> {
+    let upper = check.to_ascii_uppercase();
+    if upper.contains(" OR ") || upper.contains(" NOT ") {
+        return None;
+    }
+    if let Some(between) = upper.find(" BETWEEN ") {
+        let (subject, rest) = (&check[..between], &check[between + " BETWEEN ".len()..]);
+        let and = rest.to_ascii_uppercase().find(" AND ")?;
+        let low = rest[..and].trim().parse::<f64>().ok()?;
+        let high = rest[and + " AND ".len()..].trim().parse::<f64>().ok()?;
+        let n = subject_number(subject, column, value)?;
+        return Some(n >= low && n <= high);
+    }
+
+    // Every part of an AND chain must hold; parts that can't be read are skipped
+    let mut result = None;
+    let mut rest = check;
+    loop {
+        let (part, next) = match rest.to_ascii_uppercase().find(" AND ") {
+            Some(i) => (&rest[..i], Some(&rest[i + " AND ".len()..])),
+            None => (rest, None),
+        };
+        if let Some(holds) = comparison_holds(part, column, value) {
+            result = Some(result.unwrap_or(true) && holds);
+        }
+        match next {
+            Some(next) => rest = next,
+            None => return result,
+        }
+    }
+}
+
+/// Evaluate `subject <op> number` for the column
+fn comparison_holds(part: &str, column: &str, value: &Value) -> Option<bool> {
+    let part = part.trim();
+    let part = part
+        .strip_prefix('(')
+        .and_then(|p| p.strip_suffix(')'))
+        .unwrap_or(part);
+    for op in [">=", "<=", "!=", "<>", "==", ">", "<", "="] {
+        if let Some(i) = part.find(op) {
+            let n = subject_number(&part[..i], column, value)?;
+            let bound = part[i + op.len()..].trim().parse::<f64>().ok()?;
+            return Some(match op {
+                ">=" => n >= bound,
+                "<=" => n <= bound,
+                ">" => n > bound,
+                "<" => n < bound,
+                "!=" | "<>" => n != bound,
+                _ => n == bound,
+            });
+        }
+    }
+    None
+}
+
+/// The number a CHECK subject (`col` or `length(col)`) has for `value`
+fn subject_number(subject: &str, column: &str, value: &Value) -> Option<f64> {
+    let subject = subject.trim();
+    let lower = subject.to_ascii_lowercase();
+    if let Some(inner) = lower
+        .strip_prefix("length(")
+        .and_then(|s| s.strip_suffix(')'))
+    {
+        if !is_column(inner, column) {
+            return None;
+        }
+        return match value {
+            Value::Text(t) => Some(t.chars().count() as f64),
+            Value::Blob(b) => Some(b.len() as f64),
+            Value::Integer(i) => Some(i.to_string().len() as f64),
+            Value::Real(r) => Some(r.to_string().len() as f64),
+            Value::Null => None,
+        };
+    }
+    if !is_column(subject, column) {
+        return None;
+    }
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Real(r) => Some(*r),
+        Value::Text(t) => t.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Whether an identifier in SQL, quoted or not, names `column`
+fn is_column(ident: &str, column: &str) -> bool {
+    let ident = ident.trim();
+    let unquoted = ident
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| ident.strip_prefix('`').and_then(|s| s.strip_suffix('`')))
+        .or_else(|| ident.strip_prefix('[').and_then(|s| s.strip_suffix(']')))
+        .unwrap_or(ident);
+    unquoted.eq_ignore_ascii_case(column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            not_null: false,
+            default_value: None,
+            primary_key: false,
+            auto_increment: false,
+        }
+    }
+
+    #[test]
+    fn text_in_a_numeric_column_is_rejected() {
+        let qty = column("qty", "INTEGER");
+        let text = |t: &str| Value::Text(t.to_string());
+        assert_eq!(
+            validate_value(&text("lots"), &qty, None),
+            Err("qty is INTEGER, but lots is not a number".to_string())
+        );
+        assert!(validate_value(&text(" 12 "), &qty, None).is_ok());
+        assert!(validate_value(&text("1.5"), &column("price", "DOUBLE"), None).is_ok());
+        // INT wins over the TEXT-ish part of the name, which only counts without it
+        assert!(validate_value(&text("x"), &column("n", "BIGINT"), None).is_err());
+        assert!(validate_value(&text("x"), &column("f", "FLOATING TEXT"), None).is_ok());
+    }
+
+    #[test]
+    fn a_value_breaking_a_check_is_rejected() {
+        let sql = "CREATE TABLE t (age INTEGER CHECK (age >= 0 AND age < 150), \
+                   code TEXT CHECK (length(code) BETWEEN 2 AND 3), note TEXT)";
+        let age = column("age", "INTEGER");
+        let code = column("code", "TEXT");
+        assert_eq!(
+            validate_value(&Value::Integer(-1), &age, Some(sql)),
+            Err("-1 breaks CHECK (age >= 0 AND age < 150)".to_string())
+        );
+        assert!(validate_value(&Value::Integer(150), &age, Some(sql)).is_err());
+        assert!(validate_value(&Value::Integer(42), &age, Some(sql)).is_ok());
+        assert!(validate_value(&Value::Text("ABCD".to_string()), &code, Some(sql)).is_err());
+        assert!(validate_value(&Value::Text("AB".to_string()), &code, Some(sql)).is_ok());
+        // Checks on other columns don't apply
+        let note = column("note", "TEXT");
+        assert!(validate_value(&Value::Text("x".to_string()), &note, Some(sql)).is_ok());
+    }
+}
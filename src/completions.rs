@@ -0,0 +1,102 @@
+use crate::db::{get_table_names, Database};
+use anyhow::{Context, Result};
+use clap::Command;
+use clap_complete::Shell;
+use std::io::Write;
+
+/// First argument the completion scripts call sqr with to list the tables of the
+/// database on the command line being completed
+pub const COMPLETE_TABLES: &str = "__complete-tables";
+
+/// Bash: values for `--table`/`-t` come from sqr, everything else from `_sqr`
+const BASH_TABLES: &str = r#"
+_sqr_tables() {
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ "$prev" == "--table" || "$prev" == "-t" ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($(compgen -W "$("${COMP_WORDS[0]}" __complete-tables "${COMP_WORDS[@]:0:COMP_CWORD}" 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"))
+        return 0
+    fi
+    _sqr "$@"
+}
+"#;
+
+const ZSH_TABLES: &str = r#"(( $+functions[_sqr_tables] )) ||
+_sqr_tables() {
+    local -a line tables
+    line=(${(Q)${(z)LBUFFER}})
+    tables=(${(f)"$(${line[1]} __complete-tables $line 2>/dev/null)"})
+    compadd -a tables
+}
+
+"#;
+
+/// Write the completion script for `shell`. Bash, zsh and fish complete `--table` with
+/// the tables of the database already named on the command line, by calling sqr back
+/// with [`COMPLETE_TABLES`]; PowerShell gets the static script.
+pub fn write_completions(shell: Shell, mut cmd: Command, out: &mut dyn Write) -> Result<()> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, "sqr", &mut script);
+    let mut script = String::from_utf8(script).context("Completion script isn't UTF-8")?;
+    match shell {
+        Shell::Bash => {
+            script = script.replace("complete -F _sqr ", "complete -F _sqr_tables ");
+            if let Some(at) = script.find("\nif [[ \"${BASH_VERSINFO") {
+                script.insert_str(at, BASH_TABLES);
+            }
+        }
+        Shell::Zsh => {
+            script = script.replace(":TABLE:_default'", ":TABLE:_sqr_tables'");
+            if let Some(at) = script.find("if [ \"$funcstack[1]\" = \"_sqr\" ]") {
+                script.insert_str(at, ZSH_TABLES);
+            }
+        }
+        Shell::Fish => {
+            script = script
+                .lines()
+                .map(|line| {
+                    if line.contains(" -l table ") {
+                        format!(
+                            "{} -f -a \"(sqr {} (commandline -opc))\"",
+                            line, COMPLETE_TABLES
+                        )
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            script.push('\n');
+        }
+        _ => {}
+    }
+    out.write_all(script.as_bytes())
+        .context("Failed to write the completion script")?;
+    Ok(())
+}
+
+/// Print the tables of the database named on `words`, a partly typed command line
+/// ending at the `--table` being completed: the `--db` of a subcommand, or the
+/// database the TUI opens
+pub fn complete_tables(cmd: Command, words: &[String]) -> Result<()> {
+    let end = words
+        .iter()
+        .rposition(|word| word == "--table" || word == "-t")
+        .unwrap_or(words.len());
+    let matches = cmd
+        .ignore_errors(true)
+        .try_get_matches_from(&words[..end])?;
+    let db = match matches.subcommand() {
+        Some((_, matches)) => matches.try_get_one::<String>("db")?,
+        None => matches.try_get_one::<String>("database")?,
+    };
+    let Some(db) = db else {
+        return Ok(());
+    };
+    let conn = Database::new(db, true)?.into_connection();
+    let mut out = std::io::stdout().lock();
+    for name in get_table_names(&conn)? {
+        writeln!(out, "{}", name)?;
+    }
+    Ok(())
+}
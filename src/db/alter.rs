@@ -0,0 +1,172 @@
+use super::error::sql_error;
+use super::{format_sql_error, TableRef};
+use crate::export::quote_identifier;
+use anyhow::{anyhow, bail, Result};
+use rusqlite::Connection;
+
+/// What the Schema view can do to a table's columns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnAction {
+    Add,
+    Rename,
+    Drop,
+}
+
+impl ColumnAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            ColumnAction::Add => "add column",
+            ColumnAction::Rename => "rename column",
+            ColumnAction::Drop => "drop column",
+        }
+    }
+
+    /// The SQLite release its ALTER TABLE form arrived in, as a version number and
+    /// as text
+    fn since(self) -> (i32, &'static str) {
+        match self {
+            ColumnAction::Add => (3_002_000, "3.2.0"),
+            ColumnAction::Rename => (3_025_000, "3.25.0"),
+            ColumnAction::Drop => (3_035_000, "3.35.0"),
+        }
+    }
+
+    /// Why the SQLite sqr runs with can't do this, when it's older than the release
+    /// that added it
+    pub fn unsupported(self) -> Option<String> {
+        let (number, release) = self.since();
+        (rusqlite::version_number() < number).then(|| {
+            format!(
+                "{} needs SQLite {}; this is {}",
+                self.label(),
+                release,
+                rusqlite::version()
+            )
+        })
+    }
+}
+
+/// A column to add, as the Schema view's form describes it
+#[derive(Debug, Clone, Default)]
+pub struct NewColumn {
+    pub name: String,
+    /// Declared type; may be empty
+    pub data_type: String,
+    pub not_null: bool,
+    /// Default as typed: a number, keyword, quoted string or parenthesised expression
+    /// is used as is, anything else as a string
+    pub default: Option<String>,
+}
+
+impl NewColumn {
+    /// The column definition ADD COLUMN takes
+    pub fn definition(&self) -> String {
+        let mut definition = quote_identifier(self.name.trim());
+        let data_type = self.data_type.trim();
+        if !data_type.is_empty() {
+            definition.push(' ');
+            definition.push_str(data_type);
+        }
+        if self.not_null {
+            definition.push_str(" NOT NULL");
+        }
+        if let Some(default) = &self.default {
+            definition.push_str(" DEFAULT ");
+            definition.push_str(&default_literal(default));
+        }
+        definition
+    }
+}
+
+/// A change to one of a table's columns
+#[derive(Debug, Clone)]
+pub enum ColumnChange {
+    Add(NewColumn),
+    Rename { column: String, new_name: String },
+    Drop { column: String },
+}
+
+impl ColumnChange {
+    pub fn action(&self) -> ColumnAction {
+        match self {
+            ColumnChange::Add(_) => ColumnAction::Add,
+            ColumnChange::Rename { .. } => ColumnAction::Rename,
+            ColumnChange::Drop { .. } => ColumnAction::Drop,
+        }
+    }
+
+    /// The ALTER TABLE statement making the change to `table`
+    pub fn statement(&self, table: &TableRef) -> String {
+        let alter = format!("ALTER TABLE {}", table.quoted());
+        match self {
+            ColumnChange::Add(column) => format!("{} ADD COLUMN {}", alter, column.definition()),
+            ColumnChange::Rename { column, new_name } => format!(
+                "{} RENAME COLUMN {} TO {}",
+                alter,
+                quote_identifier(column),
+                quote_identifier(new_name.trim())
+            ),
+            ColumnChange::Drop { column } => {
+                format!("{} DROP COLUMN {}", alter, quote_identifier(column))
+            }
+        }
+    }
+}
+
+/// Make `change` to the columns of `table_name`, saying what was done
+pub fn alter_column(conn: &Connection, table_name: &str, change: &ColumnChange) -> Result<String> {
+    if let Some(reason) = change.action().unsupported() {
+        bail!(reason);
+    }
+    let table = TableRef::resolve(conn, table_name);
+    let sql = change.statement(&table);
+    conn.execute_batch(&sql).map_err(|e| sql_error(e, &sql))?;
+    Ok(match change {
+        ColumnChange::Add(column) => {
+            format!("Added column {} to {}", column.name.trim(), table_name)
+        }
+        ColumnChange::Rename { column, new_name } => {
+            format!("Renamed column {} to {}", column, new_name.trim())
+        }
+        ColumnChange::Drop { column } => format!("Dropped column {} from {}", column, table_name),
+    })
+}
+
+/// Values in a column that aren't NULL, which dropping it loses
+pub fn count_values(conn: &Connection, table_name: &str, column_name: &str) -> Result<i64> {
+    let query = format!(
+        "SELECT count({}) FROM {}",
+        quote_identifier(column_name),
+        TableRef::resolve(conn, table_name).quoted()
+    );
+    conn.query_row(&query, [], |row| row.get(0))
+        .map_err(|e| anyhow!("{}", format_sql_error(&e, &query)))
+}
+
+/// `text` as a DEFAULT clause takes it: literals, keywords and expressions as they are,
+/// other text quoted as a string
+fn default_literal(text: &str) -> String {
+    let text = text.trim();
+    let keyword = [
+        "NULL",
+        "TRUE",
+        "FALSE",
+        "CURRENT_TIME",
+        "CURRENT_DATE",
+        "CURRENT_TIMESTAMP",
+    ]
+    .iter()
+    .any(|keyword| keyword.eq_ignore_ascii_case(text));
+    let number = text.parse::<f64>().is_ok()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+    let quoted = (text.starts_with('\'') && text.ends_with('\'') && text.len() > 1)
+        || (text.starts_with('(') && text.ends_with(')'))
+        || ((text.starts_with("x'") || text.starts_with("X'")) && text.ends_with('\''));
+    if keyword || number || quoted {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', "''"))
+    }
+}
@@ -0,0 +1,188 @@
+use crate::export::quote_identifier;
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, ErrorCode};
+use std::path::Path;
+
+/// A table as the app names it, `alias.table` when it's in an attached database, split
+/// into the database it's in and its own name
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRef {
+    /// Alias of the attached database holding the table; None for the main database
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+impl TableRef {
+    /// Resolve `name` against the databases attached to `conn`
+    pub fn resolve(conn: &Connection, name: &str) -> Self {
+        Self::among(name, &attached_schemas(conn).unwrap_or_default())
+    }
+
+    /// `name` split at its first dot when what comes before it is one of `schemas`.
+    /// Attaching refuses an alias the main database has tables under, so a name can't be
+    /// read both ways.
+    pub fn among(name: &str, schemas: &[String]) -> Self {
+        if let Some((schema, table)) = name.split_once('.') {
+            if let Some(schema) = schemas.iter().find(|s| s.eq_ignore_ascii_case(schema)) {
+                return Self {
+                    schema: Some(schema.clone()),
+                    name: table.to_string(),
+                };
+            }
+        }
+        Self {
+            schema: None,
+            name: name.to_string(),
+        }
+    }
+
+    /// The table quoted for SQL: `"alias"."table"`, or `"table"` in the main database
+    pub fn quoted(&self) -> String {
+        format!("{}{}", self.prefix(), quote_identifier(&self.name))
+    }
+
+    /// The schema table of the table's database
+    pub fn master(&self) -> String {
+        format!("{}sqlite_master", self.prefix())
+    }
+
+    /// `"alias".` for a table in an attached database, which also points a PRAGMA at it
+    pub fn prefix(&self) -> String {
+        schema_prefix(self.schema.as_deref())
+    }
+
+    /// Name of the table's database as the schema argument of a pragma function takes it
+    pub fn schema_name(&self) -> &str {
+        self.schema.as_deref().unwrap_or("main")
+    }
+}
+
+/// `"alias".` naming an attached database's objects, or nothing for the main database
+pub(super) fn schema_prefix(schema: Option<&str>) -> String {
+    schema
+        .map(|schema| format!("{}.", quote_identifier(schema)))
+        .unwrap_or_default()
+}
+
+/// Aliases of the databases attached to `conn`, in the order they were attached
+pub fn attached_schemas(conn: &Connection) -> Result<Vec<String>> {
+    Ok(attached_databases(conn)?
+        .into_iter()
+        .map(|(alias, _)| alias)
+        .collect())
+}
+
+/// Alias and file of each database attached to `conn`; the file is empty for one that
+/// lives in memory
+pub fn attached_databases(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT name, file FROM pragma_database_list
+         WHERE name NOT IN ('main', 'temp') ORDER BY seq",
+    )?;
+    let databases = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(databases)
+}
+
+/// Split `PATH AS ALIAS` into its path and alias. Without `AS`, the alias is the file's
+/// name up to its extension.
+pub fn parse_attach(spec: &str) -> Result<(String, String)> {
+    let spec = spec.trim();
+    let lower = spec.to_ascii_lowercase();
+    let (path, alias) = match lower.rfind(" as ") {
+        Some(at) => (
+            spec[..at].trim(),
+            spec[at + " as ".len()..].trim().to_string(),
+        ),
+        None => {
+            let stem = Path::new(spec)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let alias = stem
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            (spec, alias)
+        }
+    };
+    if path.is_empty() {
+        bail!("Give the database to attach as PATH AS ALIAS");
+    }
+    Ok((path.to_string(), alias))
+}
+
+/// Attach the database at `path` to `conn` as `alias`, so its tables can be read as
+/// `alias.table`. The file must exist, and be a database SQLite can read.
+pub fn attach_database(conn: &Connection, path: &str, alias: &str) -> Result<()> {
+    if alias.is_empty() || alias.contains('.') {
+        bail!("Alias {:?} can't be empty or contain a dot", alias);
+    }
+    if alias.eq_ignore_ascii_case("main") || alias.eq_ignore_ascii_case("temp") {
+        bail!(
+            "{} is the name of a database already; pick another alias",
+            alias
+        );
+    }
+    if attached_schemas(conn)?
+        .iter()
+        .any(|schema| schema.eq_ignore_ascii_case(alias))
+    {
+        bail!("A database is already attached as {}", alias);
+    }
+    if !super::is_special_path(path) && !Path::new(path).exists() {
+        bail!("Database file not found: {}", path);
+    }
+    // A main table named `alias.x` couldn't be told apart from table x of the attachment
+    let clash: Option<String> = conn
+        .query_row(
+            "SELECT name FROM sqlite_master
+             WHERE lower(substr(name, 1, length(?1) + 1)) = lower(?1 || '.')",
+            [alias],
+            |row| row.get(0),
+        )
+        .ok();
+    if let Some(table) = clash {
+        bail!(
+            "Table {} of the main database clashes with alias {}; pick another alias",
+            table,
+            alias
+        );
+    }
+
+    conn.execute("ATTACH DATABASE ?1 AS ?2", [path, alias])
+        .map_err(|e| attach_error(e, path))?;
+    // SQLite reads the file lazily, so make sure it's one it understands
+    let readable = conn.query_row(
+        &format!(
+            "SELECT count(*) FROM {}.sqlite_master",
+            quote_identifier(alias)
+        ),
+        [],
+        |row| row.get::<_, i64>(0),
+    );
+    if let Err(e) = readable {
+        let _ = conn.execute("DETACH DATABASE ?1", [alias]);
+        return Err(attach_error(e, path));
+    }
+    Ok(())
+}
+
+/// Detach the database attached as `alias`
+pub fn detach_database(conn: &Connection, alias: &str) -> Result<()> {
+    conn.execute("DETACH DATABASE ?1", [alias])
+        .with_context(|| format!("Failed to detach {}", alias))?;
+    Ok(())
+}
+
+/// Why attaching `path` failed, saying so plainly when it isn't a database SQLite can
+/// read, as when it's encrypted
+fn attach_error(error: rusqlite::Error, path: &str) -> anyhow::Error {
+    match error.sqlite_error_code() {
+        Some(ErrorCode::NotADatabase) => {
+            anyhow::anyhow!("{} is not a SQLite database, or it's encrypted", path)
+        }
+        _ => anyhow::Error::from(error).context(format!("Failed to attach {}", path)),
+    }
+}
@@ -0,0 +1,99 @@
+use super::BUSY_TIMEOUT;
+use anyhow::{bail, Context, Result};
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Pages copied per step, few enough that progress is reported often and writers to
+/// the source get a look in between steps
+const PAGES_PER_STEP: i32 = 256;
+/// Wait before trying a step again while the source is locked
+const BUSY_PAUSE: Duration = Duration::from_millis(50);
+
+/// How far a backup has got, in pages of the source database
+#[derive(Debug, Clone, Copy)]
+pub struct BackupProgress {
+    pub remaining: i32,
+    pub total: i32,
+}
+
+/// Copy the database `conn` has open to the file at `path`, replacing it, while other
+/// connections go on using the source. The copy is written beside `path` and renamed
+/// over it once complete, so `path` is never left half-written. A source that stays
+/// busy for the busy timeout fails the backup. `on_progress` hears after each step and
+/// returns false to stop, returning [`BackupCancelled`].
+pub fn backup_to(
+    conn: &Connection,
+    path: &Path,
+    on_progress: &mut dyn FnMut(BackupProgress) -> bool,
+) -> Result<BackupProgress> {
+    let partial = partial_path(path);
+    let copied = copy_pages(conn, &partial, on_progress).and_then(|progress| {
+        std::fs::rename(&partial, path)
+            .with_context(|| format!("Failed to move the backup to {}", path.display()))?;
+        Ok(progress)
+    });
+    if copied.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    copied
+}
+
+fn copy_pages(
+    conn: &Connection,
+    path: &Path,
+    on_progress: &mut dyn FnMut(BackupProgress) -> bool,
+) -> Result<BackupProgress> {
+    let mut destination =
+        Connection::open(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let backup = Backup::new(conn, &mut destination).context("Failed to start the backup")?;
+    let mut locked_since: Option<Instant> = None;
+    loop {
+        let step = backup
+            .step(PAGES_PER_STEP)
+            .context("Failed to copy the database")?;
+        let progress = backup.progress();
+        let progress = BackupProgress {
+            remaining: progress.remaining,
+            total: progress.pagecount,
+        };
+        match step {
+            StepResult::Done => return Ok(progress),
+            StepResult::More => locked_since = None,
+            // The connection's busy handler has already waited out the busy timeout
+            StepResult::Busy => bail!(
+                "The database stayed busy for {}s; try the backup again",
+                BUSY_TIMEOUT.as_secs()
+            ),
+            // A statement on the source connection is writing, which the busy handler
+            // doesn't wait for, so the step is retried for as long
+            StepResult::Locked => {
+                let since = *locked_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= BUSY_TIMEOUT {
+                    bail!(
+                        "The database stayed locked for {}s; try the backup again",
+                        BUSY_TIMEOUT.as_secs()
+                    );
+                }
+                std::thread::sleep(BUSY_PAUSE);
+            }
+            _ => {}
+        }
+        if !on_progress(progress) {
+            return Err(BackupCancelled.into());
+        }
+    }
+}
+
+/// Where a backup to `path` is written until it's complete
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// What a backup fails with when its progress callback asks it to stop
+#[derive(Debug, thiserror::Error)]
+#[error("Backup cancelled")]
+pub struct BackupCancelled;
@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use rusqlite::types::FromSql;
+use rusqlite::{Connection, ErrorCode};
+
+/// What an integrity check found, with a few facts about the file
+#[derive(Debug, Clone)]
+pub struct CheckReport {
+    /// `quick_check` ran rather than the full `integrity_check`
+    pub quick: bool,
+    /// Problems the check found; none when it said "ok"
+    pub problems: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+    pub page_count: i64,
+    pub page_size: i64,
+    pub freelist_count: i64,
+    pub journal_mode: String,
+    pub encoding: String,
+}
+
+/// A row whose foreign key points at a parent row that doesn't exist
+#[derive(Debug, Clone)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    /// NULL for a WITHOUT ROWID table
+    pub rowid: Option<i64>,
+    pub parent: String,
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty() && self.foreign_key_violations.is_empty()
+    }
+
+    /// The report as lines of text, problems listed under their heading
+    pub fn lines(&self) -> Vec<String> {
+        let check = if self.quick {
+            "Quick check"
+        } else {
+            "Integrity check"
+        };
+        let mut lines = Vec::new();
+        if self.problems.is_empty() {
+            lines.push(format!("{}: ok", check));
+        } else {
+            lines.push(format!("{}: {} problems", check, self.problems.len()));
+            lines.extend(self.problems.iter().map(|problem| format!("  {}", problem)));
+        }
+        if self.foreign_key_violations.is_empty() {
+            lines.push("Foreign keys: ok".to_string());
+        } else {
+            lines.push(format!(
+                "Foreign keys: {} violations",
+                self.foreign_key_violations.len()
+            ));
+            lines.extend(self.foreign_key_violations.iter().map(|violation| {
+                let row = violation
+                    .rowid
+                    .map_or_else(|| "a row".to_string(), |rowid| format!("rowid {}", rowid));
+                format!(
+                    "  {} {} has no parent in {}",
+                    violation.table, row, violation.parent
+                )
+            }));
+        }
+        lines.push(format!(
+            "Pages: {} of {} bytes ({:.1} MB), {} free",
+            self.page_count,
+            self.page_size,
+            (self.page_count * self.page_size) as f64 / (1024.0 * 1024.0),
+            self.freelist_count
+        ));
+        lines.push(format!("Journal mode: {}", self.journal_mode));
+        lines.push(format!("Encoding: {}", self.encoding));
+        lines
+    }
+}
+
+/// Run `PRAGMA integrity_check`, or the faster `quick_check` that skips comparing
+/// indexes with their tables, then `foreign_key_check`, and read the page counts,
+/// journal mode and encoding. Interrupting the connection stops a long check.
+pub fn check_database(conn: &Connection, quick: bool) -> Result<CheckReport> {
+    let pragma = if quick {
+        "quick_check"
+    } else {
+        "integrity_check"
+    };
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}", pragma))
+        .context("Failed to start the integrity check")?;
+    let mut problems: Vec<String> = Vec::new();
+    let checked = stmt
+        .query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>());
+    if let Some(found) =
+        corruption(checked, &mut problems).context("Failed to run the integrity check")?
+    {
+        if found != ["ok"] {
+            problems.extend(found);
+        }
+    }
+
+    let mut stmt = conn
+        .prepare("PRAGMA foreign_key_check")
+        .context("Failed to start the foreign key check")?;
+    let checked = stmt
+        .query_map([], |row| {
+            Ok(ForeignKeyViolation {
+                table: row.get(0)?,
+                rowid: row.get(1)?,
+                parent: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>());
+    let foreign_key_violations = corruption(checked, &mut problems)
+        .context("Failed to run the foreign key check")?
+        .unwrap_or_default();
+
+    Ok(CheckReport {
+        quick,
+        problems,
+        foreign_key_violations,
+        page_count: pragma_value(conn, "page_count")?,
+        page_size: pragma_value(conn, "page_size")?,
+        freelist_count: pragma_value(conn, "freelist_count")?,
+        journal_mode: pragma_value(conn, "journal_mode")?,
+        encoding: pragma_value(conn, "encoding")?,
+    })
+}
+
+/// A check's rows, or none when it stopped at a page too damaged to read, which is
+/// added to `problems` as what it found
+fn corruption<T>(
+    result: rusqlite::Result<Vec<T>>,
+    problems: &mut Vec<String>,
+) -> rusqlite::Result<Option<Vec<T>>> {
+    match result {
+        Ok(rows) => Ok(Some(rows)),
+        Err(rusqlite::Error::SqliteFailure(e, message))
+            if matches!(e.code, ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) =>
+        {
+            problems.push(message.unwrap_or_else(|| e.to_string()));
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn pragma_value<T: FromSql>(conn: &Connection, name: &str) -> Result<T> {
+    conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+        .with_context(|| format!("Failed to read {}", name))
+}
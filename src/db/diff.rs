@@ -0,0 +1,495 @@
+use super::schema::{count_rows, get_columns, get_indexes, get_tables};
+use crate::export::quote_identifier;
+use crate::types::{ColumnInfo, IndexInfo, Value};
+use anyhow::{Context, Result};
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::Connection;
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+/// What to compare besides the schema
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    /// Compare row counts and, for tables with a primary key, the rows themselves
+    pub data: bool,
+    /// Most row differences listed for each table
+    pub row_limit: usize,
+}
+
+/// How the second database differs from the first
+#[derive(Debug, Default, Serialize)]
+pub struct DatabaseDiff {
+    pub tables_added: Vec<String>,
+    pub tables_removed: Vec<String>,
+    pub tables_changed: Vec<TableDiff>,
+}
+
+/// How a table in both databases differs
+#[derive(Debug, Default, Serialize)]
+pub struct TableDiff {
+    pub name: String,
+    pub columns_added: Vec<ColumnSnapshot>,
+    pub columns_removed: Vec<ColumnSnapshot>,
+    pub columns_retyped: Vec<ColumnChange>,
+    pub indexes_added: Vec<IndexSnapshot>,
+    pub indexes_removed: Vec<IndexSnapshot>,
+    pub indexes_changed: Vec<IndexChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<DataDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnSnapshot {
+    pub name: String,
+    pub data_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnChange {
+    pub name: String,
+    pub old_type: String,
+    pub new_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexSnapshot {
+    pub name: String,
+    pub unique: bool,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexChange {
+    pub old: IndexSnapshot,
+    pub new: IndexSnapshot,
+}
+
+/// How a table's rows differ
+#[derive(Debug, Default, Serialize)]
+pub struct DataDiff {
+    pub old_rows: Option<u64>,
+    pub new_rows: Option<u64>,
+    /// The primary key rows were matched on; empty when they weren't compared, as the
+    /// table has no primary key or it changed
+    pub key_columns: Vec<String>,
+    pub rows_added: Vec<RowKey>,
+    pub rows_removed: Vec<RowKey>,
+    pub rows_changed: Vec<ChangedRow>,
+    /// More rows differ than were listed
+    pub truncated: bool,
+}
+
+/// A row's primary key values
+#[derive(Debug, Clone)]
+pub struct RowKey(pub Vec<Value>);
+
+impl Serialize for RowKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|value| match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Integer(i) => (*i).into(),
+            Value::Real(r) => (*r).into(),
+            Value::Text(t) => t.clone().into(),
+            Value::Blob(_) => value.edit_text().into(),
+        }))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedRow {
+    pub key: RowKey,
+    /// The columns whose values differ
+    pub columns: Vec<String>,
+}
+
+/// A table's schema as compared: its columns and indexes
+struct TableSnapshot {
+    name: String,
+    row_count: Option<u64>,
+    columns: Vec<ColumnInfo>,
+    indexes: Vec<IndexSnapshot>,
+}
+
+impl DatabaseDiff {
+    pub fn is_empty(&self) -> bool {
+        self.tables_added.is_empty()
+            && self.tables_removed.is_empty()
+            && self.tables_changed.is_empty()
+    }
+
+    /// Write the differences for reading, one line each: `+` for what the second
+    /// database added, `-` for what it removed and `~` for what changed
+    pub fn write_report(&self, out: &mut dyn Write) -> io::Result<()> {
+        if self.is_empty() {
+            return writeln!(out, "No differences");
+        }
+        for name in &self.tables_added {
+            writeln!(out, "+ table {}", name)?;
+        }
+        for name in &self.tables_removed {
+            writeln!(out, "- table {}", name)?;
+        }
+        for table in &self.tables_changed {
+            writeln!(out, "~ table {}", table.name)?;
+            for column in &table.columns_added {
+                writeln!(out, "    + column {} {}", column.name, column.data_type)?;
+            }
+            for column in &table.columns_removed {
+                writeln!(out, "    - column {} {}", column.name, column.data_type)?;
+            }
+            for column in &table.columns_retyped {
+                writeln!(
+                    out,
+                    "    ~ column {}: {} -> {}",
+                    column.name, column.old_type, column.new_type
+                )?;
+            }
+            for index in &table.indexes_added {
+                writeln!(out, "    + index {}", index_text(index))?;
+            }
+            for index in &table.indexes_removed {
+                writeln!(out, "    - index {}", index_text(index))?;
+            }
+            for index in &table.indexes_changed {
+                writeln!(
+                    out,
+                    "    ~ index {} -> {}",
+                    index_text(&index.old),
+                    index_text(&index.new)
+                )?;
+            }
+            if let Some(data) = &table.data {
+                data.write_report(out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DataDiff {
+    fn is_empty(&self) -> bool {
+        self.old_rows == self.new_rows
+            && self.rows_added.is_empty()
+            && self.rows_removed.is_empty()
+            && self.rows_changed.is_empty()
+    }
+
+    fn write_report(&self, out: &mut dyn Write) -> io::Result<()> {
+        let count = |rows: Option<u64>| rows.map_or("?".to_string(), |n| n.to_string());
+        if self.old_rows != self.new_rows {
+            writeln!(
+                out,
+                "    rows: {} -> {}",
+                count(self.old_rows),
+                count(self.new_rows)
+            )?;
+        }
+        let key = |key: &RowKey| {
+            self.key_columns
+                .iter()
+                .zip(&key.0)
+                .map(|(column, value)| format!("{}={}", column, key_text(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        for row in &self.rows_added {
+            writeln!(out, "    + row {}", key(row))?;
+        }
+        for row in &self.rows_removed {
+            writeln!(out, "    - row {}", key(row))?;
+        }
+        for row in &self.rows_changed {
+            writeln!(
+                out,
+                "    ~ row {}: {}",
+                key(&row.key),
+                row.columns.join(", ")
+            )?;
+        }
+        if self.truncated {
+            writeln!(out, "    ... more rows differ")?;
+        }
+        Ok(())
+    }
+}
+
+/// `name (columns)`, marked UNIQUE where it is
+fn index_text(index: &IndexSnapshot) -> String {
+    format!(
+        "{}{} ({})",
+        index.name,
+        if index.unique { " UNIQUE" } else { "" },
+        index.columns.join(", ")
+    )
+}
+
+fn key_text(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Text(t) => format!("'{}'", t),
+        _ => value.edit_text(),
+    }
+}
+
+/// Compare the schemas of two databases, and their rows too if `options.data` is set.
+/// Tables are matched by name, as are the columns and indexes within them.
+pub fn diff_databases(
+    old: &Connection,
+    new: &Connection,
+    options: DiffOptions,
+) -> Result<DatabaseDiff> {
+    let old_tables = snapshot(old).context("Failed to read the first database's schema")?;
+    let new_tables = snapshot(new).context("Failed to read the second database's schema")?;
+
+    let mut diff = DatabaseDiff::default();
+    for table in &new_tables {
+        if !old_tables.iter().any(|t| t.name == table.name) {
+            diff.tables_added.push(table.name.clone());
+        }
+    }
+    for old_table in &old_tables {
+        let Some(new_table) = new_tables.iter().find(|t| t.name == old_table.name) else {
+            diff.tables_removed.push(old_table.name.clone());
+            continue;
+        };
+        let mut table = diff_schema(old_table, new_table);
+        if options.data {
+            let data = diff_data(old, new, old_table, new_table, options.row_limit)
+                .with_context(|| format!("Failed to compare the rows of {}", old_table.name))?;
+            table.data = (!data.is_empty()).then_some(data);
+        }
+        let unchanged = table.columns_added.is_empty()
+            && table.columns_removed.is_empty()
+            && table.columns_retyped.is_empty()
+            && table.indexes_added.is_empty()
+            && table.indexes_removed.is_empty()
+            && table.indexes_changed.is_empty()
+            && table.data.is_none();
+        if !unchanged {
+            diff.tables_changed.push(table);
+        }
+    }
+    Ok(diff)
+}
+
+/// Every table with its columns and indexes
+fn snapshot(conn: &Connection) -> Result<Vec<TableSnapshot>> {
+    get_tables(conn, false)?
+        .into_iter()
+        .map(|table| {
+            let indexes = get_indexes(conn, &table.name)?
+                .into_iter()
+                .map(|index: IndexInfo| IndexSnapshot {
+                    name: index.name,
+                    unique: index.unique,
+                    columns: index.columns,
+                })
+                .collect();
+            Ok(TableSnapshot {
+                columns: get_columns(conn, &table.name)?,
+                indexes,
+                row_count: count_rows(conn, &table.name).ok(),
+                name: table.name,
+            })
+        })
+        .collect()
+}
+
+fn diff_schema(old: &TableSnapshot, new: &TableSnapshot) -> TableDiff {
+    let column_snapshot = |column: &ColumnInfo| ColumnSnapshot {
+        name: column.name.clone(),
+        data_type: column.data_type.clone(),
+    };
+    let mut table = TableDiff {
+        name: old.name.clone(),
+        ..TableDiff::default()
+    };
+    for column in &new.columns {
+        if !old.columns.iter().any(|c| c.name == column.name) {
+            table.columns_added.push(column_snapshot(column));
+        }
+    }
+    for column in &old.columns {
+        match new.columns.iter().find(|c| c.name == column.name) {
+            None => table.columns_removed.push(column_snapshot(column)),
+            Some(new_column) if !new_column.data_type.eq_ignore_ascii_case(&column.data_type) => {
+                table.columns_retyped.push(ColumnChange {
+                    name: column.name.clone(),
+                    old_type: column.data_type.clone(),
+                    new_type: new_column.data_type.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for index in &new.indexes {
+        if !old.indexes.iter().any(|i| i.name == index.name) {
+            table.indexes_added.push(index.clone());
+        }
+    }
+    for index in &old.indexes {
+        match new.indexes.iter().find(|i| i.name == index.name) {
+            None => table.indexes_removed.push(index.clone()),
+            Some(new_index) if new_index != index => table.indexes_changed.push(IndexChange {
+                old: index.clone(),
+                new: new_index.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    table
+}
+
+/// Compare row counts, then walk both tables in primary key order side by side to find
+/// the rows only one has and those whose shared columns differ
+fn diff_data(
+    old: &Connection,
+    new: &Connection,
+    old_table: &TableSnapshot,
+    new_table: &TableSnapshot,
+    row_limit: usize,
+) -> Result<DataDiff> {
+    let mut data = DataDiff {
+        old_rows: old_table.row_count,
+        new_rows: new_table.row_count,
+        ..DataDiff::default()
+    };
+    let key_columns = |table: &TableSnapshot| -> Vec<String> {
+        table
+            .columns
+            .iter()
+            .filter(|c| c.primary_key)
+            .map(|c| c.name.clone())
+            .collect()
+    };
+    let keys = key_columns(old_table);
+    if keys.is_empty() || keys != key_columns(new_table) {
+        return Ok(data);
+    }
+    let shared: Vec<String> = old_table
+        .columns
+        .iter()
+        .filter(|c| !c.primary_key && new_table.columns.iter().any(|n| n.name == c.name))
+        .map(|c| c.name.clone())
+        .collect();
+
+    let quote = |names: &[String], suffix: &str| {
+        names
+            .iter()
+            .map(|name| format!("{}{}", quote_identifier(name), suffix))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let sql = format!(
+        "SELECT {} FROM {} ORDER BY {}",
+        quote(&[keys.clone(), shared.clone()].concat(), ""),
+        quote_identifier(&old_table.name),
+        quote(&keys, " COLLATE BINARY")
+    );
+    let mut old_stmt = old.prepare(&sql)?;
+    let mut new_stmt = new.prepare(&sql)?;
+    let mut old_rows = old_stmt.query([])?;
+    let mut new_rows = new_stmt.query([])?;
+    let width = keys.len() + shared.len();
+    let next = |rows: &mut rusqlite::Rows| -> Result<Option<Vec<SqliteValue>>> {
+        match rows.next()? {
+            Some(row) => Ok(Some(
+                (0..width)
+                    .map(|i| row.get(i))
+                    .collect::<rusqlite::Result<_>>()?,
+            )),
+            None => Ok(None),
+        }
+    };
+
+    /// Where the walk is: a row only one table has, or a key both do
+    enum Step {
+        Removed(RowKey),
+        Added(RowKey),
+        Matched(Option<ChangedRow>),
+    }
+    let key_len = keys.len();
+    let row_key =
+        |row: &[SqliteValue]| RowKey(row[..key_len].iter().cloned().map(Value::from).collect());
+    let mut old_row = next(&mut old_rows)?;
+    let mut new_row = next(&mut new_rows)?;
+    let mut found = 0;
+    loop {
+        let step = match (&old_row, &new_row) {
+            (None, None) => break,
+            (Some(o), None) => Step::Removed(row_key(o)),
+            (None, Some(n)) => Step::Added(row_key(n)),
+            (Some(o), Some(n)) => match compare_keys(&o[..key_len], &n[..key_len]) {
+                Ordering::Less => Step::Removed(row_key(o)),
+                Ordering::Greater => Step::Added(row_key(n)),
+                Ordering::Equal => {
+                    let columns: Vec<String> = shared
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| o[key_len + i] != n[key_len + i])
+                        .map(|(_, name)| name.clone())
+                        .collect();
+                    Step::Matched((!columns.is_empty()).then(|| ChangedRow {
+                        key: row_key(o),
+                        columns,
+                    }))
+                }
+            },
+        };
+        if !matches!(step, Step::Matched(None)) {
+            if found == row_limit {
+                data.truncated = true;
+                break;
+            }
+            found += 1;
+        }
+        match step {
+            Step::Removed(key) => {
+                data.rows_removed.push(key);
+                old_row = next(&mut old_rows)?;
+            }
+            Step::Added(key) => {
+                data.rows_added.push(key);
+                new_row = next(&mut new_rows)?;
+            }
+            Step::Matched(changed) => {
+                data.rows_changed.extend(changed);
+                old_row = next(&mut old_rows)?;
+                new_row = next(&mut new_rows)?;
+            }
+        }
+    }
+    data.key_columns = keys;
+    Ok(data)
+}
+
+/// Order two keys the way SQLite's ORDER BY does with the BINARY collation
+fn compare_keys(a: &[SqliteValue], b: &[SqliteValue]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| compare_values(a, b))
+        .find(|order| order.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// NULLs first, then numbers by value, text by its bytes and BLOBs by theirs
+fn compare_values(a: &SqliteValue, b: &SqliteValue) -> Ordering {
+    fn rank(value: &SqliteValue) -> u8 {
+        match value {
+            SqliteValue::Null => 0,
+            SqliteValue::Integer(_) | SqliteValue::Real(_) => 1,
+            SqliteValue::Text(_) => 2,
+            SqliteValue::Blob(_) => 3,
+        }
+    }
+    match (a, b) {
+        (SqliteValue::Integer(a), SqliteValue::Integer(b)) => a.cmp(b),
+        (SqliteValue::Integer(a), SqliteValue::Real(b)) => (*a as f64).total_cmp(b),
+        (SqliteValue::Real(a), SqliteValue::Integer(b)) => a.total_cmp(&(*b as f64)),
+        (SqliteValue::Real(a), SqliteValue::Real(b)) => a.total_cmp(b),
+        (SqliteValue::Text(a), SqliteValue::Text(b)) => a.as_bytes().cmp(b.as_bytes()),
+        (SqliteValue::Blob(a), SqliteValue::Blob(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
@@ -1,3 +1,9 @@
+/// A SQL error with a friendly message, keeping the SQLite error as its cause
+pub fn sql_error(error: rusqlite::Error, query: &str) -> anyhow::Error {
+    let message = format_sql_error(&error, query);
+    anyhow::Error::new(error).context(message)
+}
+
 /// User-friendly SQL error formatting
 pub fn format_sql_error(error: &rusqlite::Error, query: &str) -> String {
     match error {
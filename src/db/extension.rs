@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+/// Functions and virtual table modules the connection has that a fresh one doesn't,
+/// which is what loaded extensions added
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionList {
+    pub functions: Vec<String>,
+    pub modules: Vec<String>,
+}
+
+impl ExtensionList {
+    /// The list as lines of text, `loaded` being the files given with --extension
+    pub fn lines(&self, loaded: &[String]) -> Vec<String> {
+        let mut lines = Vec::new();
+        if loaded.is_empty() {
+            lines.push("Loaded with --extension: none".to_string());
+        } else {
+            lines.push("Loaded with --extension:".to_string());
+            lines.extend(loaded.iter().map(|path| format!("  {}", path)));
+        }
+        for (heading, names) in [("Functions", &self.functions), ("Modules", &self.modules)] {
+            if names.is_empty() {
+                lines.push(format!("{} beyond SQLite's own: none", heading));
+            } else {
+                lines.push(format!("{} beyond SQLite's own: {}", heading, names.len()));
+                lines.extend(names.iter().map(|name| format!("  {}", name)));
+            }
+        }
+        lines
+    }
+}
+
+/// Load the run-time extensions at `paths` into `conn`. Loading is only enabled while
+/// they load, unless `keep_enabled` leaves it on so SQL can call `load_extension()`.
+#[cfg(feature = "extensions")]
+pub fn load_extensions(conn: &Connection, paths: &[String], keep_enabled: bool) -> Result<()> {
+    if paths.is_empty() && !keep_enabled {
+        return Ok(());
+    }
+    // SAFETY: an extension runs arbitrary code in this process, which is what asking
+    // for it with --extension or --allow-extensions means
+    unsafe { conn.load_extension_enable() }.context("Failed to enable extension loading")?;
+    let loaded = paths.iter().try_for_each(|path| {
+        // SAFETY: as above
+        unsafe { conn.load_extension(path, None) }
+            .map_err(|e| anyhow::anyhow!("Failed to load extension {}: {}", path, e))
+    });
+    if loaded.is_err() || !keep_enabled {
+        conn.load_extension_disable()
+            .context("Failed to disable extension loading")?;
+    }
+    loaded
+}
+
+/// Without the `extensions` feature, SQLite can't load extensions at all
+#[cfg(not(feature = "extensions"))]
+pub fn load_extensions(_conn: &Connection, paths: &[String], keep_enabled: bool) -> Result<()> {
+    if paths.is_empty() && !keep_enabled {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "This sqr was built without the `extensions` feature, so it can't load extensions"
+    )
+}
+
+/// What loaded extensions added to `conn`: the functions and modules it has that a
+/// fresh in-memory connection doesn't
+pub fn list_extensions(conn: &Connection) -> Result<ExtensionList> {
+    let fresh = Connection::open_in_memory().context("Failed to open a fresh connection")?;
+    let added = |pragma: &str| -> Result<Vec<String>> {
+        let builtin = names(&fresh, pragma)?;
+        let mut names = names(conn, pragma)?;
+        // SQLite makes a pragma_* module the first time its table-valued function is used
+        names.retain(|name| !builtin.contains(name) && !name.starts_with("pragma_"));
+        Ok(names)
+    };
+    Ok(ExtensionList {
+        functions: added("function_list")?,
+        modules: added("module_list")?,
+    })
+}
+
+/// Distinct names a listing pragma returns, sorted
+fn names(conn: &Connection, pragma: &str) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT DISTINCT name FROM pragma_{} ORDER BY name",
+            pragma
+        ))
+        .with_context(|| format!("Failed to read {}", pragma))?;
+    let names = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()
+        .with_context(|| format!("Failed to read {}", pragma))?;
+    Ok(names)
+}
@@ -0,0 +1,97 @@
+use super::error::sql_error;
+use super::TableRef;
+use crate::export::quote_identifier;
+use anyhow::Result;
+use rusqlite::{Connection, ErrorCode};
+
+/// What the Schema view can do to a table's indexes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexAction {
+    Create,
+    Drop,
+}
+
+impl IndexAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            IndexAction::Create => "create index",
+            IndexAction::Drop => "drop index",
+        }
+    }
+}
+
+/// An index to create, as the Schema view's form describes it
+#[derive(Debug, Clone)]
+pub struct NewIndex {
+    pub name: String,
+    /// In the order the index sorts by them
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+impl NewIndex {
+    /// The CREATE INDEX statement making the index on `table`
+    pub fn statement(&self, table: &TableRef) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|c| quote_identifier(c)).collect();
+        format!(
+            "CREATE {}INDEX {}{} ON {}({})",
+            if self.unique { "UNIQUE " } else { "" },
+            table.prefix(),
+            quote_identifier(&self.name),
+            quote_identifier(&table.name),
+            columns.join(", ")
+        )
+    }
+}
+
+/// A name for an index on `columns` of `table`, `idx_table_col`, numbered when one
+/// of `taken` already has it
+pub fn index_name(table: &str, columns: &[String], taken: &[String]) -> String {
+    let table = table.rsplit('.').next().unwrap_or(table);
+    let base: String = std::iter::once(format!("idx_{}", table))
+        .chain(columns.iter().cloned())
+        .collect::<Vec<_>>()
+        .join("_")
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let is_taken = |name: &str| taken.iter().any(|t| t.eq_ignore_ascii_case(name));
+    if !is_taken(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", base, n))
+        .find(|name| !is_taken(name))
+        .unwrap_or(base)
+}
+
+/// Create `index` on `table_name`, saying what was done. Building it reads every row,
+/// so it can take a while on a big table; interrupting the connection stops it.
+pub fn create_index(conn: &Connection, table_name: &str, index: &NewIndex) -> Result<String> {
+    let table = TableRef::resolve(conn, table_name);
+    let sql = index.statement(&table);
+    // An interrupt keeps its error type so the caller can tell it from a failure
+    conn.execute_batch(&sql)
+        .map_err(|e| match e.sqlite_error_code() {
+            Some(ErrorCode::OperationInterrupted) => anyhow::Error::from(e),
+            _ => sql_error(e, &sql),
+        })?;
+    Ok(format!(
+        "Created index {} on {}({})",
+        index.name,
+        table_name,
+        index.columns.join(", ")
+    ))
+}
+
+/// Drop the index `index_name` of `table_name`
+pub fn drop_index(conn: &Connection, table_name: &str, index_name: &str) -> Result<String> {
+    let table = TableRef::resolve(conn, table_name);
+    let sql = format!(
+        "DROP INDEX {}{}",
+        table.prefix(),
+        quote_identifier(index_name)
+    );
+    conn.execute_batch(&sql).map_err(|e| sql_error(e, &sql))?;
+    Ok(format!("Dropped index {}", index_name))
+}
@@ -0,0 +1,41 @@
+#[cfg(feature = "sqlcipher")]
+use super::DatabaseError;
+#[cfg(feature = "sqlcipher")]
+use anyhow::Context;
+use anyhow::Result;
+use rusqlite::Connection;
+
+/// Unlock a SQLCipher database with `key`, then read its schema to make sure the key
+/// opens it. Without a key this tells an encrypted database from a plain one.
+#[cfg(feature = "sqlcipher")]
+pub(super) fn unlock(conn: &Connection, path: &str, key: Option<&str>) -> Result<()> {
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)
+            .context("Failed to set the key")?;
+    }
+    match conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    }) {
+        Ok(_) => Ok(()),
+        Err(e) if e.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) => {
+            let path = path.to_string();
+            Err(match key {
+                Some(_) => DatabaseError::WrongKey(path),
+                None => DatabaseError::Encrypted(path),
+            }
+            .into())
+        }
+        Err(e) => Err(e).context("Failed to read the database"),
+    }
+}
+
+/// Without the `sqlcipher` feature there's nothing to unlock a database with
+#[cfg(not(feature = "sqlcipher"))]
+pub(super) fn unlock(_conn: &Connection, _path: &str, key: Option<&str>) -> Result<()> {
+    if key.is_some() {
+        anyhow::bail!(
+            "This sqr was built without the `sqlcipher` feature, so it can't open encrypted databases"
+        );
+    }
+    Ok(())
+}
@@ -1,14 +1,38 @@
+mod alter;
+mod attach;
+mod backup;
+mod check;
+pub mod diff;
 mod error;
+mod extension;
+mod index;
+mod key;
+pub mod pragma;
 pub mod query;
 mod schema;
+pub mod script;
+mod temp;
 
 use anyhow::{Context, Result};
-use rusqlite::{Connection, OpenFlags};
+use rusqlite::{Connection, DatabaseName, OpenFlags};
 use std::path::Path;
+use std::time::Duration;
 use thiserror::Error;
 
+pub use alter::{alter_column, count_values, ColumnAction, ColumnChange, NewColumn};
+pub use attach::{attach_database, attached_databases, detach_database, parse_attach, TableRef};
+pub use backup::{backup_to, BackupCancelled, BackupProgress};
+pub use check::{check_database, CheckReport};
+pub use error::format_sql_error;
+pub use extension::{list_extensions, load_extensions, ExtensionList};
+pub use index::{create_index, drop_index, index_name, IndexAction, NewIndex};
 pub use query::update_cell;
-pub use schema::{get_columns, get_foreign_keys, get_indexes, get_table_info, get_tables};
+pub use schema::{
+    count_rows, estimate_rows, get_columns, get_diagram_tables, get_foreign_keys, get_indexes,
+    get_referencing_keys, get_schema_objects, get_table_info, get_table_names, get_table_sizes,
+    get_tables, schema_versions,
+};
+pub use temp::StdinDatabase;
 
 #[derive(Debug, Error)]
 pub enum DatabaseError {
@@ -18,8 +42,75 @@ pub enum DatabaseError {
     InvalidFile(String),
     #[error("SQLite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
+    /// A write another connection's lock stopped, kept apart so it can be retried
+    #[error("{0}")]
+    Locked(String),
+    #[cfg(feature = "sqlcipher")]
+    #[error("{0} is encrypted, or not a SQLite database")]
+    Encrypted(String),
+    #[cfg(feature = "sqlcipher")]
+    #[error("Wrong passphrase? {0} still can't be read")]
+    WrongKey(String),
 }
 
+/// SQLite's `data_version`, which changes whenever another connection commits
+pub fn data_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA data_version", [], |row| row.get(0))
+        .context("Failed to read data_version")
+}
+
+/// Whether `conn` has TEMP tables, views, indexes or triggers, which only it can see
+pub fn has_temp_objects(conn: &Connection) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM temp.sqlite_master)",
+        [],
+        |row| row.get(0),
+    )
+    .context("Failed to read the TEMP schema")
+}
+
+/// Open a read transaction and read from it, so later reads on `conn` see the database
+/// as it is now until [`end_snapshot`], whatever other connections commit
+pub fn begin_snapshot(conn: &Connection) -> Result<()> {
+    conn.execute_batch("BEGIN DEFERRED")
+        .context("Failed to begin a snapshot")?;
+    // A deferred transaction only takes its snapshot at its first read
+    if let Err(e) = conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(())) {
+        let _ = conn.execute_batch("ROLLBACK");
+        return Err(e).context("Failed to begin a snapshot");
+    }
+    Ok(())
+}
+
+/// Close the read transaction [`begin_snapshot`] opened
+pub fn end_snapshot(conn: &Connection) -> Result<()> {
+    conn.execute_batch("COMMIT")
+        .context("Failed to release the snapshot")
+}
+
+/// The database's journal mode, such as `delete` or `wal`
+pub fn journal_mode(conn: &Connection) -> Result<String> {
+    conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .context("Failed to read journal_mode")
+}
+
+/// Whether `path` is `:memory:` or a `file:` URI rather than the path of a file
+fn is_special_path(path: &str) -> bool {
+    path == ":memory:" || path.starts_with("file:")
+}
+
+/// Whether `path` opens a database that lives only in memory and starts out empty
+pub fn is_in_memory(path: &str) -> bool {
+    path == ":memory:" || (path.starts_with("file:") && path.contains("mode=memory"))
+}
+
+/// How long a connection waits for another to finish writing before giving up
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Statements a connection keeps prepared: a page load runs a handful, the Schema view
+/// a few per table
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
 /// Database connection wrapper
 pub struct Database {
     conn: Connection,
@@ -28,17 +119,36 @@ pub struct Database {
 impl Database {
     /// Open a database connection
     pub fn new<P: AsRef<Path>>(path: P, read_only: bool) -> Result<Self> {
+        Self::open(path, read_only, false)
+    }
+
+    /// Open a database connection, creating the file if it doesn't exist when `create`
+    /// is set. `:memory:` and `file:` URIs are handed to SQLite as they are.
+    pub fn open<P: AsRef<Path>>(path: P, read_only: bool, create: bool) -> Result<Self> {
+        Self::open_with_key(path, read_only, create, None)
+    }
+
+    /// Open a database connection as [`Database::open`] does, unlocking a SQLCipher
+    /// database with `key` before anything else reads it
+    pub fn open_with_key<P: AsRef<Path>>(
+        path: P,
+        read_only: bool,
+        create: bool,
+        key: Option<&str>,
+    ) -> Result<Self> {
         let path_str = path.as_ref().to_string_lossy().to_string();
 
         // Validate file exists
-        if !path.as_ref().exists() {
+        if !create && !is_special_path(&path_str) && !path.as_ref().exists() {
             return Err(DatabaseError::NotFound(path_str.clone()).into());
         }
 
         let flags = if read_only {
-            OpenFlags::SQLITE_OPEN_READ_ONLY
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI
         } else {
-            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_URI
         };
 
         // Try to open the database - rusqlite will validate it's a valid SQLite file
@@ -55,13 +165,20 @@ impl Database {
                 }
             })?;
 
+        key::unlock(&conn, &path_str, key)?;
+
+        // SQLite quietly falls back to read-only when the file can't be written
+        if !read_only && conn.is_readonly(DatabaseName::Main).unwrap_or(false) {
+            anyhow::bail!("Database file is not writable: {}", path_str);
+        }
+
         // Enable foreign keys
         conn.execute("PRAGMA foreign_keys = ON", [])
             .context("Failed to enable foreign keys")?;
 
-        // Set busy timeout (5 seconds)
-        conn.busy_timeout(std::time::Duration::from_secs(5))
+        conn.busy_timeout(BUSY_TIMEOUT)
             .context("Failed to set busy timeout")?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
 
         Ok(Self { conn })
     }
@@ -0,0 +1,163 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::types::FromSql;
+use rusqlite::Connection;
+
+/// The PRAGMAs the Database view shows, in the order it lists them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pragma {
+    JournalMode,
+    Synchronous,
+    PageSize,
+    CacheSize,
+    ForeignKeys,
+    UserVersion,
+    ApplicationId,
+    Encoding,
+    AutoVacuum,
+}
+
+impl Pragma {
+    pub const ALL: [Pragma; 9] = [
+        Pragma::JournalMode,
+        Pragma::Synchronous,
+        Pragma::PageSize,
+        Pragma::CacheSize,
+        Pragma::ForeignKeys,
+        Pragma::UserVersion,
+        Pragma::ApplicationId,
+        Pragma::Encoding,
+        Pragma::AutoVacuum,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pragma::JournalMode => "journal_mode",
+            Pragma::Synchronous => "synchronous",
+            Pragma::PageSize => "page_size",
+            Pragma::CacheSize => "cache_size",
+            Pragma::ForeignKeys => "foreign_keys",
+            Pragma::UserVersion => "user_version",
+            Pragma::ApplicationId => "application_id",
+            Pragma::Encoding => "encoding",
+            Pragma::AutoVacuum => "auto_vacuum",
+        }
+    }
+
+    /// Values the pragma can be set to; None when it takes any integer
+    pub fn choices(&self) -> Option<&'static [&'static str]> {
+        match self {
+            Pragma::JournalMode => Some(&["delete", "truncate", "persist", "memory", "wal", "off"]),
+            Pragma::Synchronous => Some(&["off", "normal", "full", "extra"]),
+            Pragma::ForeignKeys => Some(&["off", "on"]),
+            Pragma::AutoVacuum => Some(&["none", "full", "incremental"]),
+            Pragma::Encoding => Some(&["UTF-8", "UTF-16le", "UTF-16be"]),
+            Pragma::PageSize | Pragma::CacheSize | Pragma::UserVersion | Pragma::ApplicationId => {
+                None
+            }
+        }
+    }
+
+    /// The encoding is fixed once the database has content, so it can't be changed here
+    pub fn is_writable(&self) -> bool {
+        *self != Pragma::Encoding
+    }
+}
+
+/// The database's settings as its PRAGMAs report them
+#[derive(Debug, Clone, PartialEq)]
+pub struct PragmaSettings {
+    pub journal_mode: String,
+    /// 0 OFF, 1 NORMAL, 2 FULL, 3 EXTRA
+    pub synchronous: i64,
+    pub page_size: i64,
+    /// Pages when positive, KiB when negative
+    pub cache_size: i64,
+    pub foreign_keys: bool,
+    pub user_version: i64,
+    pub application_id: i64,
+    pub encoding: String,
+    /// 0 NONE, 1 FULL, 2 INCREMENTAL
+    pub auto_vacuum: i64,
+}
+
+impl PragmaSettings {
+    /// `pragma`'s value as text, named the way setting it takes it
+    pub fn value(&self, pragma: Pragma) -> String {
+        let named = |choices: &[&str], value: i64| {
+            choices
+                .get(value as usize)
+                .map_or_else(|| value.to_string(), |name| name.to_string())
+        };
+        match pragma {
+            Pragma::JournalMode => self.journal_mode.clone(),
+            Pragma::Synchronous => named(&["off", "normal", "full", "extra"], self.synchronous),
+            Pragma::PageSize => self.page_size.to_string(),
+            Pragma::CacheSize => self.cache_size.to_string(),
+            Pragma::ForeignKeys => if self.foreign_keys { "on" } else { "off" }.to_string(),
+            Pragma::UserVersion => self.user_version.to_string(),
+            Pragma::ApplicationId => self.application_id.to_string(),
+            Pragma::Encoding => self.encoding.clone(),
+            Pragma::AutoVacuum => named(&["none", "full", "incremental"], self.auto_vacuum),
+        }
+    }
+}
+
+/// Read every PRAGMA the Database view shows
+pub fn get_all(conn: &Connection) -> Result<PragmaSettings> {
+    Ok(PragmaSettings {
+        journal_mode: read(conn, Pragma::JournalMode)?,
+        synchronous: read(conn, Pragma::Synchronous)?,
+        page_size: read(conn, Pragma::PageSize)?,
+        cache_size: read(conn, Pragma::CacheSize)?,
+        foreign_keys: read(conn, Pragma::ForeignKeys)?,
+        user_version: read(conn, Pragma::UserVersion)?,
+        application_id: read(conn, Pragma::ApplicationId)?,
+        encoding: read(conn, Pragma::Encoding)?,
+        auto_vacuum: read(conn, Pragma::AutoVacuum)?,
+    })
+}
+
+/// Set `pragma` to `value`, one of its choices or an integer, and describe what became
+/// of it: SQLite keeps the old journal mode when it can't switch, and a new page size
+/// or auto_vacuum only takes hold of an existing database with VACUUM.
+pub fn set(conn: &Connection, pragma: Pragma, value: &str) -> Result<String> {
+    let value = value.trim();
+    let name = pragma.name();
+    if !pragma.is_writable() {
+        bail!("{} can't be changed", name);
+    }
+    match pragma.choices() {
+        Some(choices) if !choices.iter().any(|c| c.eq_ignore_ascii_case(value)) => {
+            bail!("{} takes one of {}", name, choices.join(", "))
+        }
+        None if value.parse::<i64>().is_err() => bail!("{} takes an integer", name),
+        _ => {}
+    }
+
+    let before = get_all(conn)?.value(pragma);
+    if before.eq_ignore_ascii_case(value) {
+        return Ok(format!("{} is already {}", name, before));
+    }
+    let sql = format!("PRAGMA {} = {}", name, value);
+    if pragma == Pragma::JournalMode {
+        // Setting the journal mode returns the mode it ended up in, read again below
+        conn.query_row(&sql, [], |_| Ok(()))
+    } else {
+        conn.execute_batch(&sql)
+    }
+    .with_context(|| format!("Failed to set {}", name))?;
+
+    let after = get_all(conn)?.value(pragma);
+    Ok(if after.eq_ignore_ascii_case(value) {
+        format!("{} set to {}", name, after)
+    } else if matches!(pragma, Pragma::PageSize | Pragma::AutoVacuum) {
+        format!("{} stays {} until the database is vacuumed", name, after)
+    } else {
+        format!("{} stayed {}", name, after)
+    })
+}
+
+fn read<T: FromSql>(conn: &Connection, pragma: Pragma) -> Result<T> {
+    conn.query_row(&format!("PRAGMA {}", pragma.name()), [], |row| row.get(0))
+        .with_context(|| format!("Failed to read {}", pragma.name()))
+}
@@ -1,45 +1,102 @@
-use crate::db::error::format_sql_error;
-use crate::types::{QueryResult, Value};
+use crate::db::attach::TableRef;
+use crate::db::error::{format_sql_error, sql_error};
+use crate::db::schema::primary_key_columns;
+use crate::db::DatabaseError;
+use crate::types::{
+    row_size, PageStart, PendingEdit, PlanStep, QueryResult, RowKey, RowsQuery, ScriptStep, Value,
+};
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{
+    params_from_iter, CachedStatement, Connection, OptionalExtension, Params, Statement, ToSql,
+};
+use std::collections::HashMap;
 use std::time::Instant;
 
-/// Execute a SQL query and return results
+/// Execute a SQL query with `params` bound to its placeholders and return up to
+/// `max_rows` of its results, starting `offset` rows in, stopping sooner once they would
+/// take more than `max_bytes` of memory
 pub fn execute_query(
     conn: &Connection,
     query: &str,
     max_rows: Option<usize>,
+    max_bytes: usize,
+    offset: usize,
+    params: &[SqliteValue],
 ) -> Result<QueryResult> {
     let start = Instant::now();
 
-    let mut stmt = conn
-        .prepare(query)
-        .map_err(|e| anyhow::anyhow!("{}", format_sql_error(&e, query)))?;
-
-    // Get column names
-    let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+    // A simple single-table SELECT can be edited, so also fetch what identifies its rows
+    let source_table = editable_source_table(query);
+    let rowid_stmt = source_table.as_ref().and_then(|_| {
+        let rest = query.trim_start()["select".len()..].trim_end();
+        let rest = rest.strip_suffix(';').unwrap_or(rest);
+        conn.prepare_cached(&format!("SELECT rowid AS __rowid__,{}", rest))
+            .ok()
+    });
+    let with_rowid = rowid_stmt.is_some();
+    let mut stmt = match rowid_stmt {
+        Some(stmt) => stmt,
+        None => conn
+            .prepare_cached(query)
+            .map_err(|e| sql_error(e, query))?,
+    };
 
-    // Execute and collect rows
-    let mut rows = Vec::new();
-    let row_iter = stmt.query_map([], |row| {
-        let mut values = Vec::new();
-        for i in 0..row.as_ref().column_count() {
-            let value: rusqlite::types::Value = row.get(i)?;
-            values.push(Value::from(value));
+    // Writes return no columns; run them for their change count instead
+    if stmt.column_count() == 0 {
+        let rowid_before = conn.last_insert_rowid();
+        let changed = stmt
+            .execute(params_from_iter(params))
+            .map_err(|e| sql_error(e, query))?;
+        let rowid = conn.last_insert_rowid();
+        // A statement that changed the schema leaves those prepared before it out of date
+        if !changes_rows(query) {
+            conn.flush_prepared_statement_cache();
         }
-        Ok(values)
-    })?;
+        let mut result =
+            QueryResult::new(Vec::new(), Vec::new(), start.elapsed().as_millis() as u64);
+        // The change count isn't reset by other statements, so only trust it for DML
+        result.rows_affected = changes_rows(query).then_some(changed as u64);
+        result.last_insert_rowid = (rowid != rowid_before).then_some(rowid);
+        return Ok(result);
+    }
 
-    let mut truncated = false;
     let limit = max_rows.unwrap_or(1000);
+    // Only a statement that changes nothing can be run again for another page
+    let pageable = stmt.readonly();
+    let read = match read_rows(
+        &mut stmt,
+        params_from_iter(params),
+        with_rowid,
+        offset,
+        limit,
+        max_bytes,
+    ) {
+        Ok(read) => read,
+        Err(e) => {
+            stmt.discard();
+            return Err(e);
+        }
+    };
+    let columns = column_names(&stmt, with_rowid);
+
+    let RowsRead {
+        rows,
+        rowids,
+        truncated,
+        bytes,
+        over_budget,
+    } = read;
 
-    for row_result in row_iter {
-        if rows.len() >= limit {
-            truncated = true;
-            break;
+    let (row_keys, source_table) = match source_table {
+        Some(table) => {
+            let keys = row_keys(conn, &table, with_rowid.then_some(rowids), &columns, &rows);
+            // Without keys there is no way to target a row, so the result stays read-only
+            let editable = !keys.is_empty() || rows.is_empty();
+            (keys, editable.then_some(table))
         }
-        rows.push(row_result.context("Failed to read row")?);
-    }
+        None => (Vec::new(), None),
+    };
 
     let exec_ms = start.elapsed().as_millis() as u64;
 
@@ -47,111 +104,956 @@ pub fn execute_query(
         columns,
         rows,
         truncated,
+        offset,
+        pageable,
         exec_ms,
+        row_keys,
+        source_table,
+        rows_affected: None,
+        last_insert_rowid: None,
+        bytes,
+        memory_cap: over_budget.then_some(max_bytes),
+    })
+}
+
+/// A script stopped by an interrupt from the UI
+#[derive(Debug)]
+pub struct Cancelled {
+    /// Statements that finished before it
+    pub completed: usize,
+}
+
+/// Run statements one after another, stopping at the first that fails
+pub fn execute_script(
+    conn: &Connection,
+    statements: &[String],
+    max_rows: Option<usize>,
+    max_bytes: usize,
+) -> Result<Vec<ScriptStep>, Cancelled> {
+    let mut steps = Vec::with_capacity(statements.len());
+    let mut failed = false;
+    for (completed, statement) in statements.iter().enumerate() {
+        let outcome = if failed {
+            None
+        } else {
+            match execute_query(conn, statement, max_rows, max_bytes, 0, &[]) {
+                Err(e) if is_interrupted(&e) => return Err(Cancelled { completed }),
+                result => {
+                    failed = result.is_err();
+                    Some(result.map_err(|e| e.to_string()))
+                }
+            }
+        };
+        steps.push(ScriptStep {
+            statement: statement.clone(),
+            outcome,
+        });
+    }
+    Ok(steps)
+}
+
+/// Run one statement with `params` bound to its placeholders, as a one-step script
+pub fn execute_bound(
+    conn: &Connection,
+    statement: &str,
+    params: &[SqliteValue],
+    max_rows: Option<usize>,
+    max_bytes: usize,
+) -> Result<ScriptStep, Cancelled> {
+    match execute_query(conn, statement, max_rows, max_bytes, 0, params) {
+        Err(e) if is_interrupted(&e) => Err(Cancelled { completed: 0 }),
+        result => Ok(ScriptStep {
+            statement: statement.to_string(),
+            outcome: Some(result.map_err(|e| e.to_string())),
+        }),
+    }
+}
+
+/// Names of a statement's parameters in order: `:name` as written, `?N` for positional ones
+pub fn parameter_names(conn: &Connection, statement: &str) -> Result<Vec<String>> {
+    let stmt = conn
+        .prepare_cached(statement)
+        .map_err(|e| sql_error(e, statement))?;
+    Ok((1..=stmt.parameter_count())
+        .map(|i| {
+            stmt.parameter_name(i)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("?{}", i))
+        })
+        .collect())
+}
+
+/// Whether `error` came from the statement being interrupted
+pub fn is_interrupted(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rusqlite::Error>(),
+            Some(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == rusqlite::ErrorCode::OperationInterrupted
+        )
+    })
+}
+
+/// Whether `error` came from SQLite giving up on a lock another connection holds
+pub fn is_busy(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| match cause.downcast_ref::<rusqlite::Error>() {
+            Some(e) => is_busy_code(e.sqlite_error_code()),
+            None => matches!(
+                cause.downcast_ref::<DatabaseError>(),
+                Some(DatabaseError::Locked(_))
+            ),
+        })
+}
+
+fn is_busy_code(code: Option<rusqlite::ErrorCode>) -> bool {
+    matches!(
+        code,
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Whether a statement is an INSERT, UPDATE, DELETE or REPLACE
+fn changes_rows(query: &str) -> bool {
+    let verb = query
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    matches!(verb.as_str(), "INSERT" | "UPDATE" | "DELETE" | "REPLACE")
+}
+
+/// Whether a statement creates, changes or drops a table, view, index or trigger
+pub fn changes_schema(query: &str) -> bool {
+    let verb = query
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    matches!(verb.as_str(), "CREATE" | "ALTER" | "DROP")
+}
+
+/// Run `sql`, prepared once and kept for next time, and read its rows, as many as fit in
+/// `max_bytes`, along with its column names, leaving out the rowid. A statement that
+/// fails isn't kept.
+fn read_cached<P: Params>(
+    conn: &Connection,
+    sql: &str,
+    params: P,
+    with_rowid: bool,
+    max_bytes: usize,
+) -> Result<(Vec<String>, RowsRead)> {
+    let mut stmt = conn.prepare_cached(sql).map_err(|e| sql_error(e, sql))?;
+    match read_rows(&mut stmt, params, with_rowid, 0, usize::MAX, max_bytes) {
+        Ok(read) => Ok((column_names(&stmt, with_rowid), read)),
+        Err(e) => {
+            stmt.discard();
+            Err(e)
+        }
+    }
+}
+
+/// A statement's column names, leaving out the rowid. Read once it has run: a kept
+/// statement prepared before its table changed only catches up with `*` then.
+fn column_names(stmt: &CachedStatement, with_rowid: bool) -> Vec<String> {
+    stmt.column_names()
+        .iter()
+        .skip(usize::from(with_rowid))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Rows read from a statement, with their rowids when it selected them
+#[derive(Default)]
+struct RowsRead {
+    rows: Vec<Vec<Value>>,
+    rowids: Vec<Value>,
+    /// Rows were left unread, past the row limit or the byte budget
+    truncated: bool,
+    /// Roughly how many bytes the rows take in memory
+    bytes: usize,
+    /// Reading stopped at the byte budget rather than the row limit
+    over_budget: bool,
+}
+
+/// Read up to `limit` rows from a statement, or as many as fit in `max_bytes`, splitting
+/// off a leading rowid column. The first row is kept whatever its size, so paging on
+/// from a huge row still moves forward.
+fn read_rows<P: Params>(
+    stmt: &mut Statement,
+    params: P,
+    with_rowid: bool,
+    offset: usize,
+    limit: usize,
+    max_bytes: usize,
+) -> Result<RowsRead> {
+    let mut read = RowsRead::default();
+    let mut found = stmt.query(params)?;
+
+    // Rows before the offset are stepped over without reading their values
+    for _ in 0..offset {
+        if found.next().context("Failed to read row")?.is_none() {
+            return Ok(read);
+        }
+    }
+    while let Some(row) = found.next().context("Failed to read row")? {
+        if read.rows.len() >= limit {
+            read.truncated = true;
+            return Ok(read);
+        }
+        let mut values = (0..row.as_ref().column_count())
+            .map(|i| row.get::<_, SqliteValue>(i).map(Value::from))
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read row")?;
+        if with_rowid {
+            read.rowids.push(values.remove(0));
+        }
+        let size = row_size(&values);
+        if !read.rows.is_empty() && read.bytes + size > max_bytes {
+            read.truncated = true;
+            read.over_budget = true;
+            read.rowids.truncate(read.rows.len());
+            return Ok(read);
+        }
+        read.bytes += size;
+        read.rows.push(values);
+    }
+    Ok(read)
+}
+
+/// Table a query's rows can be edited in: only a plain `SELECT * | columns FROM table`
+/// with optional WHERE, ORDER BY, and LIMIT qualifies. Anything fancier returns None.
+fn editable_source_table(query: &str) -> Option<String> {
+    let query = query.trim();
+    let query = query.strip_suffix(';').unwrap_or(query).trim_end();
+    let lower = query.to_ascii_lowercase();
+    if query.contains(';') || !lower.starts_with("select") {
+        return None;
+    }
+    // Joins, grouping, compound queries, and subqueries all break the row-to-table mapping
+    let words: Vec<&str> = lower
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .collect();
+    let blocked = [
+        "join",
+        "group",
+        "having",
+        "union",
+        "intersect",
+        "except",
+        "distinct",
+        "window",
+        "with",
+    ];
+    if words.iter().filter(|w| **w == "select").count() != 1
+        || words.iter().any(|w| blocked.contains(w))
+    {
+        return None;
+    }
+
+    // Select list: `*` or bare column names
+    let from = find_keyword(&lower, "from")?;
+    let select_list = query["select".len()..from].trim();
+    if select_list != "*" {
+        for column in select_list.split(',') {
+            let (_, rest) = parse_identifier(column.trim())?;
+            if !rest.is_empty() {
+                return None;
+            }
+        }
+    }
+
+    // A single table, followed only by WHERE / ORDER BY / LIMIT
+    let (table, rest) = parse_identifier(query[from + "from".len()..].trim_start())?;
+    let rest = rest.trim_start().to_ascii_lowercase();
+    let next_word = rest.split_whitespace().next().unwrap_or("");
+    if rest.is_empty() || ["where", "order", "limit"].contains(&next_word) {
+        Some(table)
+    } else {
+        None
+    }
+}
+
+/// Byte offset of the first `keyword` in lowercased SQL that has whitespace on both sides
+fn find_keyword(lower: &str, keyword: &str) -> Option<usize> {
+    lower.match_indices(keyword).map(|(i, _)| i).find(|&i| {
+        let before = lower[..i].chars().next_back();
+        let after = lower[i + keyword.len()..].chars().next();
+        before.is_some_and(char::is_whitespace) && after.is_some_and(char::is_whitespace)
     })
 }
 
-/// Get paginated rows from a table
+/// Keyword of the first statement in `query` that changes or removes data or schema
+pub fn destructive_statement(query: &str) -> Option<&'static str> {
+    query.split(';').find_map(|statement| {
+        let word = statement.split_whitespace().next()?.to_ascii_uppercase();
+        ["DELETE", "UPDATE", "DROP", "ALTER"]
+            .into_iter()
+            .find(|keyword| *keyword == word)
+    })
+}
+
+/// `SELECT COUNT(*)` over the rows a single DELETE or UPDATE would touch, when the
+/// statement is simple enough to rewrite. The count is an estimate: it reads the
+/// WHERE clause as written and ignores triggers and cascades.
+pub fn affected_rows_query(query: &str) -> Option<String> {
+    let query = query.trim();
+    let query = query.strip_suffix(';').unwrap_or(query).trim_end();
+    let lower = query.to_ascii_lowercase();
+    if query.contains(';') || find_keyword(&format!("{} ", lower), "returning").is_some() {
+        return None;
+    }
+
+    let mut words = lower.split_whitespace();
+    let (table, rest) = match words.next()? {
+        "delete" => {
+            let from = find_keyword(&lower, "from")?;
+            parse_identifier(query[from + "from".len()..].trim_start())?
+        }
+        "update" => {
+            // Skip an `OR <conflict action>` after UPDATE
+            let mut start = "update".len();
+            if words.next()? == "or" {
+                let action = words.next()?;
+                start = lower.find(action)? + action.len();
+            }
+            let (table, rest) = parse_identifier(query[start..].trim_start())?;
+            let rest_lower = rest.to_ascii_lowercase();
+            if !rest_lower.trim_start().starts_with("set")
+                || find_keyword(&rest_lower, "from").is_some()
+            {
+                return None;
+            }
+            // Everything from WHERE on, or nothing when the whole table is updated
+            let where_clause = find_keyword(&rest_lower, "where").map_or("", |i| &rest[i..]);
+            (table, where_clause)
+        }
+        _ => return None,
+    };
+
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.to_ascii_lowercase().starts_with("where") {
+        return None;
+    }
+    let mut count_query = format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\""));
+    if !rest.is_empty() {
+        count_query.push(' ');
+        count_query.push_str(rest);
+    }
+    Some(count_query)
+}
+
+/// Run a `SELECT COUNT(*)` query built by [`affected_rows_query`]
+pub fn count_rows(conn: &Connection, count_query: &str) -> Result<i64> {
+    conn.query_row(count_query, [], |row| row.get(0))
+        .map_err(|e| anyhow::anyhow!("{}", format_sql_error(&e, count_query)))
+}
+
+/// Run `EXPLAIN QUERY PLAN` on `query`, returning its steps in order with their depth
+pub fn explain_query_plan(conn: &Connection, query: &str) -> Result<Vec<PlanStep>> {
+    let query = query.trim_end().trim_end_matches(';');
+    let explain = format!("EXPLAIN QUERY PLAN {}", query);
+    let rows = conn
+        .prepare(&explain)
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get(3)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(i64, i64, String)>>>()
+        })
+        .map_err(|e| anyhow::anyhow!("{}", format_sql_error(&e, query)))?;
+    if rows.is_empty() {
+        anyhow::bail!("SQLite has no query plan for this statement");
+    }
+
+    // Rows come parent first, so each step's depth is one more than its parent's
+    let mut depths: HashMap<i64, usize> = HashMap::new();
+    Ok(rows
+        .into_iter()
+        .map(|(id, parent, detail)| {
+            let depth = depths.get(&parent).map_or(0, |depth| depth + 1);
+            depths.insert(id, depth);
+            PlanStep { depth, detail }
+        })
+        .collect())
+}
+
+/// Split a leading plain or double-quoted identifier off `s`
+fn parse_identifier(s: &str) -> Option<(String, &str)> {
+    if let Some(quoted) = s.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        return Some((quoted[..end].to_string(), &quoted[end + 1..]));
+    }
+    let end = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(s.len());
+    if end == 0 || s.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((s[..end].to_string(), &s[end..]))
+}
+
+/// SELECT `columns` from a table's rows as `view` filters and orders them, or just the
+/// page `page` gives the limit and offset of. Returns the SQL and the values it binds,
+/// in order. Loading a page and exporting the view both build their query here, so
+/// what's exported is what's shown.
+pub fn rows_select(
+    table: &TableRef,
+    columns: &str,
+    view: &RowsQuery,
+    page: Option<(usize, usize)>,
+) -> (String, Vec<Value>) {
+    let (filter, mut params) = rows_query_filter(view);
+    let mut sql = format!(
+        "SELECT {} FROM {}{}{}",
+        columns,
+        table.quoted(),
+        filter,
+        rows_query_order(view)
+    );
+    if let Some((limit, offset)) = page {
+        sql.push_str(" LIMIT ? OFFSET ?");
+        params.push(Value::Integer(limit as i64));
+        params.push(Value::Integer(offset as i64));
+    }
+    (sql, params)
+}
+
+/// A page of the unsorted rows query in rowid order, with the rowid first: the page
+/// `start` says, or the one `offset` rows in without one. Starting from a rowid finds
+/// a deep page without reading every row before it.
+fn keyset_select(
+    table: &TableRef,
+    view: &RowsQuery,
+    limit: usize,
+    offset: usize,
+    start: Option<PageStart>,
+) -> (String, Vec<Value>) {
+    let (filter, mut params) = rows_query_filter(view);
+    let mut sql = format!(
+        "SELECT rowid AS __rowid__, * FROM {}{}",
+        table.quoted(),
+        filter
+    );
+    let join = if filter.is_empty() { "WHERE" } else { "AND" };
+    match start {
+        Some(PageStart::After(rowid)) => {
+            sql.push_str(&format!(" {} rowid > ? ORDER BY rowid LIMIT ?", join));
+            params.push(Value::Integer(rowid));
+        }
+        // Read backwards from the boundary; the rows are put back in order after
+        Some(PageStart::Before(rowid)) => {
+            sql.push_str(&format!(" {} rowid < ? ORDER BY rowid DESC LIMIT ?", join));
+            params.push(Value::Integer(rowid));
+        }
+        None => sql.push_str(" ORDER BY rowid LIMIT ?"),
+    }
+    params.push(Value::Integer(limit as i64));
+    if start.is_none() {
+        sql.push_str(" OFFSET ?");
+        params.push(Value::Integer(offset as i64));
+    }
+    (sql, params)
+}
+
+/// The WHERE clause of a rows query and the values it binds
+fn rows_query_filter(view: &RowsQuery) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+
+    if let Some(filter) = &view.filter {
+        // The filter is user-supplied SQL; preparing the statement validates it
+        conditions.push(format!("({})", filter));
+    }
+    if let Some((column, value)) = &view.key_match {
+        conditions.push(format!("\"{}\" = ?", column.replace('"', "\"\"")));
+        params.push(value.clone());
+    }
+
+    let mut sql = String::new();
+    if !conditions.is_empty() {
+        sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    }
+    (sql, params)
+}
+
+/// The ORDER BY clause of a rows query, empty when unsorted
+fn rows_query_order(view: &RowsQuery) -> String {
+    match &view.order_by {
+        Some((column, order)) => format!(
+            " ORDER BY \"{}\" {}",
+            column.replace('"', "\"\""),
+            order.as_sql()
+        ),
+        None => String::new(),
+    }
+}
+
+/// Position of the row with `key` among the rows of `view`, or None when the view
+/// no longer includes it. Used to find the page an edited row moved to.
+pub fn row_position(
+    conn: &Connection,
+    table_name: &str,
+    view: &RowsQuery,
+    key: &RowKey,
+) -> Result<Option<usize>> {
+    let table = TableRef::resolve(conn, table_name);
+    let (filter, values) = rows_query_filter(view);
+    let mut params: Vec<&dyn ToSql> = values.iter().map(|v| v as &dyn ToSql).collect();
+    let rowid = match key {
+        RowKey::Rowid(_) => "rowid AS __rowid__, ",
+        RowKey::PrimaryKey(_) => "",
+    };
+    let condition = key_condition(key, "__rowid__", &mut params);
+    let query = format!(
+        "SELECT n FROM (SELECT ROW_NUMBER() OVER ({}) - 1 AS n, {}* FROM {}{}) WHERE {}",
+        rows_query_order(view).trim(),
+        rowid,
+        table.quoted(),
+        filter,
+        condition
+    );
+    let position = conn
+        .query_row(&query, params_from_iter(params), |row| row.get::<_, i64>(0))
+        .optional()
+        .map_err(|e| anyhow::anyhow!("{}", format_sql_error(&e, &query)))?;
+    Ok(position.map(|n| n as usize))
+}
+
+/// Get paginated rows from a table, filtered and ordered as described by `view`. An
+/// unsorted table with a rowid is read in rowid order, from `page_start` when it's
+/// given; otherwise the page is the one `offset` rows in. The page is cut short once its
+/// rows would take more than `max_bytes` of memory.
 pub fn get_table_rows(
     conn: &Connection,
     table_name: &str,
     limit: usize,
     offset: usize,
+    view: &RowsQuery,
+    page_start: Option<PageStart>,
+    max_bytes: usize,
 ) -> Result<QueryResult> {
     let start = Instant::now();
 
-    // Safely quote table name
-    let safe_table = table_name.replace('"', "\"\"");
-    let query = format!("SELECT * FROM \"{}\" LIMIT ? OFFSET ?", safe_table);
-
-    let mut stmt = conn
-        .prepare(&query)
-        .with_context(|| format!("Failed to prepare query for table: {}", table_name))?;
-
-    // Get column names
-    let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+    let page = Some((limit, offset));
+    let table = TableRef::resolve(conn, table_name);
+    let (query, params) = rows_select(&table, "*", view, page);
+    let keyset = view.order_by.is_none();
+    let (rowid_query, rowid_params) = if keyset {
+        keyset_select(&table, view, limit, offset, page_start)
+    } else {
+        rows_select(&table, "rowid AS __rowid__, *", view, page)
+    };
 
-    // Execute with limit and offset
-    let mut rows = Vec::new();
-    let row_iter = stmt.query_map([limit as i64, offset as i64], |row| {
-        let mut values = Vec::new();
-        for i in 0..row.as_ref().column_count() {
-            let value: rusqlite::types::Value = row.get(i)?;
-            values.push(Value::from(value));
+    // Views and WITHOUT ROWID tables have no rowid, so fall back to the plain query
+    let rowid_read = read_cached(
+        conn,
+        &rowid_query,
+        params_from_iter(rowid_params),
+        true,
+        max_bytes,
+    );
+    let (columns, mut read, with_rowid) = match rowid_read {
+        Ok((columns, read)) => (columns, read, true),
+        Err(e) if is_interrupted(&e) || is_busy(&e) => return Err(e),
+        Err(_) => {
+            let (columns, read) =
+                read_cached(conn, &query, params_from_iter(params), false, max_bytes)?;
+            (columns, read, false)
         }
-        Ok(values)
-    })?;
-
-    for row_result in row_iter {
-        rows.push(row_result.context("Failed to read row")?);
+    };
+    if with_rowid && keyset && matches!(page_start, Some(PageStart::Before(_))) {
+        read.rows.reverse();
+        read.rowids.reverse();
     }
+    let row_keys = row_keys(
+        conn,
+        table_name,
+        with_rowid.then_some(read.rowids),
+        &columns,
+        &read.rows,
+    );
 
     let exec_ms = start.elapsed().as_millis() as u64;
 
     Ok(QueryResult {
         columns,
-        rows,
+        rows: read.rows,
         truncated: false,
+        offset,
+        pageable: false,
         exec_ms,
+        row_keys,
+        source_table: Some(table_name.to_string()),
+        rows_affected: None,
+        last_insert_rowid: None,
+        bytes: read.bytes,
+        memory_cap: read.over_budget.then_some(max_bytes),
     })
 }
 
-/// Update a cell value in a table
-/// Uses ROWID to identify the row, and column name to identify the column
+/// Key each row by its rowid, or by its primary key values when there is no usable rowid
+fn row_keys(
+    conn: &Connection,
+    table_name: &str,
+    rowids: Option<Vec<Value>>,
+    columns: &[String],
+    rows: &[Vec<Value>],
+) -> Vec<RowKey> {
+    // A column named "rowid" shadows the real one, so only trust integer rowids
+    let rowids: Option<Vec<RowKey>> = rowids.and_then(|ids| {
+        ids.into_iter()
+            .map(|v| match v {
+                Value::Integer(i) => Some(RowKey::Rowid(i)),
+                _ => None,
+            })
+            .collect()
+    });
+    rowids.unwrap_or_else(|| primary_keys(conn, table_name, columns, rows))
+}
+
+/// Key each row by its primary key values
+fn primary_keys(
+    conn: &Connection,
+    table_name: &str,
+    columns: &[String],
+    rows: &[Vec<Value>],
+) -> Vec<RowKey> {
+    let key_columns: Option<Vec<(usize, String)>> = primary_key_columns(conn, table_name)
+        .into_iter()
+        .map(|name| columns.iter().position(|c| *c == name).map(|i| (i, name)))
+        .collect();
+    match key_columns {
+        Some(key_columns) if !key_columns.is_empty() => rows
+            .iter()
+            .map(|row| {
+                RowKey::PrimaryKey(
+                    key_columns
+                        .iter()
+                        .map(|(i, name)| (name.clone(), row[*i].clone()))
+                        .collect(),
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Update a cell value in a table, finding the row by its key
 pub fn update_cell(
     conn: &Connection,
     table_name: &str,
-    row_index: usize, // Absolute row index (including pagination offset)
+    key: &RowKey,
     column_name: &str,
-    new_value: &str,
+    new_value: &Value,
 ) -> Result<()> {
     // Safely quote identifiers
-    let safe_table = table_name.replace('"', "\"\"");
+    let table = TableRef::resolve(conn, table_name);
     let safe_column = column_name.replace('"', "\"\"");
 
-    // First, get the ROWID for the row at this index
-    let rowid_query = format!("SELECT ROWID FROM \"{}\" LIMIT 1 OFFSET ?", safe_table);
-    let rowid: i64 = conn
-        .query_row(&rowid_query, [row_index as i64], |row| row.get(0))
+    let mut params: Vec<&dyn ToSql> = vec![new_value];
+    let condition = key_condition(key, "ROWID", &mut params);
+
+    // Bind the value so its type is kept exactly (empty text and the text "NULL" included)
+    let update_query = format!(
+        "UPDATE {} SET \"{}\" = ? WHERE {}",
+        table.quoted(),
+        safe_column,
+        condition
+    );
+
+    let updated = conn
+        .execute(&update_query, params_from_iter(params))
+        .map_err(|e| write_error(e, &format!("Failed to update cell in table {}", table_name)))?;
+
+    if updated == 0 {
+        anyhow::bail!(
+            "Row no longer exists in table {}; reload to see current data",
+            table_name
+        );
+    }
+    Ok(())
+}
+
+/// WHERE condition matching the row with `key`, binding its values into `params`.
+/// `rowid` names the column holding the rowid.
+fn key_condition<'a>(key: &'a RowKey, rowid: &str, params: &mut Vec<&'a dyn ToSql>) -> String {
+    match key {
+        RowKey::Rowid(id) => {
+            params.push(id);
+            format!("{} = ?", rowid)
+        }
+        RowKey::PrimaryKey(values) => values
+            .iter()
+            .map(|(column, value)| {
+                params.push(value);
+                format!("\"{}\" = ?", column.replace('"', "\"\""))
+            })
+            .collect::<Vec<_>>()
+            .join(" AND "),
+    }
+}
+
+/// Write all edits in one transaction. If any edit fails, none are kept and the
+/// error names the edit that failed.
+pub fn apply_edits(conn: &Connection, edits: &[PendingEdit]) -> Result<()> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to begin transaction")?;
+    for (i, edit) in edits.iter().enumerate() {
+        update_cell(
+            &tx,
+            &edit.table_name,
+            &edit.key,
+            &edit.column_name,
+            &edit.value,
+        )
         .with_context(|| {
             format!(
-                "Failed to get ROWID for row {} in table: {}. Row may not exist.",
-                row_index, table_name
+                "Edit {} of {} ({}.{} at {}) failed, nothing was written",
+                i + 1,
+                edits.len(),
+                edit.table_name,
+                edit.column_name,
+                edit.key
             )
         })?;
+    }
+    // Dropping the transaction on an error above rolls everything back
+    tx.commit().context("Failed to commit edits")
+}
 
-    // Parse the new value based on the column type
-    // For simplicity, we'll try to infer the type from the value
-    let sql_value = if new_value.trim().is_empty() || new_value.trim().eq_ignore_ascii_case("NULL")
+/// Error for a failed write, pointing at --read-write when the database is read-only
+fn write_error(e: rusqlite::Error, context: &str) -> anyhow::Error {
+    // Provide more helpful error messages
+    let error_msg = e.to_string();
+    if error_msg.contains("readonly")
+        || error_msg.contains("read-only")
+        || error_msg.contains("READONLY")
     {
-        "NULL".to_string()
-    } else if new_value.parse::<i64>().is_ok() || new_value.parse::<f64>().is_ok() {
-        new_value.to_string()
+        anyhow::anyhow!(
+            "Database is opened in read-only mode. Use --read-write flag to enable editing."
+        )
+    } else if is_busy_code(e.sqlite_error_code()) {
+        DatabaseError::Locked(format!("{}: {}", context, e)).into()
     } else {
-        // Treat as text
-        format!("'{}'", new_value.replace('\'', "''"))
-    };
+        anyhow::anyhow!("{}: {}", context, e)
+    }
+}
 
-    // Update the cell using ROWID
-    let update_query = format!(
-        "UPDATE \"{}\" SET \"{}\" = {} WHERE ROWID = ?",
-        safe_table, safe_column, sql_value
+/// Number of rows whose `column` contains `find`
+pub fn count_matches(
+    conn: &Connection,
+    table_name: &str,
+    column_name: &str,
+    find: &str,
+) -> Result<i64> {
+    let query = format!(
+        "SELECT COUNT(*) FROM {} WHERE instr(\"{}\", ?1) > 0",
+        TableRef::resolve(conn, table_name).quoted(),
+        column_name.replace('"', "\"\"")
     );
+    conn.query_row(&query, [find], |row| row.get(0))
+        .map_err(|e| anyhow::anyhow!("{}", format_sql_error(&e, &query)))
+}
 
-    conn.execute(&update_query, [rowid]).map_err(|e| {
-        // Provide more helpful error messages
-        let error_msg = e.to_string();
-        if error_msg.contains("readonly")
-            || error_msg.contains("read-only")
-            || error_msg.contains("READONLY")
-        {
-            anyhow::anyhow!(
-                "Database is opened in read-only mode. Use --read-write flag to enable editing."
-            )
-        } else {
-            anyhow::anyhow!("Failed to update cell in table {}: {}", table_name, e)
-        }
+/// Replace every occurrence of `find` in a column, in one transaction.
+/// Returns the number of rows changed.
+pub fn replace_in_column(
+    conn: &Connection,
+    table_name: &str,
+    column_name: &str,
+    find: &str,
+    replacement: &str,
+) -> Result<usize> {
+    let safe_column = column_name.replace('"', "\"\"");
+    let query = format!(
+        "UPDATE {} SET \"{}\" = REPLACE(\"{}\", ?1, ?2) WHERE instr(\"{}\", ?1) > 0",
+        TableRef::resolve(conn, table_name).quoted(),
+        safe_column,
+        safe_column,
+        safe_column
+    );
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| write_error(e, "Failed to begin transaction"))?;
+    let changed = tx.execute(&query, [find, replacement]).map_err(|e| {
+        write_error(
+            e,
+            &format!("Failed to replace in {}.{}", table_name, column_name),
+        )
     })?;
+    tx.commit().context("Failed to commit replace")?;
+    Ok(changed)
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE cells (id INTEGER PRIMARY KEY, note TEXT, n INTEGER);
+             INSERT INTO cells VALUES (1, 'before', 5);",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// The value stored in `column` and its SQLite type
+    fn stored(conn: &Connection, column: &str) -> (Value, String) {
+        let query = format!("SELECT \"{0}\", typeof(\"{0}\") FROM cells", column);
+        conn.query_row(&query, [], |row| {
+            Ok((row.get::<_, SqliteValue>(0)?.into(), row.get(1)?))
+        })
+        .unwrap()
+    }
+
+    /// Save `input` typed over the cell in `column` the way the cell editor does, and
+    /// read back what's stored
+    fn save(conn: &Connection, column: &str, input: &str) -> (Value, String) {
+        let (original, _) = stored(conn, column);
+        let value = Value::from_edit(input, &original);
+        update_cell(conn, "cells", &RowKey::Rowid(1), column, &value).unwrap();
+        stored(conn, column)
+    }
+
+    #[test]
+    fn an_empty_edit_stays_empty_text() {
+        let conn = cells();
+        assert_eq!(
+            save(&conn, "note", ""),
+            (Value::Text(String::new()), "text".to_string())
+        );
+    }
+
+    #[test]
+    fn the_word_null_is_saved_as_text() {
+        let conn = cells();
+        assert_eq!(
+            save(&conn, "note", "NULL"),
+            (Value::Text("NULL".to_string()), "text".to_string())
+        );
+    }
+
+    #[test]
+    fn leading_zeros_are_kept_in_text() {
+        let conn = cells();
+        assert_eq!(
+            save(&conn, "note", "007"),
+            (Value::Text("007".to_string()), "text".to_string())
+        );
+        // A number typed over a number stays one
+        assert_eq!(
+            save(&conn, "n", "42"),
+            (Value::Integer(42), "integer".to_string())
+        );
+    }
+
+    #[test]
+    fn an_explicit_null_is_saved_as_null() {
+        let conn = cells();
+        update_cell(&conn, "cells", &RowKey::Rowid(1), "note", &Value::Null).unwrap();
+        assert_eq!(stored(&conn, "note"), (Value::Null, "null".to_string()));
+    }
+
+    /// The columns and rows of the first page of `table`, read the way the table view
+    /// reads them
+    fn page(conn: &Connection, table: &str) -> Result<(Vec<String>, Vec<Vec<Value>>)> {
+        let result = get_table_rows(conn, table, 50, 0, &RowsQuery::default(), None, usize::MAX)?;
+        Ok((result.columns, result.rows))
+    }
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn kept_page_statements_are_run_again_with_new_params() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t(a);
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 120)
+             INSERT INTO t SELECT i FROM n;",
+        )
+        .unwrap();
+        let view = RowsQuery::default();
+        for (offset, first) in [(0, 1), (50, 51), (100, 101), (0, 1)] {
+            let result = get_table_rows(&conn, "t", 50, offset, &view, None, usize::MAX).unwrap();
+            assert_eq!(result.rows[0], [Value::Integer(first)]);
+            assert_eq!(result.rows.len(), 50.min(120 - offset));
+        }
+    }
+
+    #[test]
+    fn schema_changes_run_here_show_in_the_next_page() {
+        let conn = cells();
+        assert_eq!(
+            page(&conn, "cells").unwrap().0,
+            columns(&["id", "note", "n"])
+        );
+
+        execute_query(
+            &conn,
+            "ALTER TABLE cells ADD COLUMN extra DEFAULT 'x'",
+            None,
+            usize::MAX,
+            0,
+            &[],
+        )
+        .unwrap();
+        let (names, rows) = page(&conn, "cells").unwrap();
+        assert_eq!(names, columns(&["id", "note", "n", "extra"]));
+        assert_eq!(rows[0][3], Value::Text("x".to_string()));
+
+        execute_query(&conn, "DROP TABLE cells", None, usize::MAX, 0, &[]).unwrap();
+        execute_query(&conn, "CREATE TABLE cells(b, c)", None, usize::MAX, 0, &[]).unwrap();
+        execute_query(
+            &conn,
+            "INSERT INTO cells VALUES (1, 2)",
+            None,
+            usize::MAX,
+            0,
+            &[],
+        )
+        .unwrap();
+        let (names, rows) = page(&conn, "cells").unwrap();
+        assert_eq!(names, columns(&["b", "c"]));
+        assert_eq!(rows, [[Value::Integer(1), Value::Integer(2)]]);
+    }
+
+    #[test]
+    fn schema_changes_from_another_connection_show_in_the_next_page() {
+        let path = std::env::temp_dir().join(format!("sqr-cache-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        let other = Connection::open(&path).unwrap();
+        conn.execute_batch("CREATE TABLE t(a); INSERT INTO t VALUES (1);")
+            .unwrap();
+        assert_eq!(page(&conn, "t").unwrap().0, columns(&["a"]));
+
+        other.execute_batch("ALTER TABLE t ADD COLUMN b").unwrap();
+        let (names, rows) = page(&conn, "t").unwrap();
+        assert_eq!(names, columns(&["a", "b"]));
+        assert_eq!(rows, [[Value::Integer(1), Value::Null]]);
+
+        // The dropped table's kept statement fails and is discarded
+        other.execute_batch("DROP TABLE t").unwrap();
+        assert!(page(&conn, "t").is_err());
+
+        // A view put in its place is read without a rowid
+        other
+            .execute_batch(
+                "CREATE TABLE u(x); INSERT INTO u VALUES ('v'); CREATE VIEW t AS SELECT x FROM u;",
+            )
+            .unwrap();
+        let (names, rows) = page(&conn, "t").unwrap();
+        assert_eq!(names, columns(&["x"]));
+        assert_eq!(rows, [[Value::Text("v".to_string())]]);
+
+        drop((conn, other));
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -1,18 +1,94 @@
-use crate::types::{ColumnInfo, ForeignKeyInfo, IndexInfo, TableInfo};
+use super::attach::{attached_schemas, schema_prefix, TableRef};
+use crate::export::quote_identifier;
+use crate::types::{
+    ColumnInfo, DiagramTable, ForeignKeyInfo, IndexInfo, IndexOrigin, TableInfo, TableKind,
+};
 use anyhow::Result;
 use rusqlite::Connection;
+use std::collections::HashMap;
 
-/// Get all tables in the database
+/// Get all tables in the database, then those of each attached database named
+/// `alias.table`. Virtual tables are among them, marked as such. Rows aren't counted;
+/// [`count_rows`] does that.
 pub fn get_tables(conn: &Connection, include_internal: bool) -> Result<Vec<TableInfo>> {
-    let mut stmt =
-        conn.prepare("SELECT name, sql FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+    get_all_schemas(conn, "table", include_internal)
+}
+
+/// Get the views of the database and of each attached one
+pub fn get_views(conn: &Connection) -> Result<Vec<TableInfo>> {
+    get_all_schemas(conn, "view", false)
+}
+
+/// Get the triggers of the database and of each attached one
+pub fn get_triggers(conn: &Connection) -> Result<Vec<TableInfo>> {
+    get_all_schemas(conn, "trigger", false)
+}
+
+/// Get everything the Tables pane lists: for the main database and then each attached
+/// one, its tables, views, virtual tables and triggers, in that order
+pub fn get_schema_objects(conn: &Connection, include_internal: bool) -> Result<Vec<TableInfo>> {
+    let schemas = attached_schemas(conn)?;
+    let mut objects = get_tables(conn, include_internal)?;
+    objects.extend(get_views(conn)?);
+    objects.extend(get_triggers(conn)?);
+    // Stable, so each kind stays in name order
+    objects.sort_by_key(|object| {
+        let attached = object
+            .schema
+            .as_ref()
+            .map(|schema| schemas.iter().position(|s| s == schema));
+        (attached, object.kind)
+    });
+    Ok(objects)
+}
+
+/// Get the entries of `object_type` of the main database, then of each attached one
+fn get_all_schemas(
+    conn: &Connection,
+    object_type: &str,
+    include_internal: bool,
+) -> Result<Vec<TableInfo>> {
+    let mut entries = get_schema_entries(conn, None, object_type, include_internal)?;
+    for schema in attached_schemas(conn)? {
+        entries.extend(get_schema_entries(
+            conn,
+            Some(schema),
+            object_type,
+            include_internal,
+        )?);
+    }
+    Ok(entries)
+}
+
+/// Get the tables, views or triggers, as `object_type` says, of the main database or
+/// of the one attached as `schema`
+fn get_schema_entries(
+    conn: &Connection,
+    schema: Option<String>,
+    object_type: &str,
+    include_internal: bool,
+) -> Result<Vec<TableInfo>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT name, sql FROM {}sqlite_master WHERE type = ?1 ORDER BY name",
+        schema_prefix(schema.as_deref())
+    ))?;
 
     let tables: Result<Vec<TableInfo>, anyhow::Error> = stmt
-        .query_map([], |row| {
+        .query_map([object_type], |row| {
+            let name: String = row.get(0)?;
+            let sql: Option<String> = row.get(1)?;
             Ok(TableInfo {
-                name: row.get(0)?,
-                row_count: None, // Will be loaded lazily
-                sql: row.get(1)?,
+                name: match &schema {
+                    Some(schema) => format!("{}.{}", schema, name),
+                    None => name,
+                },
+                schema: schema.clone(),
+                // Counted afterwards, so a big table doesn't hold up the list
+                row_count: None,
+                row_count_estimated: false,
+                size: None,
+                kind: object_kind(object_type, sql.as_deref()),
+                sql,
             })
         })?
         .map(|r| r.map_err(anyhow::Error::from))
@@ -21,53 +97,144 @@ pub fn get_tables(conn: &Connection, include_internal: bool) -> Result<Vec<Table
     let mut tables = tables?;
 
     if !include_internal {
-        tables.retain(|t| !t.name.starts_with("sqlite_"));
+        tables.retain(|t| !t.bare_name().starts_with("sqlite_"));
     }
 
-    // Load row counts (lazy, but do it here for now)
-    for table in &mut tables {
-        if let Ok(count) = get_table_row_count(conn, &table.name) {
-            table.row_count = Some(count);
+    Ok(tables)
+}
+
+/// The kind of a sqlite_master entry of `object_type`, whose SQL tells a virtual
+/// table from an ordinary one
+fn object_kind(object_type: &str, sql: Option<&str>) -> TableKind {
+    match object_type {
+        "view" => TableKind::View,
+        "trigger" => TableKind::Trigger,
+        _ => {
+            let words: Vec<String> = sql
+                .unwrap_or_default()
+                .split_whitespace()
+                .take(2)
+                .map(|word| word.to_ascii_uppercase())
+                .collect();
+            if words == ["CREATE", "VIRTUAL"] {
+                TableKind::Virtual
+            } else {
+                TableKind::Table
+            }
         }
     }
+}
 
-    Ok(tables)
+/// Bytes each table takes on disk with its indexes, keyed by the name the Tables pane
+/// lists it under, read from the dbstat virtual table. None when SQLite was built
+/// without dbstat.
+pub fn get_table_sizes(conn: &Connection) -> Result<Option<HashMap<String, u64>>> {
+    let mut schemas = vec![None];
+    schemas.extend(attached_schemas(conn)?.into_iter().map(Some));
+    let mut sizes = HashMap::new();
+    for schema in schemas {
+        // The second argument asks dbstat for a row per table or index, not per page
+        let sql = format!(
+            "SELECT m.tbl_name, sum(s.pgsize) FROM dbstat(?1, 1) AS s
+             JOIN {}sqlite_master AS m ON m.name = s.name
+             GROUP BY m.tbl_name",
+            schema_prefix(schema.as_deref())
+        );
+        let mut stmt = match conn.prepare_cached(&sql) {
+            Ok(stmt) => stmt,
+            Err(e) if e.to_string().contains("no such table: dbstat") => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let rows = stmt.query_map([schema.as_deref().unwrap_or("main")], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (name, size) = row?;
+            let name = match &schema {
+                Some(schema) => format!("{}.{}", schema, name),
+                None => name,
+            };
+            sizes.insert(name, size as u64);
+        }
+    }
+    Ok(Some(sizes))
 }
 
-/// Get row count for a table
-fn get_table_row_count(conn: &Connection, table_name: &str) -> Result<u64> {
-    // Use a safe query with parameter binding
+/// Get the names of the tables, without SQLite's internal ones or counting rows
+pub fn get_table_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT name FROM sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\' ORDER BY name",
+    )?;
+    let names = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(names)
+}
+
+/// Count the rows of a table
+pub fn count_rows(conn: &Connection, table_name: &str) -> Result<u64> {
+    get_table_row_count(conn, &TableRef::resolve(conn, table_name))
+}
+
+/// Guess the rows of a table from its largest rowid, which finding takes a single
+/// lookup. Deleted rows make it an overestimate; a WITHOUT ROWID table has none.
+pub fn estimate_rows(conn: &Connection, table_name: &str) -> Option<u64> {
     let query = format!(
-        "SELECT COUNT(*) FROM \"{}\"",
-        table_name.replace('"', "\"\"")
+        "SELECT max(rowid) FROM {}",
+        TableRef::resolve(conn, table_name).quoted()
     );
-    let count: i64 = conn.query_row(&query, [], |row| row.get(0))?;
+    let max: Option<i64> = conn
+        .prepare_cached(&query)
+        .and_then(|mut stmt| stmt.query_row([], |row| row.get(0)))
+        .ok()?;
+    Some(max.unwrap_or(0).max(0) as u64)
+}
+
+/// Get row count for a table
+fn get_table_row_count(conn: &Connection, table: &TableRef) -> Result<u64> {
+    let query = format!("SELECT COUNT(*) FROM {}", table.quoted());
+    let count: i64 = conn
+        .prepare_cached(&query)?
+        .query_row([], |row| row.get(0))?;
     Ok(count as u64)
 }
 
-/// Get detailed information about a table
+/// Get detailed information about a table or view; a view isn't counted
 pub fn get_table_info(conn: &Connection, table_name: &str) -> Result<TableInfo> {
-    let sql: Option<String> = conn.query_row(
-        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
-        [table_name],
-        |row| row.get(0),
-    )?;
+    let table = TableRef::resolve(conn, table_name);
+    let (object_type, sql): (String, Option<String>) = conn
+        .prepare_cached(&format!(
+            "SELECT type, sql FROM {} WHERE type IN ('table', 'view') AND name = ?",
+            table.master()
+        ))?
+        .query_row([&table.name], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let kind = object_kind(&object_type, sql.as_deref());
 
-    let row_count = get_table_row_count(conn, table_name).ok();
+    let row_count = match kind {
+        TableKind::View => None,
+        _ => get_table_row_count(conn, &table).ok(),
+    };
 
     Ok(TableInfo {
         name: table_name.to_string(),
+        schema: table.schema,
         row_count,
+        row_count_estimated: false,
+        size: None,
         sql,
+        kind,
     })
 }
 
 /// Get columns for a table
 pub fn get_columns(conn: &Connection, table_name: &str) -> Result<Vec<ColumnInfo>> {
+    let table = TableRef::resolve(conn, table_name);
     // Use PRAGMA table_info for reliable column information
-    let mut stmt = conn.prepare(&format!(
-        "PRAGMA table_info(\"{}\")",
-        table_name.replace('"', "\"\"")
+    let mut stmt = conn.prepare_cached(&format!(
+        "PRAGMA {}table_info({})",
+        table.prefix(),
+        quote_identifier(&table.name)
     ))?;
 
     let columns: Result<Vec<_>> = stmt
@@ -82,16 +249,18 @@ pub fn get_columns(conn: &Connection, table_name: &str) -> Result<Vec<ColumnInfo
             let auto_increment = pk
                 && data_type.to_uppercase().contains("INT")
                 && conn
-                    .query_row(
-                        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
-                        [table_name],
-                        |row| {
+                    .prepare_cached(&format!(
+                        "SELECT sql FROM {} WHERE type = 'table' AND name = ?",
+                        table.master()
+                    ))
+                    .and_then(|mut stmt| {
+                        stmt.query_row([&table.name], |row| {
                             let sql: Option<String> = row.get(0)?;
                             Ok(sql
                                 .map(|s| s.to_uppercase().contains("AUTOINCREMENT"))
                                 .unwrap_or(false))
-                        },
-                    )
+                        })
+                    })
                     .unwrap_or(false);
 
             Ok(ColumnInfo {
@@ -109,28 +278,166 @@ pub fn get_columns(conn: &Connection, table_name: &str) -> Result<Vec<ColumnInfo
     columns
 }
 
+/// Every table's columns and foreign keys, for the diagram: a query of each per
+/// database rather than two PRAGMAs per table, or table by table, leaving out those
+/// that fail, when a table the query reaches can't be read
+pub fn get_diagram_tables(conn: &Connection) -> Result<Vec<DiagramTable>> {
+    let schemas = std::iter::once(None).chain(attached_schemas(conn)?.into_iter().map(Some));
+    let mut tables = Vec::new();
+    for schema in schemas {
+        match get_schema_diagram(conn, schema.as_deref()) {
+            Ok(found) => tables.extend(found),
+            Err(_) => tables.extend(
+                get_all_schemas(conn, "table", false)?
+                    .into_iter()
+                    .filter(|table| table.schema == schema)
+                    .filter_map(|table| {
+                        Some(DiagramTable {
+                            columns: get_columns(conn, &table.name).ok()?,
+                            foreign_keys: get_foreign_keys(conn, &table.name).ok()?,
+                            name: table.name,
+                        })
+                    }),
+            ),
+        }
+    }
+    Ok(tables)
+}
+
+/// The diagram's tables of the main database, or of the one attached as `schema`
+fn get_schema_diagram(conn: &Connection, schema: Option<&str>) -> Result<Vec<DiagramTable>> {
+    let prefix = schema_prefix(schema);
+    let schema_name = schema.unwrap_or("main");
+    let qualified = |name: String| match schema {
+        Some(schema) => format!("{}.{}", schema, name),
+        None => name,
+    };
+
+    let mut tables: Vec<DiagramTable> = Vec::new();
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT m.name, m.sql, p.name, p.type, p.\"notnull\", p.dflt_value, p.pk
+         FROM {}sqlite_master AS m JOIN pragma_table_info(m.name, ?1) AS p
+         WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+         ORDER BY m.name, p.cid",
+        prefix
+    ))?;
+    let mut rows = stmt.query([schema_name])?;
+    while let Some(row) = rows.next()? {
+        let name = qualified(row.get(0)?);
+        if tables.last().is_none_or(|table| table.name != name) {
+            tables.push(DiagramTable {
+                name,
+                columns: Vec::new(),
+                foreign_keys: Vec::new(),
+            });
+        }
+        let sql: Option<String> = row.get(1)?;
+        let data_type: String = row.get(3)?;
+        let primary_key: bool = row.get(6)?;
+        // As get_columns guesses it: an INTEGER PRIMARY KEY of a table declared with
+        // AUTOINCREMENT
+        let auto_increment = primary_key
+            && data_type.to_uppercase().contains("INT")
+            && sql.is_some_and(|sql| sql.to_uppercase().contains("AUTOINCREMENT"));
+        if let Some(table) = tables.last_mut() {
+            table.columns.push(ColumnInfo {
+                name: row.get(2)?,
+                data_type,
+                not_null: row.get(4)?,
+                default_value: row.get(5)?,
+                primary_key,
+                auto_increment,
+            });
+        }
+    }
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT m.name, f.id, f.\"table\", f.\"from\", f.\"to\", f.on_update, f.on_delete
+         FROM {}sqlite_master AS m JOIN pragma_foreign_key_list(m.name, ?1) AS f
+         WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+         ORDER BY m.name, f.id, f.seq",
+        prefix
+    ))?;
+    let mut rows = stmt.query([schema_name])?;
+    let mut keys: Vec<ForeignKeyInfo> = Vec::new();
+    while let Some(row) = rows.next()? {
+        keys.push(ForeignKeyInfo {
+            id: row.get(1)?,
+            from_table: qualified(row.get(0)?),
+            from_column: row.get(3)?,
+            // A key points into the database its table is in
+            to_table: qualified(row.get(2)?),
+            to_column: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            on_update: row.get(5)?,
+            on_delete: row.get(6)?,
+        });
+    }
+    for mut key in keys {
+        // A NULL target column means the key references the parent's primary key
+        if key.to_column.is_empty() {
+            key.to_column = tables
+                .iter()
+                .find(|table| table.name.eq_ignore_ascii_case(&key.to_table))
+                .and_then(|table| table.columns.iter().find(|c| c.primary_key))
+                .map(|column| column.name.clone())
+                .unwrap_or_default();
+        }
+        if let Some(table) = tables.iter_mut().find(|t| t.name == key.from_table) {
+            table.foreign_keys.push(key);
+        }
+    }
+    Ok(tables)
+}
+
+/// The schema version of the main database and of each attached one, by alias; any
+/// change to a schema bumps its version
+pub fn schema_versions(conn: &Connection) -> Result<Vec<(String, i64)>> {
+    let mut versions = vec![(
+        "main".to_string(),
+        conn.query_row("PRAGMA schema_version", [], |row| row.get(0))?,
+    )];
+    for schema in attached_schemas(conn)? {
+        let version = conn.query_row(
+            &format!("PRAGMA {}schema_version", schema_prefix(Some(&schema))),
+            [],
+            |row| row.get(0),
+        )?;
+        versions.push((schema, version));
+    }
+    Ok(versions)
+}
+
 /// Get indexes for a table
 pub fn get_indexes(conn: &Connection, table_name: &str) -> Result<Vec<IndexInfo>> {
-    let mut stmt = conn.prepare(
-        "SELECT name, unique, sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ?",
-    )?;
+    let table = TableRef::resolve(conn, table_name);
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT il.name, il.\"unique\", il.origin, il.partial, m.sql
+         FROM pragma_index_list(?1, ?2) AS il
+         LEFT JOIN {} AS m ON m.type = 'index' AND m.name = il.name
+         ORDER BY il.name",
+        table.master()
+    ))?;
 
     let indexes: Result<Vec<IndexInfo>, anyhow::Error> = stmt
-        .query_map([table_name], |row| {
+        .query_map([&table.name, table.schema_name()], |row| {
             let name: String = row.get(0)?;
             let unique: bool = row.get(1)?;
-            let sql: Option<String> = row.get(2)?;
+            let origin: String = row.get(2)?;
+            let partial: bool = row.get(3)?;
+            let sql: Option<String> = row.get(4)?;
 
             // Get index columns from index_info
-            let mut col_stmt = conn.prepare(&format!(
-                "PRAGMA index_info(\"{}\")",
-                name.replace('"', "\"\"")
+            let mut col_stmt = conn.prepare_cached(&format!(
+                "PRAGMA {}index_info({})",
+                table.prefix(),
+                quote_identifier(&name)
             ))?;
 
             let columns: Result<Vec<String>, anyhow::Error> = col_stmt
                 .query_map([], |row| {
-                    let col_name: String = row.get(1)?;
-                    Ok(col_name)
+                    // Columns of an expression index have no name
+                    let col_name: Option<String> = row.get(2)?;
+                    Ok(col_name.unwrap_or_else(|| "<expression>".to_string()))
                 })?
                 .map(|r| r.map_err(anyhow::Error::from))
                 .collect();
@@ -142,6 +449,8 @@ pub fn get_indexes(conn: &Connection, table_name: &str) -> Result<Vec<IndexInfo>
                 unique,
                 columns: columns_result,
                 sql,
+                origin: IndexOrigin::from_code(&origin),
+                partial,
             })
         })?
         .map(|r| r.map_err(anyhow::Error::from))
@@ -152,19 +461,31 @@ pub fn get_indexes(conn: &Connection, table_name: &str) -> Result<Vec<IndexInfo>
 
 /// Get foreign keys for a table
 pub fn get_foreign_keys(conn: &Connection, table_name: &str) -> Result<Vec<ForeignKeyInfo>> {
-    let mut stmt = conn.prepare(&format!(
-        "PRAGMA foreign_key_list(\"{}\")",
-        table_name.replace('"', "\"\"")
+    let table = TableRef::resolve(conn, table_name);
+    let mut stmt = conn.prepare_cached(&format!(
+        "PRAGMA {}foreign_key_list({})",
+        table.prefix(),
+        quote_identifier(&table.name)
     ))?;
 
     let fks: Result<Vec<ForeignKeyInfo>, anyhow::Error> = stmt
         .query_map([], |row| {
+            // A key points into the database its table is in
+            let to_table: String = match &table.schema {
+                Some(schema) => format!("{}.{}", schema, row.get::<_, String>(2)?),
+                None => row.get(2)?,
+            };
+            // A NULL target column means the key references the parent's primary key
+            let to_column = match row.get::<_, Option<String>>(4)? {
+                Some(column) => column,
+                None => primary_key_column(conn, &to_table).unwrap_or_default(),
+            };
             Ok(ForeignKeyInfo {
                 id: row.get(0)?,
                 from_table: table_name.to_string(),
                 from_column: row.get(3)?,
-                to_table: row.get(2)?,
-                to_column: row.get(4)?,
+                to_table,
+                to_column,
                 on_update: row.get(5)?,
                 on_delete: row.get(6)?,
             })
@@ -174,3 +495,54 @@ pub fn get_foreign_keys(conn: &Connection, table_name: &str) -> Result<Vec<Forei
 
     fks
 }
+
+/// Get the foreign keys of other tables, and of the table itself, that reference
+/// `table_name`. Keys only point within their own database, so only its tables are read.
+pub fn get_referencing_keys(conn: &Connection, table_name: &str) -> Result<Vec<ForeignKeyInfo>> {
+    let table = TableRef::resolve(conn, table_name);
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT name FROM {} WHERE type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+         ORDER BY name",
+        table.master()
+    ))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut keys = Vec::new();
+    for name in names {
+        let from_table = match &table.schema {
+            Some(schema) => format!("{}.{}", schema, name),
+            None => name,
+        };
+        // The key may name the table in another case than it was created with
+        keys.extend(
+            get_foreign_keys(conn, &from_table)?
+                .into_iter()
+                .filter(|fk| fk.to_table.eq_ignore_ascii_case(table_name))
+                .map(|fk| ForeignKeyInfo {
+                    to_table: table_name.to_string(),
+                    ..fk
+                }),
+        );
+    }
+    Ok(keys)
+}
+
+/// Get the first primary key column of a table, if it declares one
+fn primary_key_column(conn: &Connection, table_name: &str) -> Option<String> {
+    primary_key_columns(conn, table_name).into_iter().next()
+}
+
+/// Get the declared primary key columns of a table (empty for views and tables without one)
+pub(super) fn primary_key_columns(conn: &Connection, table_name: &str) -> Vec<String> {
+    get_columns(conn, table_name)
+        .map(|columns| {
+            columns
+                .into_iter()
+                .filter(|c| c.primary_key)
+                .map(|c| c.name)
+                .collect()
+        })
+        .unwrap_or_default()
+}
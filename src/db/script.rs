@@ -0,0 +1,417 @@
+use std::ops::Range;
+
+/// SQLite's keywords, sorted
+pub const KEYWORDS: &[&str] = &[
+    "ABORT",
+    "ACTION",
+    "ADD",
+    "AFTER",
+    "ALL",
+    "ALTER",
+    "ALWAYS",
+    "ANALYZE",
+    "AND",
+    "AS",
+    "ASC",
+    "ATTACH",
+    "AUTOINCREMENT",
+    "BEFORE",
+    "BEGIN",
+    "BETWEEN",
+    "BY",
+    "CASCADE",
+    "CASE",
+    "CAST",
+    "CHECK",
+    "COLLATE",
+    "COLUMN",
+    "COMMIT",
+    "CONFLICT",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "CURRENT",
+    "CURRENT_DATE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "DATABASE",
+    "DEFAULT",
+    "DEFERRABLE",
+    "DEFERRED",
+    "DELETE",
+    "DESC",
+    "DETACH",
+    "DISTINCT",
+    "DO",
+    "DROP",
+    "EACH",
+    "ELSE",
+    "END",
+    "ESCAPE",
+    "EXCEPT",
+    "EXCLUDE",
+    "EXCLUSIVE",
+    "EXISTS",
+    "EXPLAIN",
+    "FAIL",
+    "FILTER",
+    "FIRST",
+    "FOLLOWING",
+    "FOR",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "GENERATED",
+    "GLOB",
+    "GROUP",
+    "GROUPS",
+    "HAVING",
+    "IF",
+    "IGNORE",
+    "IMMEDIATE",
+    "IN",
+    "INDEX",
+    "INDEXED",
+    "INITIALLY",
+    "INNER",
+    "INSERT",
+    "INSTEAD",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "KEY",
+    "LAST",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "MATCH",
+    "MATERIALIZED",
+    "NATURAL",
+    "NO",
+    "NOT",
+    "NOTHING",
+    "NOTNULL",
+    "NULL",
+    "NULLS",
+    "OF",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "OTHERS",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "PLAN",
+    "PRAGMA",
+    "PRECEDING",
+    "PRIMARY",
+    "QUERY",
+    "RAISE",
+    "RANGE",
+    "RECURSIVE",
+    "REFERENCES",
+    "REGEXP",
+    "REINDEX",
+    "RELEASE",
+    "RENAME",
+    "REPLACE",
+    "RESTRICT",
+    "RETURNING",
+    "RIGHT",
+    "ROLLBACK",
+    "ROW",
+    "ROWS",
+    "SAVEPOINT",
+    "SELECT",
+    "SET",
+    "TABLE",
+    "TEMP",
+    "TEMPORARY",
+    "THEN",
+    "TIES",
+    "TO",
+    "TRANSACTION",
+    "TRIGGER",
+    "UNBOUNDED",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "USING",
+    "VACUUM",
+    "VALUES",
+    "VIEW",
+    "VIRTUAL",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+    "WITHOUT",
+];
+
+/// What a token of SQL text is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    /// A keyword or bare identifier
+    Word,
+    Number,
+    /// A string literal in single quotes
+    String,
+    /// An identifier in double quotes, backticks or brackets
+    QuotedIdentifier,
+    /// `--` up to the end of the line, or `/* */`
+    Comment,
+    Whitespace,
+    /// Any other character, such as an operator, `;` or a parameter's `?`
+    Symbol,
+}
+
+/// A token of SQL text and the bytes it covers
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub range: Range<usize>,
+    /// The text ended before the closing quote or `*/`
+    pub open: bool,
+}
+
+/// Whether `c` may go on a word after its first character
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Split `sql` into tokens the way SQLite reads them: a doubled quote inside quotes is
+/// an escaped quote, and strings, quoted identifiers and comments may span lines
+pub fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<(usize, char)> = sql.char_indices().collect();
+    let char_at = |i: usize| chars.get(i).map(|&(_, c)| c);
+    let offset = |i: usize| chars.get(i).map_or(sql.len(), |&(pos, _)| pos);
+    let mut tokens = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let mut open = false;
+        let kind = match chars[i].1 {
+            quote @ ('\'' | '"' | '`' | '[') => {
+                let close = if quote == '[' { ']' } else { quote };
+                i += 1;
+                loop {
+                    match char_at(i) {
+                        None => {
+                            open = true;
+                            break;
+                        }
+                        // A doubled quote is an escaped quote, not the end
+                        Some(c) if c == close && close != ']' && char_at(i + 1) == Some(close) => {
+                            i += 2;
+                        }
+                        Some(c) if c == close => {
+                            i += 1;
+                            break;
+                        }
+                        Some(_) => i += 1,
+                    }
+                }
+                if quote == '\'' {
+                    TokenKind::String
+                } else {
+                    TokenKind::QuotedIdentifier
+                }
+            }
+            '-' if char_at(i + 1) == Some('-') => {
+                while char_at(i).is_some_and(|c| c != '\n') {
+                    i += 1;
+                }
+                TokenKind::Comment
+            }
+            '/' if char_at(i + 1) == Some('*') => {
+                // The closing `*/` can't share the opening's `*`
+                i += 2;
+                while i < chars.len() && !(char_at(i) == Some('*') && char_at(i + 1) == Some('/')) {
+                    i += 1;
+                }
+                open = i >= chars.len();
+                i = (i + 2).min(chars.len());
+                TokenKind::Comment
+            }
+            c if c.is_ascii_digit() => {
+                while char_at(i).is_some_and(|c| is_word_char(c) || c == '.') {
+                    i += 1;
+                }
+                TokenKind::Number
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while char_at(i).is_some_and(is_word_char) {
+                    i += 1;
+                }
+                TokenKind::Word
+            }
+            c if c.is_whitespace() => {
+                while char_at(i).is_some_and(char::is_whitespace) {
+                    i += 1;
+                }
+                TokenKind::Whitespace
+            }
+            _ => {
+                i += 1;
+                TokenKind::Symbol
+            }
+        };
+        tokens.push(Token {
+            kind,
+            range: offset(start)..offset(i),
+            open,
+        });
+    }
+    tokens
+}
+
+/// Byte ranges of the statements in a SQL script, split on semicolons outside of quotes,
+/// comments and trigger bodies. Ranges start at the first token, so comments before a
+/// statement are left out along with the `;`; comment-only statements are skipped.
+pub fn split_statements(sql: &str) -> Vec<Range<usize>> {
+    let mut statements = Vec::new();
+    // Where the current statement's first token starts
+    let mut code_start = None;
+    // Leading words of the statement, to spot CREATE [TEMP] TRIGGER, and the latest word
+    let mut leading: Vec<String> = Vec::new();
+    let mut last_word = String::new();
+    let mut in_trigger = false;
+
+    for token in tokenize(sql) {
+        let text = &sql[token.range.clone()];
+        match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment => {}
+            // Statements inside a trigger body end with `;` too; only END; closes it
+            TokenKind::Symbol if text == ";" && (!in_trigger || last_word == "END") => {
+                if let Some(start) = code_start.take() {
+                    statements.push(trim_end(sql, start..token.range.start));
+                }
+                leading.clear();
+                last_word.clear();
+                in_trigger = false;
+            }
+            TokenKind::Word => {
+                code_start.get_or_insert(token.range.start);
+                last_word = text.to_ascii_uppercase();
+                if leading.len() < 3 {
+                    leading.push(last_word.clone());
+                    in_trigger |= matches!(
+                        leading.iter().map(String::as_str).collect::<Vec<_>>()[..],
+                        ["CREATE", "TRIGGER"] | ["CREATE", "TEMP" | "TEMPORARY", "TRIGGER"]
+                    );
+                }
+            }
+            _ => {
+                code_start.get_or_insert(token.range.start);
+                last_word.clear();
+            }
+        }
+    }
+    if let Some(start) = code_start {
+        statements.push(trim_end(sql, start..sql.len()));
+    }
+    statements
+}
+
+/// The statement holding byte offset `pos`: the last one starting at or before it,
+/// or the first when the cursor is ahead of every statement
+pub fn statement_at(sql: &str, pos: usize) -> Option<Range<usize>> {
+    let statements = split_statements(sql);
+    let first = statements.first().cloned();
+    statements
+        .into_iter()
+        .rev()
+        .find(|range| range.start <= pos)
+        .or(first)
+}
+
+/// `range` of `sql` without trailing whitespace
+fn trim_end(sql: &str, range: Range<usize>) -> Range<usize> {
+    range.start..range.start + sql[range].trim_end().len()
+}
+
+/// Whether `word` is a SQLite keyword, in any case
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS
+        .binary_search(&word.to_ascii_uppercase().as_str())
+        .is_ok()
+}
+
+/// Whether `sql` seems to hold a parameter placeholder (`?`, `:name`, `@name` or `$name`)
+/// outside quotes and comments. Only a hint; preparing the statement gives the real list.
+pub fn has_parameters(sql: &str) -> bool {
+    let tokens = tokenize(sql);
+    tokens.iter().enumerate().any(|(i, token)| {
+        match &sql[token.range.clone()] {
+            "?" => token.kind == TokenKind::Symbol,
+            ":" | "@" | "$" => {
+                // Named by a word right after it, and not itself right after one
+                let after_word =
+                    i > 0 && matches!(tokens[i - 1].kind, TokenKind::Word | TokenKind::Number);
+                let names = tokens
+                    .get(i + 1)
+                    .is_some_and(|next| next.kind == TokenKind::Word);
+                names && !after_word
+            }
+            _ => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<(TokenKind, &str, bool)> {
+        tokenize(sql)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, &sql[token.range], token.open))
+            .collect()
+    }
+
+    #[test]
+    fn quotes_and_comments_hold_what_would_end_a_statement() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("SELECT 'it''s;', \"a\"\"b\", `c`, [d]] -- e;\n/*; */ x1 1.5e3 é;"),
+            vec![
+                (Word, "SELECT", false),
+                (String, "'it''s;'", false),
+                (Symbol, ",", false),
+                (QuotedIdentifier, "\"a\"\"b\"", false),
+                (Symbol, ",", false),
+                (QuotedIdentifier, "`c`", false),
+                (Symbol, ",", false),
+                (QuotedIdentifier, "[d]", false),
+                (Symbol, "]", false),
+                (Comment, "-- e;", false),
+                (Comment, "/*; */", false),
+                (Word, "x1", false),
+                (Number, "1.5e3", false),
+                (Word, "é", false),
+                (Symbol, ";", false),
+            ]
+        );
+        assert_eq!(kinds("/*/"), vec![(Comment, "/*/", true)]);
+        assert_eq!(kinds("'a''"), vec![(String, "'a''", true)]);
+        assert_eq!(
+            split_statements("SELECT ';'; -- x;\nSELECT [a;b]"),
+            vec![0..10, 18..30]
+        );
+    }
+
+    #[test]
+    fn placeholders_outside_quotes_and_comments_are_parameters() {
+        assert!(has_parameters("SELECT ?"));
+        assert!(has_parameters(
+            "SELECT * FROM t WHERE a = :a AND b = @b OR c = $c"
+        ));
+        assert!(!has_parameters("SELECT '?', \":a\" -- ?\n/* @b */"));
+        assert!(!has_parameters("SELECT a:b, 1:b, time(':00')"));
+    }
+}
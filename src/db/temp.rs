@@ -0,0 +1,60 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// First bytes of every SQLite database file
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// A database piped on standard input, copied to a temporary file that's removed when
+/// this is dropped, as it is on a panic too
+pub struct StdinDatabase {
+    path: PathBuf,
+}
+
+impl StdinDatabase {
+    /// Copy all of standard input to a temporary file, checking it holds a database
+    pub fn read() -> Result<Self> {
+        let mut stdin = io::stdin().lock();
+        if stdin.is_terminal() {
+            bail!("Nothing is piped to standard input; use e.g. `curl -s URL | sqr -`");
+        }
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let copy = Self {
+            path: std::env::temp_dir().join(format!("sqr-{}-{}.db", std::process::id(), nanos)),
+        };
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&copy.path)
+            .with_context(|| format!("Failed to create {}", copy.path.display()))?;
+
+        let mut header = [0; 16];
+        if stdin.read_exact(&mut header).is_err() || &header != SQLITE_HEADER {
+            bail!("Standard input isn't a SQLite database");
+        }
+        file.write_all(&header)
+            .and_then(|_| io::copy(&mut stdin, &mut file))
+            .context("Failed to copy the database from standard input")?;
+        Ok(copy)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StdinDatabase {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        // A database in WAL mode leaves these beside it
+        for suffix in ["-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            let _ = fs::remove_file(path);
+        }
+    }
+}
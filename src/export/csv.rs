@@ -1,50 +1,161 @@
+use super::{cell_text, ExportOptions, Progress, QuoteStyle};
 use anyhow::{Context, Result};
-use rusqlite::Connection;
-use std::fs::File;
-use std::path::Path;
-
-/// Export query results to CSV
-pub fn export_csv(conn: &Connection, output_path: &Path, sql_query: &str) -> Result<()> {
-    let mut file = File::create(output_path)
-        .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
-
-    let mut writer = csv::Writer::from_writer(&mut file);
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection};
+use std::io::Write;
 
+/// Export query results to CSV, delimited and quoted as `options` says, counting each
+/// row in `progress`
+pub fn export_csv(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    options: &ExportOptions,
+) -> Result<()> {
     // Execute query
     let mut stmt = conn
         .prepare(sql_query)
         .context("Failed to prepare SQL statement")?;
-
-    // Write header
     let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-    writer
-        .write_record(&columns)
-        .context("Failed to write CSV header")?;
 
-    // Write rows
-    let row_iter = stmt.query_map([], |row| {
-        let mut values = Vec::new();
-        for i in 0..row.as_ref().column_count() {
-            let value: rusqlite::types::Value = row.get(i)?;
-            let csv_value = match value {
-                rusqlite::types::Value::Null => String::new(),
-                rusqlite::types::Value::Integer(i) => i.to_string(),
-                rusqlite::types::Value::Real(r) => r.to_string(),
-                rusqlite::types::Value::Text(t) => t,
-                rusqlite::types::Value::Blob(_) => "<BLOB>".to_string(),
-            };
-            values.push(csv_value);
-        }
-        Ok(values)
+    let row_iter = stmt.query_map(params_from_iter(params), |row| {
+        (0..row.as_ref().column_count())
+            .map(|i| row.get(i))
+            .collect()
     })?;
+    let rows = row_iter.map(|row| row.context("Failed to read row"));
+    write_csv(writer, progress, &columns, rows, options)
+}
+
+/// Write `rows` as CSV under a header of `columns`, counting each row in `progress`
+pub(super) fn write_csv(
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    columns: &[String],
+    rows: impl Iterator<Item = Result<Vec<SqliteValue>>>,
+    options: &ExportOptions,
+) -> Result<()> {
+    let mut csv_writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .quote_style(match options.quote_style {
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        })
+        .from_writer(writer);
+
+    // Write header
+    if options.header {
+        csv_writer
+            .write_record(columns)
+            .context("Failed to write CSV header")?;
+    }
 
-    for row_result in row_iter {
-        let row = row_result.context("Failed to read row")?;
-        writer
-            .write_record(&row)
+    // Write rows
+    for row in rows {
+        let cells = row?
+            .into_iter()
+            .map(|value| cell_text(value, &options.null_text));
+        csv_writer
+            .write_record(cells)
             .context("Failed to write CSV row")?;
+        progress.row()?;
     }
 
-    writer.flush().context("Failed to flush CSV writer")?;
+    csv_writer.flush().context("Failed to flush CSV writer")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{rows_text, ExportFormat};
+
+    fn text(value: &str) -> SqliteValue {
+        SqliteValue::Text(value.to_string())
+    }
+
+    fn csv(rows: Vec<Vec<SqliteValue>>, options: &ExportOptions) -> String {
+        let columns = ["name".to_string(), "note".to_string()];
+        rows_text(ExportFormat::Csv, &columns, rows, options).unwrap()
+    }
+
+    #[test]
+    fn a_custom_delimiter_separates_the_fields() {
+        let options = ExportOptions {
+            delimiter: b';',
+            ..ExportOptions::default()
+        };
+        let rows = vec![vec![text("a"), SqliteValue::Integer(1)]];
+        assert_eq!(csv(rows, &options), "name;note\na;1\n");
+    }
+
+    #[test]
+    fn fields_holding_the_delimiter_quotes_or_line_breaks_are_quoted() {
+        let rows = vec![
+            vec![text("a,b"), text("say \"hi\"")],
+            vec![text("one\ntwo"), text("three\r\nfour")],
+            vec![text("plain"), text("carriage\rreturn")],
+        ];
+        assert_eq!(
+            csv(rows, &ExportOptions::default()),
+            "name,note\n\
+             \"a,b\",\"say \"\"hi\"\"\"\n\
+             \"one\ntwo\",\"three\r\nfour\"\n\
+             plain,\"carriage\rreturn\"\n"
+        );
+    }
+
+    #[test]
+    fn a_semicolon_is_only_quoted_when_it_is_the_delimiter() {
+        let rows = vec![vec![text("a;b"), text("c,d")]];
+        let options = ExportOptions {
+            delimiter: b';',
+            ..ExportOptions::default()
+        };
+        assert_eq!(csv(rows, &options), "name;note\n\"a;b\";c,d\n");
+    }
+
+    #[test]
+    fn always_quotes_every_field_and_the_header() {
+        let options = ExportOptions {
+            quote_style: QuoteStyle::Always,
+            ..ExportOptions::default()
+        };
+        let rows = vec![vec![text("a"), SqliteValue::Integer(1)]];
+        assert_eq!(csv(rows, &options), "\"name\",\"note\"\n\"a\",\"1\"\n");
+    }
+
+    #[test]
+    fn necessary_leaves_plain_fields_bare() {
+        let options = ExportOptions {
+            quote_style: QuoteStyle::Necessary,
+            ..ExportOptions::default()
+        };
+        let rows = vec![vec![text("a b"), SqliteValue::Real(1.5)]];
+        assert_eq!(csv(rows, &options), "name,note\na b,1.5\n");
+    }
+
+    #[test]
+    fn never_writes_fields_as_they_are() {
+        let options = ExportOptions {
+            quote_style: QuoteStyle::Never,
+            ..ExportOptions::default()
+        };
+        let rows = vec![vec![text("a,b"), text("say \"hi\"")]];
+        assert_eq!(csv(rows, &options), "name,note\na,b,say \"hi\"\n");
+    }
+
+    #[test]
+    fn no_header_and_null_text_are_honoured() {
+        let options = ExportOptions {
+            header: false,
+            null_text: "NULL".to_string(),
+            ..ExportOptions::default()
+        };
+        let rows = vec![vec![SqliteValue::Null, text("")]];
+        assert_eq!(csv(rows, &options), "NULL,\n");
+    }
+}
@@ -0,0 +1,328 @@
+use super::html::escape as escape_html;
+use crate::types::{ColumnInfo, DiagramData, DiagramTable, Relationship};
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+
+/// Text formats an ER diagram can be written in, for documentation tools to draw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagramFormat {
+    /// Mermaid `erDiagram`
+    Mermaid,
+    /// Graphviz digraph
+    Dot,
+    PlantUml,
+}
+
+impl DiagramFormat {
+    /// File extension for files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            DiagramFormat::Mermaid => "mmd",
+            DiagramFormat::Dot => "dot",
+            DiagramFormat::PlantUml => "puml",
+        }
+    }
+
+    /// The format a file name's extension asks for
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "mmd" | "mermaid" => Some(DiagramFormat::Mermaid),
+            "dot" | "gv" => Some(DiagramFormat::Dot),
+            "puml" | "plantuml" | "pu" => Some(DiagramFormat::PlantUml),
+            _ => None,
+        }
+    }
+}
+
+/// Write the diagram's tables, their columns marked PK and FK, and a relationship for
+/// each foreign key in `format`
+pub fn export_diagram(
+    diagram: &DiagramData,
+    format: DiagramFormat,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let relationships = diagram.relationships();
+    match format {
+        DiagramFormat::Mermaid => write_mermaid(diagram, &relationships, writer),
+        DiagramFormat::Dot => write_dot(diagram, &relationships, writer),
+        DiagramFormat::PlantUml => write_plantuml(diagram, &relationships, writer),
+    }
+    .context("Failed to write the diagram")?;
+    writer.flush().context("Failed to write the diagram")?;
+    Ok(())
+}
+
+fn write_mermaid(
+    diagram: &DiagramData,
+    relationships: &[Relationship],
+    writer: &mut dyn Write,
+) -> std::io::Result<()> {
+    writeln!(writer, "erDiagram")?;
+    for table in &diagram.tables {
+        writeln!(writer, "    {} {{", mermaid_entity(&table.name))?;
+        for column in &table.columns {
+            let name = mermaid_word(&column.name);
+            write!(
+                writer,
+                "        {} {}",
+                mermaid_word(&column.data_type),
+                name
+            )?;
+            let keys = key_markers(table, column);
+            if !keys.is_empty() {
+                write!(writer, " {}", keys.join(", "))?;
+            }
+            // A name that had to change keeps its real spelling as the comment
+            if name != column.name {
+                write!(writer, " \"{}\"", column.name.replace('"', "'"))?;
+            }
+            writeln!(writer)?;
+        }
+        writeln!(writer, "    }}")?;
+    }
+    for key in relationships {
+        // The referenced row is optional when a key column may be NULL
+        let parent = if key_required(diagram, key) {
+            "||"
+        } else {
+            "|o"
+        };
+        writeln!(
+            writer,
+            "    {} {}--o{{ {} : \"{}\"",
+            mermaid_entity(&key.to_table),
+            parent,
+            mermaid_entity(&key.from_table),
+            key.from_columns.join(", ").replace('"', "'")
+        )?;
+    }
+    Ok(())
+}
+
+fn write_dot(
+    diagram: &DiagramData,
+    relationships: &[Relationship],
+    writer: &mut dyn Write,
+) -> std::io::Result<()> {
+    writeln!(writer, "digraph schema {{")?;
+    writeln!(writer, "    rankdir=LR;")?;
+    writeln!(writer, "    node [shape=plaintext];")?;
+    for table in &diagram.tables {
+        // An HTML-like table, one cell per column so arrows can start at their column
+        writeln!(writer, "    {} [label=<", dot_id(&table.name))?;
+        writeln!(
+            writer,
+            "        <table border=\"0\" cellborder=\"1\" cellspacing=\"0\">"
+        )?;
+        writeln!(
+            writer,
+            "        <tr><td bgcolor=\"lightgrey\"><b>{}</b></td></tr>",
+            escape_html(&table.name)
+        )?;
+        for (i, column) in table.columns.iter().enumerate() {
+            let mut text = vec![column.name.clone()];
+            if !column.data_type.is_empty() {
+                text.push(column.data_type.clone());
+            }
+            let keys = key_markers(table, column);
+            if !keys.is_empty() {
+                text.push(keys.join(", "));
+            }
+            writeln!(
+                writer,
+                "        <tr><td align=\"left\" port=\"c{}\">{}</td></tr>",
+                i,
+                escape_html(&text.join(" "))
+            )?;
+        }
+        writeln!(writer, "        </table>>];")?;
+    }
+    for key in relationships {
+        writeln!(
+            writer,
+            "    {} -> {} [label={}];",
+            dot_port(diagram, &key.from_table, &key.from_columns[0]),
+            dot_port(diagram, &key.to_table, &key.to_columns[0]),
+            dot_id(&key.from_columns.join(", "))
+        )?;
+    }
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+fn write_plantuml(
+    diagram: &DiagramData,
+    relationships: &[Relationship],
+    writer: &mut dyn Write,
+) -> std::io::Result<()> {
+    writeln!(writer, "@startuml")?;
+    writeln!(writer, "hide circle")?;
+    writeln!(writer, "skinparam linetype ortho")?;
+    // Entities go by alias, so a name needs no more than its quotes
+    for (i, table) in diagram.tables.iter().enumerate() {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "entity \"{}\" as e{} {{",
+            table.name.replace('"', "'"),
+            i
+        )?;
+        let (keys, rest): (Vec<&ColumnInfo>, Vec<&ColumnInfo>) =
+            table.columns.iter().partition(|c| c.primary_key);
+        for column in &keys {
+            write_plantuml_column(writer, table, column)?;
+        }
+        if !keys.is_empty() && !rest.is_empty() {
+            writeln!(writer, "  --")?;
+        }
+        for column in &rest {
+            write_plantuml_column(writer, table, column)?;
+        }
+        writeln!(writer, "}}")?;
+    }
+    if !relationships.is_empty() {
+        writeln!(writer)?;
+    }
+    for key in relationships {
+        let alias = |name: &str| diagram.tables.iter().position(|t| t.name == name);
+        let (Some(parent), Some(child)) = (alias(&key.to_table), alias(&key.from_table)) else {
+            continue;
+        };
+        let arrow = if key_required(diagram, key) {
+            "||--o{"
+        } else {
+            "|o--o{"
+        };
+        writeln!(
+            writer,
+            "e{} {} e{} : {}",
+            parent,
+            arrow,
+            child,
+            key.from_columns.join(", ")
+        )?;
+    }
+    writeln!(writer, "@enduml")?;
+    Ok(())
+}
+
+/// A column line in an entity: `*` when it can't be NULL, then its name, type and keys
+fn write_plantuml_column(
+    writer: &mut dyn Write,
+    table: &DiagramTable,
+    column: &ColumnInfo,
+) -> std::io::Result<()> {
+    let required = if column.not_null || column.primary_key {
+        "* "
+    } else {
+        ""
+    };
+    write!(writer, "  {}{}", required, column.name)?;
+    if !column.data_type.is_empty() {
+        write!(writer, " : {}", column.data_type)?;
+    }
+    for marker in key_markers(table, column) {
+        write!(writer, " <<{}>>", marker)?;
+    }
+    writeln!(writer)
+}
+
+/// PK and FK, as far as they apply to `column`
+fn key_markers(table: &DiagramTable, column: &ColumnInfo) -> Vec<&'static str> {
+    let mut markers = Vec::new();
+    if column.primary_key {
+        markers.push("PK");
+    }
+    if table
+        .foreign_keys
+        .iter()
+        .any(|fk| fk.from_column == column.name)
+    {
+        markers.push("FK");
+    }
+    markers
+}
+
+/// Whether every row must reference a parent row, none of the key's columns allowing NULL
+fn key_required(diagram: &DiagramData, key: &Relationship) -> bool {
+    let Some(table) = diagram.table_named(&key.from_table) else {
+        return false;
+    };
+    key.from_columns.iter().all(|name| {
+        table
+            .columns
+            .iter()
+            .any(|c| &c.name == name && (c.not_null || c.primary_key))
+    })
+}
+
+/// Words Mermaid reads as part of the diagram's syntax rather than as a name
+const MERMAID_KEYWORDS: &[&str] = &[
+    "erDiagram",
+    "direction",
+    "title",
+    "accTitle",
+    "accDescr",
+    "classDef",
+    "class",
+    "style",
+    "end",
+];
+
+/// An entity name, in quotes unless it's a plain word. Quotes can't be escaped inside
+/// them, so any in the name become single quotes.
+fn mermaid_entity(name: &str) -> String {
+    let plain = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && !MERMAID_KEYWORDS
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(name));
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "'"))
+    }
+}
+
+/// An attribute's name or type as Mermaid takes it: a word of letters, digits and
+/// underscores, with brackets allowed in types. Anything else becomes an underscore,
+/// and a column without a declared type is `any`.
+fn mermaid_word(text: &str) -> String {
+    if text.is_empty() {
+        return "any".to_string();
+    }
+    let mut word: String = text
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '(' | ')' | '[' | ']') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        word.insert(0, '_');
+    }
+    word
+}
+
+/// A Graphviz ID: always quoted, with quotes and backslashes escaped
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The node for `table`, at the cell of `column` when the table has it
+fn dot_port(diagram: &DiagramData, table: &str, column: &str) -> String {
+    let cell = diagram
+        .table_named(table)
+        .and_then(|t| t.columns.iter().position(|c| c.name == column));
+    match cell {
+        Some(i) => format!("{}:c{}", dot_id(table), i),
+        None => dot_id(table),
+    }
+}
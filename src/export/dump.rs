@@ -0,0 +1,141 @@
+use super::sql::sql_literal;
+use super::{quote_identifier, Progress};
+use anyhow::{Context, Result};
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::Connection;
+use std::io::Write;
+
+/// Write everything needed to rebuild the database, in the order the sqlite3 shell's
+/// `.dump` uses: each table's CREATE statement followed by its rows, then the views,
+/// triggers and indexes, all in one transaction. Each row is counted in `progress`.
+pub fn export_dump(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+) -> Result<()> {
+    writeln!(writer, "PRAGMA foreign_keys=OFF;")?;
+    writeln!(writer, "BEGIN TRANSACTION;")?;
+
+    let tables = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT name, sql FROM sqlite_schema
+                 WHERE type = 'table' AND sql IS NOT NULL
+                 ORDER BY tbl_name = 'sqlite_sequence', rowid",
+            )
+            .context("Failed to read the schema")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<Vec<(String, String)>>>()
+            .context("Failed to read the schema")?
+    };
+
+    let mut writable_schema = false;
+    for (name, sql) in &tables {
+        if name == "sqlite_sequence" {
+            writeln!(writer, "DELETE FROM sqlite_sequence;")?;
+        } else if name == "sqlite_stat1" {
+            writeln!(writer, "ANALYZE sqlite_schema;")?;
+        } else if name.starts_with("sqlite_") {
+            continue;
+        } else if starts_with_ignore_case(sql, "CREATE VIRTUAL TABLE") {
+            // Creating a virtual table fills in its shadow tables, which would clash
+            // with their dumped rows, so its schema entry is written directly and the
+            // shadow tables are dumped like any other
+            if !writable_schema {
+                writeln!(writer, "PRAGMA writable_schema=ON;")?;
+                writable_schema = true;
+            }
+            let name = sql_literal(&SqliteValue::Text(name.clone()));
+            writeln!(
+                writer,
+                "INSERT INTO sqlite_schema(type,name,tbl_name,rootpage,sql) VALUES('table',{},{},0,{});",
+                name,
+                name,
+                sql_literal(&SqliteValue::Text(sql.clone()))
+            )?;
+            continue;
+        } else if let Some(rest) = shadow_table_sql(sql) {
+            writeln!(writer, "CREATE TABLE IF NOT EXISTS {};", rest)?;
+        } else {
+            writeln!(writer, "{};", sql)?;
+        }
+        dump_rows(conn, writer, progress, name)?;
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT sql FROM sqlite_schema
+             WHERE sql IS NOT NULL AND type IN ('index', 'trigger', 'view')
+             ORDER BY type COLLATE NOCASE DESC, rowid",
+        )
+        .context("Failed to read the schema")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next().context("Failed to read the schema")? {
+        let sql: String = row.get(0)?;
+        writeln!(writer, "{};", sql)?;
+    }
+
+    if writable_schema {
+        writeln!(writer, "PRAGMA writable_schema=OFF;")?;
+    }
+    writeln!(writer, "COMMIT;")?;
+    writer.flush().context("Failed to write SQL output")?;
+    Ok(())
+}
+
+/// Write an INSERT for each of `table`'s rows. Generated columns are left out for
+/// SQLite to fill in.
+fn dump_rows(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    table: &str,
+) -> Result<()> {
+    let columns = {
+        let mut stmt = conn
+            .prepare("SELECT name FROM pragma_table_xinfo(?1) WHERE hidden = 0")
+            .context("Failed to read the table's columns")?;
+        let names = stmt.query_map([table], |row| row.get::<_, String>(0))?;
+        names
+            .map(|name| name.map(|name| quote_identifier(&name)))
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read the table's columns")?
+            .join(",")
+    };
+    let table = quote_identifier(table);
+
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM {}", columns, table))
+        .context("Failed to prepare SQL statement")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next().context("Failed to read row")? {
+        let values = (0..row.as_ref().column_count())
+            .map(|i| {
+                row.get::<_, SqliteValue>(i)
+                    .map(|value| sql_literal(&value))
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read row")?;
+        writeln!(
+            writer,
+            "INSERT INTO {} VALUES({});",
+            table,
+            values.join(",")
+        )?;
+        progress.row()?;
+    }
+    Ok(())
+}
+
+/// What follows `CREATE TABLE` in the statement of a table whose name is quoted, as the
+/// shadow tables of virtual tables are. Like the sqlite3 shell, the dump creates those
+/// with `IF NOT EXISTS` in case they're already there.
+fn shadow_table_sql(sql: &str) -> Option<&str> {
+    let rest = sql.get("CREATE TABLE ".len()..)?;
+    (starts_with_ignore_case(sql, "CREATE TABLE ") && rest.starts_with(['\'', '"'])).then_some(rest)
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
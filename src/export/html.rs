@@ -0,0 +1,71 @@
+use super::{cell_text, ExportOptions, Progress};
+use anyhow::{Context, Result};
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection};
+use std::io::Write;
+
+/// Borders and padding for `--html-style`, enough to read the table in a browser
+const STYLE: &str = "<style>
+table { border-collapse: collapse; font-family: sans-serif; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }
+th { background: #f4f4f4; }
+</style>";
+
+/// Export query results as an HTML `<table>`, counting each row in `progress`
+pub fn export_html(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    options: &ExportOptions,
+) -> Result<()> {
+    let mut stmt = conn
+        .prepare(sql_query)
+        .context("Failed to prepare SQL statement")?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|s| escape(s)).collect();
+
+    if options.html_style {
+        writeln!(writer, "{}", STYLE)?;
+    }
+    writeln!(writer, "<table>")?;
+    writeln!(writer, "  <thead>")?;
+    writeln!(
+        writer,
+        "    <tr><th>{}</th></tr>",
+        columns.join("</th><th>")
+    )?;
+    writeln!(writer, "  </thead>")?;
+    writeln!(writer, "  <tbody>")?;
+
+    let mut rows = stmt.query(params_from_iter(params))?;
+    while let Some(row) = rows.next().context("Failed to read row")? {
+        let cells = (0..columns.len())
+            .map(|i| Ok(escape(&cell_text(row.get(i)?, &options.null_text))))
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read row")?;
+        writeln!(writer, "    <tr><td>{}</td></tr>", cells.join("</td><td>"))?;
+        progress.row()?;
+    }
+
+    writeln!(writer, "  </tbody>")?;
+    writeln!(writer, "</table>")?;
+    writer.flush().context("Failed to write HTML output")?;
+    Ok(())
+}
+
+/// Text with the characters HTML treats specially replaced by entities
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
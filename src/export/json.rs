@@ -1,55 +1,161 @@
+use super::{BlobEncoding, ExportOptions, JsonShape, Progress};
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose, Engine as _};
-use rusqlite::Connection;
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection, Row};
+use serde::ser::{Error as _, SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
 use serde_json::{json, Value as JsonValue};
-use std::fs::File;
+use std::cell::{Cell, RefCell};
 use std::io::Write;
-use std::path::Path;
 
-/// Export query results to JSON
-pub fn export_json(conn: &Connection, output_path: &Path, sql_query: &str) -> Result<()> {
-    let mut file = File::create(output_path)
-        .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
-
-    // Execute query
+/// Export query results as JSON shaped as `options.json_shape` says, counting each row
+/// in `progress`. Rows are written as they're read rather than collected first.
+pub fn export_json(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    options: &ExportOptions,
+) -> Result<()> {
     let mut stmt = conn
         .prepare(sql_query)
         .context("Failed to prepare SQL statement")?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+
+    let mut rows = stmt.query(params_from_iter(params))?;
+    let mut next_row = || -> Result<Option<JsonValue>> {
+        let Some(row) = rows.next().context("Failed to read row")? else {
+            return Ok(None);
+        };
+        let values =
+            row_values(row, columns.len(), options.blob_encoding).context("Failed to read row")?;
+        progress.row()?;
+        Ok(Some(match options.json_shape {
+            JsonShape::Objects => row_object(&columns, values),
+            JsonShape::Rows | JsonShape::Envelope => JsonValue::Array(values),
+        }))
+    };
+    let array = RowArray {
+        next_row: RefCell::new(&mut next_row),
+        count: Cell::new(0),
+        error: RefCell::new(None),
+    };
+
+    let mut serializer = serde_json::Serializer::pretty(&mut *writer);
+    let written = match options.json_shape {
+        JsonShape::Objects | JsonShape::Rows => array.serialize(&mut serializer),
+        JsonShape::Envelope => write_envelope(&mut serializer, &columns, &array, sql_query),
+    };
+    // A row that failed to read stopped the output with a stand-in error
+    if let Some(error) = array.error.into_inner() {
+        return Err(error);
+    }
+    written.context("Failed to write JSON output")?;
+
+    writer.flush().context("Failed to write JSON output")?;
+    Ok(())
+}
 
-    // Get column names
+/// Export query results as JSON Lines, one compact object per row, counting each row
+/// in `progress`
+pub fn export_jsonl(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    options: &ExportOptions,
+) -> Result<()> {
+    let mut stmt = conn
+        .prepare(sql_query)
+        .context("Failed to prepare SQL statement")?;
     let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
 
-    // Collect rows
-    let mut rows = Vec::new();
-    let row_iter = stmt.query_map([], |row| {
-        let mut obj = serde_json::Map::new();
-        for (i, col_name) in columns.iter().enumerate() {
-            let value: rusqlite::types::Value = row.get(i)?;
-            let json_value = match value {
-                rusqlite::types::Value::Null => JsonValue::Null,
-                rusqlite::types::Value::Integer(i) => json!(i),
-                rusqlite::types::Value::Real(r) => json!(r),
-                rusqlite::types::Value::Text(t) => json!(t),
-                rusqlite::types::Value::Blob(b) => {
-                    // Encode blob as base64
-                    json!(general_purpose::STANDARD.encode(&b))
+    let mut rows = stmt.query(params_from_iter(params))?;
+    while let Some(row) = rows.next().context("Failed to read row")? {
+        let values =
+            row_values(row, columns.len(), options.blob_encoding).context("Failed to read row")?;
+        let object = row_object(&columns, values);
+        serde_json::to_writer(&mut *writer, &object).context("Failed to write JSON output")?;
+        writeln!(writer).context("Failed to write JSON output")?;
+        progress.row()?;
+    }
+
+    writer.flush().context("Failed to write JSON output")?;
+    Ok(())
+}
+
+/// An object holding the columns, the rows, how many there were and the query
+fn write_envelope<S: Serializer>(
+    serializer: S,
+    columns: &[String],
+    rows: &RowArray,
+    query: &str,
+) -> Result<S::Ok, S::Error> {
+    let mut envelope = serializer.serialize_map(Some(4))?;
+    envelope.serialize_entry("columns", columns)?;
+    envelope.serialize_entry("rows", rows)?;
+    envelope.serialize_entry("row_count", &rows.count.get())?;
+    envelope.serialize_entry("query", query)?;
+    envelope.end()
+}
+
+/// Serializes as an array of the rows `next_row` hands out, counting them. The first
+/// error it hands out is kept in `error` and the serializer is stopped.
+struct RowArray<'a> {
+    next_row: RefCell<&'a mut dyn FnMut() -> Result<Option<JsonValue>>>,
+    count: Cell<usize>,
+    error: RefCell<Option<anyhow::Error>>,
+}
+
+impl Serialize for RowArray<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut next_row = self.next_row.borrow_mut();
+        let mut array = serializer.serialize_seq(None)?;
+        loop {
+            match next_row() {
+                Ok(Some(row)) => {
+                    array.serialize_element(&row)?;
+                    self.count.set(self.count.get() + 1);
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    *self.error.borrow_mut() = Some(e);
+                    return Err(S::Error::custom("export stopped"));
                 }
-            };
-            obj.insert(col_name.clone(), json_value);
+            }
         }
-        Ok(JsonValue::Object(obj))
-    })?;
-
-    for row_result in row_iter {
-        let row = row_result.context("Failed to read row")?;
-        rows.push(row);
+        array.end()
     }
+}
 
-    // Write as JSON array
-    let output = serde_json::to_string_pretty(&rows).context("Failed to serialize JSON")?;
-    file.write_all(output.as_bytes())
-        .context("Failed to write JSON file")?;
-    file.flush().context("Failed to flush file")?;
+/// A row's values as JSON, with BLOBs encoded as `blobs` says
+fn row_values(row: &Row, columns: usize, blobs: BlobEncoding) -> rusqlite::Result<Vec<JsonValue>> {
+    (0..columns)
+        .map(|i| Ok(json_value(row.get(i)?, blobs)))
+        .collect()
+}
 
-    Ok(())
+fn json_value(value: SqliteValue, blobs: BlobEncoding) -> JsonValue {
+    match value {
+        SqliteValue::Null => JsonValue::Null,
+        SqliteValue::Integer(i) => json!(i),
+        SqliteValue::Real(r) => json!(r),
+        SqliteValue::Text(t) => json!(t),
+        SqliteValue::Blob(b) => match blobs {
+            BlobEncoding::Base64 => json!(general_purpose::STANDARD.encode(&b)),
+            BlobEncoding::Hex => json!(b
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()),
+        },
+    }
+}
+
+/// A row as an object keyed by column name
+fn row_object(columns: &[String], values: Vec<JsonValue>) -> JsonValue {
+    let obj = columns.iter().cloned().zip(values).collect();
+    JsonValue::Object(obj)
 }
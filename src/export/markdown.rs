@@ -0,0 +1,60 @@
+use super::{cell_text, ExportOptions, Progress};
+use anyhow::{Context, Result};
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection};
+use std::io::Write;
+
+/// Export query results as a GitHub-style Markdown table, counting each row in
+/// `progress`
+pub fn export_markdown(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    options: &ExportOptions,
+) -> Result<()> {
+    let mut stmt = conn
+        .prepare(sql_query)
+        .context("Failed to prepare SQL statement")?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+
+    let rows = stmt.query_map(params_from_iter(params), |row| {
+        (0..columns.len()).map(|i| row.get(i)).collect()
+    })?;
+    let rows = rows.map(|row| row.context("Failed to read row"));
+    write_markdown(writer, progress, &columns, rows, options)
+}
+
+/// Write `rows` as a Markdown table under a header of `columns`, counting each row in
+/// `progress`
+pub(super) fn write_markdown(
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    columns: &[String],
+    rows: impl Iterator<Item = Result<Vec<SqliteValue>>>,
+    options: &ExportOptions,
+) -> Result<()> {
+    let header: Vec<String> = columns.iter().map(|s| escape(s)).collect();
+    writeln!(writer, "| {} |", header.join(" | "))?;
+    writeln!(writer, "|{}", " --- |".repeat(columns.len()))?;
+
+    for row in rows {
+        let cells: Vec<String> = row?
+            .into_iter()
+            .map(|value| escape(&cell_text(value, &options.null_text)))
+            .collect();
+        writeln!(writer, "| {} |", cells.join(" | "))?;
+        progress.row()?;
+    }
+
+    writer.flush().context("Failed to write Markdown output")?;
+    Ok(())
+}
+
+/// Text that stays inside its cell: pipes escaped and line breaks made spaces
+fn escape(text: &str) -> String {
+    text.replace("\r\n", " ")
+        .replace(['\n', '\r'], " ")
+        .replace('|', "\\|")
+}
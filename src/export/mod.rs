@@ -1,50 +1,528 @@
 mod csv;
+mod diagram;
+mod dump;
+mod html;
 mod json;
+mod markdown;
+mod parquet;
+mod schema;
+mod sql;
+mod xlsx;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::types::Value as SqliteValue;
 use rusqlite::Connection;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
+use std::rc::Rc;
 
 pub use csv::export_csv;
-pub use json::export_json;
+pub use diagram::{export_diagram, DiagramFormat};
+pub use dump::export_dump;
+pub use html::export_html;
+pub use json::{export_json, export_jsonl};
+pub use markdown::export_markdown;
+pub use parquet::export_parquet;
+pub use schema::export_schema;
+pub use sql::export_sql;
+pub use xlsx::export_xlsx;
+
+/// Rows per INSERT statement of a SQL export unless `--batch-size` says otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Rows between progress reports unless `--progress-every` says otherwise
+pub const DEFAULT_PROGRESS_ROWS: usize = 100_000;
 
 /// Export format
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ExportFormat {
     Csv,
+    /// CSV separated by tabs
+    Tsv,
     Json,
+    /// JSON Lines: one object per line
+    Jsonl,
+    /// INSERT statements
+    Sql,
+    /// GitHub-style pipe table
+    Markdown,
+    Html,
+    /// Excel workbook
+    Xlsx,
+    Parquet,
 }
 
-/// Export data to a file
-pub fn export(
-    conn: &Connection,
-    format: ExportFormat,
-    output_path: &Path,
-    table_name: Option<&str>,
-    query: Option<&str>,
-) -> Result<()> {
-    match (table_name, query) {
-        (Some(table), None) => {
-            // Export table
-            let query_str = format!("SELECT * FROM \"{}\"", table.replace('"', "\"\""));
-            export_query(conn, format, output_path, &query_str)
+impl ExportFormat {
+    /// File extension for files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Sql => "sql",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    /// The format a file name's extension asks for, looking past a `.gz` at the end
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let path = if is_gzip_path(path) {
+            Path::new(path.file_stem()?)
+        } else {
+            path
+        };
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "tsv" => Some(ExportFormat::Tsv),
+            "json" => Some(ExportFormat::Json),
+            "jsonl" | "ndjson" => Some(ExportFormat::Jsonl),
+            "sql" => Some(ExportFormat::Sql),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "html" | "htm" => Some(ExportFormat::Html),
+            "xlsx" => Some(ExportFormat::Xlsx),
+            "parquet" => Some(ExportFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// What to export: a whole table, everything a query returns, CREATE statements, or
+/// the whole database
+#[derive(Debug, Clone)]
+pub enum ExportSource {
+    Table(String),
+    /// Rows of `table` picked and ordered by `query`, as the rows view shows them
+    View {
+        table: String,
+        query: String,
+        params: Vec<SqliteValue>,
+    },
+    Query {
+        query: String,
+        /// Values bound to the query's placeholders
+        params: Vec<SqliteValue>,
+    },
+    /// The schema of the whole database, or of one table
+    Schema {
+        table: Option<String>,
+    },
+    /// A dump that rebuilds the database, schema and rows
+    Database,
+}
+
+impl ExportSource {
+    /// What the count in an export's summary is of
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ExportSource::Schema { .. } => "statements",
+            _ => "rows",
+        }
+    }
+}
+
+/// When CSV fields are put in quotes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuoteStyle {
+    Always,
+    /// Only fields holding the delimiter, a quote or a line break
+    Necessary,
+    /// Never, even when that makes the field ambiguous
+    Never,
+}
+
+/// How a Parquet file's column chunks are compressed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParquetCompression {
+    None,
+    Snappy,
+    Gzip,
+    Zstd,
+}
+
+/// How a JSON export lays out its rows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonShape {
+    /// An array of objects keyed by column name
+    Objects,
+    /// An array of arrays, in column order
+    Rows,
+    /// An object holding the columns, the rows as arrays, the row count and the query
+    Envelope,
+}
+
+/// How JSON exports write BLOBs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlobEncoding {
+    Base64,
+    Hex,
+}
+
+/// Settings that only some formats use
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// CSV: byte between fields
+    pub delimiter: u8,
+    /// CSV: when fields are quoted
+    pub quote_style: QuoteStyle,
+    /// CSV: start with a row of column names
+    pub header: bool,
+    /// SQL: start with the table's CREATE TABLE statement
+    pub schema: bool,
+    /// SQL: rows per INSERT statement
+    pub batch_size: usize,
+    /// SQL: table the INSERTs name, instead of the exported table or `query`
+    pub insert_table: Option<String>,
+    /// What a NULL cell holds in text formats
+    pub null_text: String,
+    /// HTML: start with a `<style>` block for borders and padding
+    pub html_style: bool,
+    /// Parquet: how column chunks are compressed
+    pub parquet_compression: ParquetCompression,
+    /// JSON: how the rows are laid out
+    pub json_shape: JsonShape,
+    /// JSON and JSON Lines: how BLOBs are written
+    pub blob_encoding: BlobEncoding,
+    /// Rows between calls to the progress callback
+    pub progress_rows: usize,
+    /// Compress the output with gzip
+    pub gzip: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote_style: QuoteStyle::Necessary,
+            header: true,
+            schema: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+            insert_table: None,
+            null_text: String::new(),
+            html_style: false,
+            parquet_compression: ParquetCompression::Snappy,
+            json_shape: JsonShape::Objects,
+            blob_encoding: BlobEncoding::Base64,
+            progress_rows: DEFAULT_PROGRESS_ROWS,
+            gzip: false,
+        }
+    }
+}
+
+/// How much an export has written so far
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExportProgress {
+    pub rows: usize,
+    pub bytes: u64,
+}
+
+/// What an export wrote in the end
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportSummary {
+    pub rows: usize,
+    pub bytes: u64,
+    /// Values the format couldn't hold as they were, such as cells cut short
+    pub warnings: Vec<String>,
+}
+
+/// What an export fails with when its progress callback asks it to stop
+#[derive(Debug, thiserror::Error)]
+#[error("Export cancelled")]
+pub struct ExportCancelled;
+
+/// Counts the rows an export writes, calling back with how far it has got every
+/// `every` rows. The callback returns false to stop the export.
+pub struct Progress<'a> {
+    rows: usize,
+    every: usize,
+    /// Shared with the CountingWriter under the export's writer
+    bytes: Rc<Cell<u64>>,
+    callback: &'a mut dyn FnMut(ExportProgress) -> bool,
+    warnings: Vec<String>,
+}
+
+impl Progress<'_> {
+    /// Count a written row, failing with ExportCancelled if the callback says to stop
+    pub fn row(&mut self) -> Result<()> {
+        self.rows += 1;
+        if self.rows.is_multiple_of(self.every) {
+            let current = self.current();
+            if !(self.callback)(current) {
+                return Err(ExportCancelled.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Rows written so far
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Note something the summary should mention
+    pub fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    fn current(&self) -> ExportProgress {
+        ExportProgress {
+            rows: self.rows,
+            bytes: self.bytes.get(),
         }
-        (None, Some(q)) => {
-            // Export query results
-            export_query(conn, format, output_path, q)
+    }
+}
+
+/// Where an export's bytes go: straight to its writer, or through gzip first
+enum Sink {
+    Plain(CountingWriter<Box<dyn Write>>),
+    Gzip(Box<GzEncoder<CountingWriter<Box<dyn Write>>>>),
+}
+
+impl Sink {
+    /// Flush everything through, ending the gzip stream with its trailer
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Sink::Plain(mut writer) => writer.flush(),
+            Sink::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(writer) => writer.write(buf),
+            Sink::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(writer) => writer.flush(),
+            Sink::Gzip(encoder) => encoder.flush(),
         }
-        _ => Err(anyhow::anyhow!("Must specify either --table or --query")),
     }
 }
 
-fn export_query(
+/// Passes writes through, adding the bytes written to a count shared with Progress
+struct CountingWriter<W> {
+    inner: W,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes.set(self.bytes.get() + written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A value as the text of a cell: NULL as `null_text` and a BLOB by its size
+fn cell_text(value: SqliteValue, null_text: &str) -> String {
+    match value {
+        SqliteValue::Null => null_text.to_string(),
+        SqliteValue::Integer(i) => i.to_string(),
+        SqliteValue::Real(r) => r.to_string(),
+        SqliteValue::Text(t) => t,
+        SqliteValue::Blob(b) => format!("<BLOB {} bytes>", b.len()),
+    }
+}
+
+/// An identifier in double quotes, any inside doubled
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Export the rows of `source` to `writer`, returning how much was written, compressed
+/// when `options.gzip` is set. `on_progress` hears how far it has got every
+/// `options.progress_rows` rows and returns false to stop the export with
+/// ExportCancelled.
+pub fn export_source(
+    conn: &Connection,
+    format: ExportFormat,
+    writer: Box<dyn Write>,
+    source: &ExportSource,
+    options: &ExportOptions,
+    on_progress: &mut dyn FnMut(ExportProgress) -> bool,
+) -> Result<ExportSummary> {
+    if matches!(source, ExportSource::Schema { .. } | ExportSource::Database)
+        && format != ExportFormat::Sql
+    {
+        bail!("A schema export or dump is written as SQL");
+    }
+    let bytes = Rc::new(Cell::new(0));
+    let counter = CountingWriter {
+        inner: writer,
+        bytes: Rc::clone(&bytes),
+    };
+    let sink = if options.gzip {
+        Sink::Gzip(Box::new(GzEncoder::new(counter, Compression::default())))
+    } else {
+        Sink::Plain(counter)
+    };
+    let mut buffered = BufWriter::new(sink);
+    let writer: &mut dyn Write = &mut buffered;
+    let mut progress = Progress {
+        rows: 0,
+        every: options.progress_rows.max(1),
+        bytes,
+        callback: on_progress,
+        warnings: Vec::new(),
+    };
+    let progress = &mut progress;
+    let result = match source {
+        ExportSource::Table(table) => {
+            let query = format!("SELECT * FROM {}", quote_identifier(table));
+            export_rows(conn, format, writer, progress, &query, &[], table, options)
+        }
+        ExportSource::View {
+            table,
+            query,
+            params,
+        } => export_rows(
+            conn, format, writer, progress, query, params, table, options,
+        ),
+        ExportSource::Query { query, params } => export_rows(
+            conn, format, writer, progress, query, params, "query", options,
+        ),
+        ExportSource::Schema { table } => export_schema(conn, writer, progress, table.as_deref()),
+        ExportSource::Database => export_dump(conn, writer, progress),
+    };
+
+    // Finish even after a failure, so a gzip file holds a complete stream of what was
+    // written rather than a truncated one
+    let finished = buffered
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(Sink::finish);
+    result?;
+    finished.context("Failed to finish writing the output")?;
+    Ok(ExportSummary {
+        rows: progress.rows,
+        bytes: progress.bytes.get(),
+        warnings: std::mem::take(&mut progress.warnings),
+    })
+}
+
+/// Write what `query` returns in `format`, `table` naming the rows where the format
+/// needs a name
+#[allow(clippy::too_many_arguments)]
+fn export_rows(
     conn: &Connection,
     format: ExportFormat,
-    output_path: &Path,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
     query: &str,
+    params: &[SqliteValue],
+    table: &str,
+    options: &ExportOptions,
 ) -> Result<()> {
     match format {
-        ExportFormat::Csv => export_csv(conn, output_path, query),
-        ExportFormat::Json => export_json(conn, output_path, query),
+        ExportFormat::Csv => export_csv(conn, writer, progress, query, params, options),
+        ExportFormat::Tsv => {
+            let options = ExportOptions {
+                delimiter: b'\t',
+                ..options.clone()
+            };
+            export_csv(conn, writer, progress, query, params, &options)
+        }
+        ExportFormat::Json => export_json(conn, writer, progress, query, params, options),
+        ExportFormat::Jsonl => export_jsonl(conn, writer, progress, query, params, options),
+        ExportFormat::Markdown => export_markdown(conn, writer, progress, query, params, options),
+        ExportFormat::Html => export_html(conn, writer, progress, query, params, options),
+        ExportFormat::Xlsx => export_xlsx(conn, writer, progress, query, params, table),
+        ExportFormat::Parquet => export_parquet(
+            conn,
+            writer,
+            progress,
+            query,
+            params,
+            options.parquet_compression,
+        ),
+        ExportFormat::Sql => {
+            let table = options.insert_table.as_deref().unwrap_or(table);
+            export_sql(conn, writer, progress, query, params, table, options)
+        }
     }
 }
+
+/// Rows already in memory as CSV, TSV or Markdown text, written the way an export
+/// of them would be
+pub fn rows_text(
+    format: ExportFormat,
+    columns: &[String],
+    rows: impl IntoIterator<Item = Vec<SqliteValue>>,
+    options: &ExportOptions,
+) -> Result<String> {
+    let mut buffer = Vec::new();
+    let mut keep_going = |_| true;
+    let mut progress = Progress {
+        rows: 0,
+        every: usize::MAX,
+        bytes: Rc::new(Cell::new(0)),
+        callback: &mut keep_going,
+        warnings: Vec::new(),
+    };
+    let rows = rows.into_iter().map(Ok);
+    match format {
+        ExportFormat::Csv => csv::write_csv(&mut buffer, &mut progress, columns, rows, options)?,
+        ExportFormat::Tsv => {
+            let options = ExportOptions {
+                delimiter: b'\t',
+                ..options.clone()
+            };
+            csv::write_csv(&mut buffer, &mut progress, columns, rows, &options)?
+        }
+        ExportFormat::Markdown => {
+            markdown::write_markdown(&mut buffer, &mut progress, columns, rows, options)?
+        }
+        _ => bail!("Rows can't be copied as {}", format.extension()),
+    }
+    String::from_utf8(buffer).context("The rows aren't valid UTF-8")
+}
+
+/// Whether `path` ends in `.gz`, asking for gzip-compressed output
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+/// A new file at `path` to export into
+pub fn create_file(path: &Path) -> Result<Box<dyn Write>> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create output file: {}", path.display()))?;
+    Ok(Box::new(file))
+}
+
+/// Whether `error` came from writing to a pipe whose reader has gone, as when the
+/// output is piped into `head`
+pub fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let kind = if let Some(err) = cause.downcast_ref::<io::Error>() {
+            Some(err.kind())
+        } else if let Some(err) = cause.downcast_ref::<serde_json::Error>() {
+            err.io_error_kind()
+        } else if let Some(err) = cause.downcast_ref::<::csv::Error>() {
+            match err.kind() {
+                ::csv::ErrorKind::Io(err) => Some(err.kind()),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        kind == Some(io::ErrorKind::BrokenPipe)
+    })
+}
@@ -0,0 +1,229 @@
+use super::{cell_text, ParquetCompression, Progress};
+use anyhow::{bail, Context, Result};
+use arrow_array::builder::{BinaryBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection};
+use std::io::Write;
+use std::sync::Arc;
+
+/// Rows per record batch handed to the Parquet writer
+const BATCH_ROWS: usize = 65_536;
+
+/// Export query results as a Parquet file, counting each row in `progress`. A first
+/// pass over the rows finds the types each column holds: a column of one type gets the
+/// matching Arrow type, one mixing types is written as text with a warning, and one of
+/// only NULLs follows its declared type.
+pub fn export_parquet(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    compression: ParquetCompression,
+) -> Result<()> {
+    let mut stmt = conn
+        .prepare(sql_query)
+        .context("Failed to prepare SQL statement")?;
+    let columns = stmt.columns();
+    let value_types = column_value_types(conn, sql_query, params, columns.len())?;
+
+    let mut fields = Vec::with_capacity(columns.len());
+    for (column, types) in columns.iter().zip(&value_types) {
+        let types: Vec<&str> = types.iter().map(String::as_str).collect();
+        let data_type = match types.as_slice() {
+            [] => declared_type(column.decl_type()),
+            ["integer"] => DataType::Int64,
+            ["real"] | ["integer", "real"] => DataType::Float64,
+            ["text"] => DataType::Utf8,
+            ["blob"] => DataType::Binary,
+            _ => {
+                progress.warn(format!(
+                    "column {} mixes {} values, written as text",
+                    column.name(),
+                    types.join(", ")
+                ));
+                DataType::Utf8
+            }
+        };
+        fields.push(Field::new(column.name(), data_type, true));
+    }
+    let schema = Arc::new(Schema::new(fields));
+
+    let properties = WriterProperties::builder()
+        .set_compression(match compression {
+            ParquetCompression::None => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        })
+        .build();
+    // The writer wants a Send writer, so it writes to a buffer that's drained as row
+    // groups fill up
+    let mut parquet = ArrowWriter::try_new(Vec::new(), Arc::clone(&schema), Some(properties))
+        .context("Failed to start the Parquet file")?;
+
+    let mut builders: Vec<ColumnBuilder> = schema
+        .fields()
+        .iter()
+        .map(|field| ColumnBuilder::new(field.data_type()))
+        .collect();
+    let mut batch_rows = 0;
+    let mut rows = stmt.query(params_from_iter(params))?;
+    while let Some(row) = rows.next().context("Failed to read row")? {
+        for (i, builder) in builders.iter_mut().enumerate() {
+            let value: SqliteValue = row.get(i).context("Failed to read row")?;
+            if !builder.append(value) {
+                bail!(
+                    "Column {} changed type during the export",
+                    schema.field(i).name()
+                );
+            }
+        }
+        progress.row()?;
+        batch_rows += 1;
+        if batch_rows == BATCH_ROWS {
+            write_batch(&mut parquet, &schema, &mut builders, writer)?;
+            batch_rows = 0;
+        }
+    }
+    if batch_rows > 0 {
+        write_batch(&mut parquet, &schema, &mut builders, writer)?;
+    }
+
+    parquet
+        .finish()
+        .context("Failed to finish the Parquet file")?;
+    writer
+        .write_all(&std::mem::take(parquet.inner_mut()))
+        .context("Failed to write Parquet output")?;
+    writer.flush().context("Failed to write Parquet output")?;
+    Ok(())
+}
+
+/// Hand the built rows to the Parquet writer as a batch, passing on whatever it has
+/// written so far
+fn write_batch(
+    parquet: &mut ArrowWriter<Vec<u8>>,
+    schema: &Arc<Schema>,
+    builders: &mut [ColumnBuilder],
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let arrays = builders.iter_mut().map(ColumnBuilder::finish).collect();
+    let batch = RecordBatch::try_new(Arc::clone(schema), arrays)
+        .context("Failed to build a record batch")?;
+    parquet
+        .write(&batch)
+        .context("Failed to write Parquet output")?;
+    writer
+        .write_all(&std::mem::take(parquet.inner_mut()))
+        .context("Failed to write Parquet output")?;
+    Ok(())
+}
+
+/// The storage classes (`typeof`) of each column's non-NULL values, sorted
+fn column_value_types(
+    conn: &Connection,
+    sql_query: &str,
+    params: &[SqliteValue],
+    column_count: usize,
+) -> Result<Vec<Vec<String>>> {
+    if column_count == 0 {
+        return Ok(Vec::new());
+    }
+    // Naming the columns in the CTE copes with queries that repeat a column name
+    let names: Vec<String> = (0..column_count).map(|i| format!("c{}", i)).collect();
+    let types: Vec<String> = names
+        .iter()
+        .map(|name| format!("group_concat(DISTINCT typeof({}))", name))
+        .collect();
+    let query = sql_query.trim_end().trim_end_matches(';');
+    let sql = format!(
+        "WITH q({}) AS (\n{}\n) SELECT {} FROM q",
+        names.join(", "),
+        query,
+        types.join(", ")
+    );
+    conn.query_row(&sql, params_from_iter(params), |row| {
+        (0..column_count)
+            .map(|i| {
+                let types: Option<String> = row.get(i)?;
+                let mut types: Vec<String> = types
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|t| !t.is_empty() && *t != "null")
+                    .map(str::to_string)
+                    .collect();
+                types.sort();
+                Ok(types)
+            })
+            .collect()
+    })
+    .context("Failed to find the columns' types")
+}
+
+/// The Arrow type for a declared column type, by SQLite's affinity rules
+fn declared_type(decl_type: Option<&str>) -> DataType {
+    let decl = decl_type.unwrap_or_default().to_ascii_uppercase();
+    if decl.contains("INT") {
+        DataType::Int64
+    } else if decl.contains("CHAR") || decl.contains("CLOB") || decl.contains("TEXT") {
+        DataType::Utf8
+    } else if decl.contains("BLOB") {
+        DataType::Binary
+    } else if decl.contains("REAL") || decl.contains("FLOA") || decl.contains("DOUB") {
+        DataType::Float64
+    } else {
+        DataType::Utf8
+    }
+}
+
+/// Builds one column of a record batch
+enum ColumnBuilder {
+    Int64(Int64Builder),
+    Float64(Float64Builder),
+    Utf8(StringBuilder),
+    Binary(BinaryBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Int64 => ColumnBuilder::Int64(Int64Builder::new()),
+            DataType::Float64 => ColumnBuilder::Float64(Float64Builder::new()),
+            DataType::Binary => ColumnBuilder::Binary(BinaryBuilder::new()),
+            _ => ColumnBuilder::Utf8(StringBuilder::new()),
+        }
+    }
+
+    /// Add a value, or return false when the column's type can't hold it
+    fn append(&mut self, value: SqliteValue) -> bool {
+        match (self, value) {
+            (ColumnBuilder::Int64(b), SqliteValue::Null) => b.append_null(),
+            (ColumnBuilder::Float64(b), SqliteValue::Null) => b.append_null(),
+            (ColumnBuilder::Utf8(b), SqliteValue::Null) => b.append_null(),
+            (ColumnBuilder::Binary(b), SqliteValue::Null) => b.append_null(),
+            (ColumnBuilder::Int64(b), SqliteValue::Integer(i)) => b.append_value(i),
+            (ColumnBuilder::Float64(b), SqliteValue::Integer(i)) => b.append_value(i as f64),
+            (ColumnBuilder::Float64(b), SqliteValue::Real(r)) => b.append_value(r),
+            (ColumnBuilder::Binary(b), SqliteValue::Blob(bytes)) => b.append_value(bytes),
+            // A text column holds whatever a column of mixed types had
+            (ColumnBuilder::Utf8(b), value) => b.append_value(cell_text(value, "")),
+            _ => return false,
+        }
+        true
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Int64(b) => Arc::new(b.finish()),
+            ColumnBuilder::Float64(b) => Arc::new(b.finish()),
+            ColumnBuilder::Utf8(b) => Arc::new(b.finish()),
+            ColumnBuilder::Binary(b) => Arc::new(b.finish()),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use super::Progress;
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+use std::io::Write;
+
+/// Write the CREATE statements of every table, view, index and trigger, or of just
+/// `table` and its indexes and triggers, counting each statement in `progress`. Tables
+/// come first and triggers last so the output runs as it stands. SQLite's internal
+/// objects and the shadow tables behind virtual tables are left out.
+pub fn export_schema(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    table: Option<&str>,
+) -> Result<()> {
+    if let Some(table) = table {
+        let exists = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table],
+                |_| Ok(()),
+            )
+            .optional()
+            .context("Failed to read the schema")?;
+        if exists.is_none() {
+            bail!("No table named {}", table);
+        }
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT sql FROM sqlite_master
+             WHERE sql IS NOT NULL
+               AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+               AND name NOT IN (SELECT name FROM pragma_table_list WHERE type = 'shadow')
+               AND (?1 IS NULL OR tbl_name = ?1)
+             ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'view' THEN 1
+                                WHEN 'index' THEN 2 ELSE 3 END, rowid",
+        )
+        .context("Failed to read the schema")?;
+    let mut rows = stmt.query([table])?;
+    while let Some(row) = rows.next().context("Failed to read the schema")? {
+        let sql: String = row.get(0)?;
+        writeln!(writer, "{};", sql)?;
+        progress.row()?;
+    }
+
+    writer.flush().context("Failed to write SQL output")?;
+    Ok(())
+}
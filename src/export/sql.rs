@@ -0,0 +1,104 @@
+use super::{quote_identifier, ExportOptions, Progress};
+use anyhow::{bail, Context, Result};
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use std::io::Write;
+
+/// Export query results as INSERT statements into `table_name`, counting each row in
+/// `progress`. Rows are grouped `options.batch_size` to a statement, all inside one
+/// transaction, and the table's CREATE TABLE comes first when `options.schema` is set.
+pub fn export_sql(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    table_name: &str,
+    options: &ExportOptions,
+) -> Result<()> {
+    let create_sql = if options.schema {
+        let sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table_name],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to read the table's schema")?;
+        match sql {
+            Some(sql) => Some(sql),
+            None => bail!("No CREATE TABLE statement for {}", table_name),
+        }
+    } else {
+        None
+    };
+
+    let mut stmt = conn
+        .prepare(sql_query)
+        .context("Failed to prepare SQL statement")?;
+    let columns = stmt
+        .column_names()
+        .iter()
+        .map(|name| quote_identifier(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let insert = format!(
+        "INSERT INTO {} ({}) VALUES",
+        quote_identifier(table_name),
+        columns
+    );
+
+    writeln!(writer, "BEGIN TRANSACTION;")?;
+    if let Some(create_sql) = create_sql {
+        writeln!(writer, "{};", create_sql)?;
+    }
+
+    let batch_size = options.batch_size.max(1);
+    let mut rows = stmt.query(params_from_iter(params))?;
+    while let Some(row) = rows.next().context("Failed to read row")? {
+        let values = (0..row.as_ref().column_count())
+            .map(|i| {
+                row.get::<_, SqliteValue>(i)
+                    .map(|value| sql_literal(&value))
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read row")?;
+        // Each batch starts its own INSERT, ending the one before
+        if progress.rows().is_multiple_of(batch_size) {
+            if progress.rows() > 0 {
+                writeln!(writer, ";")?;
+            }
+            writeln!(writer, "{}", insert)?;
+        } else {
+            writeln!(writer, ",")?;
+        }
+        write!(writer, "  ({})", values.join(", "))?;
+        progress.row()?;
+    }
+    if progress.rows() > 0 {
+        writeln!(writer, ";")?;
+    }
+    writeln!(writer, "COMMIT;")?;
+
+    writer.flush().context("Failed to write SQL output")?;
+    Ok(())
+}
+
+/// A value as an SQL literal that reads back as the same value and type
+pub(super) fn sql_literal(value: &SqliteValue) -> String {
+    match value {
+        SqliteValue::Null => "NULL".to_string(),
+        SqliteValue::Integer(i) => i.to_string(),
+        // Debug formatting keeps the `.0` that stops a whole number reading back as INTEGER
+        SqliteValue::Real(r) if r.is_finite() => format!("{:?}", r),
+        // SQLite reads out-of-range literals as infinity; NaN is stored as NULL anyway
+        SqliteValue::Real(r) if r.is_nan() => "NULL".to_string(),
+        SqliteValue::Real(r) if *r > 0.0 => "9e999".to_string(),
+        SqliteValue::Real(_) => "-9e999".to_string(),
+        SqliteValue::Text(t) => format!("'{}'", t.replace('\'', "''")),
+        SqliteValue::Blob(b) => {
+            let hex: String = b.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("X'{}'", hex)
+        }
+    }
+}
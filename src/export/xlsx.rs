@@ -0,0 +1,197 @@
+use super::{cell_text, Progress};
+use anyhow::{bail, Context, Result};
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection};
+use rust_xlsxwriter::{Format, Workbook};
+use std::io::Write;
+
+/// Most characters Excel holds in a cell
+const MAX_CELL_CHARS: usize = 32_767;
+
+/// Most rows in a worksheet, the header included
+const MAX_ROWS: u32 = 1_048_576;
+
+/// Export query results as an Excel workbook with one worksheet named after
+/// `sheet_name`, counting each row in `progress`. The header row is bold, numbers stay
+/// numbers and NULLs are blank cells. Text too long for a cell is cut short with a
+/// warning.
+pub fn export_xlsx(
+    conn: &Connection,
+    writer: &mut dyn Write,
+    progress: &mut Progress,
+    sql_query: &str,
+    params: &[SqliteValue],
+    sheet_name: &str,
+) -> Result<()> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(valid_sheet_name(sheet_name))?;
+
+    let mut stmt = conn
+        .prepare(sql_query)
+        .context("Failed to prepare SQL statement")?;
+    let bold = Format::new().set_bold();
+    for (col, name) in stmt.column_names().iter().enumerate() {
+        worksheet.write_string_with_format(0, col as u16, *name, &bold)?;
+    }
+    let column_count = stmt.column_count();
+
+    let mut truncated = 0;
+    let mut rows = stmt.query(params_from_iter(params))?;
+    while let Some(row) = rows.next().context("Failed to read row")? {
+        let row_num = progress.rows() as u32 + 1;
+        if row_num >= MAX_ROWS {
+            bail!(
+                "An Excel worksheet holds at most {} rows below its header",
+                MAX_ROWS - 1
+            );
+        }
+        for col in 0..column_count {
+            let value: SqliteValue = row.get(col).context("Failed to read row")?;
+            let col = col as u16;
+            match value {
+                SqliteValue::Null => {}
+                SqliteValue::Integer(i) => {
+                    worksheet.write_number(row_num, col, i as f64)?;
+                }
+                SqliteValue::Real(r) if r.is_finite() => {
+                    worksheet.write_number(row_num, col, r)?;
+                }
+                SqliteValue::Text(text) if text.chars().count() > MAX_CELL_CHARS => {
+                    let cut: String = text.chars().take(MAX_CELL_CHARS).collect();
+                    worksheet.write_string(row_num, col, cut)?;
+                    truncated += 1;
+                }
+                // Text, BLOBs by their size, and infinities, which Excel has no number for
+                value => {
+                    worksheet.write_string(row_num, col, cell_text(value, ""))?;
+                }
+            }
+        }
+        progress.row()?;
+    }
+    if truncated > 0 {
+        progress.warn(format!(
+            "{} cell(s) cut to Excel's limit of {} characters",
+            truncated, MAX_CELL_CHARS
+        ));
+    }
+
+    // The workbook is a zip archive, which can't be written a row at a time
+    let buffer = workbook
+        .save_to_buffer()
+        .context("Failed to build the workbook")?;
+    writer
+        .write_all(&buffer)
+        .context("Failed to write XLSX output")?;
+    writer.flush().context("Failed to write XLSX output")?;
+    Ok(())
+}
+
+/// `name` as a worksheet name Excel accepts: at most 31 characters, none of `[]:*?/\`,
+/// and not starting or ending with `'`
+fn valid_sheet_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .take(31)
+        .collect();
+    let name = name.trim_matches('\'');
+    if name.is_empty() {
+        "Sheet1".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{open_workbook_from_rs, Data, Reader, Xlsx};
+    use std::cell::Cell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    /// The workbook `export_xlsx` writes for `sql_query`, read back as its sheet names
+    /// and cells, with the warnings it gave
+    fn round_trip(
+        conn: &Connection,
+        sql_query: &str,
+    ) -> (Vec<String>, Vec<Vec<Data>>, Vec<String>) {
+        let mut buffer = Vec::new();
+        let mut keep_going = |_| true;
+        let mut progress = Progress {
+            rows: 0,
+            every: usize::MAX,
+            bytes: Rc::new(Cell::new(0)),
+            callback: &mut keep_going,
+            warnings: Vec::new(),
+        };
+        export_xlsx(
+            conn,
+            &mut buffer,
+            &mut progress,
+            sql_query,
+            &[],
+            "items/2024",
+        )
+        .unwrap();
+
+        let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(buffer)).unwrap();
+        let sheets = workbook.sheet_names();
+        let range = workbook.worksheet_range(&sheets[0]).unwrap();
+        let cells = range.rows().map(|row| row.to_vec()).collect();
+        (sheets, cells, progress.warnings)
+    }
+
+    #[test]
+    fn cells_keep_their_values_and_types() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (id INTEGER, price REAL, name TEXT, note TEXT, data BLOB);
+             INSERT INTO items VALUES (1, 2.5, 'apple', NULL, x'0102');
+             INSERT INTO items VALUES (-7, 1e300, '007', 'a\"b', NULL);",
+        )
+        .unwrap();
+
+        let (sheets, cells, warnings) = round_trip(&conn, "SELECT * FROM items");
+        assert_eq!(sheets, ["items_2024"]);
+        assert_eq!(
+            cells,
+            [
+                ["id", "price", "name", "note", "data"]
+                    .map(|name| Data::String(name.to_string()))
+                    .to_vec(),
+                vec![
+                    Data::Float(1.0),
+                    Data::Float(2.5),
+                    Data::String("apple".to_string()),
+                    Data::Empty,
+                    Data::String(cell_text(SqliteValue::Blob(vec![1, 2]), "")),
+                ],
+                vec![
+                    Data::Float(-7.0),
+                    Data::Float(1e300),
+                    Data::String("007".to_string()),
+                    Data::String("a\"b".to_string()),
+                    Data::Empty,
+                ],
+            ]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn text_too_long_for_a_cell_is_cut_with_a_warning() {
+        let conn = Connection::open_in_memory().unwrap();
+        let (_, cells, warnings) = round_trip(
+            &conn,
+            &format!("SELECT '{}' AS long", "é".repeat(MAX_CELL_CHARS + 10)),
+        );
+        assert_eq!(cells[1], [Data::String("é".repeat(MAX_CELL_CHARS))]);
+        assert_eq!(warnings.len(), 1);
+    }
+}
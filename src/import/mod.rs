@@ -0,0 +1,351 @@
+use crate::db::TableRef;
+use crate::export::quote_identifier;
+use anyhow::{bail, Context, Result};
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{params_from_iter, Connection};
+use std::io::Read;
+
+/// Rows per INSERT statement of an import unless `--batch-size` says otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Most skipped rows whose errors an import keeps to report
+const MAX_ERRORS: usize = 10;
+
+/// Most values SQLite binds in one statement
+const MAX_VARIABLES: usize = 32_766;
+
+/// How a CSV file is read and where its rows go
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub delimiter: u8,
+    /// The first row names the columns; without it, fields fill columns in order
+    pub header: bool,
+    /// Create the table if it doesn't exist
+    pub create: bool,
+    /// Declared types of a created table's columns, by name; the rest are TEXT
+    pub types: Vec<(String, String)>,
+    /// Skip rows that fail rather than stopping and importing nothing
+    pub skip_errors: bool,
+    pub batch_size: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            create: false,
+            types: Vec::new(),
+            skip_errors: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+/// What an import did
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    pub skipped: usize,
+    /// The table didn't exist and was created
+    pub created: bool,
+    /// Why the first skipped rows were skipped
+    pub errors: Vec<String>,
+}
+
+/// A column's type affinity, which decides how its fields are stored
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Affinity {
+    Integer,
+    Real,
+    Numeric,
+    Text,
+    Blob,
+}
+
+impl Affinity {
+    /// The affinity SQLite gives a declared type
+    fn of(declared: &str) -> Self {
+        let declared = declared.to_ascii_uppercase();
+        if declared.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|t| declared.contains(t))
+        {
+            Affinity::Text
+        } else if declared.is_empty() || declared.contains("BLOB") {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|t| declared.contains(t))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    /// A field as the value to store: numbers where the column holds numbers, with
+    /// empty fields NULL there, and text otherwise
+    fn value(self, field: &str) -> SqliteValue {
+        match self {
+            Affinity::Text | Affinity::Blob => SqliteValue::Text(field.to_string()),
+            _ if field.trim().is_empty() => SqliteValue::Null,
+            Affinity::Integer | Affinity::Numeric => {
+                if let Ok(i) = field.trim().parse() {
+                    SqliteValue::Integer(i)
+                } else {
+                    parse_real(field)
+                        .map_or_else(|| SqliteValue::Text(field.to_string()), SqliteValue::Real)
+                }
+            }
+            Affinity::Real => parse_real(field)
+                .map_or_else(|| SqliteValue::Text(field.to_string()), SqliteValue::Real),
+        }
+    }
+}
+
+/// A field written as a decimal number; words Rust would read as numbers, such as
+/// `inf` and `NaN`, stay text
+fn parse_real(field: &str) -> Option<f64> {
+    let field = field.trim();
+    field
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+        .then(|| field.parse().ok())
+        .flatten()
+}
+
+/// Insert the rows of a CSV file into `table`, all in one transaction. With a header
+/// row, its names pick the columns; without one, fields fill the table's columns in
+/// order. A row that fails stops the import with nothing written, unless
+/// `options.skip_errors` is set, when it's counted and left out.
+pub fn import_csv(
+    conn: &Connection,
+    reader: Box<dyn Read>,
+    table: &str,
+    options: &ImportOptions,
+) -> Result<ImportSummary> {
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(options.header)
+        .flexible(true)
+        .from_reader(reader);
+    let header: Option<Vec<String>> = if options.header {
+        let header = csv_reader
+            .headers()
+            .context("Failed to read the header row")?;
+        Some(header.iter().map(str::to_string).collect())
+    } else {
+        None
+    };
+    let mut records = csv_reader.into_records().peekable();
+    let width = match (&header, records.peek()) {
+        (Some(header), _) => header.len(),
+        (None, Some(Ok(record))) => record.len(),
+        (None, _) => 0,
+    };
+    if width == 0 {
+        bail!("The file has no columns to import");
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to begin transaction")?;
+    let mut summary = ImportSummary::default();
+    let target = TableRef::resolve(&tx, table);
+    let mut columns = table_columns(&tx, &target)?;
+    if columns.is_empty() {
+        if !options.create {
+            bail!("No table named {} (--create makes it)", table);
+        }
+        let names: Vec<String> = match &header {
+            Some(header) => header.clone(),
+            None => (1..=width).map(|i| format!("c{}", i)).collect(),
+        };
+        columns = create_table(&tx, &target, &names, &options.types)?;
+        summary.created = true;
+    }
+
+    // The table column each field goes to
+    let targets: Vec<usize> = match &header {
+        Some(header) => header
+            .iter()
+            .map(|name| {
+                columns
+                    .iter()
+                    .position(|(column, _)| column.eq_ignore_ascii_case(name))
+                    .with_context(|| format!("Table {} has no column named {}", table, name))
+            })
+            .collect::<Result<_>>()?,
+        None if width > columns.len() => bail!(
+            "Rows have {} fields but table {} has {} columns",
+            width,
+            table,
+            columns.len()
+        ),
+        None => (0..width).collect(),
+    };
+    let affinities: Vec<Affinity> = targets
+        .iter()
+        .map(|&i| Affinity::of(&columns[i].1))
+        .collect();
+    let insert = format!(
+        "INSERT INTO {} ({}) VALUES ",
+        target.quoted(),
+        targets
+            .iter()
+            .map(|&i| quote_identifier(&columns[i].0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let batch_rows = options.batch_size.clamp(1, (MAX_VARIABLES / width).max(1));
+    let mut batch: Vec<(u64, Vec<SqliteValue>)> = Vec::with_capacity(batch_rows);
+    for record in records {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                skip_row(&mut summary, e.to_string(), options.skip_errors)?;
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+        if record.len() != width {
+            let message = format!(
+                "line {}: has {} fields rather than {}",
+                line,
+                record.len(),
+                width
+            );
+            skip_row(&mut summary, message, options.skip_errors)?;
+            continue;
+        }
+        let values = record
+            .iter()
+            .zip(&affinities)
+            .map(|(field, affinity)| affinity.value(field))
+            .collect();
+        batch.push((line, values));
+        if batch.len() == batch_rows {
+            insert_batch(&tx, &insert, width, &mut batch, &mut summary, options)?;
+        }
+    }
+    insert_batch(&tx, &insert, width, &mut batch, &mut summary, options)?;
+
+    tx.commit().context("Failed to commit the import")?;
+    Ok(summary)
+}
+
+/// Insert the batched rows with one statement. If that fails, they're inserted one at
+/// a time to find which rows are at fault.
+fn insert_batch(
+    conn: &Connection,
+    insert: &str,
+    width: usize,
+    batch: &mut Vec<(u64, Vec<SqliteValue>)>,
+    summary: &mut ImportSummary,
+    options: &ImportOptions,
+) -> Result<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let sql = values_sql(insert, width, batch.len());
+    let params = batch.iter().flat_map(|(_, values)| values);
+    let inserted = conn
+        .prepare_cached(&sql)
+        .and_then(|mut stmt| stmt.execute(params_from_iter(params)));
+    if inserted.is_ok() {
+        summary.inserted += batch.len();
+    } else {
+        let mut stmt = conn
+            .prepare_cached(&values_sql(insert, width, 1))
+            .context("Failed to prepare the INSERT statement")?;
+        for (line, values) in batch.iter() {
+            match stmt.execute(params_from_iter(values)) {
+                Ok(_) => summary.inserted += 1,
+                Err(e) => skip_row(
+                    summary,
+                    format!("line {}: {}", line, e),
+                    options.skip_errors,
+                )?,
+            }
+        }
+    }
+    batch.clear();
+    Ok(())
+}
+
+/// `insert` followed by placeholders for `rows` rows of `width` values
+fn values_sql(insert: &str, width: usize, rows: usize) -> String {
+    let row = format!("({})", vec!["?"; width].join(", "));
+    format!("{}{}", insert, vec![row; rows].join(", "))
+}
+
+/// Count a row that couldn't be imported, or fail the import unless `skip_errors`
+fn skip_row(summary: &mut ImportSummary, message: String, skip_errors: bool) -> Result<()> {
+    if !skip_errors {
+        bail!("{}; nothing was imported", message);
+    }
+    summary.skipped += 1;
+    if summary.errors.len() < MAX_ERRORS {
+        summary.errors.push(message);
+    }
+    Ok(())
+}
+
+/// Name and declared type of each column a row can be inserted into; none if the
+/// table doesn't exist
+fn table_columns(conn: &Connection, table: &TableRef) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn
+        .prepare("SELECT name, type FROM pragma_table_xinfo(?1, ?2) WHERE hidden = 0")
+        .context("Failed to read the table's columns")?;
+    let columns = stmt
+        .query_map([&table.name, table.schema_name()], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<_>>()
+        .context("Failed to read the table's columns")?;
+    Ok(columns)
+}
+
+/// Create `table` with a column for each name, TEXT unless `types` says otherwise,
+/// returning its columns
+fn create_table(
+    conn: &Connection,
+    table: &TableRef,
+    names: &[String],
+    types: &[(String, String)],
+) -> Result<Vec<(String, String)>> {
+    if let Some((name, _)) = types
+        .iter()
+        .find(|(name, _)| !names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+    {
+        bail!("--types names {}, which isn't one of the columns", name);
+    }
+    let columns: Vec<(String, String)> = names
+        .iter()
+        .map(|name| {
+            let declared = types
+                .iter()
+                .find(|(column, _)| column.eq_ignore_ascii_case(name))
+                .map_or("TEXT", |(_, declared)| declared.as_str());
+            (name.clone(), declared.to_string())
+        })
+        .collect();
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|(name, declared)| format!("{} {}", quote_identifier(name), declared))
+        .collect();
+    conn.execute(
+        &format!(
+            "CREATE TABLE {} ({})",
+            table.quoted(),
+            definitions.join(", ")
+        ),
+        [],
+    )
+    .with_context(|| format!("Failed to create table {}", table.name))?;
+    Ok(columns)
+}
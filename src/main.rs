@@ -1,28 +1,50 @@
 mod app;
+mod completions;
 mod db;
 mod export;
+mod import;
 mod types;
 mod ui;
 mod worker;
 
 use anyhow::{Context, Result};
-use app::App;
-use clap::{Parser, Subcommand};
+use app::history::History;
+use app::saved_queries::SavedQueries;
+use app::{App, StartupIntent, ViewMode};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use completions::{complete_tables, write_completions, COMPLETE_TABLES};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use db::Database;
-use export::{export, ExportFormat};
+use db::diff::{diff_databases, DiffOptions};
+use db::query::execute_query;
+use db::{
+    backup_to, check_database, format_sql_error, BackupCancelled, BackupProgress, Database,
+    StdinDatabase,
+};
+use export::{
+    create_file, export_diagram, export_source, is_broken_pipe, is_gzip_path, BlobEncoding,
+    DiagramFormat, ExportCancelled, ExportFormat, ExportOptions, ExportProgress, ExportSource,
+    JsonShape, ParquetCompression, QuoteStyle, DEFAULT_BATCH_SIZE, DEFAULT_PROGRESS_ROWS,
+};
+use import::{import_csv, ImportOptions};
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use types::{DiagramData, QueryResult, Value};
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Parser)]
 #[command(name = "sqr")]
 #[command(about = "A fast, keyboard-first TUI for exploring SQLite databases")]
 struct Cli {
-    /// Database file path
+    /// Database file path, `:memory:` for an empty database in memory, or a `file:` URI
     #[arg(value_name = "DATABASE")]
     database: Option<String>,
 
@@ -30,10 +52,85 @@ struct Cli {
     #[arg(long)]
     read_write: bool,
 
+    /// Create the database file if it doesn't exist; implies --read-write
+    #[arg(long)]
+    create: bool,
+
     /// Number of rows per page
     #[arg(long, default_value = "100")]
     page_size: usize,
 
+    /// Number of rows per page of query results from the SQL editor
+    #[arg(long, default_value = "1000")]
+    query_limit: usize,
+
+    /// Megabytes of rows a page of table rows or query results may hold; rows past it
+    /// wait for the next page
+    #[arg(long, value_name = "MB", default_value = "16")]
+    result_memory: usize,
+
+    /// Tables with more rows than this show their largest rowid, marked ~, rather than
+    /// being counted
+    #[arg(long, value_name = "ROWS", default_value = "5000000")]
+    count_limit: u64,
+
+    /// How long to wait for another process's lock on the database before giving up
+    #[arg(long, value_name = "MS", default_value = "5000")]
+    busy_timeout: u64,
+
+    /// Times reading a locked database is tried again before it fails; writes are
+    /// retried with Ctrl+R instead
+    #[arg(long, value_name = "N", default_value = "3")]
+    busy_retries: u32,
+
+    /// Reload the current table when another process changes the database
+    #[arg(long)]
+    watch: bool,
+
+    /// Hold cell edits as pending until written together with `w`
+    #[arg(long)]
+    staged: bool,
+
+    /// After saving a cell, go on to edit the same column of the next row
+    #[arg(long)]
+    edit_next: bool,
+
+    /// Open this table on start, matched case-insensitively
+    #[arg(long)]
+    table: Option<String>,
+
+    /// Page of --table to open on, counting from 1
+    #[arg(long, requires = "table", default_value_t = 1)]
+    page: usize,
+
+    /// Put this SQL in the editor and run it on start
+    #[arg(long)]
+    query: Option<String>,
+
+    /// Start in this view rather than the table's rows
+    #[arg(long, value_enum)]
+    view: Option<ViewArg>,
+
+    /// Attach another database, whose tables are then named ALIAS.table; repeatable
+    #[arg(long, num_args = 3, value_names = ["PATH", "AS", "ALIAS"])]
+    attach: Vec<String>,
+
+    /// Load a SQLite run-time extension (.so, .dylib or .dll) on start; repeatable
+    #[arg(long, value_name = "PATH")]
+    extension: Vec<String>,
+
+    /// Let SQL load extensions itself with load_extension()
+    #[arg(long)]
+    allow_extensions: bool,
+
+    /// Passphrase of a SQLCipher-encrypted database; needs the `sqlcipher` feature
+    #[arg(long, value_name = "PASSPHRASE", conflicts_with = "key_file")]
+    key: Option<String>,
+
+    /// Read the passphrase of a SQLCipher-encrypted database from a file
+    #[arg(long, value_name = "FILE")]
+    key_file: Option<String>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -54,29 +151,450 @@ enum Commands {
         #[arg(long, short)]
         query: Option<String>,
 
-        /// Output format
+        /// Export every table to its own file in the `--out` directory
+        #[arg(long, conflicts_with_all = ["table", "query", "insert_into"])]
+        all_tables: bool,
+
+        /// Export the CREATE statements of every table, view, index and trigger, or of
+        /// the --table and its indexes and triggers, instead of rows
+        #[arg(long, conflicts_with_all = ["query", "all_tables", "insert_into", "schema"])]
+        schema_only: bool,
+
+        /// With --all-tables, include SQLite's internal sqlite_* tables
+        #[arg(long, requires = "all_tables")]
+        include_internal: bool,
+
+        /// With --all-tables, stop at the first table that fails to export
+        #[arg(long, requires = "all_tables")]
+        fail_fast: bool,
+
+        /// Output format (--schema-only is always SQL)
+        #[arg(long, short, value_enum, required_unless_present = "schema_only")]
+        format: Option<ExportFormatArg>,
+
+        /// Output file path, `-` for standard output, or directory with --all-tables
+        #[arg(long, short)]
+        out: String,
+
+        /// Start a SQL export with the table's CREATE TABLE statement
+        #[arg(long, conflicts_with_all = ["query", "insert_into"])]
+        schema: bool,
+
+        /// Rows per INSERT statement of a SQL export
+        #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+
+        /// Table a SQL export inserts into (default: the exported table, or `query`)
+        #[arg(long)]
+        insert_into: Option<String>,
+
+        /// Field delimiter of a CSV export: one character, or \t for a tab
+        #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+        delimiter: u8,
+
+        /// When CSV fields are quoted
+        #[arg(long, value_enum, default_value = "necessary")]
+        quote: QuoteStyleArg,
+
+        /// Leave out the row of column names from a CSV export
+        #[arg(long)]
+        no_header: bool,
+
+        /// Text for NULL cells in CSV, Markdown and HTML exports (default: empty)
+        #[arg(long, default_value = "")]
+        null_as: String,
+
+        /// Start an HTML export with a <style> block for borders and padding
+        #[arg(long)]
+        html_style: bool,
+
+        /// How a Parquet export's columns are compressed
+        #[arg(long, value_enum, default_value = "snappy")]
+        compression: CompressionArg,
+
+        /// Layout of a JSON export: an array of objects, an array of arrays, or an
+        /// object holding the columns, rows, row count and query
+        #[arg(long, value_enum, default_value = "objects")]
+        json_shape: JsonShapeArg,
+
+        /// How JSON and JSON Lines exports write BLOBs
+        #[arg(long, value_enum, default_value = "base64")]
+        blob_encoding: BlobEncodingArg,
+
+        /// Rows between progress reports on stderr
+        #[arg(long, default_value_t = DEFAULT_PROGRESS_ROWS)]
+        progress_every: usize,
+
+        /// Keep the partly written file when the export is cancelled with Ctrl+C
+        #[arg(long)]
+        keep_partial: bool,
+
+        /// Compress the output with gzip, as an --out ending in .gz also does
+        #[arg(long)]
+        gzip: bool,
+    },
+    /// Import the rows of a CSV file into a table
+    Import {
+        /// Database file path
+        #[arg(long, short)]
+        db: String,
+
+        /// Table the rows go into
+        #[arg(long, short)]
+        table: String,
+
+        /// CSV file to read, or `-` for standard input
+        #[arg(long, short)]
+        file: String,
+
+        /// The file has no header row; fields fill the table's columns in order
+        #[arg(long)]
+        no_header: bool,
+
+        /// Field delimiter: one character, or \t for a tab
+        #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+        delimiter: u8,
+
+        /// Create the table if it doesn't exist, with TEXT columns named by the header
+        #[arg(long)]
+        create: bool,
+
+        /// Declared types of a created table's columns, as `name=TYPE,...`
+        #[arg(long, requires = "create", value_parser = parse_column_types)]
+        types: Option<ColumnTypes>,
+
+        /// Skip rows that fail to insert rather than importing nothing
+        #[arg(long)]
+        skip_errors: bool,
+
+        /// Rows per INSERT statement
+        #[arg(long, default_value_t = import::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+    },
+    /// Run one statement read-only and print its result, for use from scripts
+    Query {
+        /// Database file path
+        #[arg(long, short)]
+        db: String,
+
+        /// SQL statement to run, or `-` to read it from standard input
+        sql: String,
+
+        /// How the result is printed
+        #[arg(long, short, value_enum, default_value = "table")]
+        format: QueryFormatArg,
+
+        /// Value for the next `?` placeholder: a number when it reads as one,
+        /// otherwise text
+        #[arg(long = "param")]
+        params: Vec<String>,
+    },
+    /// Dump the whole database as SQL that rebuilds it, like sqlite3's .dump
+    Dump {
+        /// Database file path
+        #[arg(long, short)]
+        db: String,
+
+        /// Output file path, or `-` for standard output
+        #[arg(long, short, default_value = "-")]
+        out: String,
+
+        /// Rows between progress reports on stderr
+        #[arg(long, default_value_t = DEFAULT_PROGRESS_ROWS)]
+        progress_every: usize,
+
+        /// Keep the partly written file when the dump is cancelled with Ctrl+C
+        #[arg(long)]
+        keep_partial: bool,
+
+        /// Compress the output with gzip, as an --out ending in .gz also does
+        #[arg(long)]
+        gzip: bool,
+    },
+    /// Write the ER diagram of the database's tables and foreign keys as Mermaid,
+    /// Graphviz DOT or PlantUML
+    Diagram {
+        /// Database file path
+        #[arg(long, short)]
+        db: String,
+
+        /// Output format (default: from the --out extension, .mmd, .dot or .puml,
+        /// otherwise Mermaid)
         #[arg(long, short, value_enum)]
-        format: ExportFormatArg,
+        format: Option<DiagramFormatArg>,
+
+        /// Output file path, or `-` for standard output
+        #[arg(long, short, default_value = "-")]
+        out: String,
+    },
+    /// Compare two databases' schemas, and optionally their rows. Exits with 1 when
+    /// they differ and 2 on errors, like diff.
+    Diff {
+        /// The database to compare against
+        #[arg(long)]
+        a: String,
+
+        /// The database whose changes are reported
+        #[arg(long)]
+        b: String,
+
+        /// Also compare row counts and, in tables with a primary key, the rows
+        #[arg(long)]
+        data: bool,
 
-        /// Output file path
+        /// Most row differences listed for each table
+        #[arg(long, requires = "data", default_value_t = 100)]
+        limit: usize,
+
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Copy the database to another file, safely even while other programs write to it
+    Backup {
+        /// Database file path
+        #[arg(long, short)]
+        db: String,
+
+        /// File to write the copy to
         #[arg(long, short)]
         out: String,
+
+        /// Replace the --out file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the database for corruption and broken foreign keys, and describe the
+    /// file. Exits with 1 when problems are found.
+    Check {
+        /// Database file path
+        #[arg(long, short)]
+        db: String,
+
+        /// Run quick_check, which skips comparing indexes with their tables
+        #[arg(long)]
+        quick: bool,
+    },
+    /// Print a completion script for a shell to stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
     },
 }
 
 #[derive(clap::ValueEnum, Clone, Copy)]
 enum ExportFormatArg {
     Csv,
+    Tsv,
     Json,
+    Jsonl,
+    Sql,
+    Markdown,
+    Html,
+    Xlsx,
+    Parquet,
 }
 
 impl From<ExportFormatArg> for ExportFormat {
     fn from(fmt: ExportFormatArg) -> Self {
         match fmt {
             ExportFormatArg::Csv => ExportFormat::Csv,
+            ExportFormatArg::Tsv => ExportFormat::Tsv,
             ExportFormatArg::Json => ExportFormat::Json,
+            ExportFormatArg::Jsonl => ExportFormat::Jsonl,
+            ExportFormatArg::Sql => ExportFormat::Sql,
+            ExportFormatArg::Markdown => ExportFormat::Markdown,
+            ExportFormatArg::Html => ExportFormat::Html,
+            ExportFormatArg::Xlsx => ExportFormat::Xlsx,
+            ExportFormatArg::Parquet => ExportFormat::Parquet,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum DiagramFormatArg {
+    Mermaid,
+    Dot,
+    Plantuml,
+}
+
+impl From<DiagramFormatArg> for DiagramFormat {
+    fn from(fmt: DiagramFormatArg) -> Self {
+        match fmt {
+            DiagramFormatArg::Mermaid => DiagramFormat::Mermaid,
+            DiagramFormatArg::Dot => DiagramFormat::Dot,
+            DiagramFormatArg::Plantuml => DiagramFormat::PlantUml,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum QueryFormatArg {
+    /// Columns lined up for reading
+    Table,
+    Csv,
+    Tsv,
+    Json,
+    Jsonl,
+    Markdown,
+}
+
+impl QueryFormatArg {
+    /// The export format that writes the rows, or None for the aligned table
+    fn export_format(self) -> Option<ExportFormat> {
+        match self {
+            QueryFormatArg::Table => None,
+            QueryFormatArg::Csv => Some(ExportFormat::Csv),
+            QueryFormatArg::Tsv => Some(ExportFormat::Tsv),
+            QueryFormatArg::Json => Some(ExportFormat::Json),
+            QueryFormatArg::Jsonl => Some(ExportFormat::Jsonl),
+            QueryFormatArg::Markdown => Some(ExportFormat::Markdown),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ViewArg {
+    Schema,
+    Diagram,
+}
+
+impl From<ViewArg> for ViewMode {
+    fn from(view: ViewArg) -> Self {
+        match view {
+            ViewArg::Schema => ViewMode::Schema,
+            ViewArg::Diagram => ViewMode::Diagram,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum QuoteStyleArg {
+    Always,
+    Necessary,
+    Never,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum CompressionArg {
+    None,
+    Snappy,
+    Gzip,
+    Zstd,
+}
+
+impl From<CompressionArg> for ParquetCompression {
+    fn from(compression: CompressionArg) -> Self {
+        match compression {
+            CompressionArg::None => ParquetCompression::None,
+            CompressionArg::Snappy => ParquetCompression::Snappy,
+            CompressionArg::Gzip => ParquetCompression::Gzip,
+            CompressionArg::Zstd => ParquetCompression::Zstd,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum JsonShapeArg {
+    Objects,
+    Rows,
+    Envelope,
+}
+
+impl From<JsonShapeArg> for JsonShape {
+    fn from(shape: JsonShapeArg) -> Self {
+        match shape {
+            JsonShapeArg::Objects => JsonShape::Objects,
+            JsonShapeArg::Rows => JsonShape::Rows,
+            JsonShapeArg::Envelope => JsonShape::Envelope,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum BlobEncodingArg {
+    Base64,
+    Hex,
+}
+
+impl From<BlobEncodingArg> for BlobEncoding {
+    fn from(encoding: BlobEncodingArg) -> Self {
+        match encoding {
+            BlobEncodingArg::Base64 => BlobEncoding::Base64,
+            BlobEncodingArg::Hex => BlobEncoding::Hex,
+        }
+    }
+}
+
+impl From<QuoteStyleArg> for QuoteStyle {
+    fn from(style: QuoteStyleArg) -> Self {
+        match style {
+            QuoteStyleArg::Always => QuoteStyle::Always,
+            QuoteStyleArg::Necessary => QuoteStyle::Necessary,
+            QuoteStyleArg::Never => QuoteStyle::Never,
+        }
+    }
+}
+
+/// Set by Ctrl+C during an export, which stops at its next progress report
+static EXPORT_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Report an export's progress on stderr, answering whether to carry on
+fn report_progress(label: &str, progress: ExportProgress) -> bool {
+    eprintln!("{}{}…", label, ui::export_progress_text(progress));
+    !EXPORT_CANCELLED.load(Ordering::Relaxed)
+}
+
+/// The error for an export stopped by Ctrl+C, removing the file it was writing unless
+/// `keep_partial` is set
+fn cancelled(path: &Path, keep_partial: bool) -> anyhow::Error {
+    if keep_partial {
+        return anyhow::anyhow!("Export cancelled; kept the partial file {}", path.display());
+    }
+    let _ = std::fs::remove_file(path);
+    anyhow::anyhow!(
+        "Export cancelled; removed the partial file {}",
+        path.display()
+    )
+}
+
+/// Column names and their declared types
+type ColumnTypes = Vec<(String, String)>;
+
+/// A `--types` value: `name=TYPE` pairs separated by commas, except the commas inside
+/// a type such as `DECIMAL(10,2)`
+fn parse_column_types(value: &str) -> Result<ColumnTypes, String> {
+    let mut pairs = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in value.char_indices().chain([(value.len(), ',')]) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                let pair = &value[start..i];
+                match pair.split_once('=') {
+                    Some((name, declared)) if !name.trim().is_empty() => {
+                        pairs.push((name.trim().to_string(), declared.trim().to_string()));
+                    }
+                    _ => return Err(format!("expected name=TYPE, got `{}`", pair)),
+                }
+                start = i + 1;
+            }
+            _ => {}
         }
     }
+    Ok(pairs)
+}
+
+/// A `--delimiter` value: a single ASCII character, or `\t` for a tab
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "\\t" | "\t" => Ok(b'\t'),
+        _ => match value.as_bytes() {
+            [byte] => Ok(*byte),
+            _ => Err("expected a single ASCII character or \\t".to_string()),
+        },
+    }
 }
 
 fn main() -> Result<()> {
@@ -88,6 +606,13 @@ fn main() -> Result<()> {
         )
         .init();
 
+    // Called back by the completion scripts, which want a list or nothing at all
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(COMPLETE_TABLES) {
+        let _ = complete_tables(Cli::command(), &args[2..]);
+        return Ok(());
+    }
+
     let cli = Cli::parse();
 
     // Handle export command
@@ -95,55 +620,681 @@ fn main() -> Result<()> {
         db,
         table,
         query,
+        all_tables,
+        schema_only,
+        include_internal,
+        fail_fast,
         format,
         out,
+        schema,
+        batch_size,
+        insert_into,
+        delimiter,
+        quote,
+        no_header,
+        null_as,
+        html_style,
+        compression,
+        json_shape,
+        blob_encoding,
+        progress_every,
+        keep_partial,
+        gzip,
+    }) = cli.command
+    {
+        ctrlc::set_handler(|| EXPORT_CANCELLED.store(true, Ordering::Relaxed))
+            .context("Failed to handle Ctrl+C")?;
+        let options = ExportOptions {
+            delimiter,
+            quote_style: quote.into(),
+            header: !no_header,
+            schema,
+            batch_size,
+            insert_table: insert_into,
+            null_text: null_as,
+            html_style,
+            parquet_compression: compression.into(),
+            json_shape: json_shape.into(),
+            blob_encoding: blob_encoding.into(),
+            progress_rows: progress_every,
+            gzip: gzip || (out != "-" && is_gzip_path(Path::new(&out))),
+        };
+        let format = format.map_or(ExportFormat::Sql, Into::into);
+        if all_tables {
+            if out == "-" {
+                anyhow::bail!("--all-tables needs a directory for --out, not standard output");
+            }
+            return run_export_all(
+                &db,
+                format,
+                Path::new(&out),
+                include_internal,
+                fail_fast,
+                keep_partial,
+                &options,
+            );
+        }
+        let source = match (table, query, schema_only) {
+            (table, None, true) => ExportSource::Schema { table },
+            (Some(table), None, false) => ExportSource::Table(table),
+            (None, Some(query), false) => ExportSource::Query {
+                query,
+                params: Vec::new(),
+            },
+            _ => {
+                anyhow::bail!("Must specify one of --table, --query, --all-tables or --schema-only")
+            }
+        };
+        return run_export(&db, &source, format, &out, keep_partial, &options);
+    }
+
+    // Handle import command
+    if let Some(Commands::Import {
+        db,
+        table,
+        file,
+        no_header,
+        delimiter,
+        create,
+        types,
+        skip_errors,
+        batch_size,
+    }) = cli.command
+    {
+        let options = ImportOptions {
+            delimiter,
+            header: !no_header,
+            create,
+            types: types.unwrap_or_default(),
+            skip_errors,
+            batch_size,
+        };
+        return run_import(&db, &table, &file, &options);
+    }
+
+    // Handle query command
+    if let Some(Commands::Query {
+        db,
+        sql,
+        format,
+        params,
+    }) = cli.command
+    {
+        return run_query(&db, &sql, format, &params);
+    }
+
+    // Handle backup command
+    if let Some(Commands::Backup { db, out, force }) = cli.command {
+        ctrlc::set_handler(|| EXPORT_CANCELLED.store(true, Ordering::Relaxed))
+            .context("Failed to handle Ctrl+C")?;
+        return run_backup(&db, Path::new(&out), force);
+    }
+
+    // Handle check command
+    if let Some(Commands::Check { db, quick }) = cli.command {
+        let conn = Database::new(&db, true)?.into_connection();
+        let report = check_database(&conn, quick)?;
+        for line in report.lines() {
+            println!("{}", line);
+        }
+        if !report.is_ok() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Handle completions command
+    if let Some(Commands::Completions { shell }) = cli.command {
+        return write_completions(shell, Cli::command(), &mut io::stdout().lock());
+    }
+
+    // Handle diff command
+    if let Some(Commands::Diff {
+        a,
+        b,
+        data,
+        limit,
+        json,
+    }) = cli.command
+    {
+        let options = DiffOptions {
+            data,
+            row_limit: limit,
+        };
+        match run_diff(&a, &b, options, json) {
+            Ok(true) => std::process::exit(1),
+            Ok(false) => return Ok(()),
+            Err(e) => {
+                eprintln!("Error: {:?}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // Handle dump command
+    if let Some(Commands::Dump {
+        db,
+        out,
+        progress_every,
+        keep_partial,
+        gzip,
     }) = cli.command
     {
-        return run_export(&db, table.as_deref(), query.as_deref(), format.into(), &out);
+        ctrlc::set_handler(|| EXPORT_CANCELLED.store(true, Ordering::Relaxed))
+            .context("Failed to handle Ctrl+C")?;
+        let options = ExportOptions {
+            progress_rows: progress_every,
+            gzip: gzip || (out != "-" && is_gzip_path(Path::new(&out))),
+            ..ExportOptions::default()
+        };
+        return run_export(
+            &db,
+            &ExportSource::Database,
+            ExportFormat::Sql,
+            &out,
+            keep_partial,
+            &options,
+        );
+    }
+
+    // Handle diagram command
+    if let Some(Commands::Diagram { db, format, out }) = cli.command {
+        return run_diagram(&db, format.map(Into::into), &out);
     }
 
     // Handle TUI mode
     let db_path = cli.database.context("Database path is required")?;
-    run_tui(&db_path, cli.read_write, cli.page_size)
+    let startup = StartupIntent {
+        table: cli.table,
+        page: cli.page.saturating_sub(1),
+        query: cli.query,
+        view: cli.view.map(ViewMode::from),
+    };
+    let attach = cli
+        .attach
+        .chunks(3)
+        .map(|spec| match spec {
+            [path, keyword, alias] if keyword.eq_ignore_ascii_case("as") => {
+                Ok((path.clone(), alias.clone()))
+            }
+            _ => anyhow::bail!("--attach takes PATH AS ALIAS, e.g. --attach other.db AS other"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let key = match (cli.key, cli.key_file) {
+        (Some(key), _) => Some(key),
+        (None, Some(file)) => Some(read_key_file(&file)?),
+        (None, None) => None,
+    };
+    run_tui(
+        &db_path,
+        cli.read_write,
+        cli.create,
+        cli.page_size,
+        cli.query_limit,
+        cli.result_memory,
+        cli.count_limit,
+        worker::BusyPolicy {
+            timeout: Duration::from_millis(cli.busy_timeout),
+            retries: cli.busy_retries,
+        },
+        cli.watch,
+        cli.staged,
+        cli.edit_next,
+        startup,
+        &attach,
+        &cli.extension,
+        cli.allow_extensions,
+        key,
+    )
 }
 
 fn run_export(
     db_path: &str,
-    table: Option<&str>,
-    query: Option<&str>,
+    source: &ExportSource,
     format: ExportFormat,
     output_path: &str,
+    keep_partial: bool,
+    options: &ExportOptions,
 ) -> Result<()> {
     let database = Database::new(db_path, false)?;
     let conn = database.into_connection();
 
-    export(
-        &conn,
-        format,
-        std::path::Path::new(output_path),
-        table,
-        query,
-    )?;
+    // `-` writes to standard output, leaving stdout to the data and the summary to stderr
+    let to_stdout = output_path == "-";
+    let writer: Box<dyn Write> = if to_stdout {
+        Box::new(io::stdout().lock())
+    } else {
+        create_file(Path::new(output_path))?
+    };
 
-    println!("Exported to: {}", output_path);
+    let mut on_progress = |progress| report_progress("", progress);
+    let exported = export_source(&conn, format, writer, source, options, &mut on_progress);
+    let written = match ignore_broken_pipe(exported) {
+        Ok(Some(written)) => written,
+        Ok(None) => return Ok(()),
+        Err(e) if e.is::<ExportCancelled>() && !to_stdout => {
+            return Err(cancelled(Path::new(output_path), keep_partial))
+        }
+        Err(e) => return Err(e),
+    };
+
+    if to_stdout {
+        eprintln!(
+            "Exported {} {} to standard output",
+            written.rows,
+            source.unit()
+        );
+    } else {
+        println!(
+            "Exported {} {} to: {}",
+            written.rows,
+            source.unit(),
+            output_path
+        );
+    }
+    for warning in &written.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(())
+}
+
+/// Write the diagram of the database's tables in `format`, or the one `output_path`'s
+/// extension asks for
+fn run_diagram(db_path: &str, format: Option<DiagramFormat>, output_path: &str) -> Result<()> {
+    let format = format
+        .or_else(|| DiagramFormat::from_path(Path::new(output_path)))
+        .unwrap_or(DiagramFormat::Mermaid);
+    let conn = Database::new(db_path, true)?.into_connection();
+    let diagram = DiagramData {
+        tables: db::get_diagram_tables(&conn)?,
+    };
+
+    let to_stdout = output_path == "-";
+    let mut writer: Box<dyn Write> = if to_stdout {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(io::BufWriter::new(create_file(Path::new(output_path))?))
+    };
+    if ignore_broken_pipe(export_diagram(&diagram, format, &mut writer))?.is_none() {
+        return Ok(());
+    }
+    if !to_stdout {
+        println!(
+            "Exported the diagram of {} tables to: {}",
+            diagram.tables.len(),
+            output_path
+        );
+    }
+    Ok(())
+}
+
+fn run_import(db_path: &str, table: &str, file: &str, options: &ImportOptions) -> Result<()> {
+    let database = Database::new(db_path, false)?;
+    let conn = database.into_connection();
+    let reader: Box<dyn Read> = if file == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(file).with_context(|| format!("Failed to open {}", file))?)
+    };
+
+    let summary = import_csv(&conn, reader, table, options)?;
+    if summary.created {
+        println!("Created table {}", table);
+    }
+    println!("Imported {} rows into {}", summary.inserted, table);
+    if summary.skipped > 0 {
+        eprintln!("Skipped {} rows:", summary.skipped);
+        for error in &summary.errors {
+            eprintln!("  {}", error);
+        }
+        if summary.skipped > summary.errors.len() {
+            eprintln!("  ...and {} more", summary.skipped - summary.errors.len());
+        }
+    }
     Ok(())
 }
 
-fn run_tui(db_path: &str, read_write: bool, page_size: usize) -> Result<()> {
+/// Run `sql` on a read-only connection and print what it returns to standard output.
+/// A SQL error is reported on stderr the way the TUI shows it and exits with status 1.
+fn run_query(db_path: &str, sql: &str, format: QueryFormatArg, params: &[String]) -> Result<()> {
+    let sql = if sql == "-" {
+        let mut sql = String::new();
+        io::stdin()
+            .read_to_string(&mut sql)
+            .context("Failed to read SQL from standard input")?;
+        sql
+    } else {
+        sql.to_string()
+    };
+    let params: Vec<rusqlite::types::Value> = params
+        .iter()
+        .map(|param| Value::from_edit(param, &Value::Null).into())
+        .collect();
+    let database = Database::new(db_path, true)?;
+    let conn = database.into_connection();
+
+    let printed = match format.export_format() {
+        None => execute_query(&conn, &sql, Some(usize::MAX), usize::MAX, 0, &params)
+            .and_then(|result| print_table(&result, &mut io::stdout().lock())),
+        Some(format) => {
+            let source = ExportSource::Query {
+                query: sql.clone(),
+                params,
+            };
+            let options = ExportOptions::default();
+            export_source(
+                &conn,
+                format,
+                Box::new(io::stdout().lock()),
+                &source,
+                &options,
+                &mut |_| true,
+            )
+            .map(|_| ())
+        }
+    };
+    match ignore_broken_pipe(printed) {
+        Err(e) => match e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<rusqlite::Error>())
+        {
+            Some(error) => {
+                eprintln!("{}", format_sql_error(error, &sql));
+                std::process::exit(1);
+            }
+            None => Err(e),
+        },
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Back the database up to `out`, reporting progress on stderr about once a second
+fn run_backup(db_path: &str, out: &Path, force: bool) -> Result<()> {
+    if out.exists() && !force {
+        anyhow::bail!("{} already exists (--force replaces it)", out.display());
+    }
+    let conn = Database::new(db_path, true)?.into_connection();
+    let mut last_report = Instant::now();
+    let mut on_progress = |progress: BackupProgress| {
+        if progress.total > 0 && last_report.elapsed() >= Duration::from_secs(1) {
+            last_report = Instant::now();
+            eprintln!(
+                "{} of {} pages…",
+                ui::group_digits((progress.total - progress.remaining).into()),
+                ui::group_digits(progress.total.into())
+            );
+        }
+        !EXPORT_CANCELLED.load(Ordering::Relaxed)
+    };
+    match backup_to(&conn, out, &mut on_progress) {
+        Ok(progress) => {
+            eprintln!(
+                "Backed up {} pages to {}",
+                ui::group_digits(progress.total.into()),
+                out.display()
+            );
+            Ok(())
+        }
+        Err(e) if e.is::<BackupCancelled>() => anyhow::bail!("Backup cancelled"),
+        Err(e) => Err(e),
+    }
+}
+
+/// Compare two databases and print how the second differs, returning whether it does
+fn run_diff(a: &str, b: &str, options: DiffOptions, json: bool) -> Result<bool> {
+    let old = Database::new(a, true)?.into_connection();
+    let new = Database::new(b, true)?.into_connection();
+    let diff = diff_databases(&old, &new, options)?;
+
+    let mut out = io::stdout().lock();
+    let printed = if json {
+        serde_json::to_writer_pretty(&mut out, &diff)
+            .map_err(io::Error::from)
+            .and_then(|()| writeln!(out))
+    } else {
+        diff.write_report(&mut out)
+    };
+    ignore_broken_pipe(printed.map_err(anyhow::Error::from))
+        .context("Failed to write the differences")?;
+    Ok(!diff.is_empty())
+}
+
+/// The value of `result`, or `None` if writing failed because the reader stopped
+/// early, as `| head` does, which isn't a failure
+fn ignore_broken_pipe<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if is_broken_pipe(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write a query result as columns padded to line up under their names, numbers
+/// aligned to the right. A statement that returns no columns prints nothing.
+fn print_table(result: &QueryResult, out: &mut dyn Write) -> Result<()> {
+    if result.columns.is_empty() {
+        return Ok(());
+    }
+    let cells: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| {
+                    value
+                        .full_text()
+                        .replace("\r\n", " ")
+                        .replace(['\n', '\r'], " ")
+                })
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            cells
+                .iter()
+                .map(|row| row[i].width())
+                .fold(name.width(), usize::max)
+        })
+        .collect();
+
+    let header: Vec<String> = result
+        .columns
+        .iter()
+        .zip(&widths)
+        .map(|(name, &width)| pad(name, width, false))
+        .collect();
+    writeln!(out, "{}", header.join("  ").trim_end())?;
+    let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+    writeln!(out, "{}", rule.join("  "))?;
+    for (row, values) in cells.iter().zip(&result.rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(values)
+            .zip(&widths)
+            .map(|((text, value), &width)| {
+                pad(
+                    text,
+                    width,
+                    matches!(value, Value::Integer(_) | Value::Real(_)),
+                )
+            })
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// `text` padded with spaces to `width` terminal columns, on the left when `right`, so
+/// wide characters such as CJK take the two columns they fill
+fn pad(text: &str, width: usize, right: bool) -> String {
+    let spaces = " ".repeat(width.saturating_sub(text.width()));
+    if right {
+        spaces + text
+    } else {
+        format!("{}{}", text, spaces)
+    }
+}
+
+/// Export each table to `<table>.<ext>` in `out_dir`, reporting each one as it's done.
+/// A table that fails is reported and skipped unless `fail_fast` is set.
+fn run_export_all(
+    db_path: &str,
+    format: ExportFormat,
+    out_dir: &Path,
+    include_internal: bool,
+    fail_fast: bool,
+    keep_partial: bool,
+    options: &ExportOptions,
+) -> Result<()> {
+    let database = Database::new(db_path, false)?;
+    let conn = database.into_connection();
+    let tables = db::get_tables(&conn, include_internal)?;
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+
+    let (mut total_rows, mut total_bytes, mut failed) = (0, 0, 0);
+    let start = Instant::now();
+    for table in &tables {
+        // Path separators can't be part of a file name
+        let file_name = format!(
+            "{}.{}{}",
+            table.name.replace(['/', '\\'], "_"),
+            format.extension(),
+            if options.gzip { ".gz" } else { "" }
+        );
+        let path = out_dir.join(file_name);
+        let table_start = Instant::now();
+        let source = ExportSource::Table(table.name.clone());
+        let label = format!("{}: ", table.name);
+        let mut on_progress = |progress| report_progress(&label, progress);
+        match create_file(&path).and_then(|writer| {
+            export_source(&conn, format, writer, &source, options, &mut on_progress)
+        }) {
+            Ok(written) => {
+                println!(
+                    "{}: {} rows, {} bytes in {:.2}s",
+                    table.name,
+                    written.rows,
+                    written.bytes,
+                    table_start.elapsed().as_secs_f64()
+                );
+                for warning in &written.warnings {
+                    eprintln!("{}: warning: {}", table.name, warning);
+                }
+                total_rows += written.rows;
+                total_bytes += written.bytes;
+            }
+            Err(e) if e.is::<ExportCancelled>() => return Err(cancelled(&path, keep_partial)),
+            Err(e) if fail_fast => {
+                return Err(e.context(format!("Failed to export {}", table.name)));
+            }
+            Err(e) => {
+                eprintln!("{}: failed: {:#}", table.name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Exported {} rows ({} bytes) from {} tables to {} in {:.2}s",
+        total_rows,
+        total_bytes,
+        tables.len() - failed,
+        out_dir.display(),
+        start.elapsed().as_secs_f64()
+    );
+    if failed > 0 {
+        anyhow::bail!("{} of {} tables failed to export", failed, tables.len());
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_tui(
+    db_path: &str,
+    read_write: bool,
+    create: bool,
+    page_size: usize,
+    query_limit: usize,
+    result_memory: usize,
+    count_limit: u64,
+    busy: worker::BusyPolicy,
+    watch: bool,
+    staged: bool,
+    edit_next: bool,
+    startup: StartupIntent,
+    attach: &[(String, String)],
+    extensions: &[String],
+    allow_extensions: bool,
+    key: Option<String>,
+) -> Result<()> {
+    // `-` reads a database piped on stdin into a temporary copy, removed on the way out
+    let stdin_copy = if db_path == "-" {
+        if read_write || create {
+            anyhow::bail!(
+                "A database read from stdin can only be opened read-only, as edits to its temporary copy would be lost"
+            );
+        }
+        Some(StdinDatabase::read()?)
+    } else {
+        None
+    };
+    let open_path = stdin_copy.as_ref().map_or_else(
+        || db_path.to_string(),
+        |copy| copy.path().display().to_string(),
+    );
+
     // Open database
     // Database::new expects read_only flag, so we pass !read_write
     // If read_write is true, we want read_only=false (read-write mode)
     // If read_write is false, we want read_only=true (read-only mode)
-    let database = Database::new(db_path, !read_write)
+    // A database that starts out empty is only any use if tables can be created in it
+    let read_write = read_write || create || db::is_in_memory(db_path);
+    // A file we can't write to is opened read-only even with --read-write
+    let not_writable = read_write && Path::new(db_path).exists() && !file_is_writable(db_path);
+    let read_only = !read_write || not_writable;
+    // Before the alternate screen, so a passphrase can be asked for on the terminal
+    let (database, key) = open_database(&open_path, read_only, create, key)
         .with_context(|| format!("Failed to open database: {}", db_path))?;
 
     // Create worker with database connection
-    let worker = worker::Worker::new(database.into_connection());
+    let conn = database.into_connection();
+    // Before the worker takes the connection, so everything it runs sees their functions
+    db::load_extensions(&conn, extensions, allow_extensions)?;
+    for (path, alias) in attach {
+        db::attach_database(&conn, path, alias)?;
+    }
+    let background = worker::open_background(&open_path, key.as_deref(), attach, extensions);
+    let worker = worker::Worker::new(conn, background, open_path.into(), busy);
 
     // Create app
-    let mut app = App::new(worker, page_size);
+    let mut app = App::new(worker, page_size, read_only);
+    if not_writable {
+        app.state.status_message =
+            Some("Database file is not writable; opened read-only".to_string());
+    }
+    app.state.stdin_copy = stdin_copy.is_some();
+    app.state.key = key;
+    app.state.extensions = extensions.to_vec();
+    app.state.allow_extensions = allow_extensions;
+    app.state.attached = attach.to_vec();
+    app.state.query_limit = query_limit.max(1);
+    app.state.result_memory = result_memory.max(1).saturating_mul(1024 * 1024);
+    app.state.count_limit = count_limit;
+    app.state.busy_retries = busy.retries;
+    app.state.watch = watch;
+    app.state.staged_edits = staged;
+    app.state.edit_next_row = edit_next;
+    app.state.history = History::load(Path::new(db_path));
+    app.state.saved_queries = SavedQueries::load(Path::new(db_path));
 
-    // Load initial tables
+    // Load initial tables, then open what the command line asked for
+    app.start_with(startup);
     app.load_tables();
 
     // Setup terminal
@@ -165,6 +1316,7 @@ fn run_tui(db_path: &str, read_write: bool, page_size: usize) -> Result<()> {
 
         // Process worker responses
         app.process_worker_responses()?;
+        app.tick();
 
         // Handle input and resize events
         if event::poll(std::time::Duration::from_millis(100))? {
@@ -193,3 +1345,112 @@ fn run_tui(db_path: &str, read_write: bool, page_size: usize) -> Result<()> {
 
     Ok(())
 }
+
+/// The passphrase in a --key-file, without the newline that ends it
+fn read_key_file(path: &str) -> Result<String> {
+    let key = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read key file: {}", path))?;
+    Ok(key.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// Open the database, unlocking it with `key`. Built with the `sqlcipher` feature, an
+/// encrypted database or a wrong key asks for the passphrase on the terminal, again
+/// until it opens or the passphrase is left empty. Returns the key that opened it.
+fn open_database(
+    path: &str,
+    read_only: bool,
+    create: bool,
+    key: Option<String>,
+) -> Result<(Database, Option<String>)> {
+    #[cfg(feature = "sqlcipher")]
+    use std::io::IsTerminal;
+
+    match Database::open_with_key(path, read_only, create, key.as_deref()) {
+        Ok(database) => Ok((database, key)),
+        #[cfg(feature = "sqlcipher")]
+        Err(e) if is_locked(&e) && io::stdin().is_terminal() => {
+            eprintln!("{}", e);
+            match read_passphrase()? {
+                Some(key) => open_database(path, read_only, create, Some(key)),
+                None => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether opening failed for want of the right key
+#[cfg(feature = "sqlcipher")]
+fn is_locked(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<db::DatabaseError>(),
+        Some(db::DatabaseError::Encrypted(_) | db::DatabaseError::WrongKey(_))
+    )
+}
+
+/// Ask for a passphrase on the terminal without echoing it. None when it's left empty
+/// or Esc or Ctrl+C cancels.
+#[cfg(feature = "sqlcipher")]
+fn read_passphrase() -> Result<Option<String>> {
+    use crossterm::event::{KeyCode, KeyEventKind, KeyModifiers};
+
+    eprint!("Passphrase: ");
+    io::stderr().flush()?;
+    enable_raw_mode().context("Failed to enable raw mode")?;
+    let mut passphrase = String::new();
+    let read = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        match key.code {
+            KeyCode::Enter => break Ok(Some(passphrase)),
+            KeyCode::Esc => break Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Ok(None),
+            KeyCode::Backspace => {
+                passphrase.pop();
+            }
+            KeyCode::Char(c) => passphrase.push(c),
+            _ => {}
+        }
+    };
+    disable_raw_mode().context("Failed to disable raw mode")?;
+    eprintln!();
+    Ok(read
+        .context("Failed to read the passphrase")?
+        .filter(|passphrase| !passphrase.is_empty()))
+}
+
+/// Whether this process can open the database file for writing
+fn file_is_writable(path: &str) -> bool {
+    std::fs::OpenOptions::new().write(true).open(path).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_characters_keep_the_table_aligned() {
+        let result = QueryResult::new(
+            vec!["name".to_string(), "n".to_string()],
+            vec![
+                vec![Value::Text("日本".to_string()), Value::Integer(1)],
+                vec![Value::Text("😀 ok".to_string()), Value::Integer(22)],
+                vec![Value::Text("plain".to_string()), Value::Integer(333)],
+            ],
+            0,
+        );
+        let mut out = Vec::new();
+        print_table(&result, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "name   n\n\
+             -----  ---\n\
+             日本     1\n\
+             😀 ok   22\n\
+             plain  333\n"
+        );
+    }
+}
@@ -2,7 +2,7 @@ use crate::types::{ColumnInfo, ForeignKeyInfo};
 use serde::{Deserialize, Serialize};
 
 /// Table data for ER diagram visualization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DiagramTable {
     pub name: String,
     pub columns: Vec<ColumnInfo>,
@@ -10,7 +10,407 @@ pub struct DiagramTable {
 }
 
 /// Complete diagram data with all tables and relationships
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DiagramData {
     pub tables: Vec<DiagramTable>,
 }
+
+/// Size of a table's box in the diagram: room for a name and its markers, and for five
+/// columns above the "... N more" line. Boxes keep this size however many tables there
+/// are; the diagram pans instead
+const DIAGRAM_BOX_WIDTH: i32 = 30;
+const DIAGRAM_BOX_HEIGHT: i32 = 9;
+/// Height of a collapsed table's box, its title on the top border
+const DIAGRAM_COLLAPSED_HEIGHT: i32 = 2;
+/// Space between boxes, for the arrows to run through
+const DIAGRAM_GAP_X: i32 = 10;
+const DIAGRAM_GAP_Y: i32 = 4;
+
+/// Where a table's box sits on the diagram canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableBox {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl TableBox {
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// The diagram's boxes on a canvas that can be larger than the pane showing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramLayout {
+    /// One box per table, in the order of `DiagramData::tables`
+    pub boxes: Vec<TableBox>,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl DiagramLayout {
+    /// The furthest the view can pan, leaving the last box in view
+    pub fn max_offset(&self) -> (i32, i32) {
+        (
+            (self.width - DIAGRAM_BOX_WIDTH).max(0),
+            (self.height - DIAGRAM_BOX_HEIGHT).max(0),
+        )
+    }
+
+    /// Shrink the box of table `index` to its title bar
+    pub fn collapse(&mut self, index: usize) {
+        if let Some(table_box) = self.boxes.get_mut(index) {
+            table_box.height = DIAGRAM_COLLAPSED_HEIGHT;
+        }
+    }
+
+    /// `offset` held within the canvas
+    pub fn clamp_offset(&self, offset: (i32, i32)) -> (i32, i32) {
+        let (max_x, max_y) = self.max_offset();
+        (offset.0.clamp(0, max_x), offset.1.clamp(0, max_y))
+    }
+}
+
+/// How the diagram places its tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagramLayoutKind {
+    /// Tables that reference each other kept together, parents above children
+    #[default]
+    Related,
+    /// A square grid in table order
+    Grid,
+}
+
+impl DiagramLayoutKind {
+    /// The other layout, for the toggle key
+    pub fn next(self) -> Self {
+        match self {
+            DiagramLayoutKind::Related => DiagramLayoutKind::Grid,
+            DiagramLayoutKind::Grid => DiagramLayoutKind::Related,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DiagramLayoutKind::Related => "related",
+            DiagramLayoutKind::Grid => "grid",
+        }
+    }
+}
+
+/// A foreign key between two of the diagram's tables, with the columns of a composite
+/// key together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relationship {
+    pub from_table: String,
+    pub from_columns: Vec<String>,
+    /// The referenced table as the diagram names it
+    pub to_table: String,
+    pub to_columns: Vec<String>,
+}
+
+impl DiagramData {
+    /// The table named `name`, which a foreign key may spell in another case
+    pub fn table_named(&self, name: &str) -> Option<&DiagramTable> {
+        self.tables.iter().find(|t| t.name == name).or_else(|| {
+            self.tables
+                .iter()
+                .find(|t| t.name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Every table's foreign keys, one per key however many columns it has, in the
+    /// order SQLite numbers them. Keys to a table the diagram doesn't have are left out.
+    pub fn relationships(&self) -> Vec<Relationship> {
+        let mut relationships = Vec::new();
+        for table in &self.tables {
+            let mut keys: Vec<(i32, Relationship)> = Vec::new();
+            for fk in &table.foreign_keys {
+                let Some(target) = self.table_named(&fk.to_table) else {
+                    continue;
+                };
+                match keys.iter_mut().find(|(id, _)| *id == fk.id) {
+                    Some((_, key)) => {
+                        key.from_columns.push(fk.from_column.clone());
+                        key.to_columns.push(fk.to_column.clone());
+                    }
+                    None => keys.push((
+                        fk.id,
+                        Relationship {
+                            from_table: table.name.clone(),
+                            from_columns: vec![fk.from_column.clone()],
+                            to_table: target.name.clone(),
+                            to_columns: vec![fk.to_column.clone()],
+                        },
+                    )),
+                }
+            }
+            relationships.extend(keys.into_iter().map(|(_, key)| key));
+        }
+        relationships
+    }
+
+    /// Names of the tables joined to `name` by a foreign key, whichever way it points
+    pub fn neighbours(&self, name: &str) -> Vec<&str> {
+        let mut neighbours: Vec<&str> = Vec::new();
+        for key in self.relationships() {
+            let other = if key.from_table == name {
+                &key.to_table
+            } else if key.to_table == name {
+                &key.from_table
+            } else {
+                continue;
+            };
+            if let Some(table) = self.table_named(other) {
+                if table.name != name && !neighbours.contains(&table.name.as_str()) {
+                    neighbours.push(&table.name);
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// A copy with only the tables `keep` says to, in the same order, and for each how
+    /// many of its keys join it to a table left out
+    pub fn subset(&self, keep: impl Fn(&DiagramTable) -> bool) -> (DiagramData, Vec<usize>) {
+        let kept = DiagramData {
+            tables: self.tables.iter().filter(|t| keep(t)).cloned().collect(),
+        };
+        let mut hidden = vec![0; kept.tables.len()];
+        for key in self.relationships() {
+            let position = |name: &str| kept.tables.iter().position(|t| t.name == name);
+            match (position(&key.from_table), position(&key.to_table)) {
+                (Some(i), None) | (None, Some(i)) => hidden[i] += 1,
+                _ => {}
+            }
+        }
+        (kept, hidden)
+    }
+
+    /// Place the tables as `kind` says. The related layout has nothing to go on without
+    /// foreign keys between tables, and falls back to the grid.
+    pub fn layout(&self, kind: DiagramLayoutKind) -> DiagramLayout {
+        match kind {
+            DiagramLayoutKind::Related => self.related_layout(),
+            DiagramLayoutKind::Grid => self.grid_layout(),
+        }
+    }
+
+    /// Lay the tables out in a square grid, in order
+    pub fn grid_layout(&self) -> DiagramLayout {
+        let count = self.tables.len();
+        let cols = ((count as f64).sqrt().ceil() as usize).max(1);
+        let rows = count.div_ceil(cols);
+        let boxes = (0..count)
+            .map(|i| TableBox {
+                x: (i % cols) as i32 * (DIAGRAM_BOX_WIDTH + DIAGRAM_GAP_X),
+                y: (i / cols) as i32 * (DIAGRAM_BOX_HEIGHT + DIAGRAM_GAP_Y),
+                width: DIAGRAM_BOX_WIDTH,
+                height: DIAGRAM_BOX_HEIGHT,
+            })
+            .collect();
+        DiagramLayout {
+            boxes,
+            width: (cols.min(count) as i32 * (DIAGRAM_BOX_WIDTH + DIAGRAM_GAP_X) - DIAGRAM_GAP_X)
+                .max(0),
+            height: (rows as i32 * (DIAGRAM_BOX_HEIGHT + DIAGRAM_GAP_Y) - DIAGRAM_GAP_Y).max(0),
+        }
+    }
+
+    /// Keep tables joined by foreign keys together: each group of related tables side by
+    /// side, largest first, in rows with the referenced tables above the ones
+    /// referencing them. Tables related to no other go in a grid underneath.
+    pub fn related_layout(&self) -> DiagramLayout {
+        let count = self.tables.len();
+        // Referenced table above referencing one, once per pair; a table's keys to
+        // itself don't move it
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for key in self.relationships() {
+            let index = |name: &str| self.tables.iter().position(|t| t.name == name);
+            if let (Some(parent), Some(child)) = (index(&key.to_table), index(&key.from_table)) {
+                if parent != child && !edges.contains(&(parent, child)) {
+                    edges.push((parent, child));
+                }
+            }
+        }
+        if edges.is_empty() {
+            return self.grid_layout();
+        }
+
+        let mut neighbours = vec![Vec::new(); count];
+        for &(parent, child) in &edges {
+            neighbours[parent].push(child);
+            neighbours[child].push(parent);
+        }
+        let mut groups = connected_groups(&neighbours);
+        groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+        let (groups, lone): (Vec<Vec<usize>>, Vec<Vec<usize>>) =
+            groups.into_iter().partition(|group| group.len() > 1);
+
+        let step_x = DIAGRAM_BOX_WIDTH + DIAGRAM_GAP_X;
+        let step_y = DIAGRAM_BOX_HEIGHT + DIAGRAM_GAP_Y;
+        let mut boxes = vec![
+            TableBox {
+                x: 0,
+                y: 0,
+                width: DIAGRAM_BOX_WIDTH,
+                height: DIAGRAM_BOX_HEIGHT,
+            };
+            count
+        ];
+        let (mut width, mut height) = (0, 0);
+        for group in &groups {
+            let layers = group_layers(group, &edges, &neighbours);
+            let group_width = layers.iter().map(Vec::len).max().unwrap_or(0) as i32 * step_x;
+            // Each row centred under the widest
+            for (row, layer) in layers.iter().enumerate() {
+                let indent = (group_width - layer.len() as i32 * step_x) / 2;
+                for (col, &table) in layer.iter().enumerate() {
+                    boxes[table].x = width + indent + col as i32 * step_x;
+                    boxes[table].y = row as i32 * step_y;
+                }
+            }
+            // A gap's width more between groups, to tell them apart
+            width += group_width + DIAGRAM_GAP_X;
+            height = height.max(layers.len() as i32 * step_y);
+        }
+
+        let lone: Vec<usize> = lone.into_iter().flatten().collect();
+        if !lone.is_empty() {
+            let cols = ((width + DIAGRAM_GAP_X) / step_x)
+                .max((lone.len() as f64).sqrt().ceil() as i32)
+                .max(1);
+            for (i, &table) in lone.iter().enumerate() {
+                boxes[table].x = (i as i32 % cols) * step_x;
+                boxes[table].y = height + (i as i32 / cols) * step_y;
+            }
+            width = width.max(cols.min(lone.len() as i32) * step_x);
+            height += lone.len().div_ceil(cols as usize) as i32 * step_y;
+        }
+
+        DiagramLayout {
+            boxes,
+            width: (width - DIAGRAM_GAP_X).max(0),
+            height: (height - DIAGRAM_GAP_Y).max(0),
+        }
+    }
+}
+
+/// Tables reachable from each other through `neighbours`, each group in table order
+fn connected_groups(neighbours: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut group_of = vec![None; neighbours.len()];
+    let mut groups = Vec::new();
+    for start in 0..neighbours.len() {
+        if group_of[start].is_some() {
+            continue;
+        }
+        let mut group = vec![start];
+        group_of[start] = Some(groups.len());
+        let mut next = 0;
+        while let Some(&table) = group.get(next) {
+            for &other in &neighbours[table] {
+                if group_of[other].is_none() {
+                    group_of[other] = Some(groups.len());
+                    group.push(other);
+                }
+            }
+            next += 1;
+        }
+        group.sort_unstable();
+        groups.push(group);
+    }
+    groups
+}
+
+/// Rows of a group of related tables, each table a row below every table it references.
+/// A cycle of references is broken at the table with the fewest references left to
+/// place. Rows are then ordered to bring each table under the tables it's joined to, so
+/// fewer arrows cross.
+fn group_layers(
+    group: &[usize],
+    edges: &[(usize, usize)],
+    neighbours: &[Vec<usize>],
+) -> Vec<Vec<usize>> {
+    let parents = |table: usize| {
+        edges
+            .iter()
+            .filter(move |&&(_, child)| child == table)
+            .map(|&(parent, _)| parent)
+    };
+    let mut layer_of: Vec<Option<usize>> = vec![None; neighbours.len()];
+    let mut left: Vec<usize> = group.to_vec();
+    while !left.is_empty() {
+        let unplaced = |table: usize| parents(table).filter(|&p| layer_of[p].is_none()).count();
+        let Some((position, &table)) = left
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &table)| unplaced(table))
+        else {
+            break;
+        };
+        left.remove(position);
+        layer_of[table] = Some(
+            parents(table)
+                .filter_map(|p| layer_of[p])
+                .max()
+                .map_or(0, |layer| layer + 1),
+        );
+    }
+
+    let mut layers: Vec<Vec<usize>> = Vec::new();
+    for &table in group {
+        let layer = layer_of[table].unwrap_or(0);
+        if layers.len() <= layer {
+            layers.resize(layer + 1, Vec::new());
+        }
+        layers[layer].push(table);
+    }
+
+    // Sweep down then up a few times, sorting each row by where its tables' neighbours
+    // sit in the row before
+    let mut column: Vec<f64> = vec![0.0; neighbours.len()];
+    let number = |layers: &[Vec<usize>], column: &mut [f64]| {
+        for layer in layers {
+            for (i, &table) in layer.iter().enumerate() {
+                column[table] = i as f64;
+            }
+        }
+    };
+    number(&layers, &mut column);
+    for sweep in 0..4 {
+        let rows: Vec<usize> = if sweep % 2 == 0 {
+            (1..layers.len()).collect()
+        } else {
+            (0..layers.len().saturating_sub(1)).rev().collect()
+        };
+        for row in rows {
+            let beside = if sweep % 2 == 0 { row - 1 } else { row + 1 };
+            let key = |table: usize| {
+                let joined: Vec<f64> = neighbours[table]
+                    .iter()
+                    .filter(|&&other| layer_of[other] == Some(beside))
+                    .map(|&other| column[other])
+                    .collect();
+                if joined.is_empty() {
+                    column[table]
+                } else {
+                    joined.iter().sum::<f64>() / joined.len() as f64
+                }
+            };
+            let mut keyed: Vec<(f64, usize)> = layers[row]
+                .iter()
+                .map(|&table| (key(table), table))
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            layers[row] = keyed.into_iter().map(|(_, table)| table).collect();
+            number(&layers, &mut column);
+        }
+    }
+    layers
+}
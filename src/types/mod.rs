@@ -2,6 +2,11 @@ pub mod diagram;
 pub mod query;
 pub mod table;
 
-pub use diagram::{DiagramData, DiagramTable};
-pub use query::{QueryResult, Value};
-pub use table::{ColumnInfo, ForeignKeyInfo, IndexInfo, TableInfo};
+pub use diagram::{
+    DiagramData, DiagramLayout, DiagramLayoutKind, DiagramTable, Relationship, TableBox,
+};
+pub use query::{
+    row_size, summarize_columns, PageStart, PendingEdit, PlanStep, QueryResult, RowKey, RowsQuery,
+    ScriptStep, SortOrder, Value,
+};
+pub use table::{ColumnInfo, ForeignKeyInfo, IndexInfo, IndexOrigin, TableInfo, TableKind};
@@ -1,4 +1,7 @@
-use rusqlite::types::Value as SqliteValue;
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose, Engine as _};
+use rusqlite::types::{ToSqlOutput, Value as SqliteValue, ValueRef};
+use rusqlite::ToSql;
 use serde::{Deserialize, Serialize};
 
 /// Display-friendly value representation
@@ -23,7 +26,113 @@ impl From<SqliteValue> for Value {
     }
 }
 
+impl From<Value> for SqliteValue {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::Null => SqliteValue::Null,
+            Value::Integer(i) => SqliteValue::Integer(i),
+            Value::Real(r) => SqliteValue::Real(r),
+            Value::Text(t) => SqliteValue::Text(t),
+            Value::Blob(b) => SqliteValue::Blob(b),
+        }
+    }
+}
+
+impl ToSql for Value {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(match self {
+            Value::Null => ValueRef::Null,
+            Value::Integer(i) => ValueRef::Integer(*i),
+            Value::Real(r) => ValueRef::Real(*r),
+            Value::Text(t) => ValueRef::Text(t.as_bytes()),
+            Value::Blob(b) => ValueRef::Blob(b),
+        }))
+    }
+}
+
 impl Value {
+    /// The complete value as text, never truncated and with full REAL precision.
+    /// BLOBs only show their size; `edit_text` spells out their bytes.
+    pub fn full_text(&self) -> String {
+        match self {
+            Value::Real(r) => r.to_string(),
+            Value::Text(t) => t.clone(),
+            _ => self.display(usize::MAX),
+        }
+    }
+
+    /// Full text of the value for editing: BLOBs as hex, NULL as an empty buffer
+    pub fn edit_text(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Blob(b) => {
+                let hex: String = b.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("0x{}", hex)
+            }
+            _ => self.full_text(),
+        }
+    }
+
+    /// Bytes for a BLOB edit: hex with an optional `0x` prefix, or base64 after `b64:`
+    pub fn blob_from_edit(input: &str) -> Result<Vec<u8>> {
+        let input = input.trim();
+        if let Some(encoded) = input.strip_prefix("b64:") {
+            return match general_purpose::STANDARD.decode(encoded.trim()) {
+                Ok(bytes) => Ok(bytes),
+                Err(e) => bail!("Invalid base64: {}", e),
+            };
+        }
+        let hex = input
+            .strip_prefix("0x")
+            .or_else(|| input.strip_prefix("0X"))
+            .unwrap_or(input);
+        let digits: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+        if let Some(bad) = digits.iter().find(|c| !c.is_ascii_hexdigit()) {
+            bail!("Invalid hex digit '{}' (use b64: for base64)", bad);
+        }
+        if !digits.len().is_multiple_of(2) {
+            bail!(
+                "Hex input has an odd number of digits ({}); each byte needs two",
+                digits.len()
+            );
+        }
+        Ok(digits
+            .chunks(2)
+            .map(|pair| {
+                let high = pair[0].to_digit(16).unwrap_or(0);
+                let low = pair[1].to_digit(16).unwrap_or(0);
+                (high * 16 + low) as u8
+            })
+            .collect())
+    }
+
+    /// Typed value for text typed over `original`. Numbers stay numbers when the input
+    /// still parses as one; anything else, including "NULL" and "", is stored as text.
+    pub fn from_edit(input: &str, original: &Value) -> Value {
+        match original {
+            Value::Integer(_) | Value::Null if is_canonical_integer(input) => input
+                .parse()
+                .map(Value::Integer)
+                .unwrap_or_else(|_| Value::Text(input.to_string())),
+            Value::Integer(_) | Value::Real(_) | Value::Null if is_plain_real(input) => input
+                .parse()
+                .map(Value::Real)
+                .unwrap_or_else(|_| Value::Text(input.to_string())),
+            _ => Value::Text(input.to_string()),
+        }
+    }
+
+    /// Roughly how many bytes the value takes in memory: its own size plus any text or
+    /// BLOB it holds
+    pub fn size_hint(&self) -> usize {
+        std::mem::size_of::<Value>()
+            + match self {
+                Value::Text(t) => t.len(),
+                Value::Blob(b) => b.len(),
+                _ => 0,
+            }
+    }
+
     /// Format value for display, truncating long text/blob
     pub fn display(&self, max_len: usize) -> String {
         match self {
@@ -38,8 +147,10 @@ impl Value {
                 }
             }
             Value::Text(t) => {
-                if t.len() > max_len {
-                    format!("{}...", &t[..max_len.saturating_sub(3)])
+                // Count characters, not bytes, so multibyte text truncates safely
+                if t.chars().count() > max_len {
+                    let kept: String = t.chars().take(max_len.saturating_sub(3)).collect();
+                    format!("{}...", kept)
                 } else {
                     t.clone()
                 }
@@ -55,23 +166,176 @@ impl Value {
     }
 }
 
+/// Whether `input` is an integer written the way SQLite would print it, so "007" stays text
+fn is_canonical_integer(input: &str) -> bool {
+    input.parse::<i64>().is_ok_and(|i| i.to_string() == input)
+}
+
+/// Whether `input` is a finite decimal number without leading zeros or surrounding spaces
+fn is_plain_real(input: &str) -> bool {
+    let digits = input.trim_start_matches('-');
+    let leading_zero = digits.starts_with('0') && !digits.starts_with("0.") && digits != "0";
+    input.trim() == input
+        && !leading_zero
+        && input.parse::<f64>().is_ok_and(|r| r.is_finite())
+        && input.chars().any(|c| c.is_ascii_digit())
+}
+
+/// Sort direction for a column in the rows view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    /// SQL keyword for this direction
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+
+    /// Header indicator for this direction
+    pub fn arrow(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "▲",
+            SortOrder::Desc => "▼",
+        }
+    }
+}
+
+/// Filtering and ordering applied when loading a page of table rows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RowsQuery {
+    /// User-supplied WHERE expression
+    pub filter: Option<String>,
+    /// Column that must equal a bound value (used when following a foreign key)
+    pub key_match: Option<(String, Value)>,
+    pub order_by: Option<(String, SortOrder)>,
+}
+
+/// Where a page of unsorted table rows starts, found from the rowids of the page next
+/// to it rather than by counting rows from the top
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageStart {
+    /// The page after the one ending at this rowid
+    After(i64),
+    /// The page before the one starting at this rowid
+    Before(i64),
+}
+
 /// Query execution result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryResult {
     pub columns: Vec<String>,
     pub rows: Vec<Vec<Value>>,
     pub truncated: bool,
+    /// Position of the first row in the statement's full result
+    #[serde(default)]
+    pub offset: usize,
+    /// Whether the statement only reads, so it can be run again for another page
+    #[serde(default)]
+    pub pageable: bool,
     pub exec_ms: u64,
+    /// Key identifying each row for updates; empty when the rows can't be traced to a table row
+    #[serde(default)]
+    pub row_keys: Vec<RowKey>,
+    /// Table the rows can be edited in, for results of a simple single-table SELECT
+    #[serde(default)]
+    pub source_table: Option<String>,
+    /// Rows changed by an INSERT, UPDATE, DELETE or REPLACE, which return no rows
+    #[serde(default)]
+    pub rows_affected: Option<u64>,
+    /// Rowid of the last row the statement inserted
+    #[serde(default)]
+    pub last_insert_rowid: Option<i64>,
+    /// Roughly how many bytes the rows take in memory
+    #[serde(default)]
+    pub bytes: usize,
+    /// The byte budget the rows were cut short at, when reading more would have passed it
+    #[serde(default)]
+    pub memory_cap: Option<usize>,
 }
 
 impl QueryResult {
-    #[allow(dead_code)]
     pub fn new(columns: Vec<String>, rows: Vec<Vec<Value>>, exec_ms: u64) -> Self {
         Self {
+            bytes: rows.iter().map(|row| row_size(row)).sum(),
+            memory_cap: None,
             columns,
             rows,
             truncated: false,
+            offset: 0,
+            pageable: false,
             exec_ms,
+            row_keys: Vec::new(),
+            source_table: None,
+            rows_affected: None,
+            last_insert_rowid: None,
+        }
+    }
+
+    /// One-line outcome of `statement`, e.g. "UPDATE: 57 rows affected" for a write
+    pub fn summary(&self, statement: &str) -> String {
+        let verb = statement
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        match self.rows_affected {
+            Some(count) => {
+                let mut summary = format!(
+                    "{}: {} row{} affected",
+                    verb,
+                    count,
+                    if count == 1 { "" } else { "s" }
+                );
+                if let Some(rowid) = self.last_insert_rowid {
+                    summary.push_str(&format!(", last rowid {}", rowid));
+                }
+                summary
+            }
+            None if self.columns.is_empty() => format!("{}: done in {}ms", verb, self.exec_ms),
+            None => format!("{} in {}ms", self.rows_label(), self.exec_ms),
+        }
+    }
+
+    /// Which rows the result holds: "250 rows", or "rows 1001–2000 of ≥2001" for one
+    /// page of a longer result, noting when the byte budget cut them short
+    pub fn rows_label(&self) -> String {
+        format!("{}{}", self.count_label(), self.memory_note())
+    }
+
+    /// " (truncated at 16 MB)" when the byte budget cut the rows short, else nothing
+    pub fn memory_note(&self) -> String {
+        match self.memory_cap {
+            Some(cap) => format!(" (truncated at {})", megabytes(cap)),
+            None => String::new(),
+        }
+    }
+
+    fn count_label(&self) -> String {
+        let count = self.rows.len();
+        if self.offset == 0 && !self.truncated {
+            return format!("{} rows", count);
+        }
+        // The memory note says why the rows stop
+        if !self.pageable && self.memory_cap.is_some() {
+            return format!("{} rows", count);
+        }
+        if !self.pageable {
+            return format!("{} rows (truncated)", count);
+        }
+        if count == 0 {
+            return format!("no rows past {}", self.offset);
+        }
+        let (first, last) = (self.offset + 1, self.offset + count);
+        if self.truncated {
+            format!("rows {}–{} of ≥{}", first, last, last + 1)
+        } else {
+            format!("rows {}–{} of {}", first, last, last)
         }
     }
 
@@ -80,4 +344,179 @@ impl QueryResult {
         self.truncated = truncated;
         self
     }
+
+    /// Rowids of all rows, when every row is keyed by rowid
+    pub fn rowids(&self) -> Option<Vec<i64>> {
+        if self.row_keys.is_empty() {
+            return None;
+        }
+        self.row_keys
+            .iter()
+            .map(|key| match key {
+                RowKey::Rowid(id) => Some(*id),
+                RowKey::PrimaryKey(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Roughly how many bytes a row of values takes in memory
+pub fn row_size(row: &[Value]) -> usize {
+    std::mem::size_of::<Vec<Value>>() + row.iter().map(Value::size_hint).sum::<usize>()
+}
+
+/// A byte budget in whole megabytes when it is one, e.g. "16 MB"
+fn megabytes(bytes: usize) -> String {
+    const MB: usize = 1024 * 1024;
+    if bytes.is_multiple_of(MB) {
+        format!("{} MB", bytes / MB)
+    } else {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    }
+}
+
+/// What a column's non-NULL values span
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnRange {
+    /// Every value is NULL
+    Empty,
+    Numeric {
+        min: Value,
+        max: Value,
+    },
+    /// Text or BLOB values, which get no min/max
+    Other,
+    /// Numbers alongside text or BLOBs, which don't compare
+    Mixed,
+}
+
+/// Counts and range of one column of a result
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSummary {
+    /// Non-NULL values
+    pub count: usize,
+    pub nulls: usize,
+    pub range: ColumnRange,
+}
+
+impl ColumnSummary {
+    /// Compact footer text, e.g. "n 97 · null 3 · 1..250"
+    pub fn label(&self) -> String {
+        let range = match &self.range {
+            ColumnRange::Numeric { min, max } => {
+                format!(
+                    " · {}..{}",
+                    min.display(usize::MAX),
+                    max.display(usize::MAX)
+                )
+            }
+            ColumnRange::Mixed => " · n/a".to_string(),
+            ColumnRange::Empty | ColumnRange::Other => String::new(),
+        };
+        format!("n {} · null {}{}", self.count, self.nulls, range)
+    }
+}
+
+/// Summarize each of the first `column_count` columns of `rows`
+pub fn summarize_columns(rows: &[Vec<Value>], column_count: usize) -> Vec<ColumnSummary> {
+    let as_number = |value: &Value| match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Real(r) => Some(*r),
+        _ => None,
+    };
+    (0..column_count)
+        .map(|col| {
+            let mut summary = ColumnSummary {
+                count: 0,
+                nulls: 0,
+                range: ColumnRange::Empty,
+            };
+            for value in rows.iter().filter_map(|row| row.get(col)) {
+                if *value == Value::Null {
+                    summary.nulls += 1;
+                    continue;
+                }
+                summary.count += 1;
+                summary.range = match (summary.range, as_number(value)) {
+                    (ColumnRange::Empty, Some(_)) => ColumnRange::Numeric {
+                        min: value.clone(),
+                        max: value.clone(),
+                    },
+                    (ColumnRange::Numeric { min, max }, Some(n)) => {
+                        // NaN never replaces a bound
+                        let below = as_number(&min).is_some_and(|m| n < m);
+                        let above = as_number(&max).is_some_and(|m| n > m);
+                        ColumnRange::Numeric {
+                            min: if below { value.clone() } else { min },
+                            max: if above { value.clone() } else { max },
+                        }
+                    }
+                    (ColumnRange::Empty | ColumnRange::Other, None) => ColumnRange::Other,
+                    _ => ColumnRange::Mixed,
+                };
+            }
+            summary
+        })
+        .collect()
+}
+
+/// One statement of a script run from the SQL editor, and how it went
+#[derive(Debug, Clone)]
+pub struct ScriptStep {
+    pub statement: String,
+    /// The statement's result or error; None when an earlier statement failed first
+    pub outcome: Option<Result<QueryResult, String>>,
+}
+
+/// One step of `EXPLAIN QUERY PLAN`, placed in the plan's tree
+#[derive(Debug, Clone)]
+pub struct PlanStep {
+    /// Nesting below the plan's top level
+    pub depth: usize,
+    pub detail: String,
+}
+
+impl PlanStep {
+    /// A scan of a whole table that no index helps with
+    pub fn is_full_scan(&self) -> bool {
+        self.detail.starts_with("SCAN")
+            && !self.detail.contains("INDEX")
+            && !self.detail.contains("CONSTANT ROW")
+    }
+
+    /// A lookup through an index or the rowid
+    pub fn uses_index(&self) -> bool {
+        self.detail.starts_with("SEARCH") && self.detail.contains(" USING ")
+    }
+}
+
+/// A cell change held back until all pending edits are written together
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEdit {
+    pub table_name: String,
+    pub key: RowKey,
+    pub column_name: String,
+    pub value: Value,
+}
+
+/// Identifies a table row: its rowid, or its primary key for WITHOUT ROWID tables
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RowKey {
+    Rowid(i64),
+    PrimaryKey(Vec<(String, Value)>),
+}
+
+impl std::fmt::Display for RowKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RowKey::Rowid(rowid) => write!(f, "rowid {}", rowid),
+            RowKey::PrimaryKey(values) => {
+                let parts: Vec<String> = values
+                    .iter()
+                    .map(|(column, value)| format!("{} = {}", column, value.display(30)))
+                    .collect();
+                write!(f, "{}", parts.join(", "))
+            }
+        }
+    }
 }
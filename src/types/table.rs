@@ -3,13 +3,67 @@ use serde::{Deserialize, Serialize};
 /// Information about a database table
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableInfo {
+    /// `alias.table` for a table in an attached database
     pub name: String,
+    /// Alias of the attached database holding the table; None for the main database
+    pub schema: Option<String>,
     pub row_count: Option<u64>,
+    /// The row count is the largest rowid, standing in for a count of a table too big
+    /// to count
+    #[serde(default)]
+    pub row_count_estimated: bool,
+    /// Bytes the table and its indexes take on disk, once measured with dbstat
+    pub size: Option<u64>,
     pub sql: Option<String>,
+    pub kind: TableKind,
+}
+
+/// What kind of schema object a [`TableInfo`] lists, in the order the Tables pane
+/// groups them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum TableKind {
+    #[default]
+    Table,
+    View,
+    /// A virtual table, such as an FTS index
+    Virtual,
+    Trigger,
+}
+
+impl TableKind {
+    /// Heading of the Tables pane section listing this kind
+    pub fn section(&self) -> &'static str {
+        match self {
+            TableKind::Table => "Tables",
+            TableKind::View => "Views",
+            TableKind::Virtual => "Virtual",
+            TableKind::Trigger => "Triggers",
+        }
+    }
+
+    /// What to call one of this kind
+    pub fn label(&self) -> &'static str {
+        match self {
+            TableKind::Table => "table",
+            TableKind::View => "view",
+            TableKind::Virtual => "virtual table",
+            TableKind::Trigger => "trigger",
+        }
+    }
+}
+
+impl TableInfo {
+    /// The table's name within its own database, without the alias of an attachment
+    pub fn bare_name(&self) -> &str {
+        match &self.schema {
+            Some(schema) => &self.name[schema.len() + 1..],
+            None => &self.name,
+        }
+    }
 }
 
 /// Information about a table column
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ColumnInfo {
     pub name: String,
     pub data_type: String,
@@ -27,10 +81,56 @@ pub struct IndexInfo {
     pub unique: bool,
     pub columns: Vec<String>,
     pub sql: Option<String>,
+    pub origin: IndexOrigin,
+    /// Covers only the rows matching its WHERE clause
+    pub partial: bool,
+}
+
+/// Where an index came from, as `PRAGMA index_list` reports it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IndexOrigin {
+    /// CREATE INDEX
+    #[default]
+    Created,
+    /// A UNIQUE constraint of the table
+    Unique,
+    /// The table's PRIMARY KEY
+    PrimaryKey,
+}
+
+impl IndexOrigin {
+    /// The origin from index_list's code: "c", "u" or "pk"
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "u" => IndexOrigin::Unique,
+            "pk" => IndexOrigin::PrimaryKey,
+            _ => IndexOrigin::Created,
+        }
+    }
+}
+
+impl IndexInfo {
+    /// Another of the table's `indexes` that makes this one redundant, its leading
+    /// columns being this one's. Unique and partial indexes do more than speed up
+    /// lookups, so they're never redundant; of two alike, the one named later is.
+    pub fn covered_by<'a>(&self, indexes: &'a [IndexInfo]) -> Option<&'a IndexInfo> {
+        let expression = |index: &IndexInfo| index.columns.iter().any(|c| c == "<expression>");
+        if self.unique || self.partial || self.columns.is_empty() || expression(self) {
+            return None;
+        }
+        let n = self.columns.len();
+        indexes.iter().find(|other| {
+            other.name != self.name
+                && !other.partial
+                && other.columns.len() >= n
+                && other.columns[..n] == self.columns[..]
+                && (other.columns.len() > n || other.unique || other.name < self.name)
+        })
+    }
 }
 
 /// Information about a foreign key constraint
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ForeignKeyInfo {
     pub id: i32,
     pub from_table: String,
@@ -0,0 +1,106 @@
+use crate::app::BlobViewer;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Known file signatures, checked against the start of a BLOB
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF8", "GIF image"),
+    (b"\x1f\x8b", "gzip data"),
+    (b"SQLite format 3\0", "SQLite database"),
+    (b"%PDF", "PDF document"),
+    (b"PK\x03\x04", "ZIP archive"),
+];
+
+/// Guess what a BLOB holds from its leading bytes
+fn guess_kind(bytes: &[u8]) -> Option<&'static str> {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map(|(_, kind)| *kind)
+}
+
+/// Format one hex dump line: offset, hex bytes, and printable ASCII
+fn hex_line(offset: usize, chunk: &[u8]) -> Line<'static> {
+    let mut hex = String::with_capacity(BlobViewer::BYTES_PER_LINE * 3 + 1);
+    for i in 0..BlobViewer::BYTES_PER_LINE {
+        if i == BlobViewer::BYTES_PER_LINE / 2 {
+            hex.push(' ');
+        }
+        match chunk.get(i) {
+            Some(b) => hex.push_str(&format!("{:02x} ", b)),
+            None => hex.push_str("   "),
+        }
+    }
+    let ascii: String = chunk
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+
+    Line::from(vec![
+        Span::styled(
+            format!("{:08x}  ", offset),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::raw(hex),
+        Span::styled(format!(" |{}|", ascii), Style::default().fg(Color::Cyan)),
+    ])
+}
+
+/// Render the BLOB hex viewer popup
+pub fn render_blob_viewer(frame: &mut Frame, area: Rect, viewer: &BlobViewer) {
+    let popup_area = super::centered_rect(80, 80, area);
+    frame.render_widget(Clear, popup_area);
+
+    let kind = guess_kind(&viewer.bytes)
+        .map(|k| format!(" ({})", k))
+        .unwrap_or_default();
+    let block = Block::default()
+        .title(format!(
+            " BLOB: {} - {} bytes{} | PgUp/PgDn: scroll | e: edit | Esc: close ",
+            viewer.column,
+            viewer.bytes.len(),
+            kind
+        ))
+        .borders(Borders::ALL)
+        .border_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    // Only format the lines that fit, so huge BLOBs render as fast as small ones
+    let visible = inner.height as usize;
+    let start = viewer.scroll.min(viewer.line_count().saturating_sub(1));
+    let first_byte = (start * BlobViewer::BYTES_PER_LINE).min(viewer.bytes.len());
+    let lines: Vec<Line> = viewer.bytes[first_byte..]
+        .chunks(BlobViewer::BYTES_PER_LINE)
+        .take(visible)
+        .enumerate()
+        .map(|(i, chunk)| hex_line(first_byte + i * BlobViewer::BYTES_PER_LINE, chunk))
+        .collect();
+
+    let lines = if lines.is_empty() {
+        vec![Line::from(Span::styled(
+            "(empty BLOB)",
+            Style::default().fg(Color::Gray),
+        ))]
+    } else {
+        lines
+    };
+    frame.render_widget(Paragraph::new(lines), inner);
+}
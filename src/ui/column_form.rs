@@ -0,0 +1,96 @@
+use crate::app::{ColumnField, ColumnForm};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Render the Schema view's form for a column to add
+pub fn render_column_form(frame: &mut Frame, area: Rect, form: &ColumnForm) {
+    let popup_area = super::centered_rect(50, 40, area);
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let dim = Style::default().fg(Color::DarkGray);
+    let label_width = ColumnField::ALL
+        .iter()
+        .map(|field| field.label().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for field in ColumnField::ALL {
+        let selected = field == form.selected;
+        let label_style = if selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        let mut spans = vec![
+            Span::styled(if selected { "▶ " } else { "  " }, label_style),
+            Span::styled(
+                format!("{:<width$}  ", field.label(), width = label_width),
+                label_style,
+            ),
+        ];
+        let (text, placeholder) = match field {
+            ColumnField::Name => (&form.name, ""),
+            ColumnField::Type => (&form.data_type, "e.g. TEXT, INTEGER; empty for none"),
+            ColumnField::Default => (&form.default, "e.g. 0, 'n/a', CURRENT_TIMESTAMP"),
+            ColumnField::NotNull => {
+                spans.push(Span::raw(if form.not_null { "[x]" } else { "[ ]" }));
+                lines.push(Line::from(spans));
+                continue;
+            }
+        };
+        if selected {
+            let before: String = text.chars().take(form.cursor_pos).collect();
+            let after: String = text.chars().skip(form.cursor_pos).collect();
+            spans.push(Span::raw(before));
+            spans.push(Span::styled(
+                "█",
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            ));
+            spans.push(Span::raw(after));
+        } else {
+            spans.push(Span::raw(text.clone()));
+        }
+        if text.is_empty() {
+            spans.push(Span::styled(format!(" {}", placeholder), dim));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    if let Some(error) = &form.error {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Enter", key_style),
+        Span::raw(": add  "),
+        Span::styled("Tab/↑↓", key_style),
+        Span::raw(": field  "),
+        Span::styled("Space", key_style),
+        Span::raw(": toggle NOT NULL  "),
+        Span::styled("Esc", key_style),
+        Span::raw(": cancel"),
+    ]));
+
+    let block = Block::default()
+        .title(format!(" Add column to {} ", form.table))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    let para = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(para, popup_area);
+}
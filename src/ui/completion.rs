@@ -0,0 +1,88 @@
+use crate::app::completion::Completion;
+use crate::ui::text_editor::calculate_cursor_info;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Most candidates shown at once; the list scrolls to keep the selection in view
+const MAX_VISIBLE: usize = 8;
+
+/// Render the completion popup just below the cursor in the editor at `editor_area`,
+/// kept inside `bounds`
+pub fn render_completion_popup(
+    frame: &mut Frame,
+    editor_area: Rect,
+    bounds: Rect,
+    completion: &Completion,
+    text: &str,
+    cursor_pos: usize,
+) {
+    let label_width = 8;
+    let text_width = completion
+        .candidates
+        .iter()
+        .map(|c| c.text.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(18);
+    let width = ((text_width + label_width + 3) as u16).min(bounds.width);
+    let visible = completion.candidates.len().clamp(1, MAX_VISIBLE);
+    let height = (visible as u16 + 2).min(bounds.height);
+
+    // The editor's text starts inside its border; lines are counted from 1
+    let (line, col) = calculate_cursor_info(text, cursor_pos);
+    let below_cursor = editor_area.y + line.max(1) as u16 + 1;
+    let y = if below_cursor + height <= bounds.bottom() {
+        below_cursor
+    } else {
+        bounds.bottom().saturating_sub(height)
+    };
+    let x = (editor_area.x + 1 + col as u16).min(bounds.right().saturating_sub(width));
+    let area = Rect::new(x, y, width, height);
+    frame.render_widget(Clear, area);
+
+    let first = completion
+        .selected
+        .saturating_sub(visible.saturating_sub(1));
+    let mut lines: Vec<Line> = completion
+        .candidates
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(i, candidate)| {
+            let style = if i == completion.selected {
+                Style::default()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{:<width$} ", candidate.text, width = text_width),
+                    style,
+                ),
+                Span::styled(
+                    format!("{:>width$}", candidate.kind.label(), width = label_width),
+                    style.fg(Color::Gray),
+                ),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "Loading columns...",
+            Style::default().fg(Color::Gray),
+        )));
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
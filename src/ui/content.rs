@@ -1,12 +1,21 @@
-use crate::app::{App, Focus, ViewMode};
+use crate::app::{App, Focus, ResultLayout, RowGutter, ViewMode};
+use crate::db::{ColumnAction, IndexAction};
+use crate::types::{summarize_columns, IndexOrigin, QueryResult, Value};
 use crate::ui::diagram::render_diagram;
 use ratatui::{
-    layout::{Constraint, Rect},
+    layout::{Alignment, Constraint, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
+    widgets::{
+        block::Title, Block, Borders, Cell, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Table, TableState, Wrap,
+    },
     Frame,
 };
+use std::borrow::Cow;
+
+/// Characters of the last query shown in the Query view's title
+const QUERY_TITLE_WIDTH: usize = 40;
 
 pub fn render_content(frame: &mut Frame, area: Rect, app: &App) {
     let (border_style, title_style) = if app.state.focus == Focus::Content {
@@ -26,14 +35,62 @@ pub fn render_content(frame: &mut Frame, area: Rect, app: &App) {
     };
 
     let title = match app.state.view_mode {
-        ViewMode::Rows => " Content ",
-        ViewMode::Schema => " Schema ",
-        ViewMode::Query => " Query Results ",
-        ViewMode::Diagram => " ER Diagram ",
+        ViewMode::Rows => " Content ".to_string(),
+        ViewMode::Schema => " Schema ".to_string(),
+        // Name the query the results came from by its first line
+        ViewMode::Query => match app.state.last_executed_query.as_deref() {
+            Some(sql) => {
+                let first_line = sql.trim().lines().next().unwrap_or_default();
+                let mut shown: String = first_line.chars().take(QUERY_TITLE_WIDTH).collect();
+                if first_line.chars().count() > QUERY_TITLE_WIDTH || sql.trim().contains('\n') {
+                    shown.push('…');
+                }
+                format!(" Query Results: {} ", shown)
+            }
+            None => " Query Results ".to_string(),
+        },
+        ViewMode::Diagram => {
+            let mut title = format!(
+                " ER Diagram: {} layout",
+                app.state.diagram_layout_kind.label()
+            );
+            if let Some(filter) = &app.state.diagram_filter {
+                title.push_str(&format!(", matching '{}'", filter));
+            }
+            if let Some(name) = &app.state.diagram_neighbours_of {
+                title.push_str(&format!(", joined to {}", name));
+            }
+            // How much the filters leave out
+            if let (Some(all), Some(shown)) = (&app.state.diagram_data, &app.state.diagram_shown) {
+                if shown.tables.len() < all.tables.len() {
+                    title.push_str(&format!(
+                        " ({} of {} tables)",
+                        shown.tables.len(),
+                        all.tables.len()
+                    ));
+                }
+            }
+            title.push(' ');
+            title
+        }
+    };
+
+    // Open mode badge, so a read-only session is obvious before trying to edit
+    let badge = if app.state.read_only {
+        Span::styled(" RO ", Style::default().fg(Color::Black).bg(Color::Green))
+    } else {
+        Span::styled(
+            " RW ",
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        )
     };
 
     let block = Block::default()
         .title(title)
+        .title(Title::from(badge).alignment(Alignment::Right))
         .title_style(title_style)
         .borders(Borders::ALL)
         .border_style(border_style);
@@ -51,7 +108,11 @@ fn render_rows(frame: &mut Frame, area: Rect, app: &App, block: Block) {
     frame.render_widget(block, area);
 
     if app.state.rows_loading {
-        let loading = Paragraph::new("Loading...")
+        let text = app
+            .state
+            .busy_message()
+            .unwrap_or_else(|| "Loading...".to_string());
+        let loading = Paragraph::new(text)
             .style(Style::default().fg(Color::Yellow))
             .block(Block::default());
         frame.render_widget(loading, inner);
@@ -67,80 +128,175 @@ fn render_rows(frame: &mut Frame, area: Rect, app: &App, block: Block) {
             return;
         }
 
-        // Calculate column widths (equal distribution)
-        let col_count = result.columns.len().max(1);
+        // Row numbers (and rowids, when the table has them) sit in a gutter left of the grid
+        let first_row = app.state.page_offset(app.state.current_page);
+        let row_numbers = app.state.row_gutter != RowGutter::Hidden;
+        let rowids = match app.state.row_gutter {
+            RowGutter::NumbersAndRowid => result.rowids(),
+            _ => None,
+        };
+        let mut gutter_widths = Vec::new();
+        if row_numbers {
+            let last = first_row + result.rows.len().max(1);
+            gutter_widths.push(last.to_string().len().max(1) as u16);
+        }
+        if let Some(rowids) = &rowids {
+            let widest = rowids
+                .iter()
+                .map(|id| id.to_string().len())
+                .fold("rowid".len(), usize::max);
+            gutter_widths.push(widest as u16);
+        }
+        let gutter_width: u16 = gutter_widths.iter().map(|w| w + 1).sum();
+
+        let layout = layout_columns(
+            &result.columns,
+            &result.rows,
+            // Leave the rightmost column for the scrollbar
+            inner.width.saturating_sub(gutter_width + 1),
+            1,
+            app.state.auto_column_width,
+            app.state.pinned_cols,
+            app.state.selected_col,
+        );
+        let header_style = Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD);
 
-        // Build table rows
-        let header: Vec<Cell> = result
-            .columns
+        // Build table rows, marking the sorted column
+        let header: Vec<Cell> = layout
+            .indices
             .iter()
-            .map(|col| {
-                Cell::from(col.as_str()).style(
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
-                )
+            .map(|&col_idx| {
+                let col = &result.columns[col_idx];
+                let label = match &app.state.sort_column {
+                    Some((name, order)) if name == col => format!("{} {}", col, order.arrow()),
+                    _ => col.clone(),
+                };
+                Cell::from(label).style(header_style)
             })
             .collect();
+        let mut header = layout.with_separator(header, header_style, 1);
+        let gutter_style = Style::default().fg(Color::Gray);
+        if rowids.is_some() {
+            header.insert(0, Cell::from("rowid").style(gutter_style));
+        }
+        if row_numbers {
+            header.insert(0, Cell::from("#").style(gutter_style));
+        }
 
-        // Calculate max width per column (accounting for spacing)
-        let max_width = (inner.width as usize / col_count).saturating_sub(2).min(50);
+        let selected = app
+            .state
+            .selected_row
+            .min(result.rows.len().saturating_sub(1));
+        // The header takes one line; the status line sits on the bottom border
+        let visible_height = (inner.height as usize).saturating_sub(1).max(1);
+        let mut row_heights = Vec::with_capacity(result.rows.len());
+        let selection = app.state.selected_row_range();
+        let multi_edit = app.state.multi_edit_rows();
 
         let rows: Vec<Row> = result
             .rows
             .iter()
             .enumerate()
             .map(|(row_idx, row)| {
-                let cells: Vec<Cell> = row
+                let mut height = 1;
+                let cells: Vec<Cell> = layout
+                    .indices
                     .iter()
-                    .enumerate()
-                    .map(|(col_idx, val)| {
+                    .zip(&layout.widths)
+                    .filter_map(|(&col_idx, &width)| row.get(col_idx).map(|v| (col_idx, v, width)))
+                    .map(|(col_idx, val, width)| {
+                        // A multi-row edit shows the buffer in every selected row
                         let is_editing = app.state.edit_mode
-                            && app.state.editing_row == Some(row_idx)
-                            && app.state.editing_col == Some(col_idx);
-
-                        let display = if is_editing {
-                            // Show edit buffer
-                            if app.state.edit_buffer.is_empty() {
-                                val.display(max_width)
-                            } else {
-                                // Truncate edit buffer if too long for display
-                                let buf = &app.state.edit_buffer;
-                                if buf.len() > max_width {
-                                    format!("{}...", &buf[..max_width.saturating_sub(3)])
-                                } else {
-                                    buf.clone()
-                                }
-                            }
-                        } else {
-                            val.display(max_width)
-                        };
+                            && app.state.editing_col == Some(col_idx)
+                            && (app.state.editing_row == Some(row_idx)
+                                || multi_edit
+                                    .as_ref()
+                                    .is_some_and(|rows| rows.contains(&row_idx)));
 
-                        let mut cell = Cell::from(display);
+                        let max_width = width as usize;
                         if is_editing {
+                            // Show edit buffer, truncated if too long for display
+                            let display = if app.state.edit_null {
+                                "∅".to_string()
+                            } else if app.state.edit_buffer.is_empty() {
+                                String::new()
+                            } else {
+                                Value::Text(app.state.edit_buffer.clone()).display(max_width)
+                            };
                             // Highlight editing cell
-                            cell = cell.style(
+                            return Cell::from(display).style(
                                 Style::default()
                                     .bg(Color::Yellow)
                                     .fg(Color::Black)
                                     .add_modifier(Modifier::BOLD),
                             );
                         }
+
+                        let pending = pending_cell(app, result, row_idx, col_idx);
+                        let val = pending.unwrap_or(val);
+                        let max_lines = match (app.state.wrap_cells, row_idx == selected) {
+                            (false, _) => 1,
+                            (true, false) => MAX_WRAP_LINES,
+                            // The selected row expands to show its cells in full
+                            (true, true) => visible_height,
+                        };
+                        let (display, mut style) =
+                            cell_content(val, max_width, app.state.show_value_markers, max_lines);
+                        if pending.is_some() {
+                            style = style.patch(PENDING_STYLE);
+                        }
+                        if max_lines > 1 {
+                            height = height.max(display.lines().count());
+                        }
+                        let mut cell = Cell::from(display).style(style);
+                        if row_idx == selected && col_idx == app.state.selected_col {
+                            // Highlight the cell cursor
+                            cell = cell.style(
+                                style.patch(Style::default().bg(Color::Blue).fg(Color::White)),
+                            );
+                        } else if app.state.cell_matches_search(val) {
+                            cell =
+                                cell.style(style.patch(
+                                    Style::default().bg(Color::LightMagenta).fg(Color::Black),
+                                ));
+                        }
                         cell
                     })
                     .collect();
-                Row::new(cells)
+                let mut cells =
+                    layout.with_separator(cells, Style::default().fg(Color::Gray), height);
+                if let Some(rowids) = &rowids {
+                    let rowid = rowids.get(row_idx).map(|id| id.to_string());
+                    cells.insert(0, Cell::from(rowid.unwrap_or_default()).style(gutter_style));
+                }
+                if row_numbers {
+                    let number =
+                        format!("{:>1$}", first_row + row_idx + 1, gutter_widths[0] as usize);
+                    cells.insert(0, Cell::from(number).style(gutter_style));
+                }
+                row_heights.push(height);
+                let row = Row::new(cells).height(height as u16);
+                if selection
+                    .as_ref()
+                    .is_some_and(|rows| rows.contains(&row_idx))
+                {
+                    row.style(Style::default().bg(Color::Cyan).fg(Color::Black))
+                } else if row_idx == selected {
+                    row.style(Style::default().bg(Color::DarkGray))
+                } else {
+                    row
+                }
             })
             .collect();
-        let widths: Vec<Constraint> = (0..col_count)
-            .map(|_| Constraint::Percentage((100 / col_count as u16).max(1)))
+        let mut widths: Vec<Constraint> = gutter_widths
+            .iter()
+            .map(|&w| Constraint::Length(w))
             .collect();
+        widths.extend(layout.constraints());
 
-        let header_row = Row::new(header).style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        );
+        let header_row = Row::new(header).style(header_style);
 
         let table = Table::new(rows, widths.as_slice())
             .header(header_row)
@@ -149,40 +305,187 @@ fn render_rows(frame: &mut Frame, area: Rect, app: &App, block: Block) {
             .widths(widths.as_slice())
             .style(Style::default().fg(Color::White));
 
-        frame.render_widget(table, inner);
+        // Scroll so the selected row stays visible, counting wrapped rows' heights
+        let mut offset = selected;
+        let mut used = row_heights.get(selected).copied().unwrap_or(1);
+        while offset > 0 && used + row_heights[offset - 1] <= visible_height {
+            offset -= 1;
+            used += row_heights[offset];
+        }
+        let mut table_state = TableState::default()
+            .with_selected(Some(selected))
+            .with_offset(offset);
+
+        let grid_area = Rect::new(
+            inner.x,
+            inner.y,
+            inner.width.saturating_sub(1),
+            inner.height,
+        );
+        frame.render_stateful_widget(table, grid_area, &mut table_state);
+
+        // Rows hidden above and below the visible window
+        let mut last_visible = offset;
+        let mut shown = 0;
+        while last_visible < row_heights.len()
+            && shown + row_heights[last_visible] <= visible_height
+        {
+            shown += row_heights[last_visible];
+            last_visible += 1;
+        }
+        let rows_above = offset;
+        let rows_below = result.rows.len().saturating_sub(last_visible);
+        if rows_above > 0 || rows_below > 0 {
+            let mut scrollbar_state = ScrollbarState::new(result.rows.len()).position(selected);
+            frame.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(Some("▲"))
+                    .end_symbol(Some("▼")),
+                Rect::new(
+                    inner.x,
+                    inner.y + 1,
+                    inner.width,
+                    inner.height.saturating_sub(1),
+                ),
+                &mut scrollbar_state,
+            );
+        }
+
+        // A foreign key jump that found nothing should say so rather than show an empty grid
+        if let (true, Some((col, val))) = (result.rows.is_empty(), &app.state.key_match) {
+            let message = Paragraph::new(format!(
+                "No matching row where {} = {} (Backspace: back)",
+                col,
+                val.display(50)
+            ))
+            .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(
+                message,
+                Rect::new(
+                    inner.x,
+                    inner.y + 1,
+                    inner.width,
+                    inner.height.saturating_sub(1),
+                ),
+            );
+        }
 
         // Show page info or edit mode hint
         let info_text = if app.state.edit_mode {
             if app.state.full_edit_mode {
                 "FULL EDIT MODE - Press Enter to save, Shift+Enter for newline, Esc to exit full editor".to_string()
-            } else if let Some(error) = &app.state.query_error {
+            } else if let Some(error) = &app.state.edit_error {
                 format!("ERROR: {} | Esc: Cancel | Ctrl+E: Full editor", error)
             } else {
+                let rows = match &multi_edit {
+                    Some(rows) => format!("{} rows", rows.end() - rows.start() + 1),
+                    None => format!("Row {}", app.state.editing_row.map(|r| r + 1).unwrap_or(0)),
+                };
                 format!(
-                    "EDIT MODE - Row {}, Col {} | Enter: Save | Esc: Cancel | Ctrl+E: Full editor",
-                    app.state.editing_row.map(|r| r + 1).unwrap_or(0),
+                    "EDIT MODE - {}, Col {} | Enter: Save | Esc: Cancel | Ctrl+E: Full editor | Ctrl+N: NULL",
+                    rows,
                     app.state.editing_col.map(|c| c + 1).unwrap_or(0),
                 )
             }
         } else {
-            let total_rows = app
+            // The table's total row count doesn't apply to a filtered view
+            let total_rows = if app.state.row_filter.is_some() || app.state.key_match.is_some() {
+                String::new()
+            } else {
+                app.state
+                    .table_info
+                    .as_ref()
+                    .and_then(|ti| ti.row_count)
+                    .map(|r| format!(" of {}", r))
+                    .unwrap_or_default()
+            };
+            let filter = app
                 .state
-                .table_info
+                .row_filter
                 .as_ref()
-                .and_then(|ti| ti.row_count)
-                .map(|r| format!(" of {}", r))
+                .map(|f| format!(" | Filter: {}", f))
                 .unwrap_or_default();
+            let key_match = app
+                .state
+                .key_match
+                .as_ref()
+                .map(|(col, val)| format!(" | {} = {} (Backspace: back)", col, val.display(30)))
+                .unwrap_or_default();
+            let search = app
+                .state
+                .search_term
+                .as_ref()
+                .map(|term| {
+                    let matches = app.state.search_matches();
+                    if matches.is_empty() {
+                        format!(" | Search: {} (no matches)", term)
+                    } else {
+                        format!(
+                            " | Search: {} (match {}/{})",
+                            term,
+                            app.state.search_match_index.min(matches.len() - 1) + 1,
+                            matches.len()
+                        )
+                    }
+                })
+                .unwrap_or_default();
+            let mut scroll_hints = Vec::new();
+            if rows_above > 0 {
+                scroll_hints.push(format!("▲ {} more above", rows_above));
+            }
+            if rows_below > 0 {
+                scroll_hints.push(format!("▼ {} more below", rows_below));
+            }
+            if layout.more_right(result.columns.len()) {
+                scroll_hints.push("▶ more columns".to_string());
+            }
+            let scroll_hints = if scroll_hints.is_empty() {
+                String::new()
+            } else {
+                format!(" | {}", scroll_hints.join(", "))
+            };
+            let watch = match (&app.state.last_refresh, app.state.watch) {
+                (Some(time), true) => format!(" | refreshed {}", time),
+                (None, true) => " | watching".to_string(),
+                (_, false) => String::new(),
+            };
+            let mut status = app
+                .state
+                .status_message
+                .as_ref()
+                .map(|m| format!("{} | ", m))
+                .unwrap_or_default();
+            if let Some(rows) = &selection {
+                let count = rows.end() - rows.start() + 1;
+                status.push_str(&format!(
+                    "{} row{} selected (Enter: edit column, Esc: clear) | ",
+                    count,
+                    if count == 1 { "" } else { "s" }
+                ));
+            }
             format!(
-                "Page {} (showing {} rows{}) - Use Left/Right to navigate | Enter: Edit cell",
+                "{}Page {}{} (row {}/{}{}{}, col {}){}{}{}{}{}{}{} - Ctrl+Left/Right or h/l: pages | f: filter | /: search | F: follow FK | Enter: Edit cell",
+                status,
+                if app.state.page_estimated() { "~" } else { "" },
                 app.state.current_page + 1,
+                (app.state.selected_row + 1).min(result.rows.len()),
                 result.rows.len(),
-                total_rows
+                total_rows,
+                result.memory_note(),
+                app.state.selected_col + 1,
+                filter,
+                key_match,
+                search,
+                layout.describe(result.columns.len()),
+                scroll_hints,
+                watch,
+                pending_hint(app)
             )
         };
         let info_line = Line::from(Span::styled(
             info_text,
             Style::default().fg(if app.state.edit_mode {
-                if app.state.query_error.is_some() {
+                if app.state.edit_error.is_some() {
                     Color::Red
                 } else {
                     Color::Yellow
@@ -195,6 +498,12 @@ fn render_rows(frame: &mut Frame, area: Rect, app: &App, block: Block) {
             info_line,
             Rect::new(area.x, area.y + area.height - 1, area.width, 1),
         );
+    } else if let (Some(_), Some(error)) = (&app.state.current_table, &app.state.rows_error) {
+        let error_para = Paragraph::new(format!("Error: {}", error))
+            .style(Style::default().fg(Color::Red))
+            .block(Block::default())
+            .wrap(Wrap { trim: true });
+        frame.render_widget(error_para, inner);
     } else if let Some(table_name) = &app.state.current_table {
         let empty = Paragraph::new(format!("No data for table: {}", table_name))
             .style(Style::default().fg(Color::Gray))
@@ -213,13 +522,26 @@ fn render_schema(frame: &mut Frame, area: Rect, app: &App, block: Block) {
     frame.render_widget(block, area);
 
     if app.state.schema_loading {
-        let loading = Paragraph::new("Loading schema...")
+        let text = app
+            .state
+            .busy_message()
+            .unwrap_or_else(|| "Loading schema...".to_string());
+        let loading = Paragraph::new(text)
             .style(Style::default().fg(Color::Yellow))
             .block(Block::default());
         frame.render_widget(loading, inner);
         return;
     }
 
+    // Columns are cleared when loading starts, so there are none after a failure
+    if let (Some(error), true) = (&app.state.schema_error, app.state.schema_columns.is_empty()) {
+        let error_para = Paragraph::new(format!("Error: {}", error))
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(error_para, inner);
+        return;
+    }
+
     if let Some(table_name) = &app.state.current_table {
         let mut lines = Vec::new();
 
@@ -238,11 +560,28 @@ fn render_schema(frame: &mut Frame, area: Rect, app: &App, block: Block) {
                 .add_modifier(Modifier::BOLD),
         )));
 
+        let focused = app.state.focus == Focus::Content;
+        let entry_style = |selected: bool| {
+            if selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            }
+        };
+        // Line of the selected column or index, to keep on screen
+        let mut selected_line = 0;
         if app.state.schema_columns.is_empty() {
             lines.push(Line::from("  (no columns)"));
         } else {
-            for col in &app.state.schema_columns {
-                let mut col_text = format!("  {}", col.name);
+            for (i, col) in app.state.schema_columns.iter().enumerate() {
+                let selected = focused && i == app.state.schema_selected;
+                if selected {
+                    selected_line = lines.len();
+                }
+                let mut col_text = format!("{}{}", if selected { "▶ " } else { "  " }, col.name);
                 col_text.push_str(&format!(" ({})", col.data_type));
                 if col.primary_key {
                     col_text.push_str(" PRIMARY KEY");
@@ -253,10 +592,7 @@ fn render_schema(frame: &mut Frame, area: Rect, app: &App, block: Block) {
                 if let Some(default) = &col.default_value {
                     col_text.push_str(&format!(" DEFAULT {}", default));
                 }
-                lines.push(Line::from(Span::styled(
-                    col_text,
-                    Style::default().fg(Color::White),
-                )));
+                lines.push(Line::from(Span::styled(col_text, entry_style(selected))));
             }
         }
 
@@ -269,12 +605,38 @@ fn render_schema(frame: &mut Frame, area: Rect, app: &App, block: Block) {
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
             )));
-            for idx in &app.state.schema_indexes {
-                let idx_text = format!("  {} ({})", idx.name, idx.columns.join(", "));
-                lines.push(Line::from(Span::styled(
-                    idx_text,
-                    Style::default().fg(Color::White),
-                )));
+            let indexes = &app.state.schema_indexes;
+            for (i, idx) in indexes.iter().enumerate() {
+                let selected =
+                    focused && app.state.schema_columns.len() + i == app.state.schema_selected;
+                if selected {
+                    selected_line = lines.len();
+                }
+                let mut idx_text = format!(
+                    "{}{} ({})",
+                    if selected { "▶ " } else { "  " },
+                    idx.name,
+                    idx.columns.join(", ")
+                );
+                if idx.unique {
+                    idx_text.push_str(" UNIQUE");
+                }
+                if idx.partial {
+                    idx_text.push_str(" partial");
+                }
+                match idx.origin {
+                    IndexOrigin::Created => {}
+                    IndexOrigin::Unique => idx_text.push_str(" - from a UNIQUE constraint"),
+                    IndexOrigin::PrimaryKey => idx_text.push_str(" - the primary key"),
+                }
+                let mut spans = vec![Span::styled(idx_text, entry_style(selected))];
+                if let Some(covering) = idx.covered_by(indexes) {
+                    spans.push(Span::styled(
+                        format!(" - redundant, {} covers it", covering.name),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                lines.push(Line::from(spans));
             }
         }
 
@@ -296,9 +658,65 @@ fn render_schema(frame: &mut Frame, area: Rect, app: &App, block: Block) {
             }
         }
 
+        // Keys of other tables pointing at this one
+        if !app.state.schema_referenced_by.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                "Referenced by:",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            let first = app.state.schema_columns.len() + app.state.schema_indexes.len();
+            for (i, fk) in app.state.schema_referenced_by.iter().enumerate() {
+                let selected = focused && first + i == app.state.schema_selected;
+                if selected {
+                    selected_line = lines.len();
+                }
+                let mut fk_text = format!(
+                    "{}{}.{} -> {}.{}",
+                    if selected { "▶ " } else { "  " },
+                    fk.from_table,
+                    fk.from_column,
+                    fk.to_table,
+                    fk.to_column
+                );
+                let actions: Vec<String> =
+                    [("ON DELETE", &fk.on_delete), ("ON UPDATE", &fk.on_update)]
+                        .into_iter()
+                        .filter_map(|(clause, action)| {
+                            action
+                                .as_deref()
+                                .filter(|action| !action.eq_ignore_ascii_case("NO ACTION"))
+                                .map(|action| format!("{} {}", clause, action))
+                        })
+                        .collect();
+                if !actions.is_empty() {
+                    fk_text.push_str(&format!(" ({})", actions.join(", ")));
+                }
+                lines.push(Line::from(Span::styled(fk_text, entry_style(selected))));
+            }
+        }
+
+        // A failed column change leaves the schema as it was, with the reason under it
+        if let Some(error) = &app.state.schema_error {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!("Error: {}", error),
+                Style::default().fg(Color::Red),
+            )));
+        }
+
+        if focused && !app.state.schema_columns.is_empty() {
+            lines.push(Line::from(""));
+            lines.extend(schema_action_lines(app));
+        }
+
+        let scroll = (selected_line + 1).saturating_sub(inner.height as usize);
         let schema = Paragraph::new(lines)
             .block(Block::default())
-            .wrap(Wrap { trim: true });
+            .wrap(Wrap { trim: true })
+            .scroll((scroll as u16, 0));
 
         frame.render_widget(schema, inner);
     } else {
@@ -309,89 +727,319 @@ fn render_schema(frame: &mut Frame, area: Rect, app: &App, block: Block) {
     }
 }
 
+/// Keys for changing the table's columns and indexes, those that fit what's selected;
+/// any that can't be used are dimmed with the reason
+fn schema_action_lines(app: &App) -> Vec<Line<'static>> {
+    let key_style = Style::default().fg(Color::Cyan);
+    let dim = Style::default().fg(Color::DarkGray);
+    let index_selected = app.state.selected_schema_index().is_some();
+    let reference_selected = app.state.selected_schema_reference().is_some();
+    let mut actions = vec![
+        (
+            "a",
+            ColumnAction::Add.label(),
+            app.state.column_action_blocked(ColumnAction::Add),
+        ),
+        (
+            "n",
+            IndexAction::Create.label(),
+            app.state.index_action_blocked(IndexAction::Create),
+        ),
+    ];
+    if index_selected {
+        actions.push((
+            "x",
+            IndexAction::Drop.label(),
+            app.state.index_action_blocked(IndexAction::Drop),
+        ));
+    } else if !reference_selected {
+        for (key, action) in [("r", ColumnAction::Rename), ("x", ColumnAction::Drop)] {
+            actions.push((key, action.label(), app.state.column_action_blocked(action)));
+        }
+    }
+
+    let mut keys = Vec::new();
+    let mut reasons: Vec<String> = Vec::new();
+    for (key, label, blocked) in actions {
+        match blocked {
+            None => {
+                keys.push(Span::styled(key, key_style));
+                keys.push(Span::raw(format!(": {}  ", label)));
+            }
+            Some(reason) => {
+                keys.push(Span::styled(format!("{}: {}  ", key, label), dim));
+                if !reasons.contains(&reason) {
+                    reasons.push(reason);
+                }
+            }
+        }
+    }
+    if reference_selected {
+        keys.push(Span::styled("Enter", key_style));
+        keys.push(Span::raw(": referencing rows  "));
+    }
+    keys.push(Span::styled("g", key_style));
+    keys.push(Span::raw(": generate SQL  "));
+    keys.push(Span::styled("Ctrl+X", key_style));
+    keys.push(Span::raw(": export CREATE statements"));
+
+    let mut lines = vec![Line::from(keys)];
+    lines.extend(
+        reasons
+            .into_iter()
+            .map(|reason| Line::from(Span::styled(format!("  {}", reason), dim))),
+    );
+    lines
+}
+
 fn render_query_results(frame: &mut Frame, area: Rect, app: &App, block: Block) {
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
     if app.state.query_loading {
-        let loading = Paragraph::new("Executing query...")
+        let loading = Paragraph::new(super::query_loading_text(app))
             .style(Style::default().fg(Color::Yellow))
             .block(Block::default());
         frame.render_widget(loading, inner);
         return;
     }
 
+    if let Some(message) = &app.state.query_cancelled {
+        let cancelled = Paragraph::new(message.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .block(Block::default());
+        frame.render_widget(cancelled, inner);
+        return;
+    }
+
+    // A failed query leaves the last results in place under a banner saying so
+    let mut inner = inner;
     if let Some(error) = &app.state.query_error {
-        let error_para = Paragraph::new(format!("Error: {}", error))
-            .style(Style::default().fg(Color::Red))
-            .block(Block::default())
+        if app.state.query_result.is_none() {
+            let error_para = Paragraph::new(format!("Error: {}", error))
+                .style(Style::default().fg(Color::Red))
+                .block(Block::default())
+                .wrap(Wrap { trim: true });
+            frame.render_widget(error_para, inner);
+            return;
+        }
+        let text = format!("Error: {} (showing the last successful result)", error);
+        let height = (text.chars().count() as u16)
+            .div_ceil(inner.width.max(1))
+            .clamp(1, 3)
+            .min(inner.height.saturating_sub(2));
+        let banner = Paragraph::new(text)
+            .style(
+                Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            )
             .wrap(Wrap { trim: true });
-        frame.render_widget(error_para, inner);
-        return;
+        frame.render_widget(banner, Rect::new(inner.x, inner.y, inner.width, height));
+        inner.y += height;
+        inner.height -= height;
     }
 
     if let Some(result) = &app.state.query_result {
+        // Writes and DDL return no columns; say what they did instead
         if result.columns.is_empty() {
-            let empty = Paragraph::new("No columns")
-                .style(Style::default().fg(Color::Gray))
+            let statement = app.state.last_query.as_deref().unwrap_or_default();
+            let summary = Paragraph::new(result.summary(statement))
+                .style(Style::default().fg(Color::Green))
                 .block(Block::default());
-            frame.render_widget(empty, inner);
+            frame.render_widget(summary, inner);
             return;
         }
 
-        // Calculate column widths (equal distribution)
-        let col_count = result.columns.len().max(1);
+        // The summary footer takes the grid's last line, its labels sizing the columns too
+        let footer = (app.state.show_column_summary && inner.height > 2).then(|| {
+            summarize_columns(&result.rows, result.columns.len())
+                .iter()
+                .map(|summary| Value::Text(summary.label()))
+                .collect::<Vec<_>>()
+        });
+        let sized_rows = match &footer {
+            Some(labels) => {
+                Cow::Owned([result.rows.as_slice(), std::slice::from_ref(labels)].concat())
+            }
+            None => Cow::Borrowed(result.rows.as_slice()),
+        };
+        let layout = layout_columns(
+            &result.columns,
+            &sized_rows,
+            inner.width,
+            2,
+            app.state.auto_column_width,
+            app.state.pinned_cols,
+            app.state.query_selected_col,
+        );
+        let grid_focused = app.state.query_grid_active();
+        let selected = app
+            .state
+            .query_selected_row
+            .min(result.rows.len().saturating_sub(1));
 
-        // Build table rows
-        let header: Vec<Cell> = result
-            .columns
-            .iter()
-            .map(|col| {
-                Cell::from(col.as_str()).style(
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
-                )
-            })
-            .collect();
+        let vertical = match app.state.result_layout {
+            ResultLayout::Vertical => true,
+            ResultLayout::Grid => false,
+            // A lone row too wide for the screen reads better one column per line
+            ResultLayout::Auto => {
+                result.rows.len() == 1 && layout.indices.len() < result.columns.len()
+            }
+        };
+        let layout_note = if vertical {
+            render_vertical_row(frame, inner, app, result, selected);
+            " | vertical".to_string()
+        } else {
+            let header_style = Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD);
 
-        // Calculate max width per column (accounting for spacing)
-        let max_width = (inner.width as usize / col_count).saturating_sub(2).min(50);
+            // Build table rows, underlining the focused column
+            let header: Vec<Cell> = layout
+                .indices
+                .iter()
+                .map(|&col_idx| {
+                    let style = if col_idx == app.state.query_selected_col {
+                        header_style.add_modifier(Modifier::UNDERLINED)
+                    } else {
+                        header_style
+                    };
+                    Cell::from(result.columns[col_idx].as_str()).style(style)
+                })
+                .collect();
+            let header = layout.with_separator(header, header_style, 1);
 
-        let rows: Vec<Row> = result
-            .rows
-            .iter()
-            .map(|row| {
-                let cells: Vec<Cell> = row
-                    .iter()
-                    .map(|val| {
-                        let display = val.display(max_width);
-                        Cell::from(display)
-                    })
-                    .collect();
-                Row::new(cells)
-            })
-            .collect();
-        let widths: Vec<Constraint> = (0..col_count)
-            .map(|_| Constraint::Percentage((100 / col_count as u16).max(1)))
-            .collect();
+            let rows: Vec<Row> = result
+                .rows
+                .iter()
+                .enumerate()
+                .map(|(row_idx, row)| {
+                    let cells: Vec<Cell> = layout
+                        .indices
+                        .iter()
+                        .zip(&layout.widths)
+                        .filter_map(|(&col_idx, &width)| {
+                            row.get(col_idx).map(|v| (col_idx, v, width))
+                        })
+                        .map(|(col_idx, val, width)| {
+                            let is_editing = app.state.edit_mode
+                                && app.state.editing_row == Some(row_idx)
+                                && app.state.editing_col == Some(col_idx);
+                            if is_editing {
+                                let display = if app.state.edit_null {
+                                    "∅".to_string()
+                                } else {
+                                    Value::Text(app.state.edit_buffer.clone())
+                                        .display(width as usize)
+                                };
+                                return Cell::from(display).style(
+                                    Style::default()
+                                        .bg(Color::Yellow)
+                                        .fg(Color::Black)
+                                        .add_modifier(Modifier::BOLD),
+                                );
+                            }
 
-        let table = Table::new(rows, widths.as_slice())
-            .header(Row::new(header))
-            .block(Block::default())
-            .column_spacing(2)
-            .widths(widths.as_slice());
+                            let pending = pending_cell(app, result, row_idx, col_idx);
+                            let (display, mut style) = cell_content(
+                                pending.unwrap_or(val),
+                                width as usize,
+                                app.state.show_value_markers,
+                                1,
+                            );
+                            if pending.is_some() {
+                                style = style.patch(PENDING_STYLE);
+                            }
+                            if grid_focused
+                                && row_idx == selected
+                                && col_idx == app.state.query_selected_col
+                            {
+                                // Highlight the cell cursor
+                                return Cell::from(display).style(
+                                    style.patch(Style::default().bg(Color::Blue).fg(Color::White)),
+                                );
+                            }
+                            Cell::from(display).style(style)
+                        })
+                        .collect();
+                    let row =
+                        Row::new(layout.with_separator(cells, Style::default().fg(Color::Gray), 1));
+                    if grid_focused && row_idx == selected {
+                        row.style(Style::default().bg(Color::DarkGray))
+                    } else {
+                        row
+                    }
+                })
+                .collect();
+            let widths = layout.constraints();
 
-        frame.render_widget(table, inner);
+            let table = Table::new(rows, widths.as_slice())
+                .header(Row::new(header))
+                .block(Block::default())
+                .column_spacing(2)
+                .widths(widths.as_slice());
 
-        // Show execution info
-        let info = format!(
-            "{} rows in {}ms{}",
-            result.rows.len(),
-            result.exec_ms,
-            if result.truncated { " (truncated)" } else { "" }
-        );
-        let info_line = Line::from(Span::styled(info, Style::default().fg(Color::Gray)));
+            let grid_area = Rect {
+                height: inner.height - u16::from(footer.is_some()),
+                ..inner
+            };
+
+            // Scroll so the selected row stays visible below the header
+            let visible_rows = (grid_area.height as usize).saturating_sub(1).max(1);
+            let mut table_state = TableState::default()
+                .with_selected(grid_focused.then_some(selected))
+                .with_offset(selected.saturating_sub(visible_rows - 1));
+            frame.render_stateful_widget(table, grid_area, &mut table_state);
+            if let Some(labels) = &footer {
+                let footer_area = Rect::new(inner.x, inner.y + grid_area.height, inner.width, 1);
+                render_summary_footer(frame, footer_area, labels, &layout);
+            }
+            layout.describe(result.columns.len())
+        };
+
+        // Show execution info, or the edit state while editing
+        let (info, color) = if app.state.edit_mode {
+            match &app.state.edit_error {
+                Some(error) => (format!("ERROR: {} | Esc: Cancel", error), Color::Red),
+                None => (
+                    "EDIT MODE | Enter: Save | Esc: Cancel | Ctrl+E: Full editor | Ctrl+N: NULL"
+                        .to_string(),
+                    Color::Yellow,
+                ),
+            }
+        } else {
+            let status = app
+                .state
+                .status_message
+                .as_ref()
+                .map(|m| format!("{} | ", m))
+                .unwrap_or_default();
+            let mode = match (&result.source_table, grid_focused) {
+                (Some(table), true) => format!(
+                    " | editing {} - Enter: Edit cell | Esc: back to editor",
+                    table
+                ),
+                (None, true) => " | read-only result | Esc: back to editor".to_string(),
+                (_, false) => " | Ctrl+G: browse results".to_string(),
+            };
+            let paging = result.pageable && (result.truncated || result.offset > 0);
+            (
+                format!(
+                    "{}{} in {}ms{}{}{}{}",
+                    status,
+                    result.rows_label(),
+                    result.exec_ms,
+                    if paging { " | h/l: page" } else { "" },
+                    layout_note,
+                    pending_hint(app),
+                    mode
+                ),
+                Color::Gray,
+            )
+        };
+        let info_line = Line::from(Span::styled(info, Style::default().fg(color)));
         frame.render_widget(
             info_line,
             Rect::new(area.x, area.y + area.height - 1, area.width, 1),
@@ -403,3 +1051,415 @@ fn render_query_results(frame: &mut Frame, area: Rect, app: &App, block: Block)
         frame.render_widget(empty, inner);
     }
 }
+
+/// Render one row of a query result as `column: value` lines, long values wrapped
+/// under their column, keeping the selected column in view
+fn render_vertical_row(frame: &mut Frame, area: Rect, app: &App, result: &QueryResult, row: usize) {
+    let Some(values) = result.rows.get(row) else {
+        return;
+    };
+    let name_width = result
+        .columns
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(MAX_COLUMN_WIDTH);
+    let value_width = (area.width as usize).saturating_sub(name_width + 2).max(1);
+    let name_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let grid_focused = app.state.query_grid_active();
+
+    let mut lines = Vec::new();
+    if result.rows.len() > 1 {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "Row {} of {}",
+                result.offset + row + 1,
+                result.offset + result.rows.len()
+            ),
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    // Line of the selected column, to scroll to
+    let mut selected_line = 0;
+    for (col_idx, (name, val)) in result.columns.iter().zip(values).enumerate() {
+        let is_editing = app.state.edit_mode
+            && app.state.editing_row == Some(row)
+            && app.state.editing_col == Some(col_idx);
+        let (text, mut style) = if is_editing {
+            let text = if app.state.edit_null {
+                "∅".to_string()
+            } else {
+                app.state.edit_buffer.clone()
+            };
+            (
+                text,
+                Style::default()
+                    .bg(Color::Yellow)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            let pending = pending_cell(app, result, row, col_idx);
+            let (text, mut style) = cell_content(
+                pending.unwrap_or(val),
+                value_width,
+                app.state.show_value_markers,
+                usize::MAX,
+            );
+            if pending.is_some() {
+                style = style.patch(PENDING_STYLE);
+            }
+            (text, style)
+        };
+        let selected = col_idx == app.state.query_selected_col;
+        if selected {
+            selected_line = lines.len();
+            if grid_focused && !is_editing {
+                style = style.patch(Style::default().bg(Color::Blue).fg(Color::White));
+            }
+        }
+        let label = Value::Text(name.clone()).display(name_width);
+        for (i, part) in text.split('\n').enumerate() {
+            let label = if i == 0 {
+                format!("{:>width$}: ", label, width = name_width)
+            } else {
+                " ".repeat(name_width + 2)
+            };
+            let label_style = if selected {
+                name_style.add_modifier(Modifier::UNDERLINED)
+            } else {
+                name_style
+            };
+            lines.push(Line::from(vec![
+                Span::styled(label, label_style),
+                Span::styled(part.to_string(), style),
+            ]));
+        }
+    }
+
+    // Leave the info line free at the bottom
+    let visible = (area.height as usize).saturating_sub(1).max(1);
+    let scroll = (selected_line + 1).saturating_sub(visible);
+    let paragraph = Paragraph::new(lines).scroll((scroll as u16, 0));
+    frame.render_widget(paragraph, area);
+}
+
+/// Render the summary label of each shown column, lined up under the grid's columns
+fn render_summary_footer(frame: &mut Frame, area: Rect, labels: &[Value], layout: &ColumnLayout) {
+    let style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::ITALIC);
+    let cells: Vec<Cell> = layout
+        .indices
+        .iter()
+        .zip(&layout.widths)
+        .filter_map(|(&col_idx, &width)| {
+            Some(Cell::from(labels.get(col_idx)?.display(width as usize)).style(style))
+        })
+        .collect();
+    let widths = layout.constraints();
+    let footer = Table::new(
+        vec![Row::new(layout.with_separator(
+            cells,
+            Style::default().fg(Color::Gray),
+            1,
+        ))],
+        widths.as_slice(),
+    )
+    .column_spacing(2);
+    frame.render_widget(footer, area);
+}
+
+/// Style overlaid on cells with edits that have not been written yet
+const PENDING_STYLE: Style = Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD);
+
+/// Pending (unwritten) value for a cell of `result`, if it has one
+fn pending_cell<'a>(
+    app: &'a App,
+    result: &QueryResult,
+    row_idx: usize,
+    col_idx: usize,
+) -> Option<&'a Value> {
+    let table = result.source_table.as_deref()?;
+    let key = result.row_keys.get(row_idx)?;
+    app.state
+        .pending_value(table, key, result.columns.get(col_idx)?)
+}
+
+/// Status line note for edits waiting to be written
+fn pending_hint(app: &App) -> String {
+    match app.state.pending_edits.len() {
+        0 => String::new(),
+        n => format!(" | {} pending edit(s) - w: write, W: discard", n),
+    }
+}
+
+/// Text and style for a grid cell. With `markers`, NULL, empty, and whitespace-only
+/// values are drawn dimmed so they can't be mistaken for ordinary text. A `max_lines`
+/// above 1 wraps the full value instead of truncating it.
+fn cell_content(val: &Value, max_width: usize, markers: bool, max_lines: usize) -> (String, Style) {
+    let marker = Style::default()
+        .fg(Color::Gray)
+        .add_modifier(Modifier::ITALIC);
+    match val {
+        Value::Null if markers => ("NULL".to_string(), marker),
+        Value::Text(t) if markers && t.is_empty() => ("''".to_string(), marker),
+        Value::Text(t) if markers && t.trim().is_empty() => {
+            // Make the whitespace itself visible
+            let shown: String = t
+                .chars()
+                .map(|c| match c {
+                    '\t' => '→',
+                    '\n' | '\r' => '↵',
+                    _ => '·',
+                })
+                .collect();
+            (
+                Value::Text(format!("'{}'", shown)).display(max_width),
+                marker,
+            )
+        }
+        Value::Text(t) if max_lines > 1 => (
+            wrap_cell_text(t, max_width, max_lines).join("\n"),
+            Style::default(),
+        ),
+        _ => (val.display(max_width), Style::default()),
+    }
+}
+
+/// Most lines a wrapped cell shows outside the selected row
+const MAX_WRAP_LINES: usize = 4;
+
+/// Hard-wrap text to `width` characters, keeping at most `max_lines` lines and ending
+/// with "…" when cut short
+fn wrap_cell_text(text: &str, width: usize, max_lines: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for source in text.split('\n') {
+        let chars: Vec<char> = source.trim_end_matches('\r').chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(
+            chars
+                .chunks(width)
+                .map(|chunk| chunk.iter().collect::<String>()),
+        );
+        if lines.len() > max_lines {
+            break;
+        }
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            if last.chars().count() >= width {
+                last.pop();
+            }
+            last.push('…');
+        }
+    }
+    lines
+}
+
+/// Widest a column is allowed to grow when sized to its content
+const MAX_COLUMN_WIDTH: usize = 50;
+
+/// Narrowest a column gets in equal-width mode before the grid scrolls horizontally
+const MIN_EQUAL_WIDTH: usize = 10;
+
+/// Which columns to draw and how wide, after pinning and horizontal scrolling
+struct ColumnLayout {
+    /// Indices into the result's columns, pinned columns first
+    indices: Vec<usize>,
+    widths: Vec<u16>,
+    /// How many leading entries of `indices` are pinned
+    pinned: usize,
+}
+
+impl ColumnLayout {
+    /// Whether a separator is drawn between pinned and scrolling columns
+    fn has_separator(&self) -> bool {
+        self.pinned > 0 && self.indices.len() > self.pinned
+    }
+
+    /// Table constraints, including the one-character pin separator
+    fn constraints(&self) -> Vec<Constraint> {
+        let mut constraints: Vec<Constraint> =
+            self.widths.iter().map(|&w| Constraint::Length(w)).collect();
+        if self.has_separator() {
+            constraints.insert(self.pinned, Constraint::Length(1));
+        }
+        constraints
+    }
+
+    /// Insert the pin separator, `height` lines tall, into a row's cells
+    fn with_separator<'a>(
+        &self,
+        mut cells: Vec<Cell<'a>>,
+        style: Style,
+        height: usize,
+    ) -> Vec<Cell<'a>> {
+        if self.has_separator() {
+            let line = vec!["│"; height.max(1)].join("\n");
+            cells.insert(self.pinned, Cell::from(line).style(style));
+        }
+        cells
+    }
+
+    /// Whether columns past the right edge are scrolled out of view
+    fn more_right(&self, total: usize) -> bool {
+        self.indices.last().is_some_and(|&last| last + 1 < total)
+    }
+
+    /// Describe the visible column range, e.g. " | cols 4-9/20, 1 pinned"
+    fn describe(&self, total: usize) -> String {
+        if self.indices.len() == total {
+            return if self.pinned > 0 {
+                format!(" | {} pinned", self.pinned)
+            } else {
+                String::new()
+            };
+        }
+        let scrolled = &self.indices[self.pinned..];
+        let range = match (scrolled.first(), scrolled.last()) {
+            (Some(first), Some(last)) => format!("cols {}-{}/{}", first + 1, last + 1, total),
+            _ => format!("{} cols", total),
+        };
+        if self.pinned > 0 {
+            format!(" | {}, {} pinned", range, self.pinned)
+        } else {
+            format!(" | {}", range)
+        }
+    }
+}
+
+/// Lay out columns for a grid: the first `pinned` columns always show on the left and the
+/// rest scroll horizontally so that `focus_col` stays visible.
+fn layout_columns(
+    columns: &[String],
+    rows: &[Vec<Value>],
+    total_width: u16,
+    spacing: u16,
+    auto: bool,
+    pinned: usize,
+    focus_col: usize,
+) -> ColumnLayout {
+    let gap = spacing as usize;
+    let available = total_width as usize;
+    let natural = natural_widths(columns, rows, available, gap, auto);
+    let span = |range: std::ops::Range<usize>| -> usize {
+        let len = range.len();
+        natural[range].iter().sum::<usize>() + gap * len.saturating_sub(1)
+    };
+    let count = natural.len();
+
+    // Everything fits: no scrolling needed
+    if count == 0 || span(0..count) <= available {
+        let indices: Vec<usize> = (0..count).collect();
+        let widths = fit_widths(
+            &natural,
+            available.saturating_sub(gap * count.saturating_sub(1)),
+            auto,
+        );
+        return ColumnLayout {
+            indices,
+            widths,
+            pinned: pinned.min(count),
+        };
+    }
+
+    // Pinned columns may take at most half the pane so something can still scroll
+    let mut pinned = pinned.min(count - 1);
+    while pinned > 0 && span(0..pinned) > available / 2 {
+        pinned -= 1;
+    }
+    let pinned_span = if pinned > 0 {
+        span(0..pinned) + gap + 1 + gap
+    } else {
+        0
+    };
+    let room = available.saturating_sub(pinned_span);
+
+    // Extend the window left from the focused column as far as it fits, then right
+    let focus = focus_col.clamp(pinned, count - 1);
+    let mut start = focus;
+    while start > pinned && span(start - 1..focus + 1) <= room {
+        start -= 1;
+    }
+    let mut end = focus;
+    while end + 1 < count && span(start..end + 2) <= room {
+        end += 1;
+    }
+
+    let indices: Vec<usize> = (0..pinned).chain(start..=end).collect();
+    let visible: Vec<usize> = indices.iter().map(|&i| natural[i]).collect();
+    let separator = if pinned > 0 { 1 + gap } else { 0 };
+    let widths = fit_widths(
+        &visible,
+        available.saturating_sub(separator + gap * (indices.len() - 1)),
+        auto,
+    );
+    ColumnLayout {
+        indices,
+        widths,
+        pinned,
+    }
+}
+
+/// Preferred width of each column: the longest header/cell (capped at `MAX_COLUMN_WIDTH`)
+/// in auto mode, otherwise an equal share of the pane
+fn natural_widths(
+    columns: &[String],
+    rows: &[Vec<Value>],
+    available: usize,
+    gap: usize,
+    auto: bool,
+) -> Vec<usize> {
+    let col_count = columns.len().max(1);
+    if !auto {
+        let share = available.saturating_sub(gap * (col_count - 1)) / col_count;
+        return vec![share.clamp(MIN_EQUAL_WIDTH, MAX_COLUMN_WIDTH); columns.len()];
+    }
+
+    // Header width includes room for a sort indicator
+    columns
+        .iter()
+        .enumerate()
+        .map(|(col_idx, name)| {
+            rows.iter()
+                .filter_map(|row| row.get(col_idx))
+                .map(|val| val.display(MAX_COLUMN_WIDTH).chars().count())
+                .fold(name.chars().count() + 2, usize::max)
+                .min(MAX_COLUMN_WIDTH)
+        })
+        .collect()
+}
+
+/// Fit preferred widths into `available` characters (excluding spacing).
+///
+/// When they don't fit, narrow columns keep their width and the rest share what is left.
+/// With `grow`, any leftover space goes to the widest column.
+fn fit_widths(natural: &[usize], available: usize, grow: bool) -> Vec<u16> {
+    // Hand out space smallest-first so narrow columns are never squeezed by wide ones
+    let mut order: Vec<usize> = (0..natural.len()).collect();
+    order.sort_by_key(|&i| natural[i]);
+    let mut widths = vec![0usize; natural.len()];
+    let mut remaining = available.max(natural.len());
+    for (placed, &i) in order.iter().enumerate() {
+        let share = remaining / (natural.len() - placed);
+        widths[i] = natural[i].min(share).max(1);
+        remaining = remaining.saturating_sub(widths[i]);
+    }
+
+    if grow {
+        if let Some(&widest) = order.last() {
+            widths[widest] += remaining;
+        }
+    }
+
+    widths.into_iter().map(|w| w as u16).collect()
+}
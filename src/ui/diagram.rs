@@ -1,83 +1,96 @@
 use crate::app::App;
+use crate::types::{ColumnInfo, DiagramData, DiagramLayout, DiagramTable, TableBox};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Paragraph, Wrap},
+    widgets::{Block, Paragraph, Widget, Wrap},
     Frame,
 };
+use std::collections::HashMap;
 
 pub fn render_diagram(frame: &mut Frame, area: Rect, app: &App, block: Block) {
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
     if app.state.diagram_loading {
-        let loading = Paragraph::new("Loading diagram...")
+        let text = app
+            .state
+            .busy_message()
+            .unwrap_or_else(|| "Loading diagram...".to_string());
+        let loading = Paragraph::new(text)
             .style(Style::default().fg(Color::Yellow))
             .block(Block::default());
         frame.render_widget(loading, inner);
         return;
     }
 
-    if let Some(diagram) = &app.state.diagram_data {
+    if let (Some(diagram), Some(layout)) = (&app.state.diagram_shown, &app.state.diagram_layout) {
         if diagram.tables.is_empty() {
-            let empty = Paragraph::new("No tables found")
+            let filtered = app.state.diagram_filter.is_some();
+            let message = if filtered {
+                "No tables match the filter ('/' to change it)"
+            } else {
+                "No tables found"
+            };
+            let empty = Paragraph::new(message)
                 .style(Style::default().fg(Color::Gray))
                 .block(Block::default());
             frame.render_widget(empty, inner);
             return;
         }
 
-        // Simple grid layout for tables
-        // Calculate grid dimensions
-        let table_count = diagram.tables.len();
-        let cols = (table_count as f64).sqrt().ceil() as usize;
-        let rows = table_count.div_ceil(cols);
-
-        // Make tables smaller to allow arrows to cross between them
-        // Add more spacing between tables for better arrow routing
-        let cell_width = inner.width as usize / cols.max(1);
-        let cell_height = inner.height as usize / rows.max(1);
-        let spacing_x = cell_width / 3;
-        let spacing_y = cell_height / 3;
-        let table_width = spacing_x.clamp(25, 40);
-        let table_height = spacing_y.clamp(8, 15);
-
-        // Store table positions for drawing arrows
-        use std::collections::HashMap;
-        let mut table_positions: HashMap<String, (u16, u16, u16, u16)> = HashMap::new();
-
-        let mut table_idx = 0;
-        for row_idx in 0..rows {
-            for col_idx in 0..cols {
-                if table_idx >= table_count {
-                    break;
-                }
-
-                let table = &diagram.tables[table_idx];
-                // Add spacing between tables
-                let x = inner.x + (col_idx * (table_width + spacing_x)) as u16;
-                let y = inner.y + (row_idx * (table_height + spacing_y)) as u16;
-                let available_width = (inner.width.saturating_sub(x - inner.x)) as usize;
-                let available_height = (inner.height.saturating_sub(y - inner.y)) as usize;
-                let width = table_width.min(available_width) as u16;
-                let height = table_height.min(available_height) as u16;
-                let table_area = Rect::new(x, y, width, height);
-
-                // Store position (center x, center y, width, height)
-                table_positions.insert(
-                    table.name.clone(),
-                    (x + width / 2, y + height / 2, width, height),
-                );
-
-                render_table_box(frame, table_area, table, diagram);
-                table_idx += 1;
+        // Tables keep a readable size on a canvas that may run past the pane; the pane
+        // shows the part of it the view is panned to
+        let selected = app.state.diagram_selected_index();
+        let mut offset = layout.clamp_offset(app.state.diagram_offset);
+        // Scroll so the selected table stays in view
+        if let Some(b) = selected.map(|i| layout.boxes[i]) {
+            let (width, height) = (inner.width as i32, inner.height as i32);
+            if b.x < offset.0 || b.width > width {
+                offset.0 = b.x;
+            } else if b.x + b.width > offset.0 + width {
+                offset.0 = b.x + b.width - width;
             }
+            if b.y < offset.1 || b.height > height {
+                offset.1 = b.y;
+            } else if b.y + b.height > offset.1 + height {
+                offset.1 = b.y + b.height - height;
+            }
+        }
+        let boxes: Vec<TableBox> = layout
+            .boxes
+            .iter()
+            .map(|b| TableBox {
+                x: b.x + inner.x as i32 - offset.0,
+                y: b.y + inner.y as i32 - offset.1,
+                ..*b
+            })
+            .collect();
+
+        let buf = frame.buffer_mut();
+        for (i, (table, table_box)) in diagram.tables.iter().zip(&boxes).enumerate() {
+            let hidden_keys = app.state.diagram_hidden_keys.get(i).copied().unwrap_or(0);
+            render_table_box(
+                buf,
+                inner,
+                *table_box,
+                table,
+                hidden_keys,
+                selected == Some(i),
+            );
         }
 
         // Draw arrows for foreign key relationships
-        draw_relationship_arrows(frame, inner, diagram, &table_positions);
+        let selected_name = selected.map(|i| diagram.tables[i].name.as_str());
+        draw_relationship_arrows(buf, inner, diagram, &boxes, selected_name);
+        render_pan_indicator(frame, inner, layout, offset);
+    } else if let Some(error) = &app.state.schema_error {
+        let error_para = Paragraph::new(format!("Error: {}", error))
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(error_para, inner);
     } else {
         let empty = Paragraph::new("No diagram data. Press 's' to load.")
             .style(Style::default().fg(Color::Gray))
@@ -86,74 +99,152 @@ pub fn render_diagram(frame: &mut Frame, area: Rect, app: &App, block: Block) {
     }
 }
 
+/// Arrows in the top right corner for each way the diagram runs on past the pane, with
+/// how far it's panned
+fn render_pan_indicator(frame: &mut Frame, area: Rect, layout: &DiagramLayout, offset: (i32, i32)) {
+    let mut arrows = String::new();
+    if offset.0 > 0 {
+        arrows.push('←');
+    }
+    if offset.0 + (area.width as i32) < layout.width {
+        arrows.push('→');
+    }
+    if offset.1 > 0 {
+        arrows.push('↑');
+    }
+    if offset.1 + (area.height as i32) < layout.height {
+        arrows.push('↓');
+    }
+    if arrows.is_empty() {
+        return;
+    }
+
+    let text = format!(" {} {},{} ", arrows, offset.0, offset.1);
+    let width = (text.chars().count() as u16).min(area.width);
+    let corner = Rect::new(area.right() - width, area.y, width, 1.min(area.height));
+    let indicator = Paragraph::new(text).style(Style::default().fg(Color::Black).bg(Color::Gray));
+    frame.render_widget(indicator, corner);
+}
+
+/// Draw a table's box where the layout puts it, keeping only the part inside `clip`.
+/// Keys to tables filtered out of the diagram are counted on its bottom border.
 fn render_table_box(
-    frame: &mut Frame,
-    area: Rect,
-    table: &crate::types::DiagramTable,
-    _diagram: &crate::types::DiagramData,
+    buf: &mut Buffer,
+    clip: Rect,
+    table_box: TableBox,
+    table: &DiagramTable,
+    hidden_keys: usize,
+    selected: bool,
 ) {
-    if area.width < 3 || area.height < 3 {
+    let visible = |x: i32, y: i32| {
+        x >= clip.x as i32
+            && x < clip.right() as i32
+            && y >= clip.y as i32
+            && y < clip.bottom() as i32
+    };
+    let right = table_box.x + table_box.width - 1;
+    let bottom = table_box.y + table_box.height - 1;
+    if table_box.x >= clip.right() as i32
+        || table_box.y >= clip.bottom() as i32
+        || right < clip.x as i32
+        || bottom < clip.y as i32
+    {
         return;
     }
 
+    // Draw the whole box aside, then copy over the cells inside the pane
+    let area = Rect::new(0, 0, table_box.width as u16, table_box.height as u16);
+    let mut scratch = Buffer::empty(area);
+
     // Create table box with border
-    let title_style = Style::default()
-        .fg(Color::Cyan)
-        .add_modifier(Modifier::BOLD);
+    let (title_style, border_style) = if selected {
+        (
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        (
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+            Style::default().fg(Color::White),
+        )
+    };
 
-    let block = Block::default()
-        .title(table.name.as_str())
-        .title_style(title_style)
+    let mut block = Block::default()
+        .title(Line::styled(table.name.as_str(), title_style))
         .borders(ratatui::widgets::Borders::ALL)
-        .border_style(Style::default().fg(Color::White));
+        .border_style(border_style);
+    if hidden_keys > 0 {
+        block = block.title_bottom(
+            Line::styled(
+                format!("+{} hidden", hidden_keys),
+                Style::default().fg(Color::DarkGray),
+            )
+            .right_aligned(),
+        );
+    }
 
     let inner = block.inner(area);
-    frame.render_widget(block, area);
+    block.render(area, &mut scratch);
+    // The key columns first, the primary key's then the foreign keys', as far as the
+    // box has room; the rest after them
+    let is_fk = |col: &ColumnInfo| {
+        table
+            .foreign_keys
+            .iter()
+            .any(|fk| fk.from_column == col.name)
+    };
+    let mut columns: Vec<&ColumnInfo> = table.columns.iter().collect();
+    columns.sort_by_key(|col| (!col.primary_key, !is_fk(col)));
+    let max_cols_to_show = (inner.height as usize).saturating_sub(2).min(5);
+    let width = inner.width as usize;
 
-    // Render columns (limit to fit in smaller box)
     let mut lines = Vec::new();
-    let max_cols_to_show = (inner.height as usize).saturating_sub(2).min(5); // Limit columns shown
-
-    for col in table.columns.iter().take(max_cols_to_show) {
-        let mut spans = Vec::new();
-
-        // Primary key indicator
+    for col in columns.iter().take(max_cols_to_show) {
+        // The type and keys go after the name, which gives way to them when it's long
+        let mut keys = Vec::new();
         if col.primary_key {
-            spans.push(Span::styled(
-                "*",
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD),
-            ));
-        } else {
-            spans.push(Span::raw(" "));
+            keys.push("PK");
         }
+        if is_fk(col) {
+            keys.push("FK");
+        }
+        let data_type = abbreviate_type(&col.data_type);
+        let suffix_len: usize = [data_type.chars().count(), keys.join(" ").len()]
+            .iter()
+            .filter(|&&len| len > 0)
+            .map(|len| len + 1)
+            .sum();
+        let name = truncate_name(&col.name, width.saturating_sub(suffix_len).max(4));
 
-        // Column name (truncate if too long)
-        let col_name: String = if col.name.len() > 12 {
-            format!("{}...", &col.name[..12])
-        } else {
-            col.name.as_str().into()
-        };
-
-        let col_name_style = if col.primary_key {
+        let name_style = if col.primary_key {
             Style::default()
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD)
         } else {
             Style::default().fg(Color::White)
         };
-        spans.push(Span::styled(col_name, col_name_style));
-
-        // Foreign key indicator (compact)
-        let has_fk = table
-            .foreign_keys
-            .iter()
-            .any(|fk| fk.from_column == col.name);
-        if has_fk {
-            spans.push(Span::styled("FK", Style::default().fg(Color::Green)));
+        let mut spans = vec![Span::styled(name, name_style)];
+        if !data_type.is_empty() {
+            spans.push(Span::styled(
+                format!(" {}", data_type),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        for key in keys {
+            let style = if key == "PK" {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::Green)
+            };
+            spans.push(Span::styled(format!(" {}", key), style));
         }
-
         lines.push(Line::from(spans));
     }
 
@@ -165,103 +256,212 @@ fn render_table_box(
         )));
     }
 
-    let para = Paragraph::new(lines)
-        .block(Block::default())
-        .wrap(Wrap { trim: true });
+    let para = Paragraph::new(lines);
 
-    frame.render_widget(para, inner);
+    para.render(inner, &mut scratch);
+
+    for y in 0..table_box.height {
+        for x in 0..table_box.width {
+            let (screen_x, screen_y) = (table_box.x + x, table_box.y + y);
+            if visible(screen_x, screen_y) {
+                *buf.get_mut(screen_x as u16, screen_y as u16) =
+                    scratch.get(x as u16, y as u16).clone();
+            }
+        }
+    }
+}
+
+/// A column's declared type in a few letters: its first word without a size, with the
+/// longer common names shortened, e.g. `VARCHAR(255)` as `VARCHAR` and `INTEGER` as `INT`
+fn abbreviate_type(data_type: &str) -> String {
+    let word = data_type
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let short = match word.as_str() {
+        "INTEGER" => "INT",
+        "BOOLEAN" => "BOOL",
+        "NUMERIC" => "NUM",
+        "DECIMAL" => "DEC",
+        "CHARACTER" => "CHAR",
+        _ => &word,
+    };
+    short.chars().take(9).collect()
+}
+
+/// `name` cut to `width` characters, ending in an ellipsis when it's cut
+fn truncate_name(name: &str, width: usize) -> String {
+    if name.chars().count() <= width {
+        return name.to_string();
+    }
+    let mut shown: String = name.chars().take(width.saturating_sub(1)).collect();
+    shown.push('…');
+    shown
 }
 
 fn draw_relationship_arrows(
-    frame: &mut Frame,
+    buf: &mut Buffer,
     area: Rect,
-    diagram: &crate::types::DiagramData,
-    table_positions: &std::collections::HashMap<String, (u16, u16, u16, u16)>,
+    diagram: &DiagramData,
+    boxes: &[TableBox],
+    selected: Option<&str>,
 ) {
-    let buf = frame.buffer_mut();
-    // Use brighter green for better visibility
+    // Use brighter green for better visibility; with a table selected, its keys stand
+    // out and the rest fade
     let arrow_style = Style::default().fg(Color::LightGreen);
+    let related_style = Style::default()
+        .fg(Color::LightYellow)
+        .add_modifier(Modifier::BOLD);
+    let unrelated_style = Style::default().fg(Color::DarkGray);
+    let style_for = |from: &str, to: &str| match selected {
+        None => arrow_style,
+        Some(name) if name == from || name == to => related_style,
+        Some(_) => unrelated_style,
+    };
 
-    // Collect all table rectangles for collision detection
-    let table_rects: Vec<(u16, u16, u16, u16)> = table_positions
-        .values()
-        .map(|&(cx, cy, w, h)| (cx - w / 2, cy - h / 2, w, h))
+    // Where each table's box is drawn, by name
+    let table_positions: HashMap<&str, TableBox> = diagram
+        .tables
+        .iter()
+        .map(|t| t.name.as_str())
+        .zip(boxes.iter().copied())
         .collect();
 
-    // Collect all valid relationships and deduplicate
-    // Use a set to avoid drawing the same relationship twice
-    use std::collections::HashSet;
-    let mut drawn_relationships: HashSet<(String, String)> = HashSet::new();
+    // One arrow each way between two tables, however many keys it stands for, and one
+    // loop for a table's keys to itself. A composite key's columns go together.
+    let mut arrows: Vec<(String, String, Vec<String>)> = Vec::new();
+    let mut loops: Vec<(String, Vec<String>)> = Vec::new();
+    for key in diagram.relationships() {
+        let columns = if key.from_columns.len() > 1 {
+            format!("({})", key.from_columns.join(", "))
+        } else {
+            key.from_columns.join(", ")
+        };
+        if key.from_table == key.to_table {
+            match loops.iter_mut().find(|(table, _)| *table == key.from_table) {
+                Some((_, keys)) => keys.push(columns),
+                None => loops.push((key.from_table, vec![columns])),
+            }
+            continue;
+        }
+        match arrows
+            .iter_mut()
+            .find(|(from, to, _)| *from == key.from_table && *to == key.to_table)
+        {
+            Some((_, _, keys)) => keys.push(columns),
+            None => arrows.push((key.from_table, key.to_table, vec![columns])),
+        }
+    }
 
-    // Create a set of all table names that are actually rendered
-    let rendered_tables: HashSet<String> = table_positions.keys().cloned().collect();
+    let mut drawn = Vec::new();
+    for (i, (from_table, to_table, keys)) in arrows.iter().enumerate() {
+        let (Some(from), Some(to)) = (
+            table_positions.get(from_table.as_str()),
+            table_positions.get(to_table.as_str()),
+        ) else {
+            continue;
+        };
+        // Calculate connection points on table edges (not centers). Keys both ways
+        // between two tables would share a path, so the second arrow runs beside it.
+        let beside = arrows[..i]
+            .iter()
+            .any(|(from, to, _)| from == to_table && to == from_table);
+        let shift = if beside { 2 } else { 0 };
+        let start = shift_along_edge(find_edge_point(from, to.center()), from, shift);
+        let end = shift_along_edge(find_edge_point(to, from.center()), to, shift);
+        // A lone column is listed in the box already; more say which keys the arrow is
+        let label = (keys.len() > 1 || keys[0].starts_with('(')).then(|| keys.join(", "));
+        drawn.push((start, end, label, style_for(from_table, to_table)));
+    }
 
-    for table in &diagram.tables {
-        for fk in &table.foreign_keys {
-            // Only draw if both tables exist AND are rendered in the diagram
-            if !rendered_tables.contains(&fk.from_table) || !rendered_tables.contains(&fk.to_table)
-            {
-                continue;
-            }
+    // Faded arrows first, so the selected table's are drawn over where they cross
+    drawn.sort_by_key(|arrow| arrow.3 != unrelated_style);
+    let mut labels = Vec::new();
+    for (start, end, label, style) in drawn {
+        // Draw the arrow, keeping only the part inside the pane
+        let middle = draw_curved_arrow(buf, area, start.0, start.1, end.0, end.1, boxes, style);
+        if let Some(label) = label {
+            labels.push((middle, label, style));
+        }
+    }
+    for (table, keys) in &loops {
+        if let Some(table_box) = table_positions.get(table.as_str()) {
+            let style = style_for(table, table);
+            draw_self_loop(buf, area, boxes, table_box, &keys.join(", "), style);
+        }
+    }
+    // Labels last, so no arrow runs through one
+    for ((x, y), label, style) in labels {
+        let start = x - label.chars().count() as i32 / 2;
+        draw_text(buf, area, boxes, (start, y), &label, style);
+    }
+}
 
-            // Skip self-references (table pointing to itself)
-            if fk.from_table == fk.to_table {
-                continue;
-            }
+/// `point` on the edge of `table_box` moved `by` cells along that edge
+fn shift_along_edge(point: (i32, i32), table_box: &TableBox, by: i32) -> (i32, i32) {
+    if point.0 < table_box.x || point.0 >= table_box.x + table_box.width {
+        (point.0, point.1 + by)
+    } else {
+        (point.0 + by, point.1)
+    }
+}
 
-            // Create a canonical relationship key (always from smaller to larger table name)
-            // This ensures we only draw each relationship once, regardless of direction
-            let relationship_key = if fk.from_table < fk.to_table {
-                (fk.from_table.clone(), fk.to_table.clone())
-            } else {
-                (fk.to_table.clone(), fk.from_table.clone())
-            };
+/// A loop out of the right side of a table's box and back into it, for its foreign
+/// keys to itself, with their columns beside it
+fn draw_self_loop(
+    buf: &mut Buffer,
+    area: Rect,
+    boxes: &[TableBox],
+    table_box: &TableBox,
+    columns: &str,
+    style: Style,
+) {
+    // Above the middle of the side, where arrows to other tables leave it
+    let (x, y) = (table_box.x + table_box.width, table_box.y + 1);
+    draw_text(buf, area, boxes, (x, y), "─╮", style);
+    draw_text(buf, area, boxes, (x, y + 1), " │", style);
+    draw_text(buf, area, boxes, (x, y + 2), "<╯", style);
+    draw_text(buf, area, boxes, (x + 3, y + 1), columns, style);
+}
 
-            // Skip if we've already drawn this relationship
-            if drawn_relationships.contains(&relationship_key) {
-                continue;
-            }
-            drawn_relationships.insert(relationship_key);
-
-            // Get table positions - ensure both exist
-            let from_pos = table_positions.get(&fk.from_table);
-            let to_pos = table_positions.get(&fk.to_table);
-
-            if let (Some(&(from_cx, from_cy, from_w, from_h)), Some(&(to_cx, to_cy, to_w, to_h))) =
-                (from_pos, to_pos)
-            {
-                // Calculate connection points on table edges (not centers)
-                // Start from the source table edge
-                let (start_x, start_y) =
-                    find_edge_point(from_cx, from_cy, from_w, from_h, to_cx, to_cy);
-                // End at the target table edge
-                let (end_x, end_y) = find_edge_point(to_cx, to_cy, to_w, to_h, from_cx, from_cy);
-
-                // Draw the arrow - the edge point calculation should ensure it connects properly
-                draw_curved_arrow(
-                    buf,
-                    area,
-                    start_x,
-                    start_y,
-                    end_x,
-                    end_y,
-                    &table_rects,
-                    arrow_style,
-                );
-            }
+/// Write `text` from `at` as far as the next table's box or the edge of the pane
+fn draw_text(
+    buf: &mut Buffer,
+    area: Rect,
+    boxes: &[TableBox],
+    at: (i32, i32),
+    text: &str,
+    style: Style,
+) {
+    let (x, y) = at;
+    if y < area.y as i32 || y >= area.bottom() as i32 {
+        return;
+    }
+    for (i, ch) in text.chars().enumerate() {
+        let x = x + i as i32;
+        if boxes.iter().any(|b| b.contains(x, y)) || x >= area.right() as i32 {
+            break;
+        }
+        if x >= area.x as i32 {
+            let cell = buf.get_mut(x as u16, y as u16);
+            cell.set_char(ch);
+            cell.set_style(style);
         }
     }
 }
 
 /// Find the best edge point on a table to connect from/to
-fn find_edge_point(cx: u16, cy: u16, w: u16, h: u16, target_cx: u16, target_cy: u16) -> (u16, u16) {
-    let left = cx - w / 2;
-    let right = cx + w / 2;
-    let top = cy - h / 2;
-    let bottom = cy + h / 2;
+fn find_edge_point(table_box: &TableBox, target: (i32, i32)) -> (i32, i32) {
+    let (cx, cy) = table_box.center();
+    // Just outside the border, so the arrow head isn't hidden by the box
+    let left = table_box.x - 1;
+    let right = table_box.x + table_box.width;
+    let top = table_box.y - 1;
+    let bottom = table_box.y + table_box.height;
 
-    let dx = target_cx as i16 - cx as i16;
-    let dy = target_cy as i16 - cy as i16;
+    let dx = target.0 - cx;
+    let dy = target.1 - cy;
 
     // Determine which edge to use based on direction
     if dx.abs() > dy.abs() {
@@ -281,38 +481,26 @@ fn find_edge_point(cx: u16, cy: u16, w: u16, h: u16, target_cx: u16, target_cy:
     }
 }
 
-/// Draw a curved arrow using bezier curve approximation
+/// Draw a curved arrow using bezier curve approximation. Returns the point halfway
+/// along it.
 #[allow(clippy::too_many_arguments)]
 fn draw_curved_arrow(
     buf: &mut Buffer,
     area: Rect,
-    x1: u16,
-    y1: u16,
-    x2: u16,
-    y2: u16,
-    table_rects: &[(u16, u16, u16, u16)],
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+    table_rects: &[TableBox],
     style: Style,
-) {
-    // Only draw if within bounds
-    if !(x1 >= area.x
-        && x1 < area.x + area.width
-        && y1 >= area.y
-        && y1 < area.y + area.height
-        && x2 >= area.x
-        && x2 < area.x + area.width
-        && y2 >= area.y
-        && y2 < area.y + area.height)
-    {
-        return;
-    }
-
-    let dx = (x2 as i16) - (x1 as i16);
-    let dy = (y2 as i16) - (y1 as i16);
+) -> (i32, i32) {
+    let dx = x2 - x1;
+    let dy = y2 - y1;
     let dist = ((dx * dx + dy * dy) as f64).sqrt();
 
     // Calculate control points for bezier curve
     // Use more pronounced curves for better visibility
-    let control_offset = (dist * 0.5).clamp(8.0, 25.0) as i16;
+    let control_offset = (dist * 0.5).clamp(8.0, 25.0) as i32;
 
     // Create smooth S-curves that route around tables
     // Use perpendicular offsets for natural curves
@@ -325,10 +513,10 @@ fn draw_curved_arrow(
         };
         let curve_dir = if dy > 0 { 1 } else { -1 };
         (
-            x1 as i16 + dx / 3,
-            y1 as i16 + curve_dir * perp_offset,
-            x1 as i16 + 2 * dx / 3,
-            y2 as i16 - curve_dir * perp_offset,
+            x1 + dx / 3,
+            y1 + curve_dir * perp_offset,
+            x1 + 2 * dx / 3,
+            y2 - curve_dir * perp_offset,
         )
     } else {
         // More vertical - create horizontal curves
@@ -339,10 +527,10 @@ fn draw_curved_arrow(
         };
         let curve_dir = if dx > 0 { 1 } else { -1 };
         (
-            x1 as i16 + curve_dir * perp_offset,
-            y1 as i16 + dy / 3,
-            x2 as i16 - curve_dir * perp_offset,
-            y1 as i16 + 2 * dy / 3,
+            x1 + curve_dir * perp_offset,
+            y1 + dy / 3,
+            x2 - curve_dir * perp_offset,
+            y1 + 2 * dy / 3,
         )
     };
 
@@ -350,7 +538,7 @@ fn draw_curved_arrow(
     // Use more steps to ensure we don't skip cells
     let steps = (dist as usize * 3).clamp(30, 300);
     let mut points = Vec::new();
-    let mut last_point: Option<(u16, u16)> = None;
+    let mut last_point: Option<(i32, i32)> = None;
 
     for i in 0..=steps {
         let t = i as f64 / steps as f64;
@@ -361,7 +549,7 @@ fn draw_curved_arrow(
             (x2 as f64, y2 as f64),
             t,
         );
-        let point = (x.round() as u16, y.round() as u16);
+        let point = (x.round() as i32, y.round() as i32);
 
         // Only add point if it's different from the last one (avoid duplicates)
         if last_point != Some(point) {
@@ -377,16 +565,16 @@ fn draw_curved_arrow(
         if i < points.len() - 1 {
             let (px, py) = points[i];
             let (nx, ny) = points[i + 1];
-            let dx = nx as i16 - px as i16;
-            let dy = ny as i16 - py as i16;
+            let dx = nx - px;
+            let dy = ny - py;
 
             // Fill gaps if points are more than 1 cell apart
             if dx.abs() > 1 || dy.abs() > 1 {
                 let gap_steps = dx.abs().max(dy.abs()) as usize;
                 for j in 1..gap_steps {
                     let t = j as f64 / gap_steps as f64;
-                    let x = (px as f64 + dx as f64 * t).round() as u16;
-                    let y = (py as f64 + dy as f64 * t).round() as u16;
+                    let x = (px as f64 + dx as f64 * t).round() as i32;
+                    let y = (py as f64 + dy as f64 * t).round() as i32;
                     filled_points.push((x, y));
                 }
             }
@@ -398,18 +586,17 @@ fn draw_curved_arrow(
     for i in 0..points.len() {
         let (x, y) = points[i];
 
-        if x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height {
+        // Only the part of the arrow inside the pane is drawn
+        if x >= area.x as i32
+            && x < area.right() as i32
+            && y >= area.y as i32
+            && y < area.bottom() as i32
+        {
             // Skip if inside a table
-            let mut inside_table = false;
-            for &(tx, ty, tw, th) in table_rects {
-                if x >= tx && x < tx + tw && y >= ty && y < ty + th {
-                    inside_table = true;
-                    break;
-                }
-            }
+            let inside_table = table_rects.iter().any(|b| b.contains(x, y));
 
             if !inside_table {
-                let cell = buf.get_mut(x, y);
+                let cell = buf.get_mut(x as u16, y as u16);
                 let ch = cell.symbol().chars().next().unwrap_or(' ');
 
                 if can_draw_on_cell(ch) {
@@ -418,8 +605,8 @@ fn draw_curved_arrow(
                         // Arrow head at end
                         if i > 0 {
                             let (px, py) = points[i - 1];
-                            let adx = x as i16 - px as i16;
-                            let ady = y as i16 - py as i16;
+                            let adx = x - px;
+                            let ady = y - py;
                             if adx.abs() > ady.abs() {
                                 if adx > 0 {
                                     '>'
@@ -437,8 +624,8 @@ fn draw_curved_arrow(
                     } else if i > 0 {
                         // Determine direction from previous point for smooth curves
                         let (px, py) = points[i - 1];
-                        let adx = x as i16 - px as i16;
-                        let ady = y as i16 - py as i16;
+                        let adx = x - px;
+                        let ady = y - py;
 
                         // Use appropriate character based on direction
                         if adx.abs() > 0 && ady.abs() > 0 {
@@ -469,6 +656,7 @@ fn draw_curved_arrow(
             }
         }
     }
+    points[points.len() / 2]
 }
 
 /// Calculate a point on a cubic bezier curve
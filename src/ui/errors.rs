@@ -0,0 +1,47 @@
+use crate::app::LoggedError;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+use std::collections::VecDeque;
+
+/// Render the error log popup, newest error first, starting `scroll` errors in
+pub fn render_error_log(
+    frame: &mut Frame,
+    area: Rect,
+    errors: &VecDeque<LoggedError>,
+    scroll: usize,
+) {
+    let popup_area = super::centered_rect(70, 60, area);
+    frame.render_widget(Clear, popup_area);
+
+    let mut lines = Vec::new();
+    for error in errors.iter().rev().skip(scroll) {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", error.at),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(
+                format!("[{}] ", error.context.label()),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(error.message.clone(), Style::default().fg(Color::Red)),
+        ]));
+    }
+
+    let block = Block::default()
+        .title(format!(
+            " Errors ({}) | ↑/↓: scroll | c: clear | Esc: close ",
+            errors.len()
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    let para = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(para, popup_area);
+}
@@ -0,0 +1,34 @@
+use crate::app::ExtensionsView;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Render the popup listing the extensions loaded and the functions and modules they
+/// added
+pub fn render_extensions(frame: &mut Frame, area: Rect, view: &ExtensionsView) {
+    let popup_area = super::centered_rect(60, 60, area);
+    frame.render_widget(Clear, popup_area);
+
+    let lines: Vec<Line> = view
+        .lines
+        .iter()
+        .skip(view.scroll)
+        .map(|line| {
+            let color = if line.starts_with(' ') {
+                Color::Cyan
+            } else {
+                Color::White
+            };
+            Line::styled(line.as_str(), Style::default().fg(color))
+        })
+        .collect();
+    let block = Block::default()
+        .title(" Extensions | ↑/↓: scroll | Esc: close ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
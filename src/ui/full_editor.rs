@@ -1,4 +1,5 @@
 use crate::app::App;
+use crate::types::Value;
 use crate::ui::text_editor::{render_editor_panel, render_text_editor_area};
 use ratatui::{
     layout::Constraint,
@@ -12,7 +13,7 @@ use ratatui::{
 pub fn render_full_editor(frame: &mut Frame, area: Rect, app: &App) {
     // Get column name for title
     let column_name =
-        if let (Some(result), Some(col_idx)) = (&app.state.table_rows, app.state.editing_col) {
+        if let (Some(result), Some(col_idx)) = (app.state.edit_target(), app.state.editing_col) {
             if col_idx < result.columns.len() {
                 result.columns[col_idx].clone()
             } else {
@@ -22,6 +23,16 @@ pub fn render_full_editor(frame: &mut Frame, area: Rect, app: &App) {
             "Cell".to_string()
         };
 
+    // BLOB cells take hex or base64; show what the buffer decodes to as it's typed
+    let blob_info = if app.state.edit_blob && !app.state.edit_null {
+        match Value::blob_from_edit(&app.state.edit_buffer) {
+            Ok(bytes) => format!(" [BLOB: {} bytes]", bytes.len()),
+            Err(e) => format!(" [BLOB: {}]", e),
+        }
+    } else {
+        String::new()
+    };
+
     // Editor is always focused when open, use yellow border
     let border_style = Style::default()
         .fg(Color::Yellow)
@@ -35,8 +46,8 @@ pub fn render_full_editor(frame: &mut Frame, area: Rect, app: &App) {
         frame,
         area,
         &format!(
-            "Full Editor: {} (Enter: Save, Shift+Enter: Newline, Esc: Cancel)",
-            column_name
+            "Full Editor: {}{} (Enter: Save, Shift+Enter: Newline, Esc: Cancel)",
+            column_name, blob_info
         ),
         title_style,
         border_style,
@@ -49,13 +60,20 @@ pub fn render_full_editor(frame: &mut Frame, area: Rect, app: &App) {
         chunks[0],
         &app.state.edit_buffer,
         app.state.edit_cursor_pos,
-        "Enter text here...",
+        if app.state.edit_null {
+            "∅ NULL (type to replace, Ctrl+N for empty text)"
+        } else if app.state.edit_blob {
+            "Hex bytes (optional 0x prefix) or b64:<base64>"
+        } else {
+            "Enter text here..."
+        },
         "Editor",
         border_style,
+        false,
     );
 
     // Instructions or error message
-    let instructions = if let Some(error) = &app.state.query_error {
+    let instructions = if let Some(error) = &app.state.edit_error {
         vec![
             Line::from(vec![
                 Span::styled(
@@ -67,6 +85,8 @@ pub fn render_full_editor(frame: &mut Frame, area: Rect, app: &App) {
             Line::from(vec![
                 Span::styled("Enter", Style::default().fg(Color::Cyan)),
                 Span::raw(": Retry Save  "),
+                Span::styled("Ctrl/Alt+Enter", Style::default().fg(Color::Cyan)),
+                Span::raw(": Save Anyway  "),
                 Span::styled("Esc", Style::default().fg(Color::Cyan)),
                 Span::raw(": Cancel/Exit"),
             ]),
@@ -89,7 +109,9 @@ pub fn render_full_editor(frame: &mut Frame, area: Rect, app: &App) {
                 Span::styled("Ctrl+W", Style::default().fg(Color::Cyan)),
                 Span::raw(": Delete word  "),
                 Span::styled("Arrow keys", Style::default().fg(Color::Cyan)),
-                Span::raw(": Navigate"),
+                Span::raw(": Navigate  "),
+                Span::styled("Ctrl+N", Style::default().fg(Color::Cyan)),
+                Span::raw(": NULL"),
             ]),
         ]
     };
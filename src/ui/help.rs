@@ -1,24 +1,36 @@
+use super::centered_rect;
 use ratatui::{
-    layout::{Constraint, Layout, Rect},
+    layout::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
 
-#[allow(clippy::vec_init_then_push)]
-pub fn render_help(frame: &mut Frame, area: Rect) {
+/// Render the help popup, starting `scroll` lines down
+pub fn render_help(frame: &mut Frame, area: Rect, scroll: usize) {
     // Create a centered modal
     let popup_area = centered_rect(70, 80, area);
 
     let block = Block::default()
-        .title("Help (Press ? or Esc to close)")
+        .title("Help (j/k or PgUp/PgDn to scroll, ? or Esc to close)")
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Yellow));
 
     let inner = block.inner(popup_area);
     frame.render_widget(block, popup_area);
 
+    let para = Paragraph::new(help_lines())
+        .block(Block::default())
+        .wrap(Wrap { trim: true })
+        .scroll((scroll as u16, 0));
+
+    frame.render_widget(para, inner);
+}
+
+/// Lines of the help popup
+#[allow(clippy::vec_init_then_push)]
+pub fn help_lines() -> Vec<Line<'static>> {
     let mut lines = Vec::new();
 
     lines.push(Line::from(Span::styled(
@@ -41,39 +53,225 @@ pub fn render_help(frame: &mut Frame, area: Rect) {
     ]));
     lines.push(Line::from(vec![
         Span::styled("Up / Down", Style::default().fg(Color::Cyan)),
-        Span::raw("  Navigate table list"),
+        Span::raw("  Navigate table list / rows"),
     ]));
     lines.push(Line::from(vec![
         Span::styled("Left / Right", Style::default().fg(Color::Cyan)),
+        Span::raw("  Move cell cursor"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("PgUp / PgDn / Home / End", Style::default().fg(Color::Cyan)),
+        Span::raw("  Jump through rows"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled(
+            "Ctrl+Left / Ctrl+Right, h / l",
+            Style::default().fg(Color::Cyan),
+        ),
         Span::raw("  Navigate pages"),
     ]));
+    lines.push(Line::from(vec![
+        Span::styled("Enter", Style::default().fg(Color::Cyan)),
+        Span::raw("  Select table / Execute SQL / fold a section"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("/", Style::default().fg(Color::Cyan)),
+        Span::raw("  Filter tables / search rows (n/N: next/prev)"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+O", Style::default().fg(Color::Cyan)),
+        Span::raw("  Tables: list by name / rows / size"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("F / Backspace", Style::default().fg(Color::Cyan)),
+        Span::raw("  Follow foreign key / go back"),
+    ]));
     lines.push(Line::from(""));
     lines.push(Line::from(Span::styled(
-        "Actions:",
+        "Views:",
         Style::default()
             .fg(Color::Yellow)
             .add_modifier(Modifier::BOLD),
     )));
     lines.push(Line::from(""));
-    lines.push(Line::from(vec![
-        Span::styled("Enter", Style::default().fg(Color::Cyan)),
-        Span::raw("  Select table / Execute SQL"),
-    ]));
     lines.push(Line::from(vec![
         Span::styled("s", Style::default().fg(Color::Cyan)),
         Span::raw("  Toggle schema ↔ rows view"),
     ]));
+    lines.push(Line::from(vec![
+        Span::styled("d", Style::default().fg(Color::Cyan)),
+        Span::raw("  ER diagram"),
+    ]));
     lines.push(Line::from(vec![
         Span::styled("e", Style::default().fg(Color::Cyan)),
         Span::raw("  Open SQL editor"),
     ]));
     lines.push(Line::from(vec![
-        Span::styled("Ctrl+Enter", Style::default().fg(Color::Cyan)),
-        Span::raw("  Execute SQL query"),
+        Span::styled("o", Style::default().fg(Color::Cyan)),
+        Span::raw("  Sort by selected column"),
     ]));
     lines.push(Line::from(vec![
-        Span::styled("/", Style::default().fg(Color::Cyan)),
-        Span::raw("  Filter tables"),
+        Span::styled("f", Style::default().fg(Color::Cyan)),
+        Span::raw("  Filter rows with a WHERE expression"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+H", Style::default().fg(Color::Cyan)),
+        Span::raw("  Find and replace in the selected column"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("= / ] / [", Style::default().fg(Color::Cyan)),
+        Span::raw("  Column widths / pin more / fewer columns"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("# / m / W", Style::default().fg(Color::Cyan)),
+        Span::raw("  Row numbers / value markers / wrap cells"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("v", Style::default().fg(Color::Cyan)),
+        Span::raw("  View BLOB cell as hex"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("w", Style::default().fg(Color::Cyan)),
+        Span::raw("  Toggle watch mode"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("x", Style::default().fg(Color::Cyan)),
+        Span::raw("  Export rows or query result to a file"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("y / Y", Style::default().fg(Color::Cyan)),
+        Span::raw("  Copy rows as CSV / Markdown"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("i", Style::default().fg(Color::Cyan)),
+        Span::raw("  Import a .csv or .tsv file"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("a / r / x / n", Style::default().fg(Color::Cyan)),
+        Span::raw("  Schema view: add / rename / drop column, new index"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("g / Ctrl+X", Style::default().fg(Color::Cyan)),
+        Span::raw("  Schema view: copy or write SQL / export schema"),
+    ]));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Editing:",
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    )));
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Enter", Style::default().fg(Color::Cyan)),
+        Span::raw("  Edit cell / save"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl/Alt+Enter", Style::default().fg(Color::Cyan)),
+        Span::raw("  Save even if validation fails"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+E / Ctrl+N", Style::default().fg(Color::Cyan)),
+        Span::raw("  Full editor / NULL"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("p", Style::default().fg(Color::Cyan)),
+        Span::raw("  Paste clipboard cells"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("V / Shift+↑↓", Style::default().fg(Color::Cyan)),
+        Span::raw("  Select rows"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("w / W", Style::default().fg(Color::Cyan)),
+        Span::raw("  With --staged: write / discard edits"),
+    ]));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "SQL editor:",
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    )));
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Enter / Ctrl+Enter", Style::default().fg(Color::Cyan)),
+        Span::raw("  Run statement at cursor / run all"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Tab", Style::default().fg(Color::Cyan)),
+        Span::raw("  Complete keywords, tables and columns"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+P / Ctrl+N / Ctrl+R", Style::default().fg(Color::Cyan)),
+        Span::raw("  History: previous / next / search"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+X", Style::default().fg(Color::Cyan)),
+        Span::raw("  Show the query plan"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+G", Style::default().fg(Color::Cyan)),
+        Span::raw("  Switch between editor and results"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("r / Ctrl+R", Style::default().fg(Color::Cyan)),
+        Span::raw("  Run the last query again"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("\\ / S / L", Style::default().fg(Color::Cyan)),
+        Span::raw("  Results: layout / summary / rows per page"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Alt+Q / Ctrl+Q", Style::default().fg(Color::Cyan)),
+        Span::raw("  Save query / open saved queries"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+O / Ctrl+S", Style::default().fg(Color::Cyan)),
+        Span::raw("  Load / save a .sql file"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled(
+            "Ctrl+T / Alt+1..9 / Alt+W",
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::raw("  New / switch / close buffer"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Esc / Ctrl+C", Style::default().fg(Color::Cyan)),
+        Span::raw("  Cancel a running query"),
+    ]));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Other:",
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    )));
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Ctrl+W", Style::default().fg(Color::Cyan)),
+        Span::raw("  Reopen read-write"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("E / I / B", Style::default().fg(Color::Cyan)),
+        Span::raw("  Recent errors / integrity check / back up"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("A / D", Style::default().fg(Color::Cyan)),
+        Span::raw("  Attach / detach a database"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("X / P", Style::default().fg(Color::Cyan)),
+        Span::raw("  Extensions / database settings"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Z", Style::default().fg(Color::Cyan)),
+        Span::raw("  Snapshot browsing in WAL mode"),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("R", Style::default().fg(Color::Cyan)),
+        Span::raw("  Restart a crashed database worker"),
     ]));
     lines.push(Line::from(vec![
         Span::styled("?", Style::default().fg(Color::Cyan)),
@@ -99,29 +297,5 @@ pub fn render_help(frame: &mut Frame, area: Rect) {
     lines.push(Line::from("  Middle: Content (rows/schema/query results)"));
     lines.push(Line::from("  Right:  Info and keybindings"));
 
-    let para = Paragraph::new(lines)
-        .block(Block::default())
-        .wrap(Wrap { trim: true });
-
-    frame.render_widget(para, inner);
-}
-
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(ratatui::layout::Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-
-    Layout::default()
-        .direction(ratatui::layout::Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
+    lines
 }
@@ -0,0 +1,85 @@
+use crate::app::history::History;
+use crate::app::HistoryPopup;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Render the Ctrl+R history search: the filter, then matching queries newest first
+pub fn render_history_popup(
+    frame: &mut Frame,
+    area: Rect,
+    popup: &HistoryPopup,
+    history: &History,
+) {
+    let popup_area = super::centered_rect(70, 60, area);
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let matches = history.matching(&popup.filter);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Search: ", Style::default().fg(Color::Gray)),
+            Span::raw(popup.filter.clone()),
+            Span::styled("█", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(""),
+    ];
+
+    // Keep the selection in view: two lines go to the search, two to the keys and borders
+    let visible = popup_area.height.saturating_sub(6) as usize;
+    let first = popup.selected.saturating_sub(visible.saturating_sub(1));
+    if matches.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No matching queries",
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    for (i, entry) in matches.iter().enumerate().skip(first).take(visible) {
+        let query = entry.query.split_whitespace().collect::<Vec<_>>().join(" ");
+        let time = entry
+            .timestamp
+            .get(..16)
+            .unwrap_or(&entry.timestamp)
+            .replace('T', " ");
+        let style = if i == popup.selected {
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let meta_color = if i == popup.selected {
+            Color::Gray
+        } else {
+            Color::DarkGray
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}  ", time), style.fg(meta_color)),
+            Span::styled(query, style),
+            Span::styled(
+                format!("  ({} rows, {}ms)", entry.row_count, entry.duration_ms),
+                style.fg(meta_color),
+            ),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Enter", key_style),
+        Span::raw(": insert  "),
+        Span::styled("↑/↓", key_style),
+        Span::raw(": select  "),
+        Span::styled("Esc", key_style),
+        Span::raw(": close"),
+    ]));
+
+    let block = Block::default()
+        .title(format!(" Query history ({}) ", matches.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
@@ -0,0 +1,82 @@
+use crate::app::IndexForm;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Render the Schema view's form for an index to create: the table's columns, those
+/// picked numbered in the order the index takes them
+pub fn render_index_form(frame: &mut Frame, area: Rect, form: &IndexForm) {
+    let popup_area = super::centered_rect(50, 60, area);
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let dim = Style::default().fg(Color::DarkGray);
+
+    let mut lines = Vec::new();
+    for (i, column) in form.columns.iter().enumerate() {
+        let selected = i == form.selected;
+        let style = if selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        let mark = match form.picked.iter().position(|&p| p == i) {
+            Some(at) => format!("[{}]", at + 1),
+            None => "[ ]".to_string(),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if selected { "▶ " } else { "  " }, style),
+            Span::raw(format!("{} ", mark)),
+            Span::styled(column.clone(), style),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Unique: ", dim),
+        Span::raw(if form.unique { "yes" } else { "no" }),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("Name:   ", dim),
+        Span::raw(if form.picked.is_empty() {
+            "-".to_string()
+        } else {
+            form.name()
+        }),
+    ]));
+
+    if let Some(error) = &form.error {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Space", key_style),
+        Span::raw(": pick column  "),
+        Span::styled("u", key_style),
+        Span::raw(": unique  "),
+        Span::styled("Enter", key_style),
+        Span::raw(": create  "),
+        Span::styled("Esc", key_style),
+        Span::raw(": cancel"),
+    ]));
+
+    let block = Block::default()
+        .title(format!(" New index on {} ", form.table))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    let para = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(para, popup_area);
+}
@@ -1,4 +1,7 @@
 use crate::app::{App, Focus};
+use crate::types::TableKind;
+use crate::ui::format_size;
+use crate::ui::sql_highlight::highlight_sql;
 use ratatui::{
     layout::Rect,
     style::{Color, Modifier, Style},
@@ -75,125 +78,6 @@ fn format_sql_schema(sql: &str) -> String {
     formatted
 }
 
-/// Format a line of SQL with syntax highlighting
-fn format_sql_line(line: &str) -> Line<'static> {
-    let mut spans = Vec::new();
-    let mut current_word = String::new();
-    let mut in_string = false;
-    let mut string_char = '\0';
-
-    // SQL keywords to highlight
-    let keywords = [
-        "CREATE",
-        "TABLE",
-        "IF",
-        "NOT",
-        "EXISTS",
-        "PRIMARY",
-        "KEY",
-        "FOREIGN",
-        "REFERENCES",
-        "UNIQUE",
-        "CHECK",
-        "DEFAULT",
-        "NULL",
-        "INTEGER",
-        "TEXT",
-        "REAL",
-        "BLOB",
-        "AUTOINCREMENT",
-        "CONSTRAINT",
-        "INDEX",
-        "ON",
-        "DELETE",
-        "UPDATE",
-        "CASCADE",
-        "SET",
-        "RESTRICT",
-        "NO",
-        "ACTION",
-    ];
-
-    let chars = line.chars().peekable();
-
-    for ch in chars {
-        match ch {
-            '\'' | '"' => {
-                if !in_string {
-                    in_string = true;
-                    string_char = ch;
-                    // Push current word if any
-                    if !current_word.is_empty() {
-                        spans.push(format_word_span(&current_word, &keywords));
-                        current_word.clear();
-                    }
-                    spans.push(Span::styled(
-                        ch.to_string(),
-                        Style::default().fg(Color::Green),
-                    ));
-                } else if ch == string_char {
-                    in_string = false;
-                    spans.push(Span::styled(
-                        ch.to_string(),
-                        Style::default().fg(Color::Green),
-                    ));
-                } else {
-                    current_word.push(ch);
-                }
-            }
-            c if in_string => {
-                spans.push(Span::styled(
-                    c.to_string(),
-                    Style::default().fg(Color::Green),
-                ));
-            }
-            c if c.is_alphanumeric() || c == '_' => {
-                current_word.push(c);
-            }
-            c => {
-                // Push current word if any
-                if !current_word.is_empty() {
-                    spans.push(format_word_span(&current_word, &keywords));
-                    current_word.clear();
-                }
-                // Format punctuation
-                let style = match c {
-                    '(' | ')' => Style::default().fg(Color::Cyan),
-                    ',' => Style::default().fg(Color::Gray),
-                    _ => Style::default().fg(Color::White),
-                };
-                spans.push(Span::styled(c.to_string(), style));
-            }
-        }
-    }
-
-    // Push remaining word
-    if !current_word.is_empty() {
-        spans.push(format_word_span(&current_word, &keywords));
-    }
-
-    if spans.is_empty() {
-        Line::from("")
-    } else {
-        Line::from(spans)
-    }
-}
-
-/// Format a word with appropriate styling based on whether it's a keyword
-fn format_word_span(word: &str, keywords: &[&str]) -> Span<'static> {
-    let upper_word = word.to_uppercase();
-    if keywords.contains(&upper_word.as_str()) {
-        Span::styled(
-            word.to_string(),
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
-    } else {
-        Span::styled(word.to_string(), Style::default().fg(Color::White))
-    }
-}
-
 pub fn render_info(frame: &mut Frame, area: Rect, app: &App) {
     let (border_style, title_style) = if app.state.focus == Focus::Info {
         (
@@ -222,13 +106,70 @@ pub fn render_info(frame: &mut Frame, area: Rect, app: &App) {
 
     let mut lines = Vec::new();
 
-    if let Some(table_info) = &app.state.table_info {
+    if let Some(table) = app.state.diagram_selected_table() {
+        // The table selected in the diagram, with the columns its box has no room for
+        lines.push(Line::from(Span::styled(
+            table.name.clone(),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Columns:",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for col in &table.columns {
+            let name_style = if col.primary_key {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let mut spans = vec![
+                Span::styled(col.name.clone(), name_style),
+                Span::styled(
+                    format!(" {}", col.data_type),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ];
+            if col.primary_key {
+                spans.push(Span::styled(" PK", Style::default().fg(Color::Yellow)));
+            } else if col.not_null {
+                spans.push(Span::styled(
+                    " NOT NULL",
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            for fk in table
+                .foreign_keys
+                .iter()
+                .filter(|fk| fk.from_column == col.name)
+            {
+                spans.push(Span::styled(
+                    format!(" → {}.{}", fk.to_table, fk.to_column),
+                    Style::default().fg(Color::Green),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::from(Span::styled(
+            "Enter: rows  Esc: deselect",
+            Style::default().fg(Color::DarkGray),
+        )));
+    } else if let Some(table_info) = &app.state.table_info {
         // Compact table info header
-        let table_header = if let Some(row_count) = table_info.row_count {
+        let mut table_header = if let Some(row_count) = table_info.row_count {
             format!("{} ({})", table_info.name, row_count)
         } else {
             table_info.name.clone()
         };
+        if table_info.kind != TableKind::Table {
+            table_header.push_str(&format!(" - {}", table_info.kind.label()));
+        }
         lines.push(Line::from(Span::styled(
             table_header,
             Style::default()
@@ -253,10 +194,11 @@ pub fn render_info(frame: &mut Frame, area: Rect, app: &App) {
             let max_schema_lines = 8; // Limit schema display
             let lines_to_show = sql_lines.len().min(max_schema_lines);
 
-            for line in sql_lines.iter().take(lines_to_show) {
-                let styled_line = format_sql_line(line);
-                lines.push(styled_line);
-            }
+            lines.extend(
+                highlight_sql(&formatted_sql, None)
+                    .into_iter()
+                    .take(lines_to_show),
+            );
 
             // Show truncation indicator if schema is longer
             if sql_lines.len() > max_schema_lines {
@@ -273,6 +215,64 @@ pub fn render_info(frame: &mut Frame, area: Rect, app: &App) {
         )));
     }
 
+    // In WAL mode pages can be read from one snapshot while others write
+    if let Some(mode) = &app.state.journal_mode {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(
+                "Journal: ",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(mode.clone()),
+        ]));
+        if let Some(taken) = &app.state.snapshot {
+            lines.push(Line::from(Span::styled(
+                format!("Snapshot taken {} (Z: off)", taken),
+                Style::default().fg(Color::Magenta),
+            )));
+        } else if app.state.snapshot_mode {
+            lines.push(Line::from(Span::styled(
+                "Snapshot browsing on; taken when a table opens",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+    }
+
+    // What the loaded rows hold, so a page cut short at the budget makes sense
+    let (rows_bytes, results_bytes) = app.state.result_memory_in_use();
+    if rows_bytes + results_bytes > 0 {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(
+                "Memory: ",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                "rows {}, results {} (page budget {})",
+                format_size(rows_bytes as u64),
+                format_size(results_bytes as u64),
+                format_size(app.state.result_memory as u64)
+            )),
+        ]));
+        let capped = [&app.state.table_rows, &app.state.query_result]
+            .into_iter()
+            .flatten()
+            .find(|result| result.memory_cap.is_some());
+        if let Some(result) = capped {
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "Rows{}; the rest are on the next page",
+                    result.memory_note()
+                ),
+                Style::default().fg(Color::Magenta),
+            )));
+        }
+    }
+
     lines.push(Line::from(""));
     lines.push(Line::from(Span::styled(
         "Shortcuts:",
@@ -285,9 +285,9 @@ pub fn render_info(frame: &mut Frame, area: Rect, app: &App) {
     lines.push(Line::from(vec![
         Span::styled("Tab", Style::default().fg(Color::Cyan)),
         Span::raw(": panes  "),
-        Span::styled("Up/Down", Style::default().fg(Color::Cyan)),
-        Span::raw(": nav  "),
-        Span::styled("Left/Right", Style::default().fg(Color::Cyan)),
+        Span::styled("Arrows", Style::default().fg(Color::Cyan)),
+        Span::raw(": cells  "),
+        Span::styled("h/l", Style::default().fg(Color::Cyan)),
         Span::raw(": pages"),
     ]));
 
@@ -366,10 +366,36 @@ pub fn render_info(frame: &mut Frame, area: Rect, app: &App) {
         )));
         lines.push(Line::from(vec![
             Span::styled("Enter", Style::default().fg(Color::Cyan)),
-            Span::raw(": execute  "),
+            Span::raw(": run statement  "),
             Span::styled("Shift+Enter", Style::default().fg(Color::Cyan)),
             Span::raw(": newline"),
         ]));
+        lines.push(Line::from(vec![
+            Span::styled("Ctrl/Alt+Enter", Style::default().fg(Color::Cyan)),
+            Span::raw(": run all statements"),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Ctrl+P/N", Style::default().fg(Color::Cyan)),
+            Span::raw(": history  "),
+            Span::styled("Ctrl+R", Style::default().fg(Color::Cyan)),
+            Span::raw(": search history"),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Tab", Style::default().fg(Color::Cyan)),
+            Span::raw(": complete word  "),
+            Span::styled("Ctrl+X", Style::default().fg(Color::Cyan)),
+            Span::raw(": query plan"),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Alt+Q", Style::default().fg(Color::Cyan)),
+            Span::raw(": save query  "),
+            Span::styled("Ctrl+Q", Style::default().fg(Color::Cyan)),
+            Span::raw(": saved queries"),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Ctrl+O/S", Style::default().fg(Color::Cyan)),
+            Span::raw(": open/save file"),
+        ]));
         lines.push(Line::from(vec![
             Span::styled("Ctrl+C", Style::default().fg(Color::Cyan)),
             Span::raw(": clear results  "),
@@ -388,6 +414,10 @@ pub fn render_info(frame: &mut Frame, area: Rect, app: &App) {
             Span::styled("Arrow keys", Style::default().fg(Color::Cyan)),
             Span::raw(": navigate"),
         ]));
+        lines.push(Line::from(vec![
+            Span::styled("Ctrl+G", Style::default().fg(Color::Cyan)),
+            Span::raw(": browse/edit results"),
+        ]));
     }
 
     let para = Paragraph::new(lines)
@@ -0,0 +1,60 @@
+use super::SPINNER;
+use crate::app::IntegrityCheckView;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Render the integrity check popup: a spinner while the check runs, then its report
+/// with problems in red
+pub fn render_integrity_check(frame: &mut Frame, area: Rect, view: &IntegrityCheckView) {
+    let popup_area = super::centered_rect(60, 50, area);
+    frame.render_widget(Clear, popup_area);
+
+    let (lines, title, color) = match view {
+        IntegrityCheckView::Running { elapsed_ms } => {
+            let text = format!(
+                "{} Checking the database... {:.1}s",
+                SPINNER[(elapsed_ms / 250) as usize % SPINNER.len()],
+                *elapsed_ms as f64 / 1000.0
+            );
+            (
+                vec![Line::from(text)],
+                " Integrity check | Esc: cancel ",
+                Color::Yellow,
+            )
+        }
+        IntegrityCheckView::Done { report, scroll } => {
+            let ok = report.is_ok();
+            let lines = report
+                .lines()
+                .into_iter()
+                .skip(*scroll)
+                .map(|line| {
+                    let color = if line.ends_with(": ok") {
+                        Color::Green
+                    } else if line.starts_with(' ') {
+                        Color::Red
+                    } else {
+                        Color::White
+                    };
+                    Line::styled(line, Style::default().fg(color))
+                })
+                .collect();
+            (
+                lines,
+                " Integrity check | ↑/↓: scroll | Esc: close ",
+                if ok { Color::Green } else { Color::Red },
+            )
+        }
+    };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(color));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
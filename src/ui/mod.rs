@@ -1,31 +1,82 @@
+mod blob;
+mod column_form;
+mod completion;
 mod content;
 mod diagram;
+mod errors;
+mod extensions;
 mod full_editor;
 mod help;
+mod history;
+mod index_form;
 mod info;
+mod integrity;
+mod params;
+mod pragmas;
+mod prompt;
+mod query_plan;
+mod saved_queries;
 mod sql_editor;
+mod sql_highlight;
+mod sql_templates;
 mod tables;
 mod text_editor;
 
 use crate::app::App;
+use crate::export::ExportProgress;
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::Paragraph,
     Frame,
 };
 
+pub use blob::render_blob_viewer;
+pub use column_form::render_column_form;
 pub use content::render_content;
+pub use errors::render_error_log;
+pub use extensions::render_extensions;
 pub use full_editor::render_full_editor;
-pub use help::render_help;
+pub use help::{help_lines, render_help};
+pub use history::render_history_popup;
+pub use index_form::render_index_form;
 pub use info::render_info;
+pub use integrity::render_integrity_check;
+pub use params::render_param_form;
+pub use pragmas::render_pragmas;
+pub use prompt::{render_confirm, render_prompt};
+pub use query_plan::render_query_plan;
+pub use saved_queries::render_saved_query_picker;
 pub use sql_editor::render_sql_editor;
+pub use sql_templates::render_sql_template_menu;
 pub use tables::render_tables;
 
 /// Render the main UI
 pub fn render(frame: &mut Frame, app: &App) {
-    let size = frame.size();
+    let mut size = frame.size();
+
+    // Nothing reaches the database until the worker is restarted, so say so above all
+    if app.state.worker_crashed {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(size);
+        frame.render_widget(
+            Paragraph::new("Database worker crashed — press R to restart")
+                .alignment(Alignment::Center)
+                .style(
+                    Style::default()
+                        .fg(Color::White)
+                        .bg(Color::Red)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            chunks[0],
+        );
+        size = chunks[1];
+    }
 
     if app.state.show_help {
-        render_help(frame, size);
+        render_help(frame, size, app.state.help_scroll);
         return;
     }
 
@@ -72,4 +123,159 @@ pub fn render(frame: &mut Frame, app: &App) {
         render_content(frame, chunks[1], app);
         render_info(frame, chunks[2], app);
     }
+
+    if let Some(prompt) = &app.state.prompt {
+        render_prompt(frame, size, prompt);
+    }
+
+    if let Some(pending) = &app.state.pending_confirm {
+        render_confirm(frame, size, pending);
+    }
+
+    if let Some(viewer) = &app.state.blob_viewer {
+        render_blob_viewer(frame, size, viewer);
+    }
+
+    if let Some(plan) = &app.state.query_plan {
+        render_query_plan(frame, size, plan);
+    }
+
+    if let Some(scroll) = app.state.error_log_popup {
+        render_error_log(frame, size, &app.state.error_log, scroll);
+    }
+
+    if let Some(view) = &app.state.integrity_check {
+        render_integrity_check(frame, size, view);
+    }
+
+    if let Some(view) = &app.state.extensions_view {
+        render_extensions(frame, size, view);
+    }
+
+    if let Some(view) = &app.state.pragma_view {
+        render_pragmas(frame, size, view, app.state.read_only);
+    }
+
+    if let Some(popup) = &app.state.history_popup {
+        render_history_popup(frame, size, popup, &app.state.history);
+    }
+
+    if let Some(picker) = &app.state.saved_query_picker {
+        render_saved_query_picker(frame, size, picker);
+    }
+
+    if let Some(form) = &app.state.param_form {
+        render_param_form(frame, size, form);
+    }
+    if let Some(form) = &app.state.column_form {
+        render_column_form(frame, size, form);
+    }
+    if let Some(form) = &app.state.index_form {
+        render_index_form(frame, size, form);
+    }
+    if let Some(selected) = app.state.sql_template_menu {
+        let table = app.state.schema_table.as_deref().unwrap_or_default();
+        render_sql_template_menu(frame, size, selected, table);
+    }
+}
+
+/// Spinner frames for a running query, advanced by its elapsed time
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// What to show while a query runs: a spinner and the time it has taken so far, or
+/// that it's waiting on a lock
+fn query_loading_text(app: &App) -> String {
+    if let Some(busy) = app.state.busy_message() {
+        return format!("{} {} (Esc: cancel)", SPINNER[0], busy);
+    }
+    match app.state.query_elapsed_ms {
+        Some(ms) => format!(
+            "{} Executing query... {:.1}s (Esc: cancel)",
+            SPINNER[(ms / 250) as usize % SPINNER.len()],
+            ms as f64 / 1000.0
+        ),
+        None => format!("{} Executing query... (Esc: cancel)", SPINNER[0]),
+    }
+}
+
+/// Format a count with thousands separators, e.g. 1234 as "1,234"
+pub fn group_digits(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if n < 0 {
+        format!("-{}", grouped)
+    } else {
+        grouped
+    }
+}
+
+/// Bytes in the largest unit that keeps the number above 1: 8.0 KB, 340 MB
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size >= 100.0 {
+        format!("{:.0} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// A bar of how much of a job is done with its percentage, e.g. "[██████░░░░] 60%"
+pub fn progress_bar(done: i64, total: i64) -> String {
+    const WIDTH: i64 = 20;
+    let (filled, percent) = if total > 0 {
+        (done * WIDTH / total, done * 100 / total)
+    } else {
+        (0, 0)
+    };
+    format!(
+        "[{}{}] {}%",
+        "█".repeat(filled as usize),
+        "░".repeat((WIDTH - filled) as usize),
+        percent
+    )
+}
+
+/// How far an export has got, e.g. "1,200,000 rows (350.0 MB)"
+pub fn export_progress_text(progress: ExportProgress) -> String {
+    format!(
+        "{} rows ({:.1} MB)",
+        group_digits(progress.rows as i64),
+        progress.bytes as f64 / (1024.0 * 1024.0)
+    )
+}
+
+/// Compute a rectangle centered in `r` sized as a percentage of it
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
 }
@@ -0,0 +1,96 @@
+use crate::app::ParamForm;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Render the form asking for a statement's parameter values
+pub fn render_param_form(frame: &mut Frame, area: Rect, form: &ParamForm) {
+    let popup_area = super::centered_rect(60, 50, area);
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let dim = Style::default().fg(Color::DarkGray);
+    let name_width = form
+        .fields
+        .iter()
+        .map(|field| field.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines: Vec<Line> = form
+        .statement
+        .trim()
+        .lines()
+        .map(|line| Line::from(Span::styled(line.to_string(), dim)))
+        .collect();
+    lines.push(Line::from(""));
+
+    for (i, field) in form.fields.iter().enumerate() {
+        let selected = i == form.selected;
+        let name_style = if selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        let mut spans = vec![
+            Span::styled(if selected { "▶ " } else { "  " }, name_style),
+            Span::styled(
+                format!("{:<width$}", field.name, width = name_width),
+                name_style,
+            ),
+            Span::styled(format!(" [{:<7}] ", field.kind.label()), dim),
+        ];
+        if field.null {
+            spans.push(Span::styled("∅ NULL", Style::default().fg(Color::Magenta)));
+        } else if selected {
+            let before: String = field.value.chars().take(field.cursor_pos).collect();
+            let after: String = field.value.chars().skip(field.cursor_pos).collect();
+            spans.push(Span::raw(before));
+            spans.push(Span::styled(
+                "█",
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            ));
+            spans.push(Span::raw(after));
+        } else {
+            spans.push(Span::raw(field.value.clone()));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    if let Some(error) = &form.error {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Enter", key_style),
+        Span::raw(": run  "),
+        Span::styled("Tab/↑↓", key_style),
+        Span::raw(": field  "),
+        Span::styled("Ctrl+N", key_style),
+        Span::raw(": NULL  "),
+        Span::styled("Ctrl+T", key_style),
+        Span::raw(": type  "),
+        Span::styled("Esc", key_style),
+        Span::raw(": cancel"),
+    ]));
+
+    let block = Block::default()
+        .title(format!(" Parameters ({}) ", form.fields.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    let para = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(para, popup_area);
+}
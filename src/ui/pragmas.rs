@@ -0,0 +1,125 @@
+use crate::app::{PragmaEdit, PragmaView};
+use crate::db::pragma::Pragma;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Render the Database popup: the PRAGMA settings, with the choices or typed value of
+/// the one being changed under it
+pub fn render_pragmas(frame: &mut Frame, area: Rect, view: &PragmaView, read_only: bool) {
+    let popup_area = super::centered_rect(50, 60, area);
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let dim = Style::default().fg(Color::DarkGray);
+    let name_width = Pragma::ALL
+        .iter()
+        .map(|pragma| pragma.name().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    match &view.settings {
+        None => lines.push(Line::from(Span::styled("Reading settings...", dim))),
+        Some(settings) => {
+            for (i, pragma) in Pragma::ALL.iter().enumerate() {
+                let selected = i == view.selected;
+                let name_style = if selected {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Yellow)
+                };
+                let mut spans = vec![
+                    Span::styled(if selected { "▶ " } else { "  " }, name_style),
+                    Span::styled(
+                        format!("{:<width$}  ", pragma.name(), width = name_width),
+                        name_style,
+                    ),
+                ];
+                match (&view.editing, selected) {
+                    (Some(PragmaEdit::Number(text)), true) => {
+                        spans.push(Span::raw(text.clone()));
+                        spans.push(Span::styled(
+                            "█",
+                            Style::default().fg(Color::Black).bg(Color::Yellow),
+                        ));
+                    }
+                    _ => spans.push(Span::raw(settings.value(*pragma))),
+                }
+                if !pragma.is_writable() {
+                    spans.push(Span::styled("  (fixed)", dim));
+                }
+                lines.push(Line::from(spans));
+
+                if let (Some(PragmaEdit::Choice(at)), true) = (&view.editing, selected) {
+                    for (c, choice) in pragma.choices().unwrap_or_default().iter().enumerate() {
+                        let style = if c == *at {
+                            Style::default().fg(Color::Black).bg(Color::Cyan)
+                        } else {
+                            Style::default().fg(Color::Cyan)
+                        };
+                        lines.push(Line::from(vec![
+                            Span::raw(" ".repeat(name_width + 4)),
+                            Span::styled(format!(" {} ", choice), style),
+                        ]));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(message) = &view.message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            message.clone(),
+            Style::default().fg(Color::Green),
+        )));
+    }
+
+    lines.push(Line::from(""));
+    let help = match (&view.editing, read_only) {
+        (Some(_), _) => vec![
+            Span::styled("Enter", key_style),
+            Span::raw(": apply  "),
+            Span::styled("Esc", key_style),
+            Span::raw(": cancel"),
+        ],
+        (None, true) => vec![
+            Span::styled("↑↓", key_style),
+            Span::raw(": select  "),
+            Span::styled("r", key_style),
+            Span::raw(": refresh  "),
+            Span::styled("Esc", key_style),
+            Span::raw(": close  "),
+            Span::styled("(read-only)", dim),
+        ],
+        (None, false) => vec![
+            Span::styled("↑↓", key_style),
+            Span::raw(": select  "),
+            Span::styled("Enter", key_style),
+            Span::raw(": change  "),
+            Span::styled("r", key_style),
+            Span::raw(": refresh  "),
+            Span::styled("Esc", key_style),
+            Span::raw(": close"),
+        ],
+    };
+    lines.push(Line::from(help));
+
+    let block = Block::default()
+        .title(" Database ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        popup_area,
+    );
+}
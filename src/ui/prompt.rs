@@ -0,0 +1,275 @@
+use crate::app::{ConfirmAction, PendingConfirm, Prompt};
+use crate::export::ExportSource;
+use crate::types::Value;
+use crate::ui::group_digits;
+use crate::ui::text_editor::render_text_editor_area;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Render a single-line input prompt as a popup near the top of the screen
+pub fn render_prompt(frame: &mut Frame, area: Rect, prompt: &Prompt) {
+    let width = (area.width * 3 / 5).max(20).min(area.width);
+    let popup_area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + area.height / 3,
+        width,
+        3.min(area.height),
+    );
+
+    frame.render_widget(Clear, popup_area);
+    render_text_editor_area(
+        frame,
+        popup_area,
+        &prompt.buffer,
+        prompt.cursor_pos,
+        prompt.kind.placeholder(),
+        prompt.kind.title(),
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+        false,
+    );
+}
+
+/// Render the confirmation popup for a destructive write
+pub fn render_confirm(frame: &mut Frame, area: Rect, pending: &PendingConfirm) {
+    let popup_area = super::centered_rect(60, 40, area);
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let label_style = Style::default().fg(Color::Gray);
+    let counted = match (pending.affected_rows, pending.counting) {
+        (Some(count), _) => format!("This will affect ~{} rows", group_digits(count)),
+        (None, true) => "Counting affected rows...".to_string(),
+        (None, false) => "Couldn't estimate how many rows this affects".to_string(),
+    };
+    let (title, impact, details) = match &pending.action {
+        ConfirmAction::Query {
+            statements,
+            keyword,
+            params,
+        } => (
+            match statements.len() {
+                1 => format!(" Run {} statement? ", keyword),
+                n => format!(" Run {} statements, including {}? ", n, keyword),
+            },
+            match *keyword {
+                "DROP" => "This permanently removes the object and its data".to_string(),
+                "ALTER" => "This changes the table's schema".to_string(),
+                _ if statements.len() > 1 => "Statements run in order until one fails".to_string(),
+                _ => counted,
+            },
+            {
+                let mut details: Vec<Line> = statements
+                    .join(";\n")
+                    .lines()
+                    .map(|line| Line::from(line.to_string()))
+                    .collect();
+                if !params.is_empty() {
+                    let values: Vec<String> = params.iter().map(|v| v.display(40)).collect();
+                    details.push(Line::from(vec![
+                        Span::styled("With values: ", label_style),
+                        Span::raw(values.join(", ")),
+                    ]));
+                }
+                details
+            },
+        ),
+        ConfirmAction::ReplaceInColumn(replace) => (
+            format!(" Replace in {}.{}? ", replace.table, replace.column),
+            counted,
+            vec![
+                Line::from(vec![
+                    Span::styled("Find:         ", label_style),
+                    Span::raw(replace.find.clone()),
+                ]),
+                Line::from(vec![
+                    Span::styled("Replace with: ", label_style),
+                    Span::raw(replace.replacement.clone()),
+                ]),
+            ],
+        ),
+        ConfirmAction::DropColumn { table, column } => (
+            format!(" Drop column {}.{}? ", table, column),
+            match (pending.affected_rows, pending.counting) {
+                (Some(count), _) => format!(
+                    "This permanently removes {} non-NULL value(s)",
+                    group_digits(count)
+                ),
+                (None, true) => "Counting the column's values...".to_string(),
+                (None, false) => "Couldn't count the column's values".to_string(),
+            },
+            vec![Line::from(format!(
+                "ALTER TABLE {} DROP COLUMN {}",
+                table, column
+            ))],
+        ),
+        ConfirmAction::DropIndex { table, index } => (
+            format!(" Drop index {}? ", index),
+            format!("Queries on {} that used it may get slower", table),
+            vec![Line::from(
+                "The table's rows are kept; only the index goes.",
+            )],
+        ),
+        ConfirmAction::ReopenReadWrite => (
+            " Reopen in read-write mode? ".to_string(),
+            "The database will be opened with write access".to_string(),
+            vec![Line::from(
+                "Edits made after this are written straight to the database file.",
+            )],
+        ),
+        ConfirmAction::Paste {
+            edits,
+            unchanged,
+            out_of_bounds,
+        } => {
+            let mut details = vec![Line::from(Span::styled(
+                format!("{} cell(s) already hold the pasted value", unchanged),
+                label_style,
+            ))];
+            if *out_of_bounds > 0 {
+                details.push(Line::from(Span::styled(
+                    format!(
+                        "{} cell(s) fall outside the loaded rows or columns and will be skipped",
+                        out_of_bounds
+                    ),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+            (
+                " Paste from clipboard? ".to_string(),
+                format!(
+                    "This will change {} cell(s)",
+                    group_digits(edits.len() as i64)
+                ),
+                details,
+            )
+        }
+        ConfirmAction::LoadFile { path, size, run } => (
+            " Load a large file? ".to_string(),
+            format!(
+                "{} is {:.1} MB",
+                path.display(),
+                *size as f64 / (1024.0 * 1024.0)
+            ),
+            vec![Line::from(if *run {
+                "All of it goes into the SQL editor and every statement runs."
+            } else {
+                "All of it goes into the SQL editor, which may be slow to edit."
+            })],
+        ),
+        ConfirmAction::ExportView {
+            path,
+            source,
+            overwrite,
+            ..
+        } => (
+            " Export rows? ".to_string(),
+            if *overwrite {
+                format!("{} already exists and will be replaced", path.display())
+            } else {
+                format!("Export to {}", path.display())
+            },
+            {
+                let mut details = vec![Line::from(Span::styled(
+                    "The rows this selects, as filtered and sorted on screen:",
+                    label_style,
+                ))];
+                if let ExportSource::View { query, params, .. } = source {
+                    details.push(Line::from(query.clone()));
+                    if !params.is_empty() {
+                        let values: Vec<String> = params
+                            .iter()
+                            .map(|v| Value::from(v.clone()).display(40))
+                            .collect();
+                        details.push(Line::from(vec![
+                            Span::styled("With values: ", label_style),
+                            Span::raw(values.join(", ")),
+                        ]));
+                    }
+                }
+                details
+            },
+        ),
+        ConfirmAction::OverwriteExport { path, .. } => (
+            " Overwrite file? ".to_string(),
+            format!("{} already exists", path.display()),
+            vec![Line::from(
+                "Its contents are replaced by the exported rows.",
+            )],
+        ),
+        ConfirmAction::CopyRows { text, rows, .. } => (
+            " Copy to clipboard? ".to_string(),
+            format!(
+                "{} rows make {:.1} MB of text",
+                group_digits(*rows as i64),
+                text.len() as f64 / (1024.0 * 1024.0)
+            ),
+            vec![Line::from("Some programs are slow to paste that much.")],
+        ),
+        ConfirmAction::OverwriteFile { path } => (
+            " Overwrite file? ".to_string(),
+            format!("{} already exists", path.display()),
+            vec![Line::from("Its contents are replaced by the SQL editor's.")],
+        ),
+        ConfirmAction::OverwriteBackup { path } => (
+            " Overwrite file? ".to_string(),
+            format!("{} already exists", path.display()),
+            vec![Line::from(
+                "It's replaced by the copy once the backup completes.",
+            )],
+        ),
+        ConfirmAction::OverwriteDiagram { path, .. } => (
+            " Overwrite file? ".to_string(),
+            format!("{} already exists", path.display()),
+            vec![Line::from("Its contents are replaced by the diagram.")],
+        ),
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            impact,
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    lines.extend(details);
+    lines.push(Line::from(""));
+    let verb = match pending.action {
+        ConfirmAction::LoadFile { run: false, .. } => ": load  ",
+        ConfirmAction::OverwriteFile { .. }
+        | ConfirmAction::OverwriteExport { .. }
+        | ConfirmAction::OverwriteBackup { .. }
+        | ConfirmAction::OverwriteDiagram { .. } => ": overwrite  ",
+        ConfirmAction::ExportView { .. } => ": export all  ",
+        ConfirmAction::CopyRows { .. } => ": copy  ",
+        _ => ": run  ",
+    };
+    let mut keys = vec![Span::styled("Enter/y", key_style), Span::raw(verb)];
+    if matches!(pending.action, ConfirmAction::ExportView { .. }) {
+        keys.push(Span::styled("p", key_style));
+        keys.push(Span::raw(": this page only  "));
+    }
+    keys.push(Span::styled("Esc/n", key_style));
+    keys.push(Span::raw(": cancel"));
+    if matches!(pending.action, ConfirmAction::Query { .. }) {
+        keys.push(Span::raw("  "));
+        keys.push(Span::styled("a", key_style));
+        keys.push(Span::raw(": run and stop asking this session"));
+    }
+    lines.push(Line::from(keys));
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
+    let para = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(para, popup_area);
+}
@@ -0,0 +1,45 @@
+use crate::app::QueryPlanView;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Render the query plan popup: the statement, then its plan as a tree with full
+/// table scans in red and index lookups in green
+pub fn render_query_plan(frame: &mut Frame, area: Rect, plan: &QueryPlanView) {
+    let popup_area = super::centered_rect(70, 60, area);
+    frame.render_widget(Clear, popup_area);
+
+    let statement = plan
+        .statement
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut lines = vec![
+        Line::from(Span::styled(statement, Style::default().fg(Color::Gray))),
+        Line::from(""),
+    ];
+    let branches = plan.branches();
+    for (branch, step) in branches.iter().zip(&plan.steps).skip(plan.scroll) {
+        let color = if step.is_full_scan() {
+            Color::Red
+        } else if step.uses_index() {
+            Color::Green
+        } else {
+            Color::White
+        };
+        lines.push(Line::from(vec![
+            Span::styled(branch.clone(), Style::default().fg(Color::DarkGray)),
+            Span::styled(step.detail.clone(), Style::default().fg(color)),
+        ]));
+    }
+
+    let block = Block::default()
+        .title(" Query plan | ↑/↓: scroll | y: copy | Esc: close ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
@@ -0,0 +1,77 @@
+use crate::app::SavedQueryPicker;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Render the Ctrl+Q picker: each saved query's name and first line
+pub fn render_saved_query_picker(frame: &mut Frame, area: Rect, picker: &SavedQueryPicker) {
+    let popup_area = super::centered_rect(70, 60, area);
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let name_width = picker
+        .queries
+        .iter()
+        .map(|saved| saved.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(30);
+    let mut lines = Vec::new();
+
+    // Keep the selection in view: two lines go to the keys, two to the borders
+    let visible = popup_area.height.saturating_sub(4) as usize;
+    let first = picker.selected.saturating_sub(visible.saturating_sub(1));
+    for (i, saved) in picker.queries.iter().enumerate().skip(first).take(visible) {
+        let first_line = saved
+            .query
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default();
+        let style = if i == picker.selected {
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let meta_color = if i == picker.selected {
+            Color::Gray
+        } else {
+            Color::DarkGray
+        };
+        let mut spans = vec![
+            Span::styled(
+                format!("{:<width$}  ", saved.name, width = name_width),
+                style.fg(Color::Yellow),
+            ),
+            Span::styled(first_line.to_string(), style),
+        ];
+        if saved.shared {
+            spans.push(Span::styled("  (shared)", style.fg(meta_color)));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Enter", key_style),
+        Span::raw(": load  "),
+        Span::styled("x", key_style),
+        Span::raw(": delete  "),
+        Span::styled("↑/↓", key_style),
+        Span::raw(": select  "),
+        Span::styled("Esc", key_style),
+        Span::raw(": close"),
+    ]));
+
+    let block = Block::default()
+        .title(format!(" Saved queries ({}) ", picker.queries.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
@@ -1,9 +1,12 @@
 use crate::app::App;
+use crate::types::ScriptStep;
+use crate::ui::completion::render_completion_popup;
 use crate::ui::text_editor::{render_editor_panel, render_text_editor_area};
 use ratatui::{
     layout::Constraint,
     prelude::Rect,
     style::{Color, Style},
+    text::{Line, Span},
     widgets::{Block, Paragraph, Wrap},
     Frame,
 };
@@ -13,11 +16,24 @@ pub fn render_sql_editor(frame: &mut Frame, area: Rect, app: &App) {
     let border_style = Style::default().fg(Color::Gray);
     let title_style = Style::default().fg(Color::Gray);
 
+    // Name the buffer only once there's more than one
+    let buffer = app.state.sql_buffer();
+    let count = app.state.sql_buffers.len();
+    let buffer_label = if count > 1 {
+        format!("SQL [{}/{}]", app.state.active_buffer + 1, count)
+    } else {
+        "SQL Editor".to_string()
+    };
+    let title = format!(
+        "{} (Enter: run statement at cursor, Ctrl/Alt+Enter: run all)",
+        buffer_label
+    );
+
     // Use shared editor panel rendering
     let chunks = render_editor_panel(
         frame,
         area,
-        "SQL Editor (Enter to execute)",
+        &title,
         title_style,
         border_style,
         &[Constraint::Percentage(40), Constraint::Percentage(60)],
@@ -27,19 +43,27 @@ pub fn render_sql_editor(frame: &mut Frame, area: Rect, app: &App) {
     render_text_editor_area(
         frame,
         chunks[0],
-        &app.state.sql_query,
-        app.state.sql_cursor_pos,
+        &buffer.query,
+        buffer.cursor_pos,
         "Enter SQL query here...",
         "Query",
         border_style,
+        true,
     );
 
     // Results area
     if app.state.query_loading {
-        let loading = Paragraph::new("Executing query...")
+        let loading = Paragraph::new(super::query_loading_text(app))
             .style(Style::default().fg(Color::Yellow))
             .block(Block::default().title("Results"));
         frame.render_widget(loading, chunks[1]);
+    } else if let Some(message) = &app.state.query_cancelled {
+        let cancelled = Paragraph::new(message.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .block(Block::default().title("Results"));
+        frame.render_widget(cancelled, chunks[1]);
+    } else if app.state.script_steps.len() > 1 {
+        render_script_steps(frame, chunks[1], &app.state.script_steps);
     } else if let Some(error) = &app.state.query_error {
         let error_para = Paragraph::new(format!("Error:\n\n{}", error))
             .style(Style::default().fg(Color::Red))
@@ -47,12 +71,15 @@ pub fn render_sql_editor(frame: &mut Frame, area: Rect, app: &App) {
             .wrap(Wrap { trim: true });
         frame.render_widget(error_para, chunks[1]);
     } else if let Some(result) = &app.state.query_result {
-        let result_text = format!(
-            "{} rows in {}ms{}\n\n(Results displayed in main view)",
-            result.rows.len(),
-            result.exec_ms,
-            if result.truncated { " (truncated)" } else { "" }
-        );
+        let statement = app.state.last_query.as_deref().unwrap_or_default();
+        let result_text = if result.columns.is_empty() {
+            result.summary(statement)
+        } else {
+            format!(
+                "{}\n\n(Results displayed in main view)",
+                result.summary(statement)
+            )
+        };
         let result_para = Paragraph::new(result_text)
             .style(Style::default().fg(Color::Green))
             .block(Block::default().title("Results"))
@@ -64,4 +91,50 @@ pub fn render_sql_editor(frame: &mut Frame, area: Rect, app: &App) {
             .block(Block::default().title("Results"));
         frame.render_widget(empty, chunks[1]);
     }
+
+    if let Some(completion) = &app.state.completion {
+        render_completion_popup(
+            frame,
+            chunks[0],
+            area,
+            completion,
+            &buffer.query,
+            buffer.cursor_pos,
+        );
+    }
+}
+
+/// One line per statement of a multi-statement run: its outcome, or that it didn't run
+fn render_script_steps(frame: &mut Frame, area: Rect, steps: &[ScriptStep]) {
+    let lines: Vec<Line> = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let statement = step
+                .statement
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let statement: String = if statement.chars().count() > 40 {
+                statement.chars().take(37).chain("...".chars()).collect()
+            } else {
+                statement
+            };
+            let (outcome, color) = match &step.outcome {
+                Some(Ok(result)) => (result.summary(&step.statement), Color::Green),
+                Some(Err(error)) => (format!("Error: {}", error), Color::Red),
+                None => ("not run".to_string(), Color::DarkGray),
+            };
+            Line::from(vec![
+                Span::styled(format!("{}. ", i + 1), Style::default().fg(Color::Gray)),
+                Span::raw(statement),
+                Span::raw("  "),
+                Span::styled(outcome, Style::default().fg(color)),
+            ])
+        })
+        .collect();
+    let para = Paragraph::new(lines)
+        .block(Block::default().title("Results"))
+        .wrap(Wrap { trim: true });
+    frame.render_widget(para, area);
 }
@@ -0,0 +1,92 @@
+use crate::db::script::{is_keyword, tokenize, TokenKind};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Column type names, highlighted like keywords though SQLite doesn't reserve them
+const TYPE_NAMES: &[&str] = &[
+    "BLOB", "BOOLEAN", "DATE", "DATETIME", "INT", "INTEGER", "NUMERIC", "REAL", "TEXT", "VARCHAR",
+];
+
+/// Style of each character of `sql`: keywords cyan, strings green, numbers magenta and
+/// comments dim. Strings and comments may span lines.
+pub fn sql_char_styles(sql: &str) -> Vec<Style> {
+    let plain = Style::default().fg(Color::White);
+    let keyword = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let string = Style::default().fg(Color::Green);
+    let number = Style::default().fg(Color::Magenta);
+    let comment = Style::default().fg(Color::DarkGray);
+
+    let mut styles = Vec::new();
+    for token in tokenize(sql) {
+        let text = &sql[token.range];
+        let style = match token.kind {
+            TokenKind::String | TokenKind::QuotedIdentifier => string,
+            TokenKind::Comment => comment,
+            TokenKind::Number => number,
+            TokenKind::Word
+                if is_keyword(text) || TYPE_NAMES.contains(&text.to_ascii_uppercase().as_str()) =>
+            {
+                keyword
+            }
+            TokenKind::Symbol if text == "(" || text == ")" => Style::default().fg(Color::Cyan),
+            TokenKind::Symbol if text == "," => Style::default().fg(Color::Gray),
+            _ => plain,
+        };
+        styles.extend(std::iter::repeat_n(style, text.chars().count()));
+    }
+    styles
+}
+
+/// `sql` as highlighted lines, with the block cursor before character `cursor` if given
+pub fn highlight_sql(sql: &str, cursor: Option<usize>) -> Vec<Line<'static>> {
+    styled_lines(sql, &sql_char_styles(sql), cursor)
+}
+
+/// Split `text` into lines of spans styled per character by `styles`, inserting the
+/// block cursor before character `cursor`, or at the end when it's past the text
+pub fn styled_lines(text: &str, styles: &[Style], cursor: Option<usize>) -> Vec<Line<'static>> {
+    let cursor_span = || {
+        Span::styled(
+            "█",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    };
+    let mut lines = Vec::new();
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    // The run of characters sharing a style, flushed when the style changes
+    let mut run = String::new();
+    let mut run_style = Style::default();
+
+    for (i, c) in text.chars().enumerate() {
+        let style = styles.get(i).copied().unwrap_or_default();
+        if cursor == Some(i) || c == '\n' || style != run_style {
+            if !run.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut run), run_style));
+            }
+            run_style = style;
+        }
+        if cursor == Some(i) {
+            spans.push(cursor_span());
+        }
+        if c == '\n' {
+            lines.push(Line::from(std::mem::take(&mut spans)));
+        } else {
+            run.push(c);
+        }
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, run_style));
+    }
+    if cursor.is_some_and(|pos| pos >= text.chars().count()) {
+        spans.push(cursor_span());
+    }
+    lines.push(Line::from(spans));
+    lines
+}
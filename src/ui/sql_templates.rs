@@ -0,0 +1,54 @@
+use crate::app::sql_template::SqlTemplate;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Render the Schema view's menu of SQL to generate for its table
+pub fn render_sql_template_menu(frame: &mut Frame, area: Rect, selected: usize, table: &str) {
+    let width = 50.min(area.width);
+    let height = (SqlTemplate::ALL.len() as u16 + 4).min(area.height);
+    let popup_area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, popup_area);
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let mut lines: Vec<Line> = SqlTemplate::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, template)| {
+            if i == selected {
+                Line::from(Span::styled(
+                    format!("▶ {}", template.label()),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ))
+            } else {
+                Line::from(format!("  {}", template.label()))
+            }
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Enter", key_style),
+        Span::raw(": SQL editor  "),
+        Span::styled("y", key_style),
+        Span::raw(": copy  "),
+        Span::styled("Esc", key_style),
+        Span::raw(": close"),
+    ]));
+
+    let block = Block::default()
+        .title(format!(" SQL for {} ", table))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
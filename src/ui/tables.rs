@@ -1,31 +1,127 @@
-use crate::app::{App, Focus};
+use super::{format_size, SPINNER};
+use crate::app::{App, Focus, TableEntry, TableSort};
+use crate::types::TableKind;
 use ratatui::{
     layout::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
+/// Views, virtual tables and triggers each get their own color
+fn kind_style(kind: TableKind) -> Style {
+    match kind {
+        TableKind::Table => Style::default(),
+        TableKind::View => Style::default().fg(Color::Green),
+        TableKind::Virtual => Style::default().fg(Color::Magenta),
+        TableKind::Trigger => Style::default().fg(Color::Blue),
+    }
+}
+
+/// A row count in a few characters: 950, 12.3K, 1.2M
+fn compact_count(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}K", count as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1}M", count as f64 / 1e6),
+        _ => format!("{:.1}B", count as f64 / 1e9),
+    }
+}
+
 pub fn render_tables(frame: &mut Frame, area: Rect, app: &App) {
-    let filtered_tables = app.state.filtered_tables();
-    let items: Vec<ListItem> = filtered_tables
+    let entries = app.state.table_entries();
+    // With databases attached, tables are grouped under the name of the database they're in
+    let grouped = app.state.tables.iter().any(|table| table.schema.is_some());
+    let sectioned = entries
         .iter()
-        .map(|table| {
-            let row_count = table
-                .row_count
-                .map(|c| format!(" ({})", c))
-                .unwrap_or_default();
-            let text = format!("{}{}", table.name, row_count);
-            ListItem::new(text)
-        })
-        .collect();
+        .any(|entry| matches!(entry, TableEntry::Section { .. }));
+    let mut items: Vec<ListItem> = Vec::new();
+    let elapsed_ms = app.state.counting_since.elapsed().as_millis() as usize;
+    let spinner = SPINNER[elapsed_ms / 250 % SPINNER.len()];
+    let mut selected = None;
+    let mut schema = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let entry_schema = match entry {
+            TableEntry::Section { schema, .. } => *schema,
+            TableEntry::Table(table) => table.schema.as_deref(),
+        };
+        if grouped && schema != Some(entry_schema) {
+            schema = Some(entry_schema);
+            items.push(ListItem::new(Line::from(Span::styled(
+                entry_schema.unwrap_or("main").to_string(),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ))));
+        }
+        if i == app.state.selected_table_index {
+            selected = Some(items.len());
+        }
+        let indent = "  ".repeat(usize::from(grouped));
+        let item = match entry {
+            TableEntry::Section {
+                kind,
+                count,
+                collapsed,
+                ..
+            } => ListItem::new(Line::from(Span::styled(
+                format!(
+                    "{}{} {} ({})",
+                    indent,
+                    if *collapsed { "▸" } else { "▾" },
+                    kind.section(),
+                    count
+                ),
+                kind_style(*kind).add_modifier(Modifier::BOLD),
+            ))),
+            TableEntry::Table(table) => {
+                // A table too big to count shows its largest rowid, marked ~
+                let marker = if table.row_count_estimated { "~" } else { "" };
+                let row_count = match (table.row_count, table.size) {
+                    (Some(rows), Some(size)) => format!(
+                        " ({}{} {}, {})",
+                        marker,
+                        compact_count(rows),
+                        if rows == 1 { "row" } else { "rows" },
+                        format_size(size)
+                    ),
+                    (Some(rows), None) => format!(" ({}{})", marker, rows),
+                    (None, size) if app.state.uncounted_tables.contains(&table.name) => {
+                        match size {
+                            Some(size) => format!(" ({}, {})", spinner, format_size(size)),
+                            None => format!(" ({})", spinner),
+                        }
+                    }
+                    (None, Some(size)) => format!(" ({})", format_size(size)),
+                    (None, None) => String::new(),
+                };
+                let name = if grouped {
+                    table.bare_name()
+                } else {
+                    &table.name
+                };
+                let indent = if sectioned {
+                    format!("{}  ", indent)
+                } else {
+                    indent
+                };
+                ListItem::new(Line::from(Span::styled(
+                    format!("{}{}{}", indent, name, row_count),
+                    kind_style(table.kind),
+                )))
+            }
+        };
+        items.push(item);
+    }
 
-    let title = if app.state.table_filter.is_empty() {
-        "Tables"
-    } else {
-        "Tables (filtered)"
-    };
+    let mut title = "Tables".to_string();
+    if app.state.table_sort != TableSort::Name {
+        title.push_str(&format!(" by {}", app.state.table_sort.label()));
+    }
+    if !app.state.table_filter.is_empty() {
+        title.push_str(" (filtered)");
+    }
 
     let (border_style, title_style) = if app.state.focus == Focus::Tables {
         (
@@ -50,7 +146,7 @@ pub fn render_tables(frame: &mut Frame, area: Rect, app: &App) {
         .border_style(border_style);
 
     let mut list_state = ListState::default();
-    list_state.select(Some(app.state.selected_table_index));
+    list_state.select(selected.or(Some(app.state.selected_table_index)));
 
     let list = List::new(items)
         .block(block)
@@ -64,6 +160,43 @@ pub fn render_tables(frame: &mut Frame, area: Rect, app: &App) {
 
     frame.render_stateful_widget(list, area, &mut list_state);
 
+    // A new or in-memory database has nothing to list yet
+    if app.state.tables.is_empty()
+        && !app.state.tables_loading
+        && app.state.tables_error.is_none()
+        && area.height > 2
+    {
+        let empty = Paragraph::new("No tables yet — press e to open the SQL editor")
+            .style(Style::default().fg(Color::Gray))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(
+            empty,
+            Rect::new(
+                area.x + 1,
+                area.y + 1,
+                area.width.saturating_sub(2),
+                area.height - 2,
+            ),
+        );
+    }
+
+    // A failed reload keeps the old list; say why on its last line
+    if let (Some(error), true) = (&app.state.tables_error, area.height > 2) {
+        let error_line = Line::from(Span::styled(
+            format!("Error: {}", error),
+            Style::default().fg(Color::Red),
+        ));
+        frame.render_widget(
+            error_line,
+            Rect::new(
+                area.x + 1,
+                area.y + area.height - 2,
+                area.width.saturating_sub(2),
+                1,
+            ),
+        );
+    }
+
     // Show filter if active
     if !app.state.table_filter.is_empty() {
         let filter_text = format!("Filter: {}", app.state.table_filter);
@@ -1,7 +1,8 @@
+use crate::ui::sql_highlight::{sql_char_styles, styled_lines};
 use ratatui::{
     layout::{Constraint, Layout},
     prelude::Rect,
-    style::{Color, Modifier, Style},
+    style::{Color, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
@@ -9,13 +10,19 @@ use ratatui::{
 
 /// Calculate cursor position info (line, column) for display
 pub fn calculate_cursor_info(text: &str, cursor_pos: usize) -> (usize, usize) {
-    let pos = cursor_pos.min(text.len());
-    let line = text[..pos].lines().count();
-    let col = text[..pos].lines().last().map(|l| l.len()).unwrap_or(0);
+    let before: String = text.chars().take(cursor_pos).collect();
+    let line = before.lines().count();
+    let col = before
+        .lines()
+        .last()
+        .map(|l| l.chars().count())
+        .unwrap_or(0);
     (line, col)
 }
 
-/// Render a text editor area with cursor position display
+/// Render a text editor area with cursor position display, highlighting the text as SQL
+/// when `highlight_sql` is set
+#[allow(clippy::too_many_arguments)]
 pub fn render_text_editor_area(
     frame: &mut Frame,
     area: Rect,
@@ -24,29 +31,10 @@ pub fn render_text_editor_area(
     placeholder: &str,
     title: &str,
     border_style: Style,
+    highlight_sql: bool,
 ) {
     let pos = cursor_pos.min(text.chars().count());
 
-    // Build display text with visible cursor indicator
-    let display_text = if text.is_empty() {
-        placeholder.to_string()
-    } else {
-        // Insert cursor indicator into the text at character position
-        let mut display = String::new();
-        for (char_idx, ch) in text.chars().enumerate() {
-            if char_idx == pos {
-                // Insert cursor block before this character
-                display.push('█');
-            }
-            display.push(ch);
-        }
-        // If cursor is at the end, add cursor indicator
-        if pos >= text.chars().count() {
-            display.push('█');
-        }
-        display
-    };
-
     let (line, col) = calculate_cursor_info(text, cursor_pos);
     let cursor_info = if text.is_empty() {
         title.to_string()
@@ -54,41 +42,20 @@ pub fn render_text_editor_area(
         format!("{} (Line {}, Col {})", title, line, col + 1)
     };
 
-    // Create styled text with cursor highlighted
-    let mut styled_lines = Vec::new();
-    for line_text in display_text.lines() {
-        let mut spans = Vec::new();
-
-        for ch in line_text.chars() {
-            if ch == '█' {
-                // Cursor character - highlight it
-                spans.push(Span::styled(
-                    "█",
-                    Style::default()
-                        .fg(Color::Black)
-                        .bg(Color::Yellow)
-                        .add_modifier(Modifier::BOLD),
-                ));
-            } else {
-                // Regular character
-                let style = if text.is_empty() {
-                    Style::default().fg(Color::DarkGray)
-                } else {
-                    Style::default().fg(Color::White)
-                };
-                spans.push(Span::styled(ch.to_string(), style));
-            }
-        }
-        styled_lines.push(Line::from(spans));
-    }
-
-    // If text is empty, show placeholder
-    if styled_lines.is_empty() {
-        styled_lines.push(Line::from(Span::styled(
-            placeholder,
+    // Style the text, then draw the cursor block before the character at the cursor
+    let styled_lines = if text.is_empty() {
+        vec![Line::from(Span::styled(
+            placeholder.to_string(),
             Style::default().fg(Color::DarkGray),
-        )));
-    }
+        ))]
+    } else {
+        let styles = if highlight_sql {
+            sql_char_styles(text)
+        } else {
+            vec![Style::default().fg(Color::White); text.chars().count()]
+        };
+        styled_lines(text, &styles, Some(pos))
+    };
 
     let editor = Paragraph::new(styled_lines)
         .block(
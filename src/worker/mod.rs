@@ -1,39 +1,210 @@
-use crate::db;
+use crate::db::pragma::{Pragma, PragmaSettings};
+use crate::db::{self, CheckReport, ColumnChange, Database, ExtensionList, NewIndex};
+use crate::export::{self, ExportCancelled, ExportFormat, ExportOptions, ExportSource};
+use crate::import::{self, ImportOptions};
 use crate::types::{
-    ColumnInfo, DiagramData, DiagramTable, ForeignKeyInfo, IndexInfo, QueryResult, TableInfo,
+    ColumnInfo, DiagramData, ForeignKeyInfo, IndexInfo, PageStart, PendingEdit, PlanStep,
+    QueryResult, RowKey, RowsQuery, ScriptStep, TableInfo, Value,
 };
 use anyhow::Result;
-use rusqlite::Connection;
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{Connection, InterruptHandle};
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// SQLite VM instructions between progress checks while a script runs
+const PROGRESS_OPS: i32 = 10_000;
+/// Least time between progress reports, so a busy query doesn't flood the UI
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Pause before a read that found the database locked is tried again, doubling each time
+const RETRY_PAUSE: Duration = Duration::from_millis(250);
+
+/// How the worker waits out a lock another connection holds
+#[derive(Debug, Clone, Copy)]
+pub struct BusyPolicy {
+    /// How long SQLite waits for a lock before giving up
+    pub timeout: Duration,
+    /// Times a read that gave up is tried again; writes are only retried when asked
+    pub retries: u32,
+}
 
 /// Messages sent to the worker thread
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum WorkerMessage {
     LoadTables {
         include_internal: bool,
     },
+    /// Count the rows of `tables` one at a time between other requests, cheapest first;
+    /// those past `count_limit` rows get their largest rowid instead of a count
+    CountRows {
+        tables: Vec<String>,
+        count_limit: u64,
+    },
+    /// Measure the tables' sizes on disk, after the table list has loaded
+    LoadTableSizes,
+    /// A page of a table's rows: `offset` rows in, or for an unsorted table with a
+    /// rowid, from `page_start` when it's given. Rows past `max_bytes` of memory are
+    /// left out.
     LoadTableRows {
         table_name: String,
         limit: usize,
         offset: usize,
+        view: RowsQuery,
+        page_start: Option<PageStart>,
+        max_bytes: usize,
+    },
+    /// A page of rows as [`WorkerMessage::LoadTableRows`] loads it, fetched ahead of
+    /// being asked for: it waits until nothing else is left to run, and a newer one
+    /// replaces it
+    PrefetchTableRows {
+        table_name: String,
+        limit: usize,
+        offset: usize,
+        view: RowsQuery,
+        page_start: Option<PageStart>,
+        max_bytes: usize,
+    },
+    LocateRow {
+        table_name: String,
+        view: RowsQuery,
+        key: RowKey,
     },
+    ExecuteScript {
+        statements: Vec<String>,
+        max_rows: Option<usize>,
+        max_bytes: usize,
+    },
+    /// Run one statement with values bound to its placeholders
     ExecuteQuery {
         query: String,
+        params: Vec<SqliteValue>,
+        max_rows: Option<usize>,
+        max_bytes: usize,
+    },
+    /// Run a read-only statement again for the rows starting `offset` rows in
+    LoadQueryPage {
+        query: String,
+        params: Vec<SqliteValue>,
+        offset: usize,
         max_rows: Option<usize>,
+        max_bytes: usize,
+    },
+    /// Find the placeholders a statement needs values for
+    InspectParameters {
+        statement: String,
+    },
+    ExplainQuery {
+        statement: String,
     },
     GetTableInfo {
         table_name: String,
     },
+    LoadTableForeignKeys {
+        table_name: String,
+    },
     LoadSchema {
         table_name: String,
     },
+    LoadColumnNames {
+        table_names: Vec<String>,
+    },
     LoadDiagram,
     UpdateCell {
         table_name: String,
-        row_index: usize,
+        key: RowKey,
         column_name: String,
-        new_value: String,
+        new_value: Value,
+    },
+    ApplyEdits {
+        edits: Vec<PendingEdit>,
+    },
+    CountAffectedRows {
+        count_query: String,
+    },
+    CountColumnMatches {
+        table_name: String,
+        column_name: String,
+        find: String,
+    },
+    ReplaceInColumn {
+        table_name: String,
+        column_name: String,
+        find: String,
+        replacement: String,
+    },
+    /// Count a column's values that aren't NULL, which dropping it would lose
+    CountColumnValues {
+        table_name: String,
+        column_name: String,
+    },
+    /// Add, rename or drop one of a table's columns
+    AlterColumn {
+        table_name: String,
+        change: ColumnChange,
+    },
+    /// Build an index, reporting progress; interrupting stops it
+    CreateIndex {
+        table_name: String,
+        index: NewIndex,
+    },
+    DropIndex {
+        table_name: String,
+        index_name: String,
+    },
+    /// Write every row of a table or query to a file
+    ExportResult {
+        format: ExportFormat,
+        path: PathBuf,
+        source: ExportSource,
+    },
+    /// Insert the rows of a CSV file, with a header row, into a table, or none of them
+    /// if any fails
+    ImportCsv {
+        table_name: String,
+        path: PathBuf,
+    },
+    /// Copy the database to a file, replacing it
+    Backup {
+        path: PathBuf,
+    },
+    /// Attach another database file, whose tables are then named `alias.table`
+    AttachDatabase {
+        path: String,
+        alias: String,
+    },
+    DetachDatabase {
+        alias: String,
+    },
+    CheckDataVersion,
+    /// Hold a read transaction open so the reads after it see the database as it is
+    /// now, whatever other connections commit, until EndSnapshot or a request that
+    /// can't run inside it
+    BeginSnapshot,
+    EndSnapshot,
+    /// Check the database for corruption and broken foreign keys
+    IntegrityCheck,
+    /// List what loaded extensions added to the connection
+    ListExtensions,
+    /// Read the PRAGMA settings the Database view shows
+    ReadPragmas,
+    /// Change a PRAGMA, then read them all again
+    SetPragma {
+        pragma: Pragma,
+        value: String,
+    },
+    /// Reopen the database read-write, unlocking it with the same key and loading the
+    /// same extensions into the new connection
+    ReopenReadWrite {
+        key: Option<String>,
+        extensions: Vec<String>,
+        allow_extensions: bool,
     },
     Shutdown,
 }
@@ -41,206 +212,1522 @@ pub enum WorkerMessage {
 /// Responses sent back from the worker thread
 #[derive(Debug)]
 pub enum WorkerResponse {
+    /// The table list, with the journal mode when it could be read
     TablesLoaded {
         tables: Vec<TableInfo>,
+        journal_mode: Option<String>,
+    },
+    /// Rows of a table, or its largest rowid when `estimated`; None when it couldn't be
+    /// counted
+    TableRowCount {
+        table: String,
+        count: Option<u64>,
+        estimated: bool,
+    },
+    /// Bytes each table takes on disk, by table name
+    TableSizesLoaded {
+        sizes: HashMap<String, u64>,
     },
     TableRowsLoaded {
         result: QueryResult,
     },
-    QueryExecuted {
+    /// A page fetched ahead; nothing is sent when it couldn't be
+    TableRowsPrefetched {
         result: QueryResult,
     },
+    RowLocated {
+        position: Option<usize>,
+    },
+    ScriptExecuted {
+        steps: Vec<ScriptStep>,
+    },
+    QueryPageLoaded {
+        result: QueryResult,
+    },
+    ParametersInspected {
+        statement: String,
+        names: Vec<String>,
+    },
+    /// A script or integrity check has been running for `elapsed_ms`
+    QueryProgress {
+        elapsed_ms: u64,
+    },
+    /// The script was interrupted after `completed` of `total` statements had finished
+    QueryCancelled {
+        elapsed_ms: u64,
+        completed: usize,
+        total: usize,
+    },
+    QueryPlanLoaded {
+        statement: String,
+        steps: Vec<PlanStep>,
+    },
     TableInfoLoaded {
         info: TableInfo,
     },
+    TableForeignKeysLoaded {
+        table_name: String,
+        foreign_keys: Vec<ForeignKeyInfo>,
+    },
     SchemaLoaded {
         columns: Vec<ColumnInfo>,
         indexes: Vec<IndexInfo>,
         foreign_keys: Vec<ForeignKeyInfo>,
+        /// Keys of other tables that reference this one
+        referenced_by: Vec<ForeignKeyInfo>,
+    },
+    /// Column names per table; a table that can't be read has none
+    ColumnNamesLoaded {
+        columns: Vec<(String, Vec<String>)>,
     },
     DiagramLoaded {
         data: DiagramData,
     },
+    /// A request failed; `context` says which part of the UI it belongs to
     Error {
+        context: ErrorContext,
+        message: String,
+    },
+    /// A read found the database locked and is trying again, `attempt` of the retries
+    /// allowed, `waited_ms` after it started
+    Busy {
+        attempt: u32,
+        waited_ms: u64,
+    },
+    /// A write found the database locked and gave up; it can be sent again
+    Locked {
+        context: ErrorContext,
         message: String,
     },
     CellUpdated,
+    EditsApplied {
+        count: usize,
+    },
+    AffectedRowsCounted {
+        count: Option<i64>,
+    },
+    ColumnReplaced {
+        count: usize,
+    },
+    SnapshotTaken,
+    /// The snapshot was let go, by a request that writes or changes the connection
+    SnapshotReleased,
+    SnapshotFailed {
+        message: String,
+    },
+    DataVersion {
+        version: i64,
+    },
+    ReopenedReadWrite,
+    ReopenFailed {
+        message: String,
+    },
+    Exported {
+        path: PathBuf,
+        bytes: u64,
+        rows: usize,
+        /// What `rows` counts
+        unit: &'static str,
+        warnings: Vec<String>,
+    },
+    /// How far a running export has got
+    ExportProgress {
+        rows: usize,
+        bytes: u64,
+    },
+    ExportFailed {
+        message: String,
+    },
+    Imported {
+        table_name: String,
+        inserted: usize,
+    },
+    /// How far a running backup has got, in pages
+    BackupProgress {
+        remaining: i32,
+        total: i32,
+    },
+    BackedUp {
+        path: PathBuf,
+        pages: i32,
+    },
+    BackupFailed {
+        message: String,
+    },
+    DatabaseAttached {
+        path: String,
+        alias: String,
+    },
+    DatabaseDetached {
+        alias: String,
+    },
+    /// Attaching or detaching a database failed
+    AttachFailed {
+        message: String,
+    },
+    IntegrityChecked {
+        report: CheckReport,
+    },
+    ExtensionsListed {
+        list: ExtensionList,
+    },
+    /// The PRAGMA settings, with what a change just did
+    PragmasRead {
+        settings: PragmaSettings,
+        message: Option<String>,
+    },
+    PragmaFailed {
+        message: String,
+    },
+    /// A column or index of the table was added, renamed or dropped
+    TableAltered {
+        table_name: String,
+        message: String,
+    },
+    IndexCancelled {
+        elapsed_ms: u64,
+    },
+    IntegrityCheckCancelled {
+        elapsed_ms: u64,
+    },
+    IntegrityCheckFailed {
+        message: String,
+    },
 }
 
-/// Worker thread that handles database operations
-pub struct Worker {
-    sender: mpsc::Sender<WorkerMessage>,
-    receiver: mpsc::Receiver<WorkerResponse>,
-    handle: thread::JoinHandle<()>,
+/// What a failed request was doing, so its error lands in the right place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorContext {
+    Tables,
+    Rows,
+    Query,
+    Schema,
+    Diagram,
+    Edit,
 }
 
-impl Worker {
-    /// Create a new worker with a database connection
-    pub fn new(conn: Connection) -> Self {
-        let (tx, rx) = mpsc::channel();
-        let (response_tx, response_rx) = mpsc::channel();
+impl ErrorContext {
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorContext::Tables => "tables",
+            ErrorContext::Rows => "rows",
+            ErrorContext::Query => "query",
+            ErrorContext::Schema => "schema",
+            ErrorContext::Diagram => "diagram",
+            ErrorContext::Edit => "edit",
+        }
+    }
+}
 
-        let handle = thread::spawn(move || {
-            let connection = conn;
-            loop {
-                match rx.recv() {
-                    Ok(WorkerMessage::LoadTables { include_internal }) => {
-                        match db::get_tables(&connection, include_internal) {
-                            Ok(tables) => {
-                                let _ = response_tx.send(WorkerResponse::TablesLoaded { tables });
-                            }
-                            Err(e) => {
-                                let _ = response_tx.send(WorkerResponse::Error {
-                                    message: format!("Failed to load tables: {}", e),
-                                });
-                            }
-                        }
+/// Run `work` on the connection, reporting its progress a few times a second
+fn with_progress<T>(
+    connection: &Connection,
+    response_tx: &Responder,
+    work: impl FnOnce() -> T,
+) -> T {
+    let start = Instant::now();
+    let progress_tx = AssertUnwindSafe(response_tx.clone());
+    let mut last_report = start;
+    connection.progress_handler(
+        PROGRESS_OPS,
+        Some(move || {
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                last_report = Instant::now();
+                let _ = progress_tx.send(WorkerResponse::QueryProgress {
+                    elapsed_ms: start.elapsed().as_millis() as u64,
+                });
+            }
+            false
+        }),
+    );
+    let result = work();
+    connection.progress_handler(0, None::<fn() -> bool>);
+    result
+}
+
+/// Run `read`, trying it again after a pause while it fails on a lock another
+/// connection holds, up to `busy.retries` times; each wait is reported as Busy so the UI
+/// can say what it's waiting for
+fn retry_busy<T>(
+    busy: BusyPolicy,
+    response_tx: &Responder,
+    mut read: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let start = Instant::now();
+    let mut pause = RETRY_PAUSE;
+    let mut attempt = 0;
+    loop {
+        match read() {
+            Err(e) if attempt < busy.retries && db::query::is_busy(&e) => {
+                attempt += 1;
+                let _ = response_tx.send(WorkerResponse::Busy {
+                    attempt,
+                    waited_ms: start.elapsed().as_millis() as u64,
+                });
+                thread::sleep(pause);
+                pause *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The response to a write that failed: Locked when another connection's lock stopped
+/// it, so it can be tried again, otherwise Error
+fn write_failed(context: ErrorContext, message: String, error: &anyhow::Error) -> WorkerResponse {
+    if db::query::is_busy(error) {
+        WorkerResponse::Locked { context, message }
+    } else {
+        WorkerResponse::Error { context, message }
+    }
+}
+
+/// Sends the worker's responses, tagged with the id of the request they answer
+#[derive(Clone)]
+struct Responder {
+    tx: mpsc::Sender<(u64, WorkerResponse)>,
+    request_id: u64,
+}
+
+/// Id of a request copied to the background connection to keep it like the primary
+/// one; only the primary answers it
+const UNANSWERED: u64 = 0;
+
+impl Responder {
+    fn send(&self, response: WorkerResponse) -> Result<()> {
+        if self.request_id == UNANSWERED {
+            return Ok(());
+        }
+        self.tx
+            .send((self.request_id, response))
+            .map_err(|_| anyhow::anyhow!("The app stopped listening"))
+    }
+}
+
+/// Whether `message` loads rows or info of a table that a request still in `pending`
+/// loads again, so only the later one need run
+fn superseded(message: &WorkerMessage, pending: &VecDeque<(u64, WorkerMessage)>) -> bool {
+    pending.iter().any(|(_, later)| match (message, later) {
+        (
+            WorkerMessage::LoadTableRows { table_name, .. },
+            WorkerMessage::LoadTableRows {
+                table_name: later_table,
+                ..
+            },
+        )
+        | (
+            WorkerMessage::GetTableInfo { table_name },
+            WorkerMessage::GetTableInfo {
+                table_name: later_table,
+            },
+        ) => later_table == table_name,
+        _ => false,
+    })
+}
+
+/// Order `tables` to count, cheapest first by their largest rowid, those without one
+/// last. A table whose rowid passes `count_limit` isn't counted; the rowid is sent now
+/// as an estimate.
+fn plan_row_counts(
+    conn: &Connection,
+    tables: Vec<String>,
+    count_limit: u64,
+    response_tx: &Responder,
+) -> VecDeque<String> {
+    let mut counted = Vec::new();
+    for table in tables {
+        match db::estimate_rows(conn, &table) {
+            Some(rows) if rows > count_limit => {
+                let _ = response_tx.send(WorkerResponse::TableRowCount {
+                    table,
+                    count: Some(rows),
+                    estimated: true,
+                });
+            }
+            estimate => counted.push((estimate.unwrap_or(u64::MAX), table)),
+        }
+    }
+    counted.sort();
+    counted.into_iter().map(|(_, table)| table).collect()
+}
+
+/// Run the requests arriving on `rx` against `connection` until told to stop, keeping
+/// `state` up to date
+#[allow(clippy::too_many_arguments)]
+fn run(
+    mut connection: Connection,
+    path: PathBuf,
+    rx: mpsc::Receiver<(u64, WorkerMessage)>,
+    responses: mpsc::Sender<(u64, WorkerResponse)>,
+    worker_interrupt: Arc<Mutex<InterruptHandle>>,
+    worker_copy_cancel: Arc<AtomicBool>,
+    busy: BusyPolicy,
+    state: Arc<LaneState>,
+) {
+    let _ = connection.busy_timeout(busy.timeout);
+    // Finding what references a table reads every table's foreign keys, so the
+    // answers are kept until the tables are loaded again after a schema change
+    let mut referencing_keys: HashMap<String, Vec<ForeignKeyInfo>> = HashMap::new();
+    // Tables left to count, counted one at a time while nothing else is asked for
+    let mut uncounted: VecDeque<String> = VecDeque::new();
+    // The LoadTables request the counts answer
+    let mut counting_request = 0;
+    // Requests read from the channel but not yet run
+    let mut pending: VecDeque<(u64, WorkerMessage)> = VecDeque::new();
+    // A page to fetch ahead once nothing else is waiting
+    let mut prefetch: Option<(u64, WorkerMessage)> = None;
+    // A read transaction held open for BeginSnapshot, told apart from one a script began
+    let mut snapshot = false;
+    // The diagram read last, with the schema versions it was read at
+    let mut diagram: Option<(Vec<(String, i64)>, DiagramData)> = None;
+    loop {
+        // Everything sent so far is read first, so a request that a newer one
+        // replaces can be skipped
+        while let Ok(request) = rx.try_recv() {
+            pending.push_back(request);
+        }
+        let (request_id, message) = match pending.pop_front() {
+            Some(request @ (_, WorkerMessage::PrefetchTableRows { .. })) => {
+                prefetch = Some(request);
+                continue;
+            }
+            Some(request) => request,
+            None if prefetch.is_some() => match prefetch.take() {
+                Some(request) => request,
+                None => continue,
+            },
+            None if !uncounted.is_empty() => {
+                if let Some(table) = uncounted.pop_front() {
+                    let count = db::count_rows(&connection, &table).ok();
+                    let _ = responses.send((
+                        counting_request,
+                        WorkerResponse::TableRowCount {
+                            table,
+                            count,
+                            estimated: false,
+                        },
+                    ));
+                }
+                continue;
+            }
+            None => match rx.recv() {
+                Ok(request) => request,
+                // Channel closed, exit
+                Err(_) => break,
+            },
+        };
+        if superseded(&message, &pending) {
+            continue;
+        }
+        if matches!(message, WorkerMessage::Shutdown) {
+            break;
+        }
+        let changes_visibility = changes_visibility(&message);
+        let response_tx = Responder {
+            tx: responses.clone(),
+            request_id,
+        };
+        if snapshot && leaves_snapshot(&message) {
+            snapshot = false;
+            let _ = db::end_snapshot(&connection);
+            let _ = response_tx.send(WorkerResponse::SnapshotReleased);
+        }
+        // A bug handling one request is reported as its failure rather than taking the
+        // thread, and every request after it, down with it
+        let on_panic = panic_response(&message);
+        let handled = panic::catch_unwind(AssertUnwindSafe(|| match message {
+            WorkerMessage::LoadTables { include_internal } => {
+                referencing_keys.clear();
+                match retry_busy(busy, &response_tx, || {
+                    db::get_schema_objects(&connection, include_internal)
+                }) {
+                    Ok(tables) => {
+                        let journal_mode = db::journal_mode(&connection).ok();
+                        let _ = response_tx.send(WorkerResponse::TablesLoaded {
+                            tables,
+                            journal_mode,
+                        });
                     }
-                    Ok(WorkerMessage::LoadTableRows {
-                        table_name,
-                        limit,
-                        offset,
-                    }) => {
-                        match db::query::get_table_rows(&connection, &table_name, limit, offset) {
-                            Ok(result) => {
-                                let _ =
-                                    response_tx.send(WorkerResponse::TableRowsLoaded { result });
-                            }
-                            Err(e) => {
-                                let _ = response_tx.send(WorkerResponse::Error {
-                                    message: format!("Failed to load rows: {}", e),
-                                });
-                            }
-                        }
+                    Err(e) => {
+                        let _ = response_tx.send(WorkerResponse::Error {
+                            context: ErrorContext::Tables,
+                            message: format!("Failed to load tables: {}", e),
+                        });
                     }
-                    Ok(WorkerMessage::ExecuteQuery { query, max_rows }) => {
-                        match db::query::execute_query(&connection, &query, max_rows) {
-                            Ok(result) => {
-                                let _ = response_tx.send(WorkerResponse::QueryExecuted { result });
-                            }
-                            Err(e) => {
-                                // Error message is already formatted by db::query
-                                let _ = response_tx.send(WorkerResponse::Error {
-                                    message: format!("{}", e),
-                                });
-                            }
-                        }
+                }
+            }
+            WorkerMessage::CountRows {
+                tables,
+                count_limit,
+            } => {
+                uncounted = plan_row_counts(&connection, tables, count_limit, &response_tx);
+                counting_request = request_id;
+            }
+            WorkerMessage::LoadTableSizes => {
+                // Sizes are extra, so without dbstat or when it fails there are none
+                if let Ok(Some(sizes)) = db::get_table_sizes(&connection) {
+                    let _ = response_tx.send(WorkerResponse::TableSizesLoaded { sizes });
+                }
+            }
+            WorkerMessage::LoadTableRows {
+                table_name,
+                limit,
+                offset,
+                view,
+                page_start,
+                max_bytes,
+            } => match retry_busy(busy, &response_tx, || {
+                db::query::get_table_rows(
+                    &connection,
+                    &table_name,
+                    limit,
+                    offset,
+                    &view,
+                    page_start,
+                    max_bytes,
+                )
+            }) {
+                Ok(result) => {
+                    let _ = response_tx.send(WorkerResponse::TableRowsLoaded { result });
+                }
+                Err(e) => {
+                    let _ = response_tx.send(WorkerResponse::Error {
+                        context: ErrorContext::Rows,
+                        message: format!("Failed to load rows: {}", e),
+                    });
+                }
+            },
+            WorkerMessage::PrefetchTableRows {
+                table_name,
+                limit,
+                offset,
+                view,
+                page_start,
+                max_bytes,
+            } => {
+                // Not waited on, so a lock or error just leaves the page to load when
+                // it's asked for
+                if let Ok(result) = db::query::get_table_rows(
+                    &connection,
+                    &table_name,
+                    limit,
+                    offset,
+                    &view,
+                    page_start,
+                    max_bytes,
+                ) {
+                    let _ = response_tx.send(WorkerResponse::TableRowsPrefetched { result });
+                }
+            }
+            WorkerMessage::ExecuteScript {
+                statements,
+                max_rows,
+                max_bytes,
+            } => {
+                let start = Instant::now();
+                // Each step carries its own error, already formatted by db::query
+                let result = with_progress(&connection, &response_tx, || {
+                    db::query::execute_script(&connection, &statements, max_rows, max_bytes)
+                });
+                let response = match result {
+                    Ok(steps) => WorkerResponse::ScriptExecuted { steps },
+                    Err(cancelled) => WorkerResponse::QueryCancelled {
+                        elapsed_ms: start.elapsed().as_millis() as u64,
+                        completed: cancelled.completed,
+                        total: statements.len(),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::ExecuteQuery {
+                query,
+                params,
+                max_rows,
+                max_bytes,
+            } => {
+                let start = Instant::now();
+                let result = with_progress(&connection, &response_tx, || {
+                    db::query::execute_bound(&connection, &query, &params, max_rows, max_bytes)
+                });
+                let response = match result {
+                    Ok(step) => WorkerResponse::ScriptExecuted { steps: vec![step] },
+                    Err(_) => WorkerResponse::QueryCancelled {
+                        elapsed_ms: start.elapsed().as_millis() as u64,
+                        completed: 0,
+                        total: 1,
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::LoadQueryPage {
+                query,
+                params,
+                offset,
+                max_rows,
+                max_bytes,
+            } => {
+                let start = Instant::now();
+                let result = with_progress(&connection, &response_tx, || {
+                    retry_busy(busy, &response_tx, || {
+                        db::query::execute_query(
+                            &connection,
+                            &query,
+                            max_rows,
+                            max_bytes,
+                            offset,
+                            &params,
+                        )
+                    })
+                });
+                let response = match result {
+                    Ok(result) => WorkerResponse::QueryPageLoaded { result },
+                    Err(e) if db::query::is_interrupted(&e) => WorkerResponse::QueryCancelled {
+                        elapsed_ms: start.elapsed().as_millis() as u64,
+                        completed: 0,
+                        total: 1,
+                    },
+                    Err(e) => WorkerResponse::Error {
+                        context: ErrorContext::Query,
+                        message: e.to_string(),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::InspectParameters { statement } => {
+                // A statement that doesn't prepare has nothing to ask for; running it
+                // reports the error
+                let names = db::query::parameter_names(&connection, &statement).unwrap_or_default();
+                let _ = response_tx.send(WorkerResponse::ParametersInspected { statement, names });
+            }
+            WorkerMessage::ExplainQuery { statement } => {
+                match db::query::explain_query_plan(&connection, &statement) {
+                    Ok(steps) => {
+                        let _ =
+                            response_tx.send(WorkerResponse::QueryPlanLoaded { statement, steps });
                     }
-                    Ok(WorkerMessage::GetTableInfo { table_name }) => {
-                        match db::get_table_info(&connection, &table_name) {
-                            Ok(info) => {
-                                let _ = response_tx.send(WorkerResponse::TableInfoLoaded { info });
-                            }
-                            Err(e) => {
-                                let _ = response_tx.send(WorkerResponse::Error {
-                                    message: format!("Failed to load table info: {}", e),
-                                });
-                            }
-                        }
+                    Err(e) => {
+                        let _ = response_tx.send(WorkerResponse::Error {
+                            context: ErrorContext::Query,
+                            message: e.to_string(),
+                        });
                     }
-                    Ok(WorkerMessage::LoadSchema { table_name }) => {
-                        match (
-                            db::get_columns(&connection, &table_name),
-                            db::get_indexes(&connection, &table_name),
-                            db::get_foreign_keys(&connection, &table_name),
-                        ) {
-                            (Ok(columns), Ok(indexes), Ok(foreign_keys)) => {
-                                let _ = response_tx.send(WorkerResponse::SchemaLoaded {
-                                    columns,
-                                    indexes,
-                                    foreign_keys,
-                                });
-                            }
-                            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
-                                let _ = response_tx.send(WorkerResponse::Error {
-                                    message: format!("Failed to load schema: {}", e),
-                                });
-                            }
+                }
+            }
+            WorkerMessage::GetTableInfo { table_name } => {
+                match retry_busy(busy, &response_tx, || {
+                    db::get_table_info(&connection, &table_name)
+                }) {
+                    Ok(info) => {
+                        let _ = response_tx.send(WorkerResponse::TableInfoLoaded { info });
+                    }
+                    Err(e) => {
+                        let _ = response_tx.send(WorkerResponse::Error {
+                            context: ErrorContext::Schema,
+                            message: format!("Failed to load table info: {}", e),
+                        });
+                    }
+                }
+            }
+            WorkerMessage::LoadTableForeignKeys { table_name } => {
+                match db::get_foreign_keys(&connection, &table_name) {
+                    Ok(foreign_keys) => {
+                        let _ = response_tx.send(WorkerResponse::TableForeignKeysLoaded {
+                            table_name,
+                            foreign_keys,
+                        });
+                    }
+                    Err(e) => {
+                        let _ = response_tx.send(WorkerResponse::Error {
+                            context: ErrorContext::Schema,
+                            message: format!("Failed to load foreign keys: {}", e),
+                        });
+                    }
+                }
+            }
+            WorkerMessage::LoadSchema { table_name } => {
+                match retry_busy(busy, &response_tx, || {
+                    Ok((
+                        db::get_columns(&connection, &table_name)?,
+                        db::get_indexes(&connection, &table_name)?,
+                        db::get_foreign_keys(&connection, &table_name)?,
+                    ))
+                }) {
+                    Ok((columns, indexes, foreign_keys)) => {
+                        // What references the table is extra, so a failure to read
+                        // it leaves the list empty
+                        let referenced_by = referencing_keys
+                            .entry(table_name.clone())
+                            .or_insert_with(|| {
+                                db::get_referencing_keys(&connection, &table_name)
+                                    .unwrap_or_default()
+                            })
+                            .clone();
+                        let _ = response_tx.send(WorkerResponse::SchemaLoaded {
+                            columns,
+                            indexes,
+                            foreign_keys,
+                            referenced_by,
+                        });
+                    }
+                    Err(e) => {
+                        let _ = response_tx.send(WorkerResponse::Error {
+                            context: ErrorContext::Schema,
+                            message: format!("Failed to load schema: {}", e),
+                        });
+                    }
+                }
+            }
+            WorkerMessage::LoadColumnNames { table_names } => {
+                let columns = table_names
+                    .into_iter()
+                    .map(|table_name| {
+                        let names = db::get_columns(&connection, &table_name)
+                            .map(|columns| columns.into_iter().map(|c| c.name).collect())
+                            .unwrap_or_default();
+                        (table_name, names)
+                    })
+                    .collect();
+                let _ = response_tx.send(WorkerResponse::ColumnNamesLoaded { columns });
+            }
+            WorkerMessage::LoadDiagram => {
+                // Any change to a schema bumps its version, so while the versions stay
+                // the same the diagram read last is still right
+                let versions = db::schema_versions(&connection).ok();
+                let cached = diagram
+                    .as_ref()
+                    .filter(|(read_at, _)| Some(read_at) == versions.as_ref());
+                if let Some((_, data)) = cached {
+                    let _ = response_tx.send(WorkerResponse::DiagramLoaded { data: data.clone() });
+                } else {
+                    match retry_busy(busy, &response_tx, || db::get_diagram_tables(&connection)) {
+                        Ok(tables) => {
+                            let data = DiagramData { tables };
+                            diagram = versions.map(|versions| (versions, data.clone()));
+                            let _ = response_tx.send(WorkerResponse::DiagramLoaded { data });
+                        }
+                        Err(e) => {
+                            let _ = response_tx.send(WorkerResponse::Error {
+                                context: ErrorContext::Diagram,
+                                message: format!("Failed to load diagram: {}", e),
+                            });
                         }
                     }
-                    Ok(WorkerMessage::LoadDiagram) => {
-                        match db::get_tables(&connection, false) {
-                            Ok(tables) => {
-                                let mut diagram_tables = Vec::new();
-                                for table in tables {
-                                    match (
-                                        db::get_columns(&connection, &table.name),
-                                        db::get_foreign_keys(&connection, &table.name),
-                                    ) {
-                                        (Ok(columns), Ok(foreign_keys)) => {
-                                            diagram_tables.push(DiagramTable {
-                                                name: table.name,
-                                                columns,
-                                                foreign_keys,
-                                            });
-                                        }
-                                        _ => {
-                                            // Skip tables that fail to load
-                                        }
-                                    }
-                                }
-                                let _ = response_tx.send(WorkerResponse::DiagramLoaded {
-                                    data: DiagramData {
-                                        tables: diagram_tables,
-                                    },
-                                });
-                            }
-                            Err(e) => {
-                                let _ = response_tx.send(WorkerResponse::Error {
-                                    message: format!("Failed to load diagram: {}", e),
-                                });
-                            }
+                }
+            }
+            WorkerMessage::LocateRow {
+                table_name,
+                view,
+                key,
+            } => {
+                // A failed lookup just leaves the selection where it is
+                let position = db::query::row_position(&connection, &table_name, &view, &key)
+                    .ok()
+                    .flatten();
+                let _ = response_tx.send(WorkerResponse::RowLocated { position });
+            }
+            WorkerMessage::UpdateCell {
+                table_name,
+                key,
+                column_name,
+                new_value,
+            } => match db::update_cell(&connection, &table_name, &key, &column_name, &new_value) {
+                Ok(_) => {
+                    let _ = response_tx.send(WorkerResponse::CellUpdated);
+                }
+                Err(e) => {
+                    let _ = response_tx.send(write_failed(
+                        ErrorContext::Edit,
+                        format!("Failed to update cell: {}", e),
+                        &e,
+                    ));
+                }
+            },
+            WorkerMessage::ApplyEdits { edits } => {
+                match db::query::apply_edits(&connection, &edits) {
+                    Ok(()) => {
+                        let _ =
+                            response_tx.send(WorkerResponse::EditsApplied { count: edits.len() });
+                    }
+                    Err(e) => {
+                        // Include the cause so the failing edit's constraint error shows
+                        let _ = response_tx.send(write_failed(
+                            ErrorContext::Edit,
+                            format!("Failed to write edits: {:#}", e),
+                            &e,
+                        ));
+                    }
+                }
+            }
+            WorkerMessage::CountAffectedRows { count_query } => {
+                // A failed count only leaves the estimate out of the confirmation
+                let count = db::query::count_rows(&connection, &count_query).ok();
+                let _ = response_tx.send(WorkerResponse::AffectedRowsCounted { count });
+            }
+            WorkerMessage::CountColumnMatches {
+                table_name,
+                column_name,
+                find,
+            } => {
+                let count =
+                    db::query::count_matches(&connection, &table_name, &column_name, &find).ok();
+                let _ = response_tx.send(WorkerResponse::AffectedRowsCounted { count });
+            }
+            WorkerMessage::ReplaceInColumn {
+                table_name,
+                column_name,
+                find,
+                replacement,
+            } => {
+                match db::query::replace_in_column(
+                    &connection,
+                    &table_name,
+                    &column_name,
+                    &find,
+                    &replacement,
+                ) {
+                    Ok(count) => {
+                        let _ = response_tx.send(WorkerResponse::ColumnReplaced { count });
+                    }
+                    Err(e) => {
+                        let _ = response_tx.send(write_failed(
+                            ErrorContext::Edit,
+                            format!("{}", e),
+                            &e,
+                        ));
+                    }
+                }
+            }
+            WorkerMessage::CountColumnValues {
+                table_name,
+                column_name,
+            } => {
+                let count = db::count_values(&connection, &table_name, &column_name).ok();
+                let _ = response_tx.send(WorkerResponse::AffectedRowsCounted { count });
+            }
+            WorkerMessage::AlterColumn { table_name, change } => {
+                let response = match db::alter_column(&connection, &table_name, &change) {
+                    Ok(message) => WorkerResponse::TableAltered {
+                        table_name,
+                        message,
+                    },
+                    Err(e) => write_failed(
+                        ErrorContext::Schema,
+                        format!("Couldn't {}: {}", change.action().label(), e),
+                        &e,
+                    ),
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::CreateIndex { table_name, index } => {
+                let start = Instant::now();
+                let result = with_progress(&connection, &response_tx, || {
+                    db::create_index(&connection, &table_name, &index)
+                });
+                let response = match result {
+                    Ok(message) => WorkerResponse::TableAltered {
+                        table_name,
+                        message,
+                    },
+                    Err(e) if db::query::is_interrupted(&e) => WorkerResponse::IndexCancelled {
+                        elapsed_ms: start.elapsed().as_millis() as u64,
+                    },
+                    Err(e) => write_failed(
+                        ErrorContext::Schema,
+                        format!("Couldn't create index {}: {}", index.name, e),
+                        &e,
+                    ),
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::DropIndex {
+                table_name,
+                index_name,
+            } => {
+                let response = match db::drop_index(&connection, &table_name, &index_name) {
+                    Ok(message) => WorkerResponse::TableAltered {
+                        table_name,
+                        message,
+                    },
+                    Err(e) => write_failed(
+                        ErrorContext::Schema,
+                        format!("Couldn't drop index {}: {}", index_name, e),
+                        &e,
+                    ),
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::ExportResult {
+                format,
+                path,
+                source,
+            } => {
+                let options = ExportOptions {
+                    gzip: export::is_gzip_path(&path),
+                    ..ExportOptions::default()
+                };
+                let mut on_progress = |progress: export::ExportProgress| {
+                    let _ = response_tx.send(WorkerResponse::ExportProgress {
+                        rows: progress.rows,
+                        bytes: progress.bytes,
+                    });
+                    !worker_copy_cancel.load(Ordering::Relaxed)
+                };
+                let response = match export::create_file(&path).and_then(|writer| {
+                    export::export_source(
+                        &connection,
+                        format,
+                        writer,
+                        &source,
+                        &options,
+                        &mut on_progress,
+                    )
+                }) {
+                    Ok(written) => WorkerResponse::Exported {
+                        path,
+                        bytes: written.bytes,
+                        rows: written.rows,
+                        unit: source.unit(),
+                        warnings: written.warnings,
+                    },
+                    Err(e) if e.is::<ExportCancelled>() => {
+                        let _ = std::fs::remove_file(&path);
+                        WorkerResponse::ExportFailed {
+                            message: format!("Export cancelled; removed {}", path.display()),
                         }
                     }
-                    Ok(WorkerMessage::UpdateCell {
+                    Err(e) => WorkerResponse::ExportFailed {
+                        message: format!("Export failed: {:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::ImportCsv { table_name, path } => {
+                // A .tsv file is separated by tabs
+                let tsv = path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("tsv"));
+                let options = ImportOptions {
+                    delimiter: if tsv { b'\t' } else { b',' },
+                    ..ImportOptions::default()
+                };
+                let response = match std::fs::File::open(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|file| {
+                        import::import_csv(&connection, Box::new(file), &table_name, &options)
+                    }) {
+                    Ok(summary) => WorkerResponse::Imported {
                         table_name,
-                        row_index,
-                        column_name,
-                        new_value,
-                    }) => {
-                        match db::update_cell(
-                            &connection,
-                            &table_name,
-                            row_index,
-                            &column_name,
-                            &new_value,
-                        ) {
-                            Ok(_) => {
-                                let _ = response_tx.send(WorkerResponse::CellUpdated);
-                            }
-                            Err(e) => {
-                                let _ = response_tx.send(WorkerResponse::Error {
-                                    message: format!("Failed to update cell: {}", e),
+                        inserted: summary.inserted,
+                    },
+                    Err(e) => WorkerResponse::Error {
+                        context: ErrorContext::Edit,
+                        message: format!("Import failed: {:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::Backup { path } => {
+                let mut on_progress = |progress: db::BackupProgress| {
+                    let _ = response_tx.send(WorkerResponse::BackupProgress {
+                        remaining: progress.remaining,
+                        total: progress.total,
+                    });
+                    !worker_copy_cancel.load(Ordering::Relaxed)
+                };
+                let response = match db::backup_to(&connection, &path, &mut on_progress) {
+                    Ok(progress) => WorkerResponse::BackedUp {
+                        path,
+                        pages: progress.total,
+                    },
+                    Err(e) if e.is::<db::BackupCancelled>() => WorkerResponse::BackupFailed {
+                        message: "Backup cancelled".to_string(),
+                    },
+                    Err(e) => WorkerResponse::BackupFailed {
+                        message: format!("Backup failed: {:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::IntegrityCheck => {
+                let start = Instant::now();
+                let result = with_progress(&connection, &response_tx, || {
+                    db::check_database(&connection, false)
+                });
+                let response = match result {
+                    Ok(report) => WorkerResponse::IntegrityChecked { report },
+                    Err(e) if db::query::is_interrupted(&e) => {
+                        WorkerResponse::IntegrityCheckCancelled {
+                            elapsed_ms: start.elapsed().as_millis() as u64,
+                        }
+                    }
+                    Err(e) => WorkerResponse::IntegrityCheckFailed {
+                        message: format!("Integrity check failed: {:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::AttachDatabase { path, alias } => {
+                let response = match db::attach_database(&connection, &path, &alias) {
+                    Ok(()) => WorkerResponse::DatabaseAttached { path, alias },
+                    Err(e) => WorkerResponse::AttachFailed {
+                        message: format!("{:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::DetachDatabase { alias } => {
+                let response = match db::detach_database(&connection, &alias) {
+                    Ok(()) => WorkerResponse::DatabaseDetached { alias },
+                    Err(e) => WorkerResponse::AttachFailed {
+                        message: format!("{:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::ListExtensions => {
+                let response = match db::list_extensions(&connection) {
+                    Ok(list) => WorkerResponse::ExtensionsListed { list },
+                    Err(e) => WorkerResponse::Error {
+                        context: ErrorContext::Schema,
+                        message: format!("Failed to list extensions: {:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::ReadPragmas => {
+                let response = match db::pragma::get_all(&connection) {
+                    Ok(settings) => WorkerResponse::PragmasRead {
+                        settings,
+                        message: None,
+                    },
+                    Err(e) => WorkerResponse::PragmaFailed {
+                        message: format!("{:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::SetPragma { pragma, value } => {
+                let response = match db::pragma::set(&connection, pragma, &value)
+                    .and_then(|message| Ok((db::pragma::get_all(&connection)?, message)))
+                {
+                    Ok((settings, message)) => WorkerResponse::PragmasRead {
+                        settings,
+                        message: Some(message),
+                    },
+                    Err(e) => WorkerResponse::PragmaFailed {
+                        message: format!("{:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::BeginSnapshot => {
+                // A new snapshot starts from now
+                if std::mem::take(&mut snapshot) {
+                    let _ = db::end_snapshot(&connection);
+                }
+                let response = match db::begin_snapshot(&connection) {
+                    Ok(()) => {
+                        snapshot = true;
+                        WorkerResponse::SnapshotTaken
+                    }
+                    Err(e) => WorkerResponse::SnapshotFailed {
+                        message: format!("{:#}", e),
+                    },
+                };
+                let _ = response_tx.send(response);
+            }
+            WorkerMessage::EndSnapshot => {
+                if std::mem::take(&mut snapshot) {
+                    let _ = db::end_snapshot(&connection);
+                }
+            }
+            WorkerMessage::CheckDataVersion => {
+                // A failed check just means no refresh this time
+                if let Ok(version) = db::data_version(&connection) {
+                    let _ = response_tx.send(WorkerResponse::DataVersion { version });
+                }
+            }
+            WorkerMessage::ReopenReadWrite {
+                key,
+                extensions,
+                allow_extensions,
+            } => {
+                // Keep the current connection unless the new one opens
+                let reopened = Database::open_with_key(&path, false, false, key.as_deref())
+                    .and_then(|database| {
+                        let connection = database.into_connection();
+                        db::load_extensions(&connection, &extensions, allow_extensions)?;
+                        Ok(connection)
+                    });
+                match reopened {
+                    Ok(reopened) => {
+                        // Attachments belong to the connection, so bring them along;
+                        // one that lives in memory has nothing to reopen
+                        let attached = db::attached_databases(&connection).unwrap_or_default();
+                        connection = reopened;
+                        let _ = connection.busy_timeout(busy.timeout);
+                        for (alias, file) in attached.into_iter().filter(|(_, f)| !f.is_empty()) {
+                            if let Err(e) = db::attach_database(&connection, &file, &alias) {
+                                let _ = response_tx.send(WorkerResponse::AttachFailed {
+                                    message: format!("Failed to reattach {}: {:#}", alias, e),
                                 });
                             }
                         }
+                        if let Ok(mut handle) = worker_interrupt.lock() {
+                            *handle = connection.get_interrupt_handle();
+                        }
+                        let _ = response_tx.send(WorkerResponse::ReopenedReadWrite);
                     }
-                    Ok(WorkerMessage::Shutdown) => {
-                        break;
-                    }
-                    Err(_) => {
-                        // Channel closed, exit
-                        break;
+                    Err(e) => {
+                        let _ = response_tx.send(WorkerResponse::ReopenFailed {
+                            message: format!("Failed to reopen read-write: {:#}", e),
+                        });
                     }
                 }
             }
-        });
+            // Handled before, as it ends the loop
+            WorkerMessage::Shutdown => {}
+        }));
+        if let Err(payload) = handled {
+            // A panic in the middle of a script leaves its progress handler behind
+            connection.progress_handler(0, None::<fn() -> bool>);
+            let _ = response_tx.send(on_panic(format!(
+                "Internal error: {}",
+                panic_text(payload.as_ref())
+            )));
+        }
+        if changes_visibility {
+            // When it can't tell, it's safer to think the connection has something
+            let private =
+                !connection.is_autocommit() || db::has_temp_objects(&connection).unwrap_or(true);
+            state.private.store(private, Ordering::SeqCst);
+            state.changes_handled.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
 
+/// Whether `message` writes or changes the connection, so can't run inside a snapshot's
+/// read transaction
+fn leaves_snapshot(message: &WorkerMessage) -> bool {
+    matches!(
+        message,
+        WorkerMessage::ExecuteScript { .. }
+            | WorkerMessage::ExecuteQuery { .. }
+            | WorkerMessage::UpdateCell { .. }
+            | WorkerMessage::ApplyEdits { .. }
+            | WorkerMessage::ReplaceInColumn { .. }
+            | WorkerMessage::AlterColumn { .. }
+            | WorkerMessage::CreateIndex { .. }
+            | WorkerMessage::DropIndex { .. }
+            | WorkerMessage::ImportCsv { .. }
+            | WorkerMessage::AttachDatabase { .. }
+            | WorkerMessage::DetachDatabase { .. }
+            | WorkerMessage::SetPragma { .. }
+            | WorkerMessage::ReopenReadWrite { .. }
+    )
+}
+
+/// Whether `message` may start or end what only its connection sees: a transaction or
+/// TEMP objects
+fn changes_visibility(message: &WorkerMessage) -> bool {
+    leaves_snapshot(message)
+        || matches!(
+            message,
+            WorkerMessage::BeginSnapshot | WorkerMessage::EndSnapshot
+        )
+}
+
+/// How to report a panic while handling `message`: as the failure its own responses
+/// have for one, otherwise as an error where its part of the UI shows errors
+fn panic_response(message: &WorkerMessage) -> fn(String) -> WorkerResponse {
+    match message {
+        WorkerMessage::ExportResult { .. } => |message| WorkerResponse::ExportFailed { message },
+        WorkerMessage::Backup { .. } => |message| WorkerResponse::BackupFailed { message },
+        WorkerMessage::IntegrityCheck => |message| WorkerResponse::IntegrityCheckFailed { message },
+        WorkerMessage::ReadPragmas | WorkerMessage::SetPragma { .. } => {
+            |message| WorkerResponse::PragmaFailed { message }
+        }
+        WorkerMessage::AttachDatabase { .. } | WorkerMessage::DetachDatabase { .. } => {
+            |message| WorkerResponse::AttachFailed { message }
+        }
+        WorkerMessage::ReopenReadWrite { .. } => |message| WorkerResponse::ReopenFailed { message },
+        WorkerMessage::BeginSnapshot | WorkerMessage::EndSnapshot => {
+            |message| WorkerResponse::SnapshotFailed { message }
+        }
+        WorkerMessage::LoadTables { .. }
+        | WorkerMessage::CountRows { .. }
+        | WorkerMessage::LoadTableSizes
+        | WorkerMessage::CheckDataVersion
+        | WorkerMessage::Shutdown => |message| WorkerResponse::Error {
+            context: ErrorContext::Tables,
+            message,
+        },
+        WorkerMessage::LoadTableRows { .. }
+        | WorkerMessage::PrefetchTableRows { .. }
+        | WorkerMessage::LocateRow { .. } => |message| WorkerResponse::Error {
+            context: ErrorContext::Rows,
+            message,
+        },
+        WorkerMessage::ExecuteScript { .. }
+        | WorkerMessage::ExecuteQuery { .. }
+        | WorkerMessage::LoadQueryPage { .. }
+        | WorkerMessage::InspectParameters { .. }
+        | WorkerMessage::ExplainQuery { .. } => |message| WorkerResponse::Error {
+            context: ErrorContext::Query,
+            message,
+        },
+        WorkerMessage::GetTableInfo { .. }
+        | WorkerMessage::LoadTableForeignKeys { .. }
+        | WorkerMessage::LoadSchema { .. }
+        | WorkerMessage::LoadColumnNames { .. }
+        | WorkerMessage::ListExtensions
+        | WorkerMessage::AlterColumn { .. }
+        | WorkerMessage::CreateIndex { .. }
+        | WorkerMessage::DropIndex { .. } => |message| WorkerResponse::Error {
+            context: ErrorContext::Schema,
+            message,
+        },
+        WorkerMessage::LoadDiagram => |message| WorkerResponse::Error {
+            context: ErrorContext::Diagram,
+            message,
+        },
+        WorkerMessage::UpdateCell { .. }
+        | WorkerMessage::ApplyEdits { .. }
+        | WorkerMessage::CountAffectedRows { .. }
+        | WorkerMessage::CountColumnMatches { .. }
+        | WorkerMessage::ReplaceInColumn { .. }
+        | WorkerMessage::CountColumnValues { .. }
+        | WorkerMessage::ImportCsv { .. } => |message| WorkerResponse::Error {
+            context: ErrorContext::Edit,
+            message,
+        },
+    }
+}
+
+/// The message a panic was raised with, when it was given one
+fn panic_text(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("the worker panicked")
+}
+
+/// A thread running requests against its own connection
+struct Lane {
+    sender: mpsc::Sender<(u64, WorkerMessage)>,
+    handle: thread::JoinHandle<()>,
+    /// Interrupts whatever the lane's connection is running; replaced on reopen
+    interrupt: Arc<Mutex<InterruptHandle>>,
+    state: Arc<LaneState>,
+}
+
+/// Whether a lane's connection sees the database as another connection would, kept
+/// up to date by the lane as it handles requests
+#[derive(Default)]
+struct LaneState {
+    /// Requests sent that may change what only the connection sees, and how many of
+    /// them it has handled
+    changes_sent: AtomicU64,
+    changes_handled: AtomicU64,
+    /// Whether, after the last of them, the connection has a transaction open or TEMP
+    /// objects, which another connection can't see
+    private: AtomicBool,
+}
+
+impl LaneState {
+    /// Whether another connection reads what this one would, with no change to that
+    /// still waiting to be handled
+    fn shared(&self) -> bool {
+        self.changes_handled.load(Ordering::SeqCst) == self.changes_sent.load(Ordering::SeqCst)
+            && !self.private.load(Ordering::SeqCst)
+    }
+}
+
+impl Lane {
+    fn spawn(
+        conn: Connection,
+        path: PathBuf,
+        responses: mpsc::Sender<(u64, WorkerResponse)>,
+        copy_cancel: Arc<AtomicBool>,
+        busy: BusyPolicy,
+    ) -> Self {
+        let (sender, rx) = mpsc::channel();
+        let interrupt = Arc::new(Mutex::new(conn.get_interrupt_handle()));
+        let lane_interrupt = Arc::clone(&interrupt);
+        let state = Arc::new(LaneState::default());
+        let lane_state = Arc::clone(&state);
+        let handle = thread::spawn(move || {
+            run(
+                conn,
+                path,
+                rx,
+                responses,
+                lane_interrupt,
+                copy_cancel,
+                busy,
+                lane_state,
+            );
+        });
         Self {
-            sender: tx,
-            receiver: response_rx,
+            sender,
             handle,
+            interrupt,
+            state,
+        }
+    }
+
+    fn send(&self, request_id: u64, message: WorkerMessage) -> Result<()> {
+        // Counted before it's sent, so it's waiting from the moment it could be handled
+        if changes_visibility(&message) {
+            self.state.changes_sent.fetch_add(1, Ordering::SeqCst);
+        }
+        self.sender
+            .send((request_id, message))
+            .map_err(|_| anyhow::anyhow!("Worker thread disconnected"))
+    }
+
+    fn interrupt(&self) {
+        if let Ok(handle) = self.interrupt.lock() {
+            handle.interrupt();
+        }
+    }
+
+    fn shutdown(self) -> Result<()> {
+        self.send(0, WorkerMessage::Shutdown)?;
+        self.handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("Worker thread panicked"))
+    }
+}
+
+/// Whether `message` is slow, read-only work the background connection can take so
+/// it doesn't hold up browsing: counting rows, measuring tables, exports of tables,
+/// integrity checks and the diagram. A query export stays on the primary connection,
+/// as its SQL may need something only that connection has, as does anything naming a
+/// TEMP object.
+fn runs_in_background(message: &WorkerMessage) -> bool {
+    if names_temp_object(message) {
+        return false;
+    }
+    match message {
+        WorkerMessage::CountRows { .. }
+        | WorkerMessage::LoadTableSizes
+        | WorkerMessage::IntegrityCheck
+        | WorkerMessage::LoadDiagram => true,
+        WorkerMessage::ExportResult { source, .. } => !matches!(source, ExportSource::Query { .. }),
+        _ => false,
+    }
+}
+
+/// Whether `message` names an object of the TEMP schema as `temp.name`
+fn names_temp_object(message: &WorkerMessage) -> bool {
+    let is_temp = |name: &str| {
+        name.split_once('.')
+            .is_some_and(|(schema, _)| schema.eq_ignore_ascii_case("temp"))
+    };
+    match message {
+        WorkerMessage::CountRows { tables, .. } => tables.iter().any(|table| is_temp(table)),
+        WorkerMessage::ExportResult {
+            source:
+                ExportSource::Table(table)
+                | ExportSource::View { table, .. }
+                | ExportSource::Schema { table: Some(table) },
+            ..
+        } => is_temp(table),
+        _ => false,
+    }
+}
+
+/// A second, read-only connection to the database for slow reads, set up like the
+/// primary one. None for an in-memory database, which another connection can't see,
+/// or when it can't be opened, leaving all the work to the primary connection.
+pub fn open_background(
+    path: &str,
+    key: Option<&str>,
+    attach: &[(String, String)],
+    extensions: &[String],
+) -> Option<rusqlite::Connection> {
+    if db::is_in_memory(path) {
+        return None;
+    }
+    let conn = Database::open_with_key(path, true, false, key)
+        .ok()?
+        .into_connection();
+    db::load_extensions(&conn, extensions, false).ok()?;
+    for (path, alias) in attach {
+        db::attach_database(&conn, path, alias).ok()?;
+    }
+    Some(conn)
+}
+
+/// Runs database work on worker threads: everything on the primary connection, or with
+/// a second, read-only connection, slow reads on that one so browsing stays responsive.
+/// Writes only ever go to the primary connection, and while it has a transaction open
+/// or TEMP objects, which the second connection can't see, so do the slow reads.
+pub struct Worker {
+    primary: Lane,
+    background: Option<Lane>,
+    receiver: mpsc::Receiver<(u64, WorkerResponse)>,
+    /// Id of the last request sent; each one gets the next
+    last_request: AtomicU64,
+    /// Stops a running export or backup at its next progress report
+    copy_cancel: Arc<AtomicBool>,
+    /// The database file and lock policy, for starting the threads again
+    path: PathBuf,
+    busy: BusyPolicy,
+}
+
+impl Worker {
+    /// Create a new worker with a database connection to the file at `path`, and a
+    /// read-only `background` connection to it for slow work when there is one. Both
+    /// wait out other connections' locks as `busy` says.
+    pub fn new(
+        conn: Connection,
+        background: Option<Connection>,
+        path: PathBuf,
+        busy: BusyPolicy,
+    ) -> Self {
+        let (responses, receiver) = mpsc::channel();
+        let copy_cancel = Arc::new(AtomicBool::new(false));
+        let primary = Lane::spawn(
+            conn,
+            path.clone(),
+            responses.clone(),
+            Arc::clone(&copy_cancel),
+            busy,
+        );
+        let background = background.map(|conn| {
+            Lane::spawn(
+                conn,
+                path.clone(),
+                responses,
+                Arc::clone(&copy_cancel),
+                busy,
+            )
+        });
+        Self {
+            primary,
+            background,
+            receiver,
+            last_request: AtomicU64::new(0),
+            copy_cancel,
+            path,
+            busy,
         }
     }
 
-    /// Send a message to the worker
-    pub fn send(&self, message: WorkerMessage) -> Result<()> {
-        self.sender.send(message)?;
+    /// Whether a worker thread has died, leaving whatever is sent to it unanswered.
+    /// Panics handling a request are caught, so this takes one outside of them.
+    pub fn crashed(&self) -> bool {
+        self.primary.handle.is_finished()
+            || self
+                .background
+                .as_ref()
+                .is_some_and(|lane| lane.handle.is_finished())
+    }
+
+    /// Start the worker threads again on new connections to the database file, opened
+    /// `read_only` or not with the same key, extensions and `attached` databases as
+    /// before. Request ids carry on, so answers from the old threads stay stale.
+    pub fn restart(
+        &mut self,
+        read_only: bool,
+        key: Option<&str>,
+        extensions: &[String],
+        allow_extensions: bool,
+        attached: &[(String, String)],
+    ) -> Result<()> {
+        let path = self.path.to_string_lossy().to_string();
+        if db::is_in_memory(&path) {
+            anyhow::bail!("An in-memory database can't be reopened; restart sqr");
+        }
+        let conn = Database::open_with_key(&path, read_only, false, key)?.into_connection();
+        db::load_extensions(&conn, extensions, allow_extensions)?;
+        // One that lives in memory has nothing to reattach
+        let attached: Vec<(String, String)> = attached
+            .iter()
+            .filter(|(file, _)| !file.is_empty() && !db::is_in_memory(file))
+            .cloned()
+            .collect();
+        for (file, alias) in &attached {
+            db::attach_database(&conn, file, alias)?;
+        }
+        let background = self
+            .background
+            .is_some()
+            .then(|| open_background(&path, key, &attached, extensions))
+            .flatten();
+        // The old threads end once their channels close, if they haven't already
+        *self = Self {
+            last_request: AtomicU64::new(self.last_request.load(Ordering::Relaxed)),
+            ..Self::new(conn, background, self.path.clone(), self.busy)
+        };
         Ok(())
     }
 
-    /// Try to receive a response (non-blocking)
-    pub fn try_recv(&self) -> Result<Option<WorkerResponse>> {
+    /// Stop the statement the primary connection is running, if any
+    pub fn interrupt(&self) {
+        self.primary.interrupt();
+    }
+
+    /// Stop the statement the connection doing slow work is running, if any
+    pub fn interrupt_background(&self) {
+        self.background
+            .as_ref()
+            .unwrap_or(&self.primary)
+            .interrupt();
+    }
+
+    /// Stop the running export or backup, if any, at its next progress report
+    pub fn cancel_copy(&self) {
+        self.copy_cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Send a message to the worker, returning the id its responses will carry
+    pub fn send(&self, message: WorkerMessage) -> Result<u64> {
+        // A new export or backup starts uncancelled, whatever happened to the last one
+        if matches!(
+            message,
+            WorkerMessage::ExportResult { .. } | WorkerMessage::Backup { .. }
+        ) {
+            self.copy_cancel.store(false, Ordering::Relaxed);
+        }
+        let request_id = self.last_request.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(background) = &self.background {
+            // Attachments belong to a connection, so the background one makes the same
+            // ones, unanswered
+            let copy = match &message {
+                WorkerMessage::AttachDatabase { path, alias } => {
+                    Some(WorkerMessage::AttachDatabase {
+                        path: path.clone(),
+                        alias: alias.clone(),
+                    })
+                }
+                WorkerMessage::DetachDatabase { alias } => Some(WorkerMessage::DetachDatabase {
+                    alias: alias.clone(),
+                }),
+                _ => None,
+            };
+            if let Some(copy) = copy {
+                background.send(UNANSWERED, copy)?;
+            }
+            if runs_in_background(&message) && self.primary.state.shared() {
+                background.send(request_id, message)?;
+                return Ok(request_id);
+            }
+        }
+        self.primary.send(request_id, message)?;
+        Ok(request_id)
+    }
+
+    /// Id the next request sent will get; every request sent so far has a smaller one
+    pub fn next_request_id(&self) -> u64 {
+        self.last_request.load(Ordering::Relaxed) + 1
+    }
+
+    /// Try to receive a response (non-blocking), with the id of the request it answers
+    pub fn try_recv(&self) -> Result<Option<(u64, WorkerResponse)>> {
         match self.receiver.try_recv() {
             Ok(response) => Ok(Some(response)),
             Err(mpsc::TryRecvError::Empty) => Ok(None),
@@ -250,20 +1737,160 @@ impl Worker {
         }
     }
 
-    /// Receive a response (blocking)
+    /// Receive a response (blocking), with the id of the request it answers
     #[allow(dead_code)]
-    pub fn recv(&self) -> Result<WorkerResponse> {
+    pub fn recv(&self) -> Result<(u64, WorkerResponse)> {
         self.receiver
             .recv()
             .map_err(|e| anyhow::anyhow!("Worker thread disconnected: {}", e))
     }
 
-    /// Shutdown the worker thread
+    /// Shutdown the worker threads
     pub fn shutdown(self) -> Result<()> {
-        self.sender.send(WorkerMessage::Shutdown)?;
-        self.handle
-            .join()
-            .map_err(|_| anyhow::anyhow!("Worker thread panicked"))?;
-        Ok(())
+        if let Some(background) = self.background {
+            background.shutdown()?;
+        }
+        self.primary.shutdown()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUSY: BusyPolicy = BusyPolicy {
+        timeout: Duration::from_secs(5),
+        retries: 0,
+    };
+
+    /// A worker with both connections to a database at `path` whose table `t` has one
+    /// row
+    fn worker(path: &std::path::Path) -> Worker {
+        let _ = std::fs::remove_file(path);
+        let conn = Database::open_with_key(path, false, true, None)
+            .unwrap()
+            .into_connection();
+        conn.execute_batch("CREATE TABLE t(a); INSERT INTO t VALUES (1);")
+            .unwrap();
+        let background = open_background(&path.to_string_lossy(), None, &[], &[]);
+        assert!(background.is_some());
+        Worker::new(conn, background, path.to_path_buf(), BUSY)
+    }
+
+    fn execute(worker: &Worker, query: &str) -> u64 {
+        worker
+            .send(WorkerMessage::ExecuteQuery {
+                query: query.to_string(),
+                params: Vec::new(),
+                max_rows: None,
+                max_bytes: usize::MAX,
+            })
+            .unwrap()
+    }
+
+    /// Count table `t`, without waiting for what was sent before
+    fn count(worker: &Worker) -> u64 {
+        worker
+            .send(WorkerMessage::CountRows {
+                tables: vec!["t".to_string()],
+                count_limit: u64::MAX,
+            })
+            .unwrap()
+    }
+
+    /// The responses to `ids`, in the order they arrive, leaving out progress reports
+    fn answers(worker: &Worker, ids: &[u64]) -> Vec<WorkerResponse> {
+        let mut answers = Vec::new();
+        while answers.len() < ids.len() {
+            let (id, response) = worker.recv().unwrap();
+            if ids.contains(&id) && !matches!(response, WorkerResponse::QueryProgress { .. }) {
+                answers.push(response);
+            }
+        }
+        answers
+    }
+
+    /// Whether the primary lane comes to share what it sees with the background one.
+    /// It marks that after answering, so this waits a little.
+    fn becomes_shared(worker: &Worker) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !worker.primary.state.shared() {
+            if Instant::now() > deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+
+    fn row_count(response: &WorkerResponse) -> Option<u64> {
+        match response {
+            WorkerResponse::TableRowCount { count, .. } => *count,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn a_temp_table_is_counted_where_it_was_made() {
+        let path = std::env::temp_dir().join(format!("sqr-temp-lane-{}.db", std::process::id()));
+        let worker = worker(&path);
+
+        // Shadows the main table of the same name, on the primary connection only
+        let created = execute(
+            &worker,
+            "CREATE TEMP TABLE t AS SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3",
+        );
+        let counted = count(&worker);
+        let responses = answers(&worker, &[created, counted]);
+        assert_eq!(responses.iter().find_map(row_count), Some(3));
+        assert!(!worker.primary.state.shared());
+
+        let dropped = execute(&worker, "DROP TABLE temp.t");
+        let counted = count(&worker);
+        let responses = answers(&worker, &[dropped, counted]);
+        assert_eq!(responses.iter().find_map(row_count), Some(1));
+        assert!(becomes_shared(&worker));
+
+        worker.shutdown().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn uncommitted_rows_are_counted_where_they_were_written() {
+        let path = std::env::temp_dir().join(format!("sqr-tx-lane-{}.db", std::process::id()));
+        let worker = worker(&path);
+
+        let began = execute(&worker, "BEGIN");
+        let inserted = execute(&worker, "INSERT INTO t VALUES (2)");
+        let counted = count(&worker);
+        let responses = answers(&worker, &[began, inserted, counted]);
+        assert_eq!(responses.iter().find_map(row_count), Some(2));
+        assert!(!worker.primary.state.shared());
+
+        // Once committed, the background connection sees the row too
+        let committed = execute(&worker, "COMMIT");
+        answers(&worker, &[committed]);
+        assert!(becomes_shared(&worker));
+        let counted = count(&worker);
+        assert_eq!(row_count(&answers(&worker, &[counted])[0]), Some(2));
+
+        worker.shutdown().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn requests_naming_temp_objects_stay_on_the_primary_connection() {
+        let count = |table: &str| WorkerMessage::CountRows {
+            tables: vec!["t".to_string(), table.to_string()],
+            count_limit: u64::MAX,
+        };
+        assert!(runs_in_background(&count("main_t")));
+        assert!(!runs_in_background(&count("temp.t")));
+        assert!(!runs_in_background(&count("TEMP.t")));
+        assert!(!runs_in_background(&WorkerMessage::ExportResult {
+            format: ExportFormat::Csv,
+            path: PathBuf::from("t.csv"),
+            source: ExportSource::Table("temp.t".to_string()),
+        }));
     }
 }
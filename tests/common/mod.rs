@@ -0,0 +1,32 @@
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A directory of its own under the system's temporary directory
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sqr-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A database at `path` built by running `sql`
+pub fn create_fixture(path: &Path, sql: &str) {
+    Connection::open(path).unwrap().execute_batch(sql).unwrap();
+}
+
+/// A command running the `sqr` binary under test
+pub fn sqr() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sqr"))
+}
+
+/// What `command` prints, failing the test if it exits unsuccessfully
+pub fn run(command: &mut Command) -> Vec<u8> {
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output.stdout
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+
+mod common;
+
+use common::{create_fixture, run, sqr, temp_dir};
+
+/// Names with spaces and SQL keywords, a column without a type, a composite primary
+/// key and a composite foreign key that may be NULL
+const FIXTURE: &str = "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT NOT NULL, \"first name\" VARCHAR(40));
+    CREATE TABLE \"order\"(id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL REFERENCES users(id), note);
+    CREATE TABLE \"line items\"(order_id INTEGER REFERENCES \"order\", sku TEXT, qty DECIMAL(10,2), PRIMARY KEY(order_id, sku));
+    CREATE TABLE stock(sku TEXT, warehouse TEXT, order_id, line_sku,
+        FOREIGN KEY(order_id, line_sku) REFERENCES \"line items\"(order_id, sku));";
+
+const MERMAID: &str = r#"erDiagram
+    "line items" {
+        INTEGER order_id PK, FK
+        TEXT sku PK
+        DECIMAL(10_2) qty
+    }
+    order {
+        INTEGER id PK
+        INTEGER user_id FK
+        any note
+    }
+    stock {
+        TEXT sku
+        TEXT warehouse
+        any order_id FK
+        any line_sku FK
+    }
+    users {
+        INTEGER id PK
+        TEXT name
+        VARCHAR(40) first_name "first name"
+    }
+    order ||--o{ "line items" : "order_id"
+    users ||--o{ order : "user_id"
+    "line items" |o--o{ stock : "order_id, line_sku"
+"#;
+
+const DOT: &str = r#"digraph schema {
+    rankdir=LR;
+    node [shape=plaintext];
+    "line items" [label=<
+        <table border="0" cellborder="1" cellspacing="0">
+        <tr><td bgcolor="lightgrey"><b>line items</b></td></tr>
+        <tr><td align="left" port="c0">order_id INTEGER PK, FK</td></tr>
+        <tr><td align="left" port="c1">sku TEXT PK</td></tr>
+        <tr><td align="left" port="c2">qty DECIMAL(10,2)</td></tr>
+        </table>>];
+    "order" [label=<
+        <table border="0" cellborder="1" cellspacing="0">
+        <tr><td bgcolor="lightgrey"><b>order</b></td></tr>
+        <tr><td align="left" port="c0">id INTEGER PK</td></tr>
+        <tr><td align="left" port="c1">user_id INTEGER FK</td></tr>
+        <tr><td align="left" port="c2">note</td></tr>
+        </table>>];
+    "stock" [label=<
+        <table border="0" cellborder="1" cellspacing="0">
+        <tr><td bgcolor="lightgrey"><b>stock</b></td></tr>
+        <tr><td align="left" port="c0">sku TEXT</td></tr>
+        <tr><td align="left" port="c1">warehouse TEXT</td></tr>
+        <tr><td align="left" port="c2">order_id FK</td></tr>
+        <tr><td align="left" port="c3">line_sku FK</td></tr>
+        </table>>];
+    "users" [label=<
+        <table border="0" cellborder="1" cellspacing="0">
+        <tr><td bgcolor="lightgrey"><b>users</b></td></tr>
+        <tr><td align="left" port="c0">id INTEGER PK</td></tr>
+        <tr><td align="left" port="c1">name TEXT</td></tr>
+        <tr><td align="left" port="c2">first name VARCHAR(40)</td></tr>
+        </table>>];
+    "line items":c0 -> "order":c0 [label="order_id"];
+    "order":c1 -> "users":c0 [label="user_id"];
+    "stock":c2 -> "line items":c0 [label="order_id, line_sku"];
+}
+"#;
+
+const PLANTUML: &str = r#"@startuml
+hide circle
+skinparam linetype ortho
+
+entity "line items" as e0 {
+  * order_id : INTEGER <<PK>> <<FK>>
+  * sku : TEXT <<PK>>
+  --
+  qty : DECIMAL(10,2)
+}
+
+entity "order" as e1 {
+  * id : INTEGER <<PK>>
+  --
+  * user_id : INTEGER <<FK>>
+  note
+}
+
+entity "stock" as e2 {
+  sku : TEXT
+  warehouse : TEXT
+  order_id <<FK>>
+  line_sku <<FK>>
+}
+
+entity "users" as e3 {
+  * id : INTEGER <<PK>>
+  --
+  * name : TEXT
+  first name : VARCHAR(40)
+}
+
+e1 ||--o{ e0 : order_id
+e3 ||--o{ e1 : user_id
+e0 |o--o{ e2 : order_id, line_sku
+@enduml
+"#;
+
+/// The diagram `sqr diagram` prints in `format`
+fn diagram(db: &Path, format: &str) -> String {
+    String::from_utf8(run(sqr()
+        .arg("diagram")
+        .arg("--db")
+        .arg(db)
+        .arg("--format")
+        .arg(format)))
+    .unwrap()
+}
+
+#[test]
+fn diagram_formats() {
+    let dir = temp_dir("diagram");
+    let db = dir.join("schema.db");
+    create_fixture(&db, FIXTURE);
+
+    assert_eq!(diagram(&db, "mermaid"), MERMAID);
+    assert_eq!(diagram(&db, "dot"), DOT);
+    assert_eq!(diagram(&db, "plantuml"), PLANTUML);
+
+    // Without --format, the extension of --out picks it
+    let out = dir.join("schema.puml");
+    run(sqr()
+        .arg("diagram")
+        .arg("--db")
+        .arg(&db)
+        .arg("-o")
+        .arg(&out));
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), PLANTUML);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
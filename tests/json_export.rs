@@ -0,0 +1,98 @@
+use serde_json::Value;
+use std::path::Path;
+
+mod common;
+
+use common::{create_fixture, run, sqr, temp_dir};
+
+/// Rows in the fixture, enough that collecting them before writing would show
+const ROWS: i64 = 100_000;
+
+/// The events table with `ROWS` rows
+fn create_events(path: &Path) {
+    create_fixture(
+        path,
+        &format!(
+            "CREATE TABLE events(id INTEGER PRIMARY KEY, name TEXT, score REAL, payload BLOB);
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {})
+             INSERT INTO events SELECT i, 'event \"' || i || '\"', i / 4.0, CASE WHEN i % 2 THEN x'00ff' END FROM n;",
+            ROWS
+        ),
+    );
+}
+
+/// The file `sqr export` writes for the events table with `args`
+fn export(dir: &Path, source: &Path, name: &str, args: &[&str]) -> Vec<u8> {
+    let out = dir.join(name);
+    run(sqr()
+        .arg("export")
+        .arg("--db")
+        .arg(source)
+        .args(["--table", "events"])
+        .args(args)
+        .arg("--out")
+        .arg(&out));
+    std::fs::read(&out).unwrap()
+}
+
+/// The JSON `sqr export` writes for the events table in `shape`
+fn export_json(dir: &Path, source: &Path, shape: &str) -> Value {
+    let json = export(
+        dir,
+        source,
+        &format!("{}.json", shape),
+        &["--format", "json", "--json-shape", shape],
+    );
+    serde_json::from_slice(&json).unwrap()
+}
+
+#[test]
+fn a_large_table_streams_to_valid_json() {
+    let dir = temp_dir("json-export");
+    let source = dir.join("source.db");
+    create_events(&source);
+
+    let objects = export_json(&dir, &source, "objects");
+    let rows = objects.as_array().unwrap();
+    assert_eq!(rows.len(), ROWS as usize);
+    assert_eq!(rows[0]["id"], 1);
+    assert_eq!(rows[0]["name"], "event \"1\"");
+    assert_eq!(rows[0]["score"], 0.25);
+    assert!(rows[1]["payload"].is_null());
+    let last = &rows[ROWS as usize - 1];
+    assert_eq!(last["id"], ROWS);
+    assert_eq!(last["name"], format!("event \"{}\"", ROWS));
+
+    let envelope = export_json(&dir, &source, "envelope");
+    assert_eq!(envelope["row_count"], ROWS);
+    assert_eq!(envelope["rows"].as_array().unwrap().len(), ROWS as usize);
+    assert_eq!(
+        envelope["columns"],
+        serde_json::json!(["id", "name", "score", "payload"])
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_large_table_streams_to_one_json_object_per_line() {
+    let dir = temp_dir("jsonl-export");
+    let source = dir.join("source.db");
+    create_events(&source);
+
+    let jsonl = export(&dir, &source, "events.jsonl", &["--format", "jsonl"]);
+    let jsonl = String::from_utf8(jsonl).unwrap();
+    let rows: Vec<Value> = jsonl
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), ROWS as usize);
+    assert!(jsonl.ends_with('\n'));
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(row["id"], i as i64 + 1);
+    }
+    assert_eq!(rows[0]["name"], "event \"1\"");
+    assert!(rows[1]["payload"].is_null());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}